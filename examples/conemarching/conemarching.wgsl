@@ -11,11 +11,14 @@ struct CameraUniform {
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
-struct TimeUniform {
+struct Globals {
   time: f32,
+  delta_time: f32,
+  frame: u32,
+  resolution: vec2<f32>,
 };
 @group(1) @binding(0)
-var<uniform> time: TimeUniform;
+var<uniform> globals: Globals;
 
 @group(2) @binding(0)
 var t_depth: texture_2d<f32>;
@@ -175,7 +178,7 @@ fn closest(object1: vec4<f32>, object2: vec4<f32>) -> vec4<f32> {
 }
 
 fn sdf(point: vec3<f32>) -> vec4<f32> {
-    let sphere_pos = vec3<f32>(0.0, 0.0, 5.0) * sin(time.time * 0.25);
+    let sphere_pos = vec3<f32>(0.0, 0.0, 5.0) * sin(globals.time * 0.25);
     let sphere_radius = 0.6;
     let sphere_color = vec3<f32>(1.0, 0.0, 0.0);
     let sphere_distance = sphere(point - sphere_pos, sphere_radius);
@@ -1,11 +1,10 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
     window::WindowBuilder,
 };
-use zero::{const_vec, impl_simple_buffer, impl_simple_texture_bind_group, prelude::*};
+use zero::{const_vec, prelude::*};
 
 struct FpsLogger {
     last_log: std::time::Instant,
@@ -30,96 +29,31 @@ impl FpsLogger {
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct TimeUniform {
-    time: f32,
-}
-
-impl From<&Time> for TimeUniform {
-    fn from(value: &Time) -> Self {
-        Self { time: value.time }
-    }
-}
-
-#[derive(Debug)]
-pub struct Time {
-    time: f32,
-}
-
-impl_simple_buffer!(
-    Time,
-    TimeUniform,
-    TimeResources,
-    TimeHandle,
-    TimeBindGroup,
-    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
-    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
-    { BufferBindingType::Uniform }
-);
-
-#[derive(Debug)]
-pub struct DepthResource {
-    texture: GpuTexture,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct DepthHandle {
-    pub texture_id: ResourceId,
-}
-
-impl ResourceHandle for DepthHandle {
-    type OriginalResource<'a> = EmptyTexture;
-    type ResourceType = DepthResource;
-
-    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
-        Self {
-            texture_id: storage.insert_texture(resource.texture),
-        }
-    }
-
-    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
-        storage.replace_texture(self.texture_id, resource.texture);
-    }
-}
-
-impl_simple_texture_bind_group!(
-    DepthHandle,
-    DepthBindGroup,
-    { TextureViewDimension::D2 },
-    { TextureSampleType::Float { filterable: false } },
-    { SamplerBindingType::NonFiltering }
-);
-
 fn main() {
     env_logger::init();
 
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TimeBindGroup>(&renderer);
-    storage.register_bind_group_layout::<DepthBindGroup>(&renderer);
-
     let depth_prepass_pipeline = PipelineBuilder {
         shader_path: "./examples/conemarching/depth_prepass.wgsl",
         label: Some("depth_prepass_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<TimeBindGroup>(),
-                storage.get_bind_group_layout::<DepthBindGroup>(),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<GlobalsBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<ConeDepthBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
         vertex_layouts: &[MeshVertex::layout()],
         vertex_entry_point: "vs_main",
         color_targets: Some(&[Some(ColorTargetState {
-            format: TextureFormat::R32Float,
+            format: CONE_MARCHER_DEPTH_FORMAT,
             blend: None,
             write_mask: ColorWrites::ALL,
         })]),
@@ -146,9 +80,9 @@ fn main() {
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<TimeBindGroup>(),
-                storage.get_bind_group_layout::<DepthBindGroup>(),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<GlobalsBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<ConeDepthBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -176,137 +110,13 @@ fn main() {
     .build(&renderer);
     let final_pipeline_id = storage.insert_pipeline(final_pipeline);
 
-    let depth_0 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((16, 16)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_0_handle = DepthHandle::new(&mut storage, depth_0);
-    let depth_0_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_0_handle);
-
-    let depth_1 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((32, 32)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_1_handle = DepthHandle::new(&mut storage, depth_1);
-    let depth_1_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_1_handle);
-
-    let depth_2 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((64, 64)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_2_handle = DepthHandle::new(&mut storage, depth_2);
-    let depth_2_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_2_handle);
+    let levels = ConeMarcher::levels_for_resolution(renderer.size().width, renderer.size().height);
+    let cone_marcher = ConeMarcher::new(&renderer, &mut storage, levels);
 
-    let depth_3 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((128, 128)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_3_handle = DepthHandle::new(&mut storage, depth_3);
-    let depth_3_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_3_handle);
-
-    let depth_4 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((256, 256)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_4_handle = DepthHandle::new(&mut storage, depth_4);
-    let depth_4_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_4_handle);
-
-    let depth_5 = DepthResource {
-        texture: EmptyTexture {
-            dimensions: Some((512, 512)),
-            format: TextureFormat::R32Float,
-            filtered: false,
-        }
-        .build(&renderer),
-    };
-    let depth_5_handle = DepthHandle::new(&mut storage, depth_5);
-    let depth_5_bind_group = DepthBindGroup::new(&renderer, &mut storage, &depth_5_handle);
-
-    let phase_1 = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: depth_1_handle.texture_id,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
-
-    let phase_2 = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: depth_2_handle.texture_id,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
-
-    let phase_3 = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: depth_3_handle.texture_id,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
-
-    let phase_4 = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: depth_4_handle.texture_id,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
-
-    let phase_5 = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: depth_5_handle.texture_id,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
-
-    let final_phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        None,
-    );
+    let final_phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .build();
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
         position: (-10.0, 0.0, 0.0).into(),
@@ -322,23 +132,24 @@ fn main() {
 
     let mut camera_controller = CameraController::new(5.0, 0.7);
 
-    let mut time = Time { time: 0.0 };
-    let time_handle = TimeHandle::new(&mut storage, time.build(&renderer));
-    let time_bind_group = TimeBindGroup::new(&renderer, &mut storage, &time_handle);
+    let mut globals = Globals::new(renderer.size().width, renderer.size().height);
+    let globals_handle = GlobalsHandle::new(&mut storage, globals.build(&renderer));
+    let globals_bind_group = GlobalsBindGroup::new(&renderer, &mut storage, &globals_handle);
 
     let mesh: Mesh = Quad::new(2.0, 2.0).into();
     let mesh_id = storage.insert_mesh(mesh.build(&renderer));
 
     let mut last_render_time = std::time::Instant::now();
     let mut fps_logger = FpsLogger::new();
+    // Ray marching does not need to run faster than the display
+    let mut frame_limiter = FrameLimiter::new(60.0);
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => {
-                if let DeviceEvent::MouseMotion { delta } = event {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -359,11 +170,15 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
+                    globals.resize(physical_size.width, physical_size.height);
                 }
                 WindowEvent::RedrawRequested => {
                     let now = std::time::Instant::now();
@@ -372,15 +187,15 @@ fn main() {
 
                     fps_logger.log(now, dt);
 
-                    time.time += dt.as_secs_f32();
-                    time_handle.update(&renderer, &storage, &time);
+                    globals.advance(dt);
+                    globals_handle.update(&renderer, &storage, &globals);
 
                     camera_controller.update_camera(&mut camera, dt);
                     camera_handle.update(&renderer, &storage, &camera);
 
                     let current_frame_context = match renderer.current_frame() {
                         Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                             renderer.resize(None);
                             return;
                         }
@@ -401,70 +216,15 @@ fn main() {
 
                     let mut encoder = renderer.create_encoder();
 
-                    let depth_1_command = MeshRenderCommand {
-                        pipeline_id: depth_prepass_pipeline_id,
+                    let prepass_commands = cone_marcher.prepass_commands(
+                        depth_prepass_pipeline_id,
                         mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_0_bind_group.0
+                        &[
+                            BindGroupMeta::new(0, camera_bind_group.0),
+                            BindGroupMeta::new(1, globals_bind_group.0),
                         ],
-                    };
-
-                    let depth_2_command = MeshRenderCommand {
-                        pipeline_id: depth_prepass_pipeline_id,
-                        mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_1_bind_group.0
-                        ],
-                    };
-
-                    let depth_3_command = MeshRenderCommand {
-                        pipeline_id: depth_prepass_pipeline_id,
-                        mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_2_bind_group.0
-                        ],
-                    };
-
-                    let depth_4_command = MeshRenderCommand {
-                        pipeline_id: depth_prepass_pipeline_id,
-                        mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_3_bind_group.0
-                        ],
-                    };
-
-                    let depth_5_command = MeshRenderCommand {
-                        pipeline_id: depth_prepass_pipeline_id,
-                        mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_4_bind_group.0
-                        ],
-                    };
+                        2,
+                    );
 
                     let final_command = MeshRenderCommand {
                         pipeline_id: final_pipeline_id,
@@ -472,22 +232,21 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            camera_bind_group.0,
-                            time_bind_group.0,
-                            depth_5_bind_group.0
+                            BindGroupMeta::new(0, camera_bind_group.0),
+                            BindGroupMeta::new(1, globals_bind_group.0),
+                            BindGroupMeta::new(2, cone_marcher.final_bind_group().0),
                         ],
                     };
 
                     {
-                        for (phase, command) in [
-                            (&phase_1, depth_1_command),
-                            (&phase_2, depth_2_command),
-                            (&phase_3, depth_3_command),
-                            (&phase_4, depth_4_command),
-                            (&phase_5, depth_5_command),
-                            (&final_phase, final_command),
-                        ] {
+                        for (phase, command) in cone_marcher
+                            .phases
+                            .iter()
+                            .zip(prepass_commands)
+                            .chain(std::iter::once((&final_phase, final_command)))
+                        {
                             let mut render_pass =
                                 phase.render_pass(&mut encoder, &current_frame_storage);
                             command.execute(&mut render_pass, &current_frame_storage);
@@ -497,6 +256,7 @@ fn main() {
                     let commands = encoder.finish();
                     renderer.submit(std::iter::once(commands));
                     current_frame_context.present();
+                    frame_limiter.wait();
                 }
                 _ => {}
             },
@@ -0,0 +1,69 @@
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct Instance {
+  transform: mat4x4<f32>,
+  aabb_min: vec4<f32>,
+  aabb_max: vec4<f32>,
+};
+@group(1) @binding(0)
+var<storage, read> instances: array<Instance>;
+@group(1) @binding(1)
+var<storage, read_write> visible: array<u32>;
+
+struct DrawIndexedIndirectArgs {
+  index_count: u32,
+  instance_count: atomic<u32>,
+  first_index: u32,
+  base_vertex: i32,
+  first_instance: u32,
+};
+@group(1) @binding(2)
+var<storage, read_write> draw_args: DrawIndexedIndirectArgs;
+
+// Instance is culled if all corners of its AABB
+// are outside of the same clip plane
+fn is_visible(instance: Instance) -> bool {
+  let mvp = camera.view_projection * instance.transform;
+  var outside_left = true;
+  var outside_right = true;
+  var outside_bottom = true;
+  var outside_top = true;
+  var outside_near = true;
+  var outside_far = true;
+  for (var i = 0u; i < 8u; i++) {
+    let corner = vec4<f32>(
+      select(instance.aabb_min.x, instance.aabb_max.x, (i & 1u) != 0u),
+      select(instance.aabb_min.y, instance.aabb_max.y, (i & 2u) != 0u),
+      select(instance.aabb_min.z, instance.aabb_max.z, (i & 4u) != 0u),
+      1.0,
+    );
+    let clip = mvp * corner;
+    outside_left = outside_left && clip.x < -clip.w;
+    outside_right = outside_right && clip.w < clip.x;
+    outside_bottom = outside_bottom && clip.y < -clip.w;
+    outside_top = outside_top && clip.w < clip.y;
+    outside_near = outside_near && clip.z < 0.0;
+    outside_far = outside_far && clip.w < clip.z;
+  }
+  return !(outside_left || outside_right || outside_bottom || outside_top || outside_near || outside_far);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  if arrayLength(&instances) <= id.x {
+    return;
+  }
+  if is_visible(instances[id.x]) {
+    let index = atomicAdd(&draw_args.instance_count, 1u);
+    visible[index] = id.x;
+  }
+}
@@ -0,0 +1,273 @@
+use winit::{
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::WindowBuilder,
+};
+use zero::{const_vec, prelude::*};
+
+const GRID_SIZE: i32 = 64;
+
+struct FpsLogger {
+    last_log: std::time::Instant,
+}
+
+impl FpsLogger {
+    fn new() -> Self {
+        Self {
+            last_log: std::time::Instant::now(),
+        }
+    }
+
+    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration) {
+        if 1.0 <= (now - self.last_log).as_secs_f32() {
+            println!(
+                "Frame time: {:.2}ms(FPS: {:.2})",
+                dt.as_secs_f64() * 1000.0,
+                1.0 / dt.as_secs_f64()
+            );
+            self.last_log = now;
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
+    let mut storage = RenderStorage::default();
+
+    let culling_pipeline = ComputePipelineBuilder {
+        shader_path: "./examples/culling/culling.wgsl",
+        label: Some("culling_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<GpuCullingBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        entry_point: "cs_main",
+    }
+    .build(&renderer);
+    let culling_pipeline_id = storage.insert_compute_pipeline(culling_pipeline);
+
+    let instanced_pipeline = PipelineBuilder {
+        shader_path: "./examples/culling/instanced.wgsl",
+        label: Some("instanced_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<GpuCullingInstancesBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[MeshVertex::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let instanced_pipeline_id = storage.insert_pipeline(instanced_pipeline);
+
+    let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
+
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
+
+    let mut camera = Camera::Perspective(PerspectiveCamera {
+        position: (-10.0, 2.0, 0.0).into(),
+        yaw: Deg(0.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: renderer.size().width as f32 / renderer.size().height as f32,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
+    let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
+    let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
+
+    let mut camera_controller = CameraController::new(5.0, 0.7);
+
+    let cube = Cube::new(1.0, 1.0, 1.0);
+    let cube_mesh: Mesh = cube.into();
+    let cube_id = storage.insert_mesh(cube_mesh.build(&renderer));
+
+    let mut instances = Vec::new();
+    for x in -GRID_SIZE / 2..GRID_SIZE / 2 {
+        for z in -GRID_SIZE / 2..GRID_SIZE / 2 {
+            let transform = Transform {
+                translation: (x as f32 * 3.0, 0.0, z as f32 * 3.0).into(),
+                rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(0.0)),
+                scale: (1.0, 1.0, 1.0).into(),
+            };
+            instances.push(CullingInstance::new(
+                &transform,
+                [cube.min_x, cube.min_y, cube.min_z],
+                [cube.max_x, cube.max_y, cube.max_z],
+            ));
+        }
+    }
+    let culling = GpuCulling {
+        instances,
+        index_count: cube_mesh.indices.len() as u32,
+    };
+    let culling_handle = GpuCullingHandle::new(&mut storage, culling.build(&renderer));
+    let culling_bind_group = GpuCullingBindGroup::new(&renderer, &mut storage, &culling_handle);
+    let culling_instances_bind_group =
+        GpuCullingInstancesBindGroup::new(&renderer, &mut storage, &culling_handle);
+
+    let green_material = ColorMaterial {
+        ambient: [0.4, 0.9, 0.4],
+        diffuse: [0.4, 0.9, 0.4],
+        specular: [0.1, 0.1, 0.1],
+        shininess: 1.0,
+    };
+    let green_material_handle =
+        ColorMaterialHandle::new(&mut storage, green_material.build(&renderer));
+    let green_material_bind_group =
+        ColorMaterialBindGroup::new(&renderer, &mut storage, &green_material_handle);
+
+    let mut last_render_time = std::time::Instant::now();
+    let mut fps_logger = FpsLogger::new();
+    _ = event_loop.run(|event, target| {
+        target.set_control_flow(ControlFlow::Poll);
+        match event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => camera_controller.set_mouse_active(*state == ElementState::Pressed),
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: key,
+                            state,
+                            ..
+                        },
+                    ..
+                } => match key {
+                    Key::Named(NamedKey::Escape) => target.exit(),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
+                },
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
+                    camera.resize(physical_size.width, physical_size.height);
+                    storage.replace_texture(
+                        depth_texture_id,
+                        EmptyTexture::new_depth().build(&renderer),
+                    );
+                }
+                WindowEvent::RedrawRequested => {
+                    let now = std::time::Instant::now();
+                    let dt = now - last_render_time;
+                    last_render_time = now;
+
+                    fps_logger.log(now, dt);
+
+                    camera_controller.update_camera(&mut camera, dt);
+                    camera_handle.update(&renderer, &storage, &camera);
+                    culling_handle.reset(&renderer, &storage, &culling);
+
+                    let current_frame_context = match renderer.current_frame() {
+                        Ok(cfc) => cfc,
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                            renderer.resize(None);
+                            return;
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            target.exit();
+                            return;
+                        }
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            return;
+                        }
+                    };
+
+                    let current_frame_storage = CurrentFrameStorage {
+                        storage: &storage,
+                        current_frame_view: current_frame_context.view(),
+                    };
+
+                    let mut encoder = renderer.create_encoder();
+
+                    let culling_command = CullingCommand {
+                        pipeline_id: culling_pipeline_id,
+                        num_instances: culling.instances.len() as u32,
+                        bind_groups: const_vec![camera_bind_group.0, culling_bind_group.0],
+                    };
+                    culling_command.execute(&mut encoder, &storage);
+
+                    let cubes = IndirectMeshCommand {
+                        pipeline_id: instanced_pipeline_id,
+                        mesh_id: cube_id,
+                        indirect_buffer_id: culling_handle.indirect_buffer_id,
+                        bind_groups: const_vec![
+                            BindGroupMeta::new(0, green_material_bind_group.0),
+                            BindGroupMeta::new(1, camera_bind_group.0),
+                            BindGroupMeta::new(2, culling_instances_bind_group.0),
+                        ],
+                    };
+
+                    {
+                        let mut render_pass =
+                            phase.render_pass(&mut encoder, &current_frame_storage);
+                        cubes.execute(&mut render_pass, &current_frame_storage);
+                    }
+
+                    let commands = encoder.finish();
+                    renderer.submit(std::iter::once(commands));
+                    current_frame_context.present();
+                }
+                _ => {}
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => {}
+        }
+    });
+}
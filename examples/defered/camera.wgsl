@@ -0,0 +1,11 @@
+// Matches `CameraUniform`, shared by all passes
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+  prev_view_projection: mat4x4<f32>,
+  jitter: vec2<f32>,
+};
@@ -0,0 +1,103 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_position: texture_2d<f32>;
+@group(0) @binding(1)
+var s_position: sampler;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(3)
+var s_normal: sampler;
+@group(0) @binding(4)
+var t_albedo: texture_2d<f32>;
+@group(0) @binding(5)
+var s_albedo: sampler;
+
+struct DebugViewUniform {
+  view: u32,
+  znear: f32,
+  zfar: f32,
+};
+@group(1) @binding(0)
+var<uniform> debug_view: DebugViewUniform;
+
+@group(2) @binding(0)
+var t_depth: texture_2d<f32>;
+@group(3) @binding(0)
+var t_shadow: texture_2d<f32>;
+
+const VIEW_POSITION: u32 = 0u;
+const VIEW_NORMAL: u32 = 1u;
+const VIEW_ALBEDO: u32 = 2u;
+const VIEW_DEPTH: u32 = 3u;
+const VIEW_SHADOW_MAP: u32 = 4u;
+
+fn load_depth(t: texture_2d<f32>, tex_coords: vec2<f32>) -> f32 {
+  let dimensions = textureDimensions(t);
+  let coords = min(vec2<u32>(tex_coords * vec2<f32>(dimensions)), dimensions - 1u);
+  return textureLoad(t, coords, 0).r;
+}
+
+// Decodes normal packed by `encode_normal` in the geometry pass
+fn decode_normal(e: vec2<f32>) -> vec3<f32> {
+  var n = vec3<f32>(e, 1.0 - abs(e.x) - abs(e.y));
+  let t = max(-n.z, 0.0);
+  n.x = n.x + select(t, -t, n.x >= 0.0);
+  n.y = n.y + select(t, -t, n.y >= 0.0);
+  return normalize(n);
+}
+
+// Converts [0, 1] perspective depth into [0, 1] range between near and far planes
+fn linearize_depth(depth: f32) -> f32 {
+  let znear = debug_view.znear;
+  let zfar = debug_view.zfar;
+  let view_z = znear * zfar / (zfar - depth * (zfar - znear));
+  return (view_z - znear) / (zfar - znear);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  switch debug_view.view {
+    case VIEW_POSITION: {
+      return vec4<f32>(textureSample(t_position, s_position, vertex.tex_coords).xyz, 1.0);
+    }
+    case VIEW_NORMAL: {
+      let normal = decode_normal(textureSample(t_normal, s_normal, vertex.tex_coords).xy);
+      return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+    }
+    case VIEW_ALBEDO: {
+      return vec4<f32>(textureSample(t_albedo, s_albedo, vertex.tex_coords).rgb, 1.0);
+    }
+    case VIEW_DEPTH: {
+      let depth = linearize_depth(load_depth(t_depth, vertex.tex_coords));
+      return vec4<f32>(vec3<f32>(depth), 1.0);
+    }
+    case VIEW_SHADOW_MAP: {
+      // Orthographic projection, depth is already linear
+      let depth = load_depth(t_shadow, vertex.tex_coords);
+      return vec4<f32>(vec3<f32>(depth), 1.0);
+    }
+    default: {
+      return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+  }
+}
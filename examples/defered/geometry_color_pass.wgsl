@@ -3,18 +3,12 @@
 struct TransformUniform {
   transform: mat4x4<f32>,
   rotate: mat4x4<f32>,
+  prev_transform: mat4x4<f32>,
 };
 @group(1) @binding(0)
 var<uniform> transform: TransformUniform;
 
-struct CameraUniform {
-  view: mat4x4<f32>,
-  projection: mat4x4<f32>,
-  view_projection: mat4x4<f32>,
-  view_projection_inverse: mat4x4<f32>,
-  view_projection_without_translation: mat4x4<f32>,
-  position: vec3<f32>,
-};
+#include "camera.wgsl"
 @group(2) @binding(0)
 var<uniform> camera: CameraUniform;
 
@@ -30,6 +24,8 @@ struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
   @location(0) world_position: vec4<f32>,
   @location(1) world_normal: vec3<f32>,
+  @location(2) curr_clip_position: vec4<f32>,
+  @location(3) prev_clip_position: vec4<f32>,
 };
 
 @vertex
@@ -41,6 +37,10 @@ fn vs_main(
 
   var out: VertexOutput;
   out.clip_position = camera.view_projection * world_position;
+  out.curr_clip_position = out.clip_position;
+  out.prev_clip_position = camera.prev_view_projection * transform.prev_transform * vec4<f32>(vertex.position, 1.0);
+  // Jitter is not a part of the motion
+  out.clip_position = vec4<f32>(out.clip_position.xy + camera.jitter * out.clip_position.w, out.clip_position.zw);
   out.world_position = world_position;
   out.world_normal = world_normal.xyz;
 
@@ -58,18 +58,43 @@ struct MaterialProperties {
 @group(0) @binding(0)
 var<uniform> properties: MaterialProperties;
 
+// Octahedral normal encoding. Packs unit vector into 2 components in [-1, 1] range.
+fn oct_wrap(v: vec2<f32>) -> vec2<f32> {
+  return (1.0 - abs(v.yx)) * select(vec2<f32>(-1.0), vec2<f32>(1.0), v >= vec2<f32>(0.0));
+}
+
+fn encode_normal(n: vec3<f32>) -> vec2<f32> {
+  let p = n.xy / (abs(n.x) + abs(n.y) + abs(n.z));
+  if (n.z < 0.0) {
+    return oct_wrap(p);
+  }
+  return p;
+}
+
+// Screen space motion since the previous frame in texture coordinates
+fn velocity(curr_clip: vec4<f32>, prev_clip: vec4<f32>) -> vec2<f32> {
+  let curr_ndc = curr_clip.xy / curr_clip.w;
+  let prev_ndc = prev_clip.xy / prev_clip.w;
+  return (curr_ndc - prev_ndc) * vec2<f32>(0.5, -0.5);
+}
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
+
 struct FragmentOut {
   @location(0) position: vec4<f32>,
-  @location(1) normal: vec4<f32>,
+  @location(1) normal: vec2<f32>,
   @location(2) albedo: vec4<f32>,
+  @location(3) velocity: vec2<f32>,
 };
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> FragmentOut {
   var out: FragmentOut;
   out.position = vertex.world_position;
-  out.normal = vec4<f32>(vertex.world_normal, 1.0);
-  out.albedo = vec4<f32>(properties.ambient, 1.0);
+  out.normal = encode_normal(normalize(vertex.world_normal));
+  out.albedo = vec4<f32>(properties.ambient, 1.0 / MAX_SHININESS);
+  out.velocity = velocity(vertex.curr_clip_position, vertex.prev_clip_position);
 
   return out; 
 }
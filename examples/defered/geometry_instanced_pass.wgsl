@@ -0,0 +1,106 @@
+// Vertex shader
+
+#include "camera.wgsl"
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+  @location(3) tangent: vec3<f32>,
+  @location(4) bitangent: vec3<f32>,
+};
+
+struct InstanceInput {
+  @location(5) model_0: vec4<f32>,
+  @location(6) model_1: vec4<f32>,
+  @location(7) model_2: vec4<f32>,
+  @location(8) model_3: vec4<f32>,
+  @location(9) color: vec4<f32>,
+  @location(10) material_index: u32,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) world_position: vec4<f32>,
+  @location(1) world_normal: vec3<f32>,
+  @location(2) curr_clip_position: vec4<f32>,
+  @location(3) prev_clip_position: vec4<f32>,
+  @location(4) color: vec4<f32>,
+  @location(5) @interpolate(flat) material_index: u32,
+};
+
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+  instance: InstanceInput,
+) -> VertexOutput {
+  let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+  let world_position = model * vec4<f32>(vertex.position, 1.0);
+  // Instances are expected to be uniformly scaled
+  let world_normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
+
+  var out: VertexOutput;
+  out.clip_position = camera.view_projection * world_position;
+  out.curr_clip_position = out.clip_position;
+  // Instances do not move
+  out.prev_clip_position = camera.prev_view_projection * world_position;
+  // Jitter is not a part of the motion
+  out.clip_position = vec4<f32>(out.clip_position.xy + camera.jitter * out.clip_position.w, out.clip_position.zw);
+  out.world_position = world_position;
+  out.world_normal = world_normal;
+  out.color = instance.color;
+  out.material_index = instance.material_index;
+
+  return out;
+}
+
+// Fragment shader
+
+// Octahedral normal encoding. Packs unit vector into 2 components in [-1, 1] range.
+fn oct_wrap(v: vec2<f32>) -> vec2<f32> {
+  return (1.0 - abs(v.yx)) * select(vec2<f32>(-1.0), vec2<f32>(1.0), v >= vec2<f32>(0.0));
+}
+
+fn encode_normal(n: vec3<f32>) -> vec2<f32> {
+  let p = n.xy / (abs(n.x) + abs(n.y) + abs(n.z));
+  if (n.z < 0.0) {
+    return oct_wrap(p);
+  }
+  return p;
+}
+
+// Screen space motion since the previous frame in texture coordinates
+fn velocity(curr_clip: vec4<f32>, prev_clip: vec4<f32>) -> vec2<f32> {
+  let curr_ndc = curr_clip.xy / curr_clip.w;
+  let prev_ndc = prev_clip.xy / prev_clip.w;
+  return (curr_ndc - prev_ndc) * vec2<f32>(0.5, -0.5);
+}
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
+
+// Shininess of the materials selected by the instance material index
+const SHININESS = array<f32, 4>(1.0, 8.0, 32.0, 128.0);
+
+struct FragmentOut {
+  @location(0) position: vec4<f32>,
+  @location(1) normal: vec2<f32>,
+  @location(2) albedo: vec4<f32>,
+  @location(3) velocity: vec2<f32>,
+};
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> FragmentOut {
+  var shininess = SHININESS;
+  let material = min(vertex.material_index, 3u);
+
+  var out: FragmentOut;
+  out.position = vertex.world_position;
+  out.normal = encode_normal(normalize(vertex.world_normal));
+  out.albedo = vec4<f32>(vertex.color.rgb, shininess[material] / MAX_SHININESS);
+  out.velocity = velocity(vertex.curr_clip_position, vertex.prev_clip_position);
+
+  return out;
+}
@@ -3,18 +3,12 @@
 struct TransformUniform {
   transform: mat4x4<f32>,
   rotate: mat4x4<f32>,
+  prev_transform: mat4x4<f32>,
 };
 @group(1) @binding(0)
 var<uniform> transform: TransformUniform;
 
-struct CameraUniform {
-  view: mat4x4<f32>,
-  projection: mat4x4<f32>,
-  view_projection: mat4x4<f32>,
-  view_projection_inverse: mat4x4<f32>,
-  view_projection_without_translation: mat4x4<f32>,
-  position: vec3<f32>,
-};
+#include "camera.wgsl"
 @group(2) @binding(0)
 var<uniform> camera: CameraUniform;
 
@@ -33,6 +27,8 @@ struct VertexOutput {
   @location(2) world_normal: vec3<f32>,
   @location(3) world_tangent: vec3<f32>,
   @location(4) world_bitangent: vec3<f32>,
+  @location(5) curr_clip_position: vec4<f32>,
+  @location(6) prev_clip_position: vec4<f32>,
 };
 
 @vertex
@@ -47,6 +43,10 @@ fn vs_main(
 
   var out: VertexOutput;
   out.clip_position = camera.view_projection * world_position;
+  out.curr_clip_position = out.clip_position;
+  out.prev_clip_position = camera.prev_view_projection * transform.prev_transform * vec4<f32>(vertex.position, 1.0);
+  // Jitter is not a part of the motion
+  out.clip_position = vec4<f32>(out.clip_position.xy + camera.jitter * out.clip_position.w, out.clip_position.zw);
   out.tex_coords = vertex.tex_coords;
   out.world_position = world_position;
   out.world_tangent = world_tangent.xyz;
@@ -62,8 +62,14 @@ struct MaterialProperties {
     ambient: vec3<f32>,
     diffuse: vec3<f32>,
     specular: vec3<f32>,
-    // for now  r - shininess, else is empty
-    shininess: vec4<f32>,
+    // Matches padding of the `MaterialPropertiesUniform`
+    _pad: f32,
+    shininess: f32,
+    // Normal texture green channel points down (DirectX convention)
+    flip_normal_y: u32,
+    // 0 - off, 1 - offset, 2 - occlusion
+    parallax_mode: u32,
+    parallax_scale: f32,
 };
 @group(0) @binding(4)
 var<uniform> properties: MaterialProperties;
@@ -76,33 +82,109 @@ var s_diffuse: sampler;
 var t_normal: texture_2d<f32>;
 @group(0) @binding(3)
 var s_normal: sampler;
+@group(0) @binding(5)
+var t_height: texture_2d<f32>;
+@group(0) @binding(6)
+var s_height: sampler;
+
+// Depth below the surface, 0 at the highest point
+fn sample_depth(tex_coords: vec2<f32>) -> f32 {
+  return 1.0 - textureSampleLevel(t_height, s_height, tex_coords, 0.0).r;
+}
+
+// Offsets texture coordinates along the tangent space view direction
+fn parallax_tex_coords(tex_coords: vec2<f32>, view_dir: vec3<f32>) -> vec2<f32> {
+  if (properties.parallax_mode == 1u) {
+    let depth = sample_depth(tex_coords);
+    return tex_coords - view_dir.xy * (depth * properties.parallax_scale);
+  }
+  if (properties.parallax_mode == 2u) {
+    // More layers when looking at the surface at the grazing angle
+    let layers = mix(32.0, 8.0, abs(view_dir.z));
+    let layer_depth = 1.0 / layers;
+    let delta = view_dir.xy / max(view_dir.z, 0.05) * properties.parallax_scale / layers;
+
+    var current_tex_coords = tex_coords;
+    var current_layer_depth = 0.0;
+    var current_depth = sample_depth(current_tex_coords);
+    for (var i = 0; i < 32 && current_layer_depth < current_depth; i = i + 1) {
+      current_tex_coords = current_tex_coords - delta;
+      current_depth = sample_depth(current_tex_coords);
+      current_layer_depth = current_layer_depth + layer_depth;
+    }
+
+    // Interpolate between the layers before and after the intersection
+    let prev_tex_coords = current_tex_coords + delta;
+    let after = current_depth - current_layer_depth;
+    let before = sample_depth(prev_tex_coords) - current_layer_depth + layer_depth;
+    // Equal depths at both layers would divide by zero, current layer is exact enough then
+    let difference = after - before;
+    let weight = select(after / difference, 0.0, abs(difference) < 1e-5);
+    return mix(current_tex_coords, prev_tex_coords, weight);
+  }
+  return tex_coords;
+}
+
+// Octahedral normal encoding. Packs unit vector into 2 components in [-1, 1] range.
+fn oct_wrap(v: vec2<f32>) -> vec2<f32> {
+  return (1.0 - abs(v.yx)) * select(vec2<f32>(-1.0), vec2<f32>(1.0), v >= vec2<f32>(0.0));
+}
+
+fn encode_normal(n: vec3<f32>) -> vec2<f32> {
+  let p = n.xy / (abs(n.x) + abs(n.y) + abs(n.z));
+  if (n.z < 0.0) {
+    return oct_wrap(p);
+  }
+  return p;
+}
+
+// Screen space motion since the previous frame in texture coordinates
+fn velocity(curr_clip: vec4<f32>, prev_clip: vec4<f32>) -> vec2<f32> {
+  let curr_ndc = curr_clip.xy / curr_clip.w;
+  let prev_ndc = prev_clip.xy / prev_clip.w;
+  return (curr_ndc - prev_ndc) * vec2<f32>(0.5, -0.5);
+}
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
 
 struct FragmentOut {
   @location(0) position: vec4<f32>,
-  @location(1) normal: vec4<f32>,
+  @location(1) normal: vec2<f32>,
   @location(2) albedo: vec4<f32>,
+  @location(3) velocity: vec2<f32>,
 };
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> FragmentOut {
-  let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, vertex.tex_coords);
-
   let tangent_to_world_matrix = mat3x3<f32>(
     vertex.world_tangent,
     vertex.world_bitangent,
     vertex.world_normal,
   );
 
-  let object_normal: vec4<f32> = textureSample(t_normal, s_normal, vertex.tex_coords);
-  let world_object_normal = tangent_to_world_matrix * object_normal.xyz;
-  let normal = vec4<f32>(world_object_normal, 1.0);
-  
-  let albedo = vec4<f32>(object_color.rgb, properties.shininess.r);
+  // Transpose is the inverse for the orthonormal basis
+  let world_view_dir = normalize(camera.position - vertex.world_position.xyz);
+  let tangent_view_dir = normalize(transpose(tangent_to_world_matrix) * world_view_dir);
+  let tex_coords = parallax_tex_coords(vertex.tex_coords, tangent_view_dir);
+
+  let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, tex_coords);
+
+  // Normal texture stores tangent space normal in [0, 1] range
+  var object_normal = textureSample(t_normal, s_normal, tex_coords).xyz * 2.0 - 1.0;
+  if (properties.flip_normal_y != 0u) {
+    object_normal.y = -object_normal.y;
+  }
+  let world_object_normal = tangent_to_world_matrix * object_normal;
+  let normal = encode_normal(normalize(world_object_normal));
+
+  let albedo = vec4<f32>(object_color.rgb, properties.shininess / MAX_SHININESS);
 
   var out: FragmentOut;
   out.position = vertex.world_position;
   out.normal = normal;
   out.albedo = albedo;
+  out.velocity = velocity(vertex.curr_clip_position, vertex.prev_clip_position);
 
   return out; 
 }
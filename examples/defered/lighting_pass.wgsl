@@ -1,22 +1,19 @@
 // Vertex shader
 
-struct VertexInput {
-  @location(0) position: vec3<f32>,
-  @location(1) tex_coords: vec2<f32>,
-};
-
 struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
   @location(0) tex_coords: vec2<f32>,
 };
 
+// Fullscreen triangle without vertex buffer
 @vertex
 fn vs_main(
-  vertex: VertexInput,
+  @builtin(vertex_index) vertex_index: u32,
 ) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
   var out: VertexOutput;
-  out.clip_position = vec4<f32>(vertex.position, 1.0);
-  out.tex_coords = vertex.tex_coords;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
   return out;
 }
 
@@ -33,23 +30,19 @@ var s_shadow: sampler;
 @group(3) @binding(2)
 var<uniform> d_light: ShadowDLightUniform;
 
-struct CameraUniform {
-  view: mat4x4<f32>,
-  projection: mat4x4<f32>,
-  view_projection: mat4x4<f32>,
-  view_projection_inverse: mat4x4<f32>,
-  view_projection_without_translation: mat4x4<f32>,
-  position: vec3<f32>,
-};
+#include "camera.wgsl"
 @group(2) @binding(0)
 var<uniform> camera: CameraUniform;
 
 struct LightUniform {
   position: vec3<f32>,
   color: vec3<f32>,
+  // Matches padding of the `PointLightUniform`
+  _pad: f32,
   a_constant: f32,
   a_linear: f32,
   a_quadratic: f32,
+  intensity: f32,
 };
 
 struct Lights {
@@ -57,7 +50,21 @@ struct Lights {
   lights: array<LightUniform>,
 };
 
+struct LightTilesUniform {
+  tile_size: u32,
+  max_lights_per_tile: u32,
+  tiles_x: u32,
+  tiles_y: u32,
+  width: u32,
+  height: u32,
+  attenuation_cutoff: f32,
+};
+
 @group(1) @binding(0)
+var<uniform> light_tiles: LightTilesUniform;
+@group(1) @binding(1)
+var<storage, read> tiles: array<u32>;
+@group(1) @binding(2)
 var<storage, read> lights: Lights;
 
 @group(0) @binding(0)
@@ -73,6 +80,18 @@ var t_albedo: texture_2d<f32>;
 @group(0) @binding(5)
 var s_albedo: sampler;
 
+// Decodes normal packed by `encode_normal` in the geometry pass
+fn decode_normal(e: vec2<f32>) -> vec3<f32> {
+  var n = vec3<f32>(e, 1.0 - abs(e.x) - abs(e.y));
+  let t = max(-n.z, 0.0);
+  n.x = n.x + select(t, -t, n.x >= 0.0);
+  n.y = n.y + select(t, -t, n.y >= 0.0);
+  return normalize(n);
+}
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
+
 fn shadow_calculations(frag_pos_light_space: vec4<f32>, bias: f32) -> f32 {
   // XY is in (-1, 1) space, Z is in (0, 1) space
   let proj_coords = (frag_pos_light_space.xyz / frag_pos_light_space.w).xyz;
@@ -103,31 +122,37 @@ fn shadow_calculations(frag_pos_light_space: vec4<f32>, bias: f32) -> f32 {
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
   let vertex_position: vec4<f32> = textureSample(t_position, s_position, vertex.tex_coords);
-  let vertex_normal: vec4<f32> = textureSample(t_normal, s_normal, vertex.tex_coords);
+  let vertex_normal = decode_normal(textureSample(t_normal, s_normal, vertex.tex_coords).xy);
   let vertex_albedo: vec4<f32> = textureSample(t_albedo, s_albedo, vertex.tex_coords);
 
   let albedo_color = vertex_albedo.rgb;
-  let shininess = vertex_albedo.a;
+  let shininess = vertex_albedo.a * MAX_SHININESS;
 
   let pos_in_light = d_light.view_projection * vertex_position;
 
+  // Only lights binned into the tile of this pixel
+  let tile_coords = vec2<u32>(vertex.clip_position.xy) / light_tiles.tile_size;
+  let tile = (tile_coords.y * light_tiles.tiles_x + tile_coords.x) * (light_tiles.max_lights_per_tile + 1u);
+  let tile_lights = tiles[tile];
+
   var result: vec3<f32> = vec3<f32>(0.0, 0.0, 0.0); 
-  for(var i: i32 = 0; i < lights.lights_num; i = i + 1) {
+  for(var t: u32 = 0u; t < tile_lights; t = t + 1u) {
+    let i = tiles[tile + 1u + t];
     let distance = distance(lights.lights[i].position, vertex_position.xyz);
-    let attenuation = 1.0 / (lights.lights[i].a_constant + lights.lights[i].a_linear * distance + 
+    let attenuation = lights.lights[i].intensity / (lights.lights[i].a_constant + lights.lights[i].a_linear * distance + 
                       lights.lights[i].a_quadratic * (distance * distance));  
 
     let light_dir = normalize(lights.lights[i].position - vertex_position.xyz);
     let view_dir = normalize(camera.position - vertex_position.xyz);
     let half_dir = normalize(view_dir + light_dir);
 
-    let diffuse_strength = max(dot(vertex_normal.xyz, light_dir), 0.0);
+    let diffuse_strength = max(dot(vertex_normal, light_dir), 0.0);
     let diffuse_color = albedo_color * lights.lights[i].color * diffuse_strength;
 
-    let specular_strength = pow(max(dot(vertex_normal.xyz, half_dir), 0.0), shininess);
+    let specular_strength = pow(max(dot(vertex_normal, half_dir), 0.0), shininess);
     let specular_color = lights.lights[i].color * specular_strength;
     
-    let bias = max(0.001 * (1.0 - dot(vertex_normal.xyz, light_dir)), 0.0001);
+    let bias = max(0.001 * (1.0 - dot(vertex_normal, light_dir)), 0.0001);
     let shadow = shadow_calculations(pos_in_light, bias);
 
     result = (1.0 - shadow) * (result + (diffuse_color + specular_color) * attenuation);
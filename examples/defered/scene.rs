@@ -1,4 +1,3 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -18,12 +17,16 @@ impl FpsLogger {
         }
     }
 
-    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration) {
+    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration, stats: RenderStats) {
         if 1.0 <= (now - self.last_log).as_secs_f32() {
             println!(
-                "Frame time: {:.2}ms(FPS: {:.2})",
+                "Frame time: {:.2}ms(FPS: {:.2}) draw calls: {} triangles: {} pipeline switches: {} bind group switches: {}",
                 dt.as_secs_f64() * 1000.0,
-                1.0 / dt.as_secs_f64()
+                1.0 / dt.as_secs_f64(),
+                stats.draw_calls,
+                stats.triangles,
+                stats.pipeline_switches,
+                stats.bind_group_switches,
             );
             self.last_log = now;
         }
@@ -36,20 +39,10 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<MaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ColorMaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<GBufferBindGroup>(&renderer);
-    storage.register_bind_group_layout::<PointLightBindGroup>(&renderer);
-    storage.register_bind_group_layout::<PointLightsBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowMapBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowMapDLightBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowBindGroup>(&renderer);
-    storage.register_bind_group_layout::<SkyboxBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TransformBindGroup>(&renderer);
+    let g_buffer = GBuffer::compact().with_velocity();
 
     let g_pipeline = PipelineBuilder {
         shader_path: "./examples/defered/geometry_pass.wgsl",
@@ -57,31 +50,15 @@ fn main() {
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<MaterialBindGroup>(),
-                storage.get_bind_group_layout::<TransformBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
+                &storage.get_bind_group_layout::<MaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
         vertex_layouts: &[MeshVertex::layout()],
         vertex_entry_point: "vs_main",
-        color_targets: Some(&[
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-        ]),
+        color_targets: Some(&g_buffer.color_targets()),
         fragment_entry_point: "fs_main",
         primitive: PrimitiveState {
             topology: PrimitiveTopology::TriangleList,
@@ -111,31 +88,15 @@ fn main() {
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<ColorMaterialBindGroup>(),
-                storage.get_bind_group_layout::<TransformBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
         vertex_layouts: &[MeshVertex::layout()],
         vertex_entry_point: "vs_main",
-        color_targets: Some(&[
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-            Some(ColorTargetState {
-                format: TextureFormat::Rgba32Float,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            }),
-        ]),
+        color_targets: Some(&g_buffer.color_targets()),
         fragment_entry_point: "fs_main",
         primitive: PrimitiveState {
             topology: PrimitiveTopology::TriangleList,
@@ -159,18 +120,52 @@ fn main() {
     .build(&renderer);
     let g_color_pipeline_id = storage.insert_pipeline(g_color_pipeline);
 
+    let g_instanced_pipeline = PipelineBuilder {
+        shader_path: "./examples/defered/geometry_instanced_pass.wgsl",
+        label: Some("g_instanced_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&storage.get_bind_group_layout::<CameraBindGroup>(&renderer)],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[MeshVertex::layout(), InstanceData::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&g_buffer.color_targets()),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let g_instanced_pipeline_id = storage.insert_pipeline(g_instanced_pipeline);
+
     let shadow_map_pipeline = PipelineBuilder {
         shader_path: "./examples/defered/shadow_map.wgsl",
         label: Some("shadow_map_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<TransformBindGroup>(),
-                storage.get_bind_group_layout::<ShadowMapDLightBindGroup>(),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<ShadowMapDLightBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
-        vertex_layouts: &[MeshVertex::layout()],
+        vertex_layouts: &[MeshVertex::position_only_layout()],
         vertex_entry_point: "vs_main",
         color_targets: None,
         fragment_entry_point: "fs_main",
@@ -202,17 +197,17 @@ fn main() {
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<GBufferBindGroup>(),
-                storage.get_bind_group_layout::<PointLightsBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<ShadowBindGroup>(),
+                &storage.get_bind_group_layout::<GBufferBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<LightTilesBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<ShadowBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
-        vertex_layouts: &[TextureVertex::layout()],
+        vertex_layouts: &[],
         vertex_entry_point: "vs_main",
         color_targets: Some(&[Some(ColorTargetState {
-            format: renderer.surface_format(),
+            format: SSR_COLOR_FORMAT,
             blend: None,
             write_mask: ColorWrites::ALL,
         })]),
@@ -233,14 +228,55 @@ fn main() {
     .build(&renderer);
     let lighting_pipeline_id = storage.insert_pipeline(lighting_pipeline);
 
+    let ssr_pipeline = PipelineBuilder {
+        shader_path: "./examples/defered/ssr.wgsl",
+        label: Some("ssr_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<GBufferBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<SsrBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<BlitSourceBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: if renderer.needs_manual_gamma() {
+            "fs_main_gamma"
+        } else {
+            "fs_main"
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let ssr_pipeline_id = storage.insert_pipeline(ssr_pipeline);
+
     let skybox_pipeline = PipelineBuilder {
         shader_path: "./examples/defered/skybox.wgsl",
         label: Some("skybox_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<SkyboxBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
+                &storage.get_bind_group_layout::<SkyboxBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -251,7 +287,11 @@ fn main() {
             blend: None,
             write_mask: ColorWrites::ALL,
         })]),
-        fragment_entry_point: "fs_main",
+        fragment_entry_point: if renderer.needs_manual_gamma() {
+            "fs_main_gamma"
+        } else {
+            "fs_main"
+        },
         primitive: PrimitiveState {
             topology: PrimitiveTopology::TriangleList,
             strip_index_format: None,
@@ -274,88 +314,135 @@ fn main() {
     .build(&renderer);
     let skybox_pipeline_id = storage.insert_pipeline(skybox_pipeline);
 
-    let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
-    let shadow_map_handle =
-        ShadowMapHandle::new(&mut storage, ShadowMap::default().build(&renderer));
+    let debug_view_pipeline = PipelineBuilder {
+        shader_path: "./examples/defered/debug_view.wgsl",
+        label: Some("debug_view_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<GBufferBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<DebugViewPassBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<FloatDepthTextureBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<FloatDepthTextureBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let debug_view_pipeline_id = storage.insert_pipeline(debug_view_pipeline);
+
+    let depth_texture_id =
+        storage.insert_texture(EmptyTexture::new_depth_sampled().build(&renderer));
+    // Nearest sampler, so debug view can bind it with `FloatDepthTextureBindGroup`
+    let shadow_map = ShadowMap {
+        shadow_map: EmptyTexture::new_depth_sampled(),
+    };
+    let shadow_map_handle = ShadowMapHandle::new(&mut storage, shadow_map.build(&renderer));
 
-    let g_buffer = GBuffer::new(TextureFormat::Rgba32Float);
     let g_buffer_handle = GBufferHandle::new(&mut storage, g_buffer.build(&renderer));
     let g_buffer_bind_group = GBufferBindGroup::new(&renderer, &mut storage, &g_buffer_handle);
 
-    let geometry_phase = RenderPhase::new(
-        const_vec![
-            ColorAttachment {
-                view_id: g_buffer_handle.position_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: StoreOp::Store,
-                },
-            },
-            ColorAttachment {
-                view_id: g_buffer_handle.normal_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: StoreOp::Store,
-                },
-            },
-            ColorAttachment {
-                view_id: g_buffer_handle.albedo_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: StoreOp::Store,
-                },
-            },
-        ],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
-    );
+    let ssr = Ssr::default();
+    let ssr_handle = SsrHandle::new(&mut storage, ssr.build(&renderer));
+    let ssr_bind_group = SsrBindGroup::new(&renderer, &mut storage, &ssr_handle);
 
-    let shadow_phase = RenderPhase::new(
-        const_vec![],
-        Some(DepthStencil {
-            view_id: shadow_map_handle.texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
+    let ssr_color_handle = SsrColorHandle::new(&mut storage, SsrColor.build(&renderer));
+    let ssr_color_source = BlitSourceHandle {
+        texture_id: ssr_color_handle.texture_id,
+    };
+    let ssr_color_bind_group = BlitSourceBindGroup::new(&renderer, &mut storage, &ssr_color_source);
+
+    // Lit scene is resolved with TAA before SSR samples reflections from it
+    let taa = Taa::default();
+    let taa_handle = TaaHandle::new(&mut storage, taa.build(&renderer));
+    let taa_bind_group = TaaBindGroup::new(&renderer, &mut storage, &taa_handle);
+    let taa_pipeline = TaaPipeline::new(&renderer, &mut storage);
+    let mut taa_history = TaaHistoryTargets::new(&renderer, &mut storage);
+    let velocity_bind_group = GBufferVelocityBindGroup::new(
+        &renderer,
+        &mut storage,
+        &g_buffer_handle.velocity().unwrap(),
     );
 
-    let lighting_phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(Color::BLACK),
-                store: StoreOp::Store,
-            },
-        }],
-        None,
+    let mut debug_view = DebugViewPass {
+        view: DebugView::Final,
+        znear: 0.1,
+        zfar: 100.0,
+    };
+    let debug_view_handle = DebugViewPassHandle::new(&mut storage, debug_view.build(&renderer));
+    let debug_view_bind_group =
+        DebugViewPassBindGroup::new(&renderer, &mut storage, &debug_view_handle);
+    let depth_texture_handle = DepthTextureHandle {
+        texture_id: depth_texture_id,
+    };
+    let depth_texture_bind_group =
+        FloatDepthTextureBindGroup::new(&renderer, &mut storage, &depth_texture_handle);
+    let shadow_map_bind_group = FloatDepthTextureBindGroup::new(
+        &renderer,
+        &mut storage,
+        &DepthTextureHandle {
+            texture_id: shadow_map_handle.texture_id,
+        },
     );
 
-    let skybox_phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: StoreOp::Store,
-            },
-        }],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
-    );
+    let geometry_phase = RenderPhase::builder()
+        .color(g_buffer_handle.position_texture_id)
+        .clear(Color::TRANSPARENT)
+        .color(g_buffer_handle.normal_texture_id)
+        .clear(Color::TRANSPARENT)
+        .color(g_buffer_handle.albedo_texture_id)
+        .clear(Color::TRANSPARENT)
+        .color(g_buffer_handle.velocity_texture_id.unwrap())
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
+
+    let shadow_phase = RenderPhase::builder()
+        .depth(shadow_map_handle.texture_id)
+        .clear(1.0)
+        .build();
+
+    let lighting_phase = RenderPhase::builder()
+        .color(ssr_color_handle.texture_id)
+        .clear(Color::BLACK)
+        .build();
+
+    let ssr_phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::BLACK)
+        .build();
+
+    let debug_view_phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::BLACK)
+        .build();
+
+    let skybox_phase = RenderPhase::builder()
+        .color_load(TextureId::WINDOW_VIEW_ID)
+        // Skybox is drawn only where geometry left the depth cleared
+        .depth_read_only(depth_texture_id)
+        .build();
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
         position: (-10.0, 2.0, 0.0).into(),
@@ -372,25 +459,33 @@ fn main() {
     let mut camera_controller = CameraController::new(5.0, 0.7);
 
     let light = PointLight::new((-1.0, 9.0, 5.0), (1.0, 1.0, 1.0), 1.0, 0.109, 0.032);
-    let light_2 = PointLight::new((-2.0, 1.0, -2.0), (0.8, 0.1, 0.1), 1.0, 0.109, 0.032);
-    let light_3 = PointLight::new((-2.0, 1.0, 0.0), (0.1, 0.8, 0.1), 1.0, 0.209, 0.032);
-    let light_4 = PointLight::new((-2.0, 1.0, 2.0), (0.1, 0.1, 0.8), 1.0, 0.209, 0.032);
+    let light_2 = PointLight::with_range((-2.0, 1.0, -2.0), (0.8, 0.1, 0.1), 4.0, 20.0);
+    let light_3 = PointLight::with_range((-2.0, 1.0, 0.0), (0.1, 0.8, 0.1), 4.0, 20.0);
+    let light_4 = PointLight::with_range((-2.0, 1.0, 2.0), (0.1, 0.1, 0.8), 4.0, 20.0);
     let lights = PointLights {
         lights: vec![light, light_2, light_3, light_4],
     };
     let lights_handle = PointLightsHandle::new(&mut storage, lights.build(&renderer));
-    let lights_bind_group = PointLightsBindGroup::new(&renderer, &mut storage, &lights_handle);
-
-    let shadow_d_light = ShadowMapDLight::new(
-        (-2.0, 9.0, 8.0),
-        (1.0, -3.0, -3.0),
-        -10.0,
-        10.0,
-        -10.0,
-        10.0,
-        0.1,
-        8.0,
+
+    let mut light_tiles = LightTiles::new(renderer.size().width, renderer.size().height);
+    let light_tiles_handle = LightTilesHandle::new(&mut storage, light_tiles.build(&renderer));
+    let light_tiles_compute_bind_group = LightTilesComputeBindGroup::new(
+        &renderer,
+        &mut storage,
+        &(light_tiles_handle, lights_handle),
     );
+    let light_tiles_bind_group = LightTilesBindGroup::new(
+        &renderer,
+        &mut storage,
+        &(light_tiles_handle, lights_handle),
+    );
+    let light_tiles_pipeline = LightTilesPipeline::new(&renderer, &mut storage);
+
+    // Shadow bounds follow the camera, scene bounds cover the biggest box
+    let shadow_light_direction = Vector3::new(1.0, -3.0, -3.0);
+    let scene_aabb = Some(([-13.5, -0.5, -7.5], [13.5, 3.5, 7.5]));
+    let shadow_d_light =
+        ShadowMapDLight::fit_to_camera(shadow_light_direction, &camera, scene_aabb);
     let shadow_d_light_handle =
         ShadowMapDLightHandle::new(&mut storage, shadow_d_light.build(&renderer));
     let shadow_d_light_bind_group =
@@ -402,6 +497,18 @@ fn main() {
         &(shadow_map_handle, shadow_d_light_handle),
     );
 
+    let volumetric_light = VolumetricLight {
+        light_direction: shadow_d_light.direction,
+        color: [1.0, 0.9, 0.7],
+        ..Default::default()
+    };
+    let volumetric_light_handle =
+        VolumetricLightHandle::new(&mut storage, volumetric_light.build(&renderer));
+    let volumetric_light_bind_group =
+        VolumetricLightBindGroup::new(&renderer, &mut storage, &volumetric_light_handle);
+    let volumetric_light_pipeline =
+        VolumetricLightPipeline::new(&renderer, &mut storage, SSR_COLOR_FORMAT);
+
     let box_mesh: Mesh = Cube::new(9.0, 1.0, 5.0).into();
     let box_id = storage.insert_mesh(box_mesh.build(&renderer));
 
@@ -426,6 +533,22 @@ fn main() {
     let box2_transform_bind_group =
         TransformBindGroup::new(&renderer, &mut storage, &box2_transform_handle);
 
+    // Row of small cubes with different colors and shininess
+    let small_cubes = Instances {
+        instances: (0..8)
+            .map(|i| {
+                let transform = Transform {
+                    translation: (-3.5 + i as f32, 0.75, -2.0).into(),
+                    rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(i as f32 * 10.0)),
+                    scale: (0.5, 0.5, 0.5).into(),
+                };
+                let t = i as f32 / 7.0;
+                InstanceData::new(&transform, [t, 0.3, 1.0 - t, 1.0], i % 4)
+            })
+            .collect(),
+    };
+    let small_cubes_handle = InstancesHandle::new(&mut storage, small_cubes.build(&renderer));
+
     let grey_material = ColorMaterial {
         ambient: [0.4, 0.4, 0.4],
         diffuse: [0.6, 0.6, 0.6],
@@ -481,15 +604,14 @@ fn main() {
 
     let mut last_render_time = std::time::Instant::now();
     let mut fps_logger = FpsLogger::new();
+    let mut frame = 0;
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => match event {
-                DeviceEvent::MouseMotion { delta } => {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-                _ => {}
-            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -510,38 +632,98 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => {
+                        let key = k.into();
+                        // Number keys 1-6 switch between debug views
+                        if let Some(view) = DebugView::from_key(key) {
+                            debug_view.view = view;
+                            debug_view_handle.update(&renderer, &storage, &debug_view);
+                        }
+                        _ = camera_controller.process_key(key, (*state).into());
+                    }
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
                     storage.replace_texture(
                         depth_texture_id,
-                        EmptyTexture::new_depth().build(&renderer),
+                        EmptyTexture::new_depth_sampled().build(&renderer),
                     );
                     g_buffer_handle.replace(&mut storage, g_buffer.build(&renderer));
                     g_buffer_bind_group.replace(&renderer, &mut storage, &g_buffer_handle);
+                    ssr_color_handle.replace(&mut storage, SsrColor.build(&renderer));
+                    ssr_color_bind_group.replace(&renderer, &mut storage, &ssr_color_source);
+                    velocity_bind_group.replace(
+                        &renderer,
+                        &mut storage,
+                        &g_buffer_handle.velocity().unwrap(),
+                    );
+                    taa_history.resize(&renderer, &mut storage);
+                    depth_texture_bind_group.replace(
+                        &renderer,
+                        &mut storage,
+                        &depth_texture_handle,
+                    );
+                    light_tiles.resize(physical_size.width, physical_size.height);
+                    light_tiles_handle.replace(&mut storage, light_tiles.build(&renderer));
+                    light_tiles_compute_bind_group.replace(
+                        &renderer,
+                        &mut storage,
+                        &(light_tiles_handle, lights_handle),
+                    );
+                    light_tiles_bind_group.replace(
+                        &renderer,
+                        &mut storage,
+                        &(light_tiles_handle, lights_handle),
+                    );
                 }
                 WindowEvent::RedrawRequested => {
                     let now = std::time::Instant::now();
                     let dt = now - last_render_time;
                     last_render_time = now;
 
-                    fps_logger.log(now, dt);
+                    fps_logger.log(now, dt, storage.last_frame_stats());
 
+                    let prev_view_projection = camera.view_projection();
                     camera_controller.update_camera(&mut camera, dt);
-                    camera_handle.update(&renderer, &storage, &camera);
+                    let size = renderer.size();
+                    camera_handle.update_jittered(
+                        &renderer,
+                        &storage,
+                        &camera,
+                        prev_view_projection,
+                        taa.jitter(frame, size.width, size.height),
+                    );
+                    frame = frame.wrapping_add(1);
+                    shadow_d_light_handle.update(
+                        &renderer,
+                        &storage,
+                        &ShadowMapDLight::fit_to_camera(
+                            shadow_light_direction,
+                            &camera,
+                            scene_aabb,
+                        ),
+                    );
 
+                    let prev_cube_transform = cube_transform;
                     cube_transform.rotation = cube_transform.rotation
                         * cgmath::Quaternion::from_axis_angle(
                             cgmath::Vector3::unit_y(),
                             cgmath::Deg(-dt.as_secs_f32() * 30.0),
                         );
-                    cube_transform_handle.update(&renderer, &storage, &cube_transform);
+                    cube_transform_handle.update_with_previous(
+                        &renderer,
+                        &storage,
+                        &cube_transform,
+                        &prev_cube_transform,
+                    );
 
                     let current_frame_context = match renderer.current_frame() {
                         Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                             renderer.resize(None);
                             return;
                         }
@@ -568,10 +750,11 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            grey_material_bind_group.0,
-                            box_transform_bind_group.0,
-                            camera_bind_group.0,
+                            BindGroupMeta::new(0, grey_material_bind_group.0),
+                            BindGroupMeta::new(1, box_transform_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
                         ],
                     };
                     let box2 = MeshRenderCommand {
@@ -580,32 +763,39 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            green_material_bind_group.0,
-                            box2_transform_bind_group.0,
-                            camera_bind_group.0,
+                            BindGroupMeta::new(0, green_material_bind_group.0),
+                            BindGroupMeta::new(1, box2_transform_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
                         ],
                     };
-                    let cube = MeshRenderCommand {
-                        pipeline_id: g_pipeline_id,
-                        mesh_id: cube_model_handler[0].mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            cube_model_handler[0].material_bind_group.0,
-                            cube_transform_bind_group.0,
-                            camera_bind_group.0,
-                        ],
+                    let cube = Model::geometry_commands(
+                        &cube_model_handler,
+                        g_pipeline_id,
+                        cube_transform_bind_group,
+                        camera_bind_group,
+                    );
+
+                    let small_cubes_command = InstancedMeshCommand {
+                        pipeline_id: g_instanced_pipeline_id,
+                        mesh_id: box2_id,
+                        instance_buffer_id: small_cubes_handle.buffer_id,
+                        instance_count: small_cubes.instances.len() as u32,
+                        bind_groups: const_vec![BindGroupMeta::new(0, camera_bind_group.0)],
                     };
 
-                    {
-                        let mut render_pass =
-                            geometry_phase.render_pass(&mut encoder, &current_frame_storage);
-                        for command in [box1, box2, cube] {
-                            command.execute(&mut render_pass, &current_frame_storage);
-                        }
-                    }
+                    let geometry_commands: Vec<_> = [box1, box2].into_iter().chain(cube).collect();
+                    let geometry_job =
+                        |encoder: &mut CommandEncoder,
+                         current_frame_storage: &CurrentFrameStorage| {
+                            let mut render_pass =
+                                geometry_phase.render_pass(encoder, current_frame_storage);
+                            for command in geometry_commands.iter() {
+                                command.execute(&mut render_pass, current_frame_storage);
+                            }
+                            small_cubes_command.execute(&mut render_pass, current_frame_storage);
+                        };
 
                     let box1 = MeshRenderCommand {
                         pipeline_id: shadow_map_pipeline_id,
@@ -613,9 +803,10 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            box_transform_bind_group.0,
-                            shadow_d_light_bind_group.0
+                            BindGroupMeta::new(0, box_transform_bind_group.0),
+                            BindGroupMeta::new(1, shadow_d_light_bind_group.0),
                         ],
                     };
                     let box2 = MeshRenderCommand {
@@ -624,46 +815,90 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            box2_transform_bind_group.0,
-                            shadow_d_light_bind_group.0
+                            BindGroupMeta::new(0, box2_transform_bind_group.0),
+                            BindGroupMeta::new(1, shadow_d_light_bind_group.0),
                         ],
                     };
-                    let cube = MeshRenderCommand {
-                        pipeline_id: shadow_map_pipeline_id,
-                        mesh_id: cube_model_handler[0].mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            cube_transform_bind_group.0,
-                            shadow_d_light_bind_group.0
+                    let cube = Model::shadow_commands(
+                        &cube_model_handler,
+                        shadow_map_pipeline_id,
+                        cube_transform_bind_group,
+                        shadow_d_light_bind_group,
+                    );
+                    let shadow_commands: Vec<_> = [box1, box2].into_iter().chain(cube).collect();
+                    let shadow_job =
+                        |encoder: &mut CommandEncoder,
+                         current_frame_storage: &CurrentFrameStorage| {
+                            let mut render_pass =
+                                shadow_phase.render_pass(encoder, current_frame_storage);
+                            for command in shadow_commands.iter() {
+                                command.execute(&mut render_pass, current_frame_storage);
+                            }
+                        };
+                    // Geometry and shadow phases do not depend on each other
+                    let parallel_commands = encode_parallel(
+                        &renderer,
+                        &current_frame_storage,
+                        &[&geometry_job, &shadow_job],
+                    );
+
+                    light_tiles_pipeline
+                        .command(
+                            &light_tiles,
+                            light_tiles_compute_bind_group,
+                            camera_bind_group,
+                        )
+                        .execute(&mut encoder, &storage);
+
+                    let command = FullscreenTriangle::command(
+                        lighting_pipeline_id,
+                        const_vec![
+                            BindGroupMeta::new(0, g_buffer_bind_group.0),
+                            BindGroupMeta::new(1, light_tiles_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
+                            BindGroupMeta::new(3, shadow_bind_group.0),
                         ],
-                    };
+                    );
+                    let volumetric_light_command = volumetric_light_pipeline.command(
+                        depth_texture_bind_group,
+                        camera_bind_group,
+                        volumetric_light_bind_group,
+                    );
                     {
                         let mut render_pass =
-                            shadow_phase.render_pass(&mut encoder, &current_frame_storage);
-                        for command in [box1, box2, cube] {
-                            command.execute(&mut render_pass, &current_frame_storage);
-                        }
+                            lighting_phase.render_pass(&mut encoder, &current_frame_storage);
+                        command.execute(&mut render_pass, &current_frame_storage);
+                        volumetric_light_command.execute(&mut render_pass, &current_frame_storage);
                     }
 
-                    let command = MeshRenderCommand {
-                        pipeline_id: lighting_pipeline_id,
-                        mesh_id: g_buffer_handle.mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            g_buffer_bind_group.0,
-                            lights_bind_group.0,
-                            camera_bind_group.0,
-                            shadow_bind_group.0,
+                    taa_history.swap();
+                    let command = taa_pipeline.command(
+                        ssr_color_bind_group,
+                        &taa_history,
+                        velocity_bind_group,
+                        taa_bind_group,
+                    );
+                    {
+                        let mut render_pass = taa_history
+                            .current_phase()
+                            .render_pass(&mut encoder, &current_frame_storage);
+                        command.execute(&mut render_pass, &current_frame_storage);
+                    }
+
+                    let command = FullscreenTriangle::command(
+                        ssr_pipeline_id,
+                        const_vec![
+                            BindGroupMeta::new(0, g_buffer_bind_group.0),
+                            BindGroupMeta::new(1, camera_bind_group.0),
+                            BindGroupMeta::new(2, ssr_bind_group.0),
+                            BindGroupMeta::new(3, taa_history.current_bind_group().0),
                         ],
-                    };
+                    );
                     {
                         let mut render_pass =
-                            lighting_phase.render_pass(&mut encoder, &current_frame_storage);
+                            ssr_phase.render_pass(&mut encoder, &current_frame_storage);
                         command.execute(&mut render_pass, &current_frame_storage);
                     }
 
@@ -673,7 +908,11 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
-                        bind_groups: const_vec![skybox_bind_group.0, camera_bind_group.0],
+                        viewport: None,
+                        bind_groups: const_vec![
+                            BindGroupMeta::new(0, skybox_bind_group.0),
+                            BindGroupMeta::new(1, camera_bind_group.0),
+                        ],
                     };
                     {
                         let mut render_pass =
@@ -681,9 +920,29 @@ fn main() {
                         command.execute(&mut render_pass, &current_frame_storage);
                     }
 
+                    if debug_view.view != DebugView::Final {
+                        let command = FullscreenTriangle::command(
+                            debug_view_pipeline_id,
+                            const_vec![
+                                BindGroupMeta::new(0, g_buffer_bind_group.0),
+                                BindGroupMeta::new(1, debug_view_bind_group.0),
+                                BindGroupMeta::new(2, depth_texture_bind_group.0),
+                                BindGroupMeta::new(3, shadow_map_bind_group.0),
+                            ],
+                        );
+                        let mut render_pass =
+                            debug_view_phase.render_pass(&mut encoder, &current_frame_storage);
+                        command.execute(&mut render_pass, &current_frame_storage);
+                    }
+
                     let commands = encoder.finish();
-                    renderer.submit(std::iter::once(commands));
+                    renderer.submit(
+                        parallel_commands
+                            .into_iter()
+                            .chain(std::iter::once(commands)),
+                    );
                     current_frame_context.present();
+                    storage.end_frame();
                 }
                 _ => {}
             },
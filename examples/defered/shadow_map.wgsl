@@ -15,10 +15,6 @@ var<uniform> d_light: ShadowDLightUniform;
 
 struct VertexInput {
   @location(0) position: vec3<f32>,
-  @location(1) tex_coords: vec2<f32>,
-  @location(2) normal: vec3<f32>,
-  @location(3) tangent: vec3<f32>,
-  @location(4) bitangent: vec3<f32>,
 };
 
 struct VertexOutput {
@@ -1,13 +1,6 @@
 // Vertex shader
 
-struct CameraUniform {
-  view: mat4x4<f32>,
-  projection: mat4x4<f32>,
-  view_projection: mat4x4<f32>,
-  view_projection_inverse: mat4x4<f32>,
-  view_projection_without_translation: mat4x4<f32>,
-  position: vec3<f32>,
-};
+#include "camera.wgsl"
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
@@ -40,7 +33,23 @@ var t_cube: texture_cube<f32>;
 @group(0) @binding(1)
 var s_cube: sampler;
 
+// 0-1 sRGB gamma from 0-1 linear
+fn gamma_from_linear_rgb(rgb: vec3<f32>) -> vec3<f32> {
+  let cutoff = rgb < vec3<f32>(0.0031308);
+  let lower = rgb * vec3<f32>(12.92);
+  let higher = vec3<f32>(1.055) * pow(rgb, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+  return select(higher, lower, cutoff);
+}
+
+// Used with sRGB surfaces
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
   return textureSample(t_cube, s_cube, vertex.uv);
 }
+
+// Used with linear surfaces
+@fragment
+fn fs_main_gamma(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let color = textureSample(t_cube, s_cube, vertex.uv);
+  return vec4<f32>(gamma_from_linear_rgb(color.rgb), color.a);
+}
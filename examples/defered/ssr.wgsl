@@ -0,0 +1,142 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_position: texture_2d<f32>;
+@group(0) @binding(1)
+var s_position: sampler;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(3)
+var s_normal: sampler;
+@group(0) @binding(4)
+var t_albedo: texture_2d<f32>;
+@group(0) @binding(5)
+var s_albedo: sampler;
+
+#include "camera.wgsl"
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct SsrUniform {
+  max_steps: u32,
+  thickness: f32,
+  max_distance: f32,
+  roughness_fade: f32,
+};
+@group(2) @binding(0)
+var<uniform> ssr: SsrUniform;
+
+@group(3) @binding(0)
+var t_color: texture_2d<f32>;
+@group(3) @binding(1)
+var s_color: sampler;
+
+fn world_to_uv(position: vec3<f32>) -> vec3<f32> {
+  let clip = camera.view_projection * vec4<f32>(position, 1.0);
+  let ndc = clip.xyz / clip.w;
+  // Y is flipped because texture coords are Y-down.
+  return vec3<f32>(ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5), ndc.z);
+}
+
+// Decodes normal packed by `encode_normal` in the geometry pass
+fn decode_normal(e: vec2<f32>) -> vec3<f32> {
+  var n = vec3<f32>(e, 1.0 - abs(e.x) - abs(e.y));
+  let t = max(-n.z, 0.0);
+  n.x = n.x + select(t, -t, n.x >= 0.0);
+  n.y = n.y + select(t, -t, n.y >= 0.0);
+  return normalize(n);
+}
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
+
+fn view_depth(position: vec3<f32>) -> f32 {
+  return -(camera.view * vec4<f32>(position, 1.0)).z;
+}
+
+fn ssr_color(vertex: VertexOutput) -> vec4<f32> {
+  let color = textureSample(t_color, s_color, vertex.tex_coords);
+  let position = textureSample(t_position, s_position, vertex.tex_coords);
+  let normal = decode_normal(textureSample(t_normal, s_normal, vertex.tex_coords).xy);
+  let shininess = textureSample(t_albedo, s_albedo, vertex.tex_coords).a * MAX_SHININESS;
+
+  // Background has no geometry
+  if (position.w == 0.0) {
+    return color;
+  }
+
+  // Blinn-Phong shininess to roughness approximation
+  let roughness = sqrt(2.0 / (shininess + 2.0));
+  let roughness_fade = 1.0 - clamp(roughness / ssr.roughness_fade, 0.0, 1.0);
+  if (roughness_fade == 0.0) {
+    return color;
+  }
+
+  let view_dir = normalize(position.xyz - camera.position);
+  let reflect_dir = normalize(reflect(view_dir, normal));
+  let step_size = ssr.max_distance / f32(ssr.max_steps);
+
+  var ray = position.xyz;
+  for (var i = 0u; i < ssr.max_steps; i++) {
+    ray = ray + reflect_dir * step_size;
+    let uv = world_to_uv(ray);
+    if (uv.x < 0.0 || 1.0 < uv.x || uv.y < 0.0 || 1.0 < uv.y || uv.z < 0.0 || 1.0 < uv.z) {
+      break;
+    }
+
+    let scene_position = textureSampleLevel(t_position, s_position, uv.xy, 0.0);
+    if (scene_position.w == 0.0) {
+      continue;
+    }
+    let depth_difference = view_depth(ray) - view_depth(scene_position.xyz);
+    if (0.0 < depth_difference && depth_difference < ssr.thickness) {
+      let reflected = textureSampleLevel(t_color, s_color, uv.xy, 0.0);
+      // Fade reflections near screen edges and at the end of the ray
+      let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+      let edge_fade = clamp(edge * 10.0, 0.0, 1.0);
+      let distance_fade = 1.0 - f32(i) / f32(ssr.max_steps);
+      return color + reflected * roughness_fade * edge_fade * distance_fade;
+    }
+  }
+  return color;
+}
+
+// 0-1 sRGB gamma from 0-1 linear
+fn gamma_from_linear_rgb(rgb: vec3<f32>) -> vec3<f32> {
+  let cutoff = rgb < vec3<f32>(0.0031308);
+  let lower = rgb * vec3<f32>(12.92);
+  let higher = vec3<f32>(1.055) * pow(rgb, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+  return select(higher, lower, cutoff);
+}
+
+// Used with sRGB surfaces
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  return ssr_color(vertex);
+}
+
+// Used with linear surfaces
+@fragment
+fn fs_main_gamma(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let color = ssr_color(vertex);
+  return vec4<f32>(gamma_from_linear_rgb(color.rgb), color.a);
+}
@@ -1,4 +1,4 @@
-use wgpu::{BlendFactor, BlendOperation, StoreOp};
+use wgpu::{BlendFactor, BlendOperation};
 use winit::{
     event::{Event, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -6,9 +6,9 @@ use winit::{
     window::WindowBuilder,
 };
 use zero::{
-    const_vec,
     egui::{EguiBufferBindGroup, EguiRenderContext, EguiTextureBindGroup, EguiVertex},
     prelude::*,
+    text::TextRenderContext,
 };
 
 struct FpsLogger {
@@ -40,20 +40,17 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<EguiBufferBindGroup>(&renderer);
-    storage.register_bind_group_layout::<EguiTextureBindGroup>(&renderer);
-
     let egui_pipeline = PipelineBuilder {
         shader_path: "./examples/egui/egui.wgsl",
         label: Some("egui_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<EguiBufferBindGroup>(),
-                storage.get_bind_group_layout::<EguiTextureBindGroup>(),
+                &storage.get_bind_group_layout::<EguiBufferBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<EguiTextureBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -75,10 +72,10 @@ fn main() {
             }),
             write_mask: ColorWrites::ALL,
         })]),
-        fragment_entry_point: if renderer.surface_format().is_srgb() {
-            "fs_main_linear_framebuffer"
-        } else {
+        fragment_entry_point: if renderer.needs_manual_gamma() {
             "fs_main_gamma_framebuffer"
+        } else {
+            "fs_main_linear_framebuffer"
         },
         primitive: PrimitiveState {
             front_face: FrontFace::Cw,
@@ -92,18 +89,12 @@ fn main() {
     .build(&renderer);
     let egui_pipeline_id = storage.insert_pipeline(egui_pipeline);
 
-    let egui_phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: StoreOp::Store,
-            },
-        }],
-        None,
-    );
+    let egui_phase = RenderPhase::builder()
+        .color_load(TextureId::WINDOW_VIEW_ID)
+        .build();
 
     let mut egui_render_context = EguiRenderContext::new(&renderer, &mut storage);
+    let mut text_render_context = TextRenderContext::new(&renderer, &mut storage);
     let egui_ctx = egui::Context::default();
     let mut winit_egui = egui_winit::State::new(
         egui_ctx.clone(),
@@ -135,8 +126,8 @@ fn main() {
                             },
                         ..
                     } => target.exit(),
-                    WindowEvent::Resized(physical_size) => {
-                        renderer.resize(Some(*physical_size));
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                        renderer.handle_scale_factor_change(window.inner_size());
                     }
                     WindowEvent::RedrawRequested => {
                         let now = std::time::Instant::now();
@@ -169,9 +160,17 @@ fn main() {
                         let clipped = egui_ctx.tessellate(egui_out.shapes, 1.0);
                         egui_render_context.update_meshes(&renderer, &mut storage, &clipped);
 
+                        text_render_context.draw_text(
+                            [10.0, 10.0],
+                            &format!("Frame time: {:.2}ms", dt.as_secs_f64() * 1000.0),
+                            16.0,
+                            [255, 255, 255, 255],
+                        );
+                        text_render_context.update(&renderer, &mut storage);
+
                         let current_frame_context = match renderer.current_frame() {
                             Ok(cfc) => cfc,
-                            Err(SurfaceError::Lost) => {
+                            Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                                 renderer.resize(None);
                                 return;
                             }
@@ -192,8 +191,10 @@ fn main() {
 
                         let mut encoder = renderer.create_encoder();
 
-                        let commands =
+                        let mut commands =
                             egui_render_context.create_commands(egui_pipeline_id, &clipped);
+                        commands
+                            .extend(text_render_context.create_command(egui_pipeline_id, &storage));
 
                         {
                             let mut render_pass =
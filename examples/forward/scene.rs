@@ -1,4 +1,3 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -36,24 +35,18 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<MaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ColorMaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<PointLightBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TransformBindGroup>(&renderer);
-
     let color_pipeline = PipelineBuilder {
         shader_path: "./examples/forward/color.wgsl",
         label: Some("color_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<ColorMaterialBindGroup>(),
-                storage.get_bind_group_layout::<TransformBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -93,10 +86,10 @@ fn main() {
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<MaterialBindGroup>(),
-                storage.get_bind_group_layout::<TransformBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<PointLightBindGroup>(),
+                &storage.get_bind_group_layout::<MaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<PointLightBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -132,22 +125,14 @@ fn main() {
 
     let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
 
-    let phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
+    let mut render_system = RenderSystem::default();
+    let phase_id = render_system.add_phase(
+        RenderPhase::builder()
+            .color(TextureId::WINDOW_VIEW_ID)
+            .clear(Color::TRANSPARENT)
+            .depth(depth_texture_id)
+            .clear(1.0)
+            .build(),
     );
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
@@ -231,12 +216,10 @@ fn main() {
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => match event {
-                DeviceEvent::MouseMotion { delta } => {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-                _ => {}
-            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -257,11 +240,14 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
                     storage.replace_texture(
                         depth_texture_id,
                         EmptyTexture::new_depth().build(&renderer),
@@ -284,39 +270,17 @@ fn main() {
                         );
                     cube_transform_handle.update(&renderer, &storage, &cube_transform);
 
-                    let current_frame_context = match renderer.current_frame() {
-                        Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
-                            renderer.resize(None);
-                            return;
-                        }
-                        Err(SurfaceError::OutOfMemory) => {
-                            target.exit();
-                            return;
-                        }
-                        Err(e) => {
-                            eprintln!("{:?}", e);
-                            return;
-                        }
-                    };
-
-                    let current_frame_storage = CurrentFrameStorage {
-                        storage: &storage,
-                        current_frame_view: current_frame_context.view(),
-                    };
-
-                    let mut encoder = renderer.create_encoder();
-
                     let box1 = MeshRenderCommand {
                         pipeline_id: color_pipeline_id,
                         mesh_id: box_id,
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            grey_material_bind_group.0,
-                            box_transform_bind_group.0,
-                            camera_bind_group.0,
+                            BindGroupMeta::new(0, grey_material_bind_group.0),
+                            BindGroupMeta::new(1, box_transform_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
                         ],
                     };
                     let box2 = MeshRenderCommand {
@@ -325,37 +289,33 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
+                        viewport: None,
                         bind_groups: const_vec![
-                            green_material_bind_group.0,
-                            box2_transform_bind_group.0,
-                            camera_bind_group.0,
+                            BindGroupMeta::new(0, green_material_bind_group.0),
+                            BindGroupMeta::new(1, box2_transform_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
                         ],
                     };
-                    let cube = MeshRenderCommand {
-                        pipeline_id: texture_pipeline_id,
-                        mesh_id: cube_model_handler[0].mesh_id,
-                        index_slice: None,
-                        vertex_slice: None,
-                        scissor_rect: None,
-                        bind_groups: const_vec![
-                            cube_model_handler[0].material_bind_group.0,
+                    let cube = Model::render_commands(
+                        &cube_model_handler,
+                        texture_pipeline_id,
+                        &[
                             cube_transform_bind_group.0,
                             camera_bind_group.0,
                             light_bind_group.0,
                         ],
-                    };
+                    );
 
-                    {
-                        let mut render_pass =
-                            phase.render_pass(&mut encoder, &current_frame_storage);
-                        for command in [box1, box2, cube] {
-                            command.execute(&mut render_pass, &current_frame_storage);
-                        }
+                    render_system
+                        .set_commands(phase_id, [box1, box2].into_iter().chain(cube).collect());
+                    match render_system.run(&renderer, &mut storage) {
+                        Ok(_) => {}
+                        Err(RenderSystemError::Surface(
+                            SurfaceError::Lost | SurfaceError::Outdated,
+                        )) => renderer.resize(None),
+                        Err(RenderSystemError::Surface(SurfaceError::OutOfMemory)) => target.exit(),
+                        Err(e) => eprintln!("{:?}", e),
                     }
-
-                    let commands = encoder.finish();
-                    renderer.submit(std::iter::once(commands));
-                    current_frame_context.present();
                 }
                 _ => {}
             },
@@ -21,9 +21,12 @@ var<uniform> camera: CameraUniform;
 struct LightUniform {
   position: vec3<f32>,
   color: vec3<f32>,
+  // Matches padding of the `PointLightUniform`
+  _pad: f32,
   constant: f32,
   linear: f32,
   quadratic: f32,
+  intensity: f32,
 };
 @group(3) @binding(0)
 var<uniform> light: LightUniform;
@@ -96,7 +99,7 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
   let object_normal: vec4<f32> = textureSample(t_normal, s_normal, vertex.tex_coords);
 
   let distance = distance(vertex.tangent_light, vertex.tangent_position);
-  let attenuation = 1.0 / (light.constant + light.linear * distance + 
+  let attenuation = light.intensity / (light.constant + light.linear * distance + 
     		    light.quadratic * (distance * distance));  
 
   let ambient_strength = 0.1;
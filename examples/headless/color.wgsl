@@ -0,0 +1,74 @@
+// Vertex shader
+
+struct TransformUniform {
+  transform: mat4x4<f32>,
+  rotate: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> transform: TransformUniform;
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(2) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+  @location(3) tangent: vec3<f32>,
+  @location(4) bitangent: vec3<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+) -> VertexOutput {
+  let world_position = transform.transform * vec4<f32>(vertex.position, 1.0);
+
+  var out: VertexOutput;
+  out.clip_position = camera.view_projection * world_position;
+  out.normal = (transform.rotate * vec4<f32>(vertex.normal, 0.0)).xyz;
+  return out;
+}
+
+// Fragment shader
+
+struct MaterialProperties {
+    ambient: vec3<f32>,
+    diffuse: vec3<f32>,
+    specular: vec3<f32>,
+    shininess: f32,
+};
+@group(0) @binding(0)
+var<uniform> properties: MaterialProperties;
+
+struct AmbientLightUniform {
+  color: vec3<f32>,
+  intensity: f32,
+  sky_color: vec3<f32>,
+  ground_color: vec3<f32>,
+};
+@group(3) @binding(0)
+var<uniform> ambient_light: AmbientLightUniform;
+
+fn ambient(normal: vec3<f32>) -> vec3<f32> {
+  let hemisphere = mix(ambient_light.ground_color, ambient_light.sky_color, normal.y * 0.5 + 0.5);
+  return ambient_light.color * ambient_light.intensity * hemisphere;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  return vec4<f32>(properties.diffuse * ambient(normalize(vertex.normal)), 1.0);
+}
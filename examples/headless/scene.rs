@@ -1,232 +1,89 @@
-use zero::prelude::*;
+use zero::{const_vec, prelude::*};
 
 const WIDTH: u32 = 1280;
 const HEIGHT: u32 = 720;
+const FRAMES: usize = 24;
 
 fn main() {
     env_logger::init();
 
-    let renderer = pollster::block_on(Renderer::new(WIDTH, HEIGHT));
-    let mut render_system = RenderSystem::default();
+    let renderer = pollster::block_on(Renderer::new_headless(WIDTH, HEIGHT));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<MaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ColorMaterialBindGroup>(&renderer);
-    storage.register_bind_group_layout::<GBufferBindGroup>(&renderer);
-    storage.register_bind_group_layout::<PointLightBindGroup>(&renderer);
-    storage.register_bind_group_layout::<PointLightsBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowMapBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowMapDLightBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ShadowBindGroup>(&renderer);
-    storage.register_bind_group_layout::<SkyboxBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TransformBindGroup>(&renderer);
-
-    let g_pipeline = PipelineBuilder {
-        bind_group_layouts: vec![
-            storage.get_bind_group_layout::<MaterialBindGroup>(),
-            storage.get_bind_group_layout::<TransformBindGroup>(),
-            storage.get_bind_group_layout::<CameraBindGroup>(),
-        ],
-        vertex_layouts: vec![MeshVertex::layout()],
-        shader_path: "./shaders/geometry_pass.wgsl",
-        write_depth: true,
-        color_targets: Some(vec![TextureFormat::Rgba32Float; 3]),
-        ..Default::default()
-    }
-    .build(&renderer);
-    let g_pipeline_id = storage.insert_pipeline(g_pipeline);
-
-    let g_color_pipeline = PipelineBuilder {
-        bind_group_layouts: vec![
-            storage.get_bind_group_layout::<ColorMaterialBindGroup>(),
-            storage.get_bind_group_layout::<TransformBindGroup>(),
-            storage.get_bind_group_layout::<CameraBindGroup>(),
-        ],
-        vertex_layouts: vec![MeshVertex::layout()],
-        shader_path: "./shaders/geometry_color_pass.wgsl",
-        write_depth: true,
-        color_targets: Some(vec![TextureFormat::Rgba32Float; 3]),
-        ..Default::default()
-    }
-    .build(&renderer);
-    let g_color_pipeline_id = storage.insert_pipeline(g_color_pipeline);
-
-    let shadow_map_pipeline = PipelineBuilder {
-        bind_group_layouts: vec![
-            storage.get_bind_group_layout::<TransformBindGroup>(),
-            storage.get_bind_group_layout::<ShadowMapDLightBindGroup>(),
-        ],
-        vertex_layouts: vec![MeshVertex::layout()],
-        shader_path: "./shaders/shadow_map.wgsl",
-        write_depth: true,
-        cull_mode: Face::Front,
-        ..Default::default()
-    }
-    .build(&renderer);
-    let shadow_map_pipeline_id = storage.insert_pipeline(shadow_map_pipeline);
-
-    let lighting_pipeline = PipelineBuilder {
-        bind_group_layouts: vec![
-            storage.get_bind_group_layout::<GBufferBindGroup>(),
-            storage.get_bind_group_layout::<PointLightsBindGroup>(),
-            storage.get_bind_group_layout::<CameraBindGroup>(),
-            storage.get_bind_group_layout::<ShadowBindGroup>(),
-        ],
-        vertex_layouts: vec![TextureVertex::layout()],
-        shader_path: "./shaders/lighting_pass.wgsl",
-        depth_enabled: false,
-        color_targets: Some(vec![renderer.surface_format()]),
-        ..Default::default()
-    }
-    .build(&renderer);
-    let lighting_pipeline_id = storage.insert_pipeline(lighting_pipeline);
-
-    let skybox_pipeline = PipelineBuilder {
-        bind_group_layouts: vec![
-            storage.get_bind_group_layout::<SkyboxBindGroup>(),
-            storage.get_bind_group_layout::<CameraBindGroup>(),
-        ],
-        vertex_layouts: vec![SkyboxVertex::layout()],
-        shader_path: "./shaders/skybox.wgsl",
-        write_depth: false,
-        color_targets: Some(vec![renderer.surface_format()]),
-        ..Default::default()
-    }
-    .build(&renderer);
-    let skybox_pipeline_id = storage.insert_pipeline(skybox_pipeline);
-
-    let depth_texture_id = storage.insert_texture(EmptyTexture::default().build(&renderer));
-    let shadow_map_handle =
-        ShadowMapHandle::new(&mut storage, ShadowMap::default().build(&renderer));
-
-    let g_buffer = GBuffer::new(TextureFormat::Rgba32Float);
-    let g_buffer_handle = GBufferHandle::new(&mut storage, g_buffer.build(&renderer));
-    let g_buffer_bind_group = GBufferBindGroup::new(&renderer, &mut storage, &g_buffer_handle);
-
-    let geometry_phase = RenderPhase::new(
-        vec![
-            ColorAttachment {
-                view_id: g_buffer_handle.position_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: true,
-                },
-            },
-            ColorAttachment {
-                view_id: g_buffer_handle.normal_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: true,
-                },
-            },
-            ColorAttachment {
-                view_id: g_buffer_handle.albedo_texture_id,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: true,
-                },
-            },
-        ],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: true,
-            }),
-            stencil_ops: None,
+    let color_pipeline = PipelineBuilder {
+        shader_path: "./examples/headless/color.wgsl",
+        label: Some("color_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<DynamicTransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<AmbientLightBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
         }),
-    );
-    render_system.add_phase("geometry", geometry_phase);
-
-    let shadow_phase = RenderPhase::new(
-        vec![],
-        Some(DepthStencil {
-            view_id: shadow_map_handle.texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: true,
-            }),
-            stencil_ops: None,
-        }),
-    );
-    render_system.add_phase("shadow", shadow_phase);
-
-    let lighting_phase = RenderPhase::new(
-        vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(Color::BLACK),
-                store: true,
-            },
-        }],
-        None,
-    );
-    render_system.add_phase("lighting", lighting_phase);
-
-    let skybox_phase = RenderPhase::new(
-        vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: true,
-            },
-        }],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: true,
-            }),
-            stencil_ops: None,
+        vertex_layouts: &[MeshVertex::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
         }),
-    );
-
-    render_system.add_phase("skybox", skybox_phase);
-
-    let camera = Camera::new(
-        (-10.0, 2.0, 0.0),
-        Deg(0.0),
-        Deg(0.0),
-        renderer.size().width,
-        renderer.size().height,
-        Deg(90.0),
-        0.1,
-        100.0,
-    );
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let color_pipeline_id = storage.insert_pipeline(color_pipeline);
+
+    let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
+
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::BLACK)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
+
+    let mut camera = Camera::Perspective(PerspectiveCamera {
+        position: (-10.0, 2.0, 0.0).into(),
+        yaw: Deg(0.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: renderer.size().width as f32 / renderer.size().height as f32,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
     let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
     let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
 
-    let light = PointLight::new((-1.0, 9.0, 5.0), (1.0, 1.0, 1.0), 1.0, 0.109, 0.032);
-    let light_2 = PointLight::new((-2.0, 1.0, -2.0), (0.8, 0.1, 0.1), 1.0, 0.109, 0.032);
-    let light_3 = PointLight::new((-2.0, 1.0, 0.0), (0.1, 0.8, 0.1), 1.0, 0.209, 0.032);
-    let light_4 = PointLight::new((-2.0, 1.0, 2.0), (0.1, 0.1, 0.8), 1.0, 0.209, 0.032);
-    let lights = PointLights {
-        lights: vec![light, light_2, light_3, light_4],
+    // Only light of the scene, lit from the sky
+    let ambient_light = AmbientLight {
+        sky_color: (1.0, 1.0, 0.95).into(),
+        ground_color: (0.3, 0.3, 0.4).into(),
+        ..AmbientLight::new((1.0, 1.0, 1.0), 1.0)
     };
-    let lights_handle = PointLightsHandle::new(&mut storage, lights.build(&renderer));
-    let lights_bind_group = PointLightsBindGroup::new(&renderer, &mut storage, &lights_handle);
-
-    let shadow_d_light = ShadowMapDLight::new(
-        (-2.0, 9.0, 8.0),
-        (1.0, -3.0, -3.0),
-        -10.0,
-        10.0,
-        -10.0,
-        10.0,
-        0.1,
-        8.0,
-    );
-    let shadow_d_light_handle =
-        ShadowMapDLightHandle::new(&mut storage, shadow_d_light.build(&renderer));
-    let shadow_d_light_bind_group =
-        ShadowMapDLightBindGroup::new(&renderer, &mut storage, &shadow_d_light_handle);
-
-    let shadow_bind_group = ShadowBindGroup::new(
-        &renderer,
-        &mut storage,
-        &(shadow_map_handle, shadow_d_light_handle),
-    );
+    let ambient_light_handle =
+        AmbientLightHandle::new(&mut storage, ambient_light.build(&renderer));
+    let ambient_light_bind_group =
+        AmbientLightBindGroup::new(&renderer, &mut storage, &ambient_light_handle);
 
     let box_mesh: Mesh = Cube::new(9.0, 1.0, 5.0).into();
     let box_id = storage.insert_mesh(box_mesh.build(&renderer));
@@ -236,9 +93,6 @@ fn main() {
         rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
         scale: (3.0, 1.0, 3.0).into(),
     };
-    let box_transform_handle = TransformHandle::new(&mut storage, box_transform.build(&renderer));
-    let box_transform_bind_group =
-        TransformBindGroup::new(&renderer, &mut storage, &box_transform_handle);
 
     let box2_mesh: Mesh = Cube::new(1.0, 1.0, 1.0).into();
     let box2_id = storage.insert_mesh(box2_mesh.build(&renderer));
@@ -248,9 +102,12 @@ fn main() {
         rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
         scale: (1.0, 1.0, 1.0).into(),
     };
-    let box2_transform_handle = TransformHandle::new(&mut storage, box2_transform.build(&renderer));
-    let box2_transform_bind_group =
-        TransformBindGroup::new(&renderer, &mut storage, &box2_transform_handle);
+
+    // Both transforms share one buffer and bind group
+    let transforms = Transforms(vec![box_transform, box2_transform]);
+    let transforms_handle = TransformsHandle::new(&mut storage, transforms.build(&renderer));
+    let transforms_bind_group =
+        DynamicTransformBindGroup::new(&renderer, &mut storage, &transforms_handle);
 
     let grey_material = ColorMaterial {
         ambient: [0.4, 0.4, 0.4],
@@ -274,102 +131,78 @@ fn main() {
     let green_material_bind_group =
         ColorMaterialBindGroup::new(&renderer, &mut storage, &green_material_handle);
 
-    let cube_model = Model::load("./res/cube/cube.obj").unwrap();
-    let (cube_model_handler, _cube_model_materials) = cube_model.build(&renderer, &mut storage);
-
-    let cube_transform = Transform {
-        translation: (2.0, 2.0, 4.0).into(),
-        rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(69.0)),
-        scale: (1.0, 1.0, 1.0).into(),
-    };
-    let cube_transform_handle = TransformHandle::new(&mut storage, cube_transform.build(&renderer));
-    let cube_transform_bind_group =
-        TransformBindGroup::new(&renderer, &mut storage, &cube_transform_handle);
-
-    let skybox = Skybox::load([
-        "./res/skybox/right.jpg",
-        "./res/skybox/left.jpg",
-        "./res/skybox/top.jpg",
-        "./res/skybox/bottom.jpg",
-        "./res/skybox/front.jpg",
-        "./res/skybox/back.jpg",
-    ])
-    .unwrap();
-    let skybox_handle = SkyboxHandle::new(&mut storage, skybox.build(&renderer));
-    let skybox_bind_group = SkyboxBindGroup::new(&renderer, &mut storage, &skybox_handle);
-
-    let box1 = RenderCommand::new(
-        g_color_pipeline_id,
-        box_id,
-        vec![
-            grey_material_bind_group.0,
-            box_transform_bind_group.0,
-            camera_bind_group.0,
-        ],
-    );
-    let box2 = RenderCommand::new(
-        g_color_pipeline_id,
-        box2_id,
-        vec![
-            green_material_bind_group.0,
-            box2_transform_bind_group.0,
-            camera_bind_group.0,
-        ],
-    );
-    let cube = RenderCommand::new(
-        g_pipeline_id,
-        cube_model_handler[0].mesh_id,
-        vec![
-            cube_model_handler[0].material_bind_group.0,
-            cube_transform_bind_group.0,
-            camera_bind_group.0,
+    let box1 = MeshRenderCommand {
+        pipeline_id: color_pipeline_id,
+        mesh_id: box_id,
+        index_slice: None,
+        vertex_slice: None,
+        scissor_rect: None,
+        viewport: None,
+        bind_groups: const_vec![
+            BindGroupMeta::new(0, grey_material_bind_group.0),
+            BindGroupMeta::with_dynamic_offset(
+                1,
+                transforms_bind_group.0,
+                transforms_handle.offset(0)
+            ),
+            BindGroupMeta::new(2, camera_bind_group.0),
+            BindGroupMeta::new(3, ambient_light_bind_group.0),
         ],
-    );
-    render_system.add_phase_commands("geometry", vec![box1, box2, cube]);
-
-    let box1 = RenderCommand::new(
-        shadow_map_pipeline_id,
-        box_id,
-        vec![box_transform_bind_group.0, shadow_d_light_bind_group.0],
-    );
-    let box2 = RenderCommand::new(
-        shadow_map_pipeline_id,
-        box2_id,
-        vec![box2_transform_bind_group.0, shadow_d_light_bind_group.0],
-    );
-    let cube = RenderCommand::new(
-        shadow_map_pipeline_id,
-        cube_model_handler[0].mesh_id,
-        vec![cube_transform_bind_group.0, shadow_d_light_bind_group.0],
-    );
-    render_system.add_phase_commands("shadow", vec![box1, box2, cube]);
-
-    let command = RenderCommand::new(
-        lighting_pipeline_id,
-        g_buffer_handle.mesh_id,
-        vec![
-            g_buffer_bind_group.0,
-            lights_bind_group.0,
-            camera_bind_group.0,
-            shadow_bind_group.0,
+    };
+    let box2 = MeshRenderCommand {
+        pipeline_id: color_pipeline_id,
+        mesh_id: box2_id,
+        index_slice: None,
+        vertex_slice: None,
+        scissor_rect: None,
+        viewport: None,
+        bind_groups: const_vec![
+            BindGroupMeta::new(0, green_material_bind_group.0),
+            BindGroupMeta::with_dynamic_offset(
+                1,
+                transforms_bind_group.0,
+                transforms_handle.offset(1)
+            ),
+            BindGroupMeta::new(2, camera_bind_group.0),
+            BindGroupMeta::new(3, ambient_light_bind_group.0),
         ],
-    );
-    render_system.add_phase_commands("lighting", vec![command]);
-
-    let command = RenderCommand::new(
-        skybox_pipeline_id,
-        skybox_handle.mesh_id,
-        vec![skybox_bind_group.0, camera_bind_group.0],
-    );
-    render_system.add_phase_commands("skybox", vec![command]);
-
-    render_system.run(&renderer, &storage);
+    };
 
-    let texture_buffer = TextureBuffer::new(&renderer, WIDTH, HEIGHT);
-    texture_buffer.copy_render_surface_to_texture(&renderer);
+    let mut recorder = HeadlessRecorder::new(
+        &renderer,
+        &mut storage,
+        RecorderOutput::ImageSequence {
+            directory: "headless_frames".into(),
+            prefix: "frame_".to_string(),
+        },
+    )
+    .unwrap();
 
-    pollster::block_on(texture_buffer.get_image_buffer(&renderer))
-        .unwrap()
-        .save("image.png")
+    // Turntable around the boxes
+    recorder
+        .record(FRAMES, |frame_index, renderer, storage| {
+            let angle = Deg(360.0 * frame_index as f32 / FRAMES as f32);
+            if let Camera::Perspective(camera) = &mut camera {
+                let (sin, cos) = Rad::from(angle).0.sin_cos();
+                camera.position = (-10.0 * cos, 2.0, -10.0 * sin).into();
+                camera.yaw = angle.into();
+            }
+            camera_handle.update(renderer, storage, &camera);
+
+            let current_frame = renderer.current_frame().unwrap();
+            let current_frame_storage = CurrentFrameStorage {
+                storage,
+                current_frame_view: current_frame.view(),
+            };
+
+            let mut encoder = renderer.create_encoder();
+            {
+                let mut render_pass = phase.render_pass(&mut encoder, &current_frame_storage);
+                box1.execute(&mut render_pass, &current_frame_storage);
+                box2.execute(&mut render_pass, &current_frame_storage);
+            }
+            renderer.submit(std::iter::once(encoder.finish()));
+            current_frame.present();
+        })
         .unwrap();
 }
@@ -17,18 +17,42 @@ struct LineGizmoUniform {
     depth_bias: f32,
 }
 
-struct ScreenUniform {
+struct Globals {
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    resolution: vec2<f32>,
+}
+
+@group(1) @binding(0) var<uniform> globals: Globals;
+
+struct LineStyle {
     width: f32,
-    height: f32,
+    cap: u32,
+    join: u32,
 }
 
-@group(1) @binding(0) var<uniform> screen: ScreenUniform;
+@group(2) @binding(0) var<uniform> style: LineStyle;
+
+const CAP_BUTT: u32 = 0u;
+const CAP_ROUND: u32 = 1u;
+const CAP_SQUARE: u32 = 2u;
+
+const JOIN_MITER: u32 = 0u;
+const JOIN_BEVEL: u32 = 1u;
+
+// Miters longer than this times half of the width become bevels
+const MITER_LIMIT: f32 = 4.0;
+const ROUND_CAP_TRIANGLES: u32 = 8u;
+const PI: f32 = 3.14159265;
 
 struct VertexInput {
     @location(0) position_a: vec3<f32>,
     @location(1) position_b: vec3<f32>,
     @location(2) color_a: vec4<f32>,
     @location(3) color_b: vec4<f32>,
+    @location(4) position_prev: vec3<f32>,
+    @location(5) position_next: vec3<f32>,
     @builtin(vertex_index) index: u32,
 };
 
@@ -39,52 +63,146 @@ struct VertexOutput {
 
 const EPSILON: f32 = 4.88e-04;
 
+fn perpendicular(v: vec2<f32>) -> vec2<f32> {
+    return vec2(-v.y, v.x);
+}
+
+// Offset scale along the normal to reach the miter point.
+// Returns 0 if the miter is too long and bevel should be used instead.
+fn miter_scale(normal_a: vec2<f32>, normal_b: vec2<f32>) -> f32 {
+    let sum = normal_a + normal_b;
+    if dot(sum, sum) < EPSILON {
+        return 0.0;
+    }
+    let cos_half = dot(normalize(sum), normal_a);
+    if cos_half * MITER_LIMIT < 1.0 {
+        return 0.0;
+    }
+    return 1.0 / cos_half;
+}
+
 @vertex
 fn vs_main(vertex: VertexInput) -> VertexOutput {
-  var line_gizmo = LineGizmoUniform(1.0, 0.0);
-
-  var positions = array<vec3<f32>, 6>(
-        vec3(0.0, -0.5, 0.),
-        vec3(0.0, -0.5, 1.),
-        vec3(0.0, 0.5, 1.),
-        vec3(0.0, -0.5, 0.),
-        vec3(0.0, 0.5, 1.),
-        vec3(0.0, 0.5, 0.)
-    );
-    let position = positions[vertex.index];
+    var line_gizmo = LineGizmoUniform(style.width, 0.0);
 
     // algorithm based on https://wwwtyro.net/2019/11/18/instanced-lines.html
     var clip_a = camera.view_projection * vec4(vertex.position_a, 1.);
     var clip_b = camera.view_projection * vec4(vertex.position_b, 1.);
+    var clip_prev = camera.view_projection * vec4(vertex.position_prev, 1.);
+    var clip_next = camera.view_projection * vec4(vertex.position_next, 1.);
 
     // Manual near plane clipping to avoid errors when doing the perspective divide inside this shader.
     clip_a = clip_near_plane(clip_a, clip_b);
     clip_b = clip_near_plane(clip_b, clip_a);
+    clip_prev = clip_near_plane(clip_prev, clip_a);
+    clip_next = clip_near_plane(clip_next, clip_b);
 
-    let clip = mix(clip_a, clip_b, position.z);
-
-    let resolution = vec2<f32>(screen.width, screen.height);
+    let resolution = globals.resolution;
     let screen_a = resolution * (0.5 * clip_a.xy / clip_a.w + 0.5);
     let screen_b = resolution * (0.5 * clip_b.xy / clip_b.w + 0.5);
+    let screen_prev = resolution * (0.5 * clip_prev.xy / clip_prev.w + 0.5);
+    let screen_next = resolution * (0.5 * clip_next.xy / clip_next.w + 0.5);
 
-    let x_basis = normalize(screen_a - screen_b);
-    let y_basis = vec2(-x_basis.y, x_basis.x);
+    let has_prev = any(vertex.position_prev != vertex.position_a);
+    let has_next = any(vertex.position_next != vertex.position_b);
 
-    var color = mix(vertex.color_a, vertex.color_b, position.z);
+    let direction = normalize(screen_b - screen_a);
+    let normal = perpendicular(direction);
+    let normal_prev = perpendicular(normalize(screen_a - screen_prev));
+    let normal_next = perpendicular(normalize(screen_next - screen_b));
 
     var line_width = line_gizmo.line_width;
     var alpha = 1.0;
 
-    line_width /= clip.w;
-
     // Line thinness fade from https://acegikmo.com/shapes/docs/#anti-aliasing
     if line_width > 0.0 && line_width < 1. {
-        color.a *= line_width;
+        alpha = line_width;
         line_width = 1.;
     }
+    let half_width = 0.5 * line_width;
+
+    var miter_a = 0.0;
+    if has_prev && style.join == JOIN_MITER {
+        miter_a = miter_scale(normal_prev, normal);
+    }
+    var miter_b = 0.0;
+    if has_next && style.join == JOIN_MITER {
+        miter_b = miter_scale(normal, normal_next);
+    }
+
+    // Segment end the current vertex belongs to: 0 for a, 1 for b
+    var t: f32;
+    var screen: vec2<f32>;
+    if vertex.index < 6u {
+        // Segment body
+        var corners = array<vec2<f32>, 6>(
+            vec2(0.0, -1.0),
+            vec2(1.0, -1.0),
+            vec2(1.0, 1.0),
+            vec2(0.0, -1.0),
+            vec2(1.0, 1.0),
+            vec2(0.0, 1.0)
+        );
+        let corner = corners[vertex.index];
+        t = corner.x;
+
+        var offset: vec2<f32>;
+        if t == 0.0 {
+            if 0.0 < miter_a {
+                offset = normalize(normal_prev + normal) * miter_a;
+            } else {
+                offset = normal;
+            }
+            if !has_prev && style.cap == CAP_SQUARE {
+                offset -= direction * corner.y;
+            }
+        } else {
+            if 0.0 < miter_b {
+                offset = normalize(normal + normal_next) * miter_b;
+            } else {
+                offset = normal;
+            }
+            if !has_next && style.cap == CAP_SQUARE {
+                offset += direction * corner.y;
+            }
+        }
+        screen = mix(screen_a, screen_b, t) + offset * corner.y * half_width;
+    } else if vertex.index < 9u {
+        // Bevel join at the end b, fills the gap on the outer side of the turn
+        t = 1.0;
+        screen = screen_b;
+        if has_next && miter_b == 0.0 {
+            // Positive for left turns, the outer side is then opposite to the normal
+            let turn = -dot(direction, normal_next);
+            let side = select(1.0, -1.0, 0.0 < turn);
+            let i = vertex.index - 6u;
+            if i == 1u {
+                screen += normal * side * half_width;
+            } else if i == 2u {
+                screen += normal_next * side * half_width;
+            }
+        }
+    } else {
+        // Round caps, first half of the triangles for the end a
+        let i = vertex.index - 9u;
+        let triangle = i / 3u;
+        let is_a = triangle < ROUND_CAP_TRIANGLES;
+        t = select(1.0, 0.0, is_a);
+        let center = mix(screen_a, screen_b, t);
+        screen = center;
+        let has_cap = select(!has_next, !has_prev, is_a);
+        let corner = i % 3u;
+        if has_cap && style.cap == CAP_ROUND && corner != 0u {
+            let step = triangle % ROUND_CAP_TRIANGLES + corner - 1u;
+            let angle = PI * f32(step) / f32(ROUND_CAP_TRIANGLES);
+            let forward = select(direction, -direction, is_a);
+            screen += (normal * cos(angle) + forward * sin(angle)) * half_width;
+        }
+    }
 
-    let offset = line_width * (position.x * x_basis + position.y * y_basis);
-    let screen = mix(screen_a, screen_b, position.z) + offset;
+    let clip = mix(clip_a, clip_b, t);
+    var color = mix(vertex.color_a, vertex.color_b, t);
+    color.a *= alpha;
 
     var depth: f32;
     if line_gizmo.depth_bias >= 0. {
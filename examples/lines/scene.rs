@@ -1,11 +1,10 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
     window::WindowBuilder,
 };
-use zero::{const_vec, impl_simple_buffer, prelude::*};
+use zero::{const_vec, prelude::*};
 
 struct FpsLogger {
     last_log: std::time::Instant,
@@ -30,59 +29,24 @@ impl FpsLogger {
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct ScreenUniform {
-    width: f32,
-    height: f32,
-}
-
-impl From<&Screen> for ScreenUniform {
-    fn from(value: &Screen) -> Self {
-        Self {
-            width: value.width,
-            height: value.height,
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct Screen {
-    width: f32,
-    height: f32,
-}
-
-impl_simple_buffer!(
-    Screen,
-    ScreenUniform,
-    ScreenResources,
-    ScreenHandle,
-    ScreenBindGroup,
-    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
-    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
-    { BufferBindingType::Uniform }
-);
-
 fn main() {
     env_logger::init();
 
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<ScreenBindGroup>(&renderer);
-
     let pipeline = PipelineBuilder {
         shader_path: "./examples/lines/line.wgsl",
         label: None,
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<ScreenBindGroup>(),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<GlobalsBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<LineStyleBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -110,23 +74,12 @@ fn main() {
 
     let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
 
-    let phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
-    );
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
         position: (-10.0, 2.0, 0.0).into(),
@@ -140,12 +93,17 @@ fn main() {
     let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
     let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
 
-    let mut screen = Screen {
-        width: renderer.size().width as f32,
-        height: renderer.size().height as f32,
+    let mut globals = Globals::new(renderer.size().width, renderer.size().height);
+    let globals_handle = GlobalsHandle::new(&mut storage, globals.build(&renderer));
+    let globals_bind_group = GlobalsBindGroup::new(&renderer, &mut storage, &globals_handle);
+
+    let style = LineStyle {
+        width: 6.0,
+        cap: LineCap::Round,
+        join: LineJoin::Miter,
     };
-    let screen_handle = ScreenHandle::new(&mut storage, screen.build(&renderer));
-    let screen_bind_group = ScreenBindGroup::new(&renderer, &mut storage, &screen_handle);
+    let style_handle = LineStyleHandle::new(&mut storage, style.build(&renderer));
+    let style_bind_group = LineStyleBindGroup::new(&renderer, &mut storage, &style_handle);
 
     let mut camera_controller = CameraController::new(5.0, 0.7);
 
@@ -153,11 +111,13 @@ fn main() {
     let vertices = (0..cube.vertices.len())
         .flat_map(|i| {
             (i..cube.vertices.len())
-                .map(|j| LineVertex {
-                    position_a: cube.vertices[i].position,
-                    position_b: cube.vertices[j].position,
-                    color_a: [0.5, 0.5, 0.5, 1.0],
-                    color_b: [1.0, 0.0, 1.0, 1.0],
+                .map(|j| {
+                    LineVertex::segment(
+                        cube.vertices[i].position,
+                        cube.vertices[j].position,
+                        [0.5, 0.5, 0.5, 1.0],
+                        [1.0, 0.0, 1.0, 1.0],
+                    )
                 })
                 .collect::<Vec<_>>()
         })
@@ -166,17 +126,49 @@ fn main() {
 
     let line_id = storage.insert_mesh(line.build(&renderer));
 
+    let zigzag_points = (0..8)
+        .map(|i| {
+            let y = if i % 2 == 0 { 4.0 } else { 6.0 };
+            ([0.0, y, i as f32 - 4.0], [0.0, 1.0, 0.5, 1.0])
+        })
+        .collect::<Vec<_>>();
+    let zigzag = Line::polyline(&zigzag_points, false);
+    let zigzag_id = storage.insert_mesh(zigzag.build(&renderer));
+
+    let points_pipeline = PointCloudPipeline::new(
+        &renderer,
+        &mut storage,
+        renderer.surface_format(),
+        Some(TextureFormat::Depth32Float),
+        PointPrimitive::Quads,
+    );
+    let point_style = PointStyle {
+        size: 8.0,
+        shape: PointShape::Circle,
+    };
+    let point_style_handle = PointStyleHandle::new(&mut storage, point_style.build(&renderer));
+    let point_style_bind_group =
+        PointStyleBindGroup::new(&renderer, &mut storage, &point_style_handle);
+
+    let helix_points = (0..64)
+        .map(|i| {
+            let t = i as f32 * 0.3;
+            let color = [i as f32 / 64.0, 0.5, 1.0 - i as f32 / 64.0, 1.0];
+            ([t.cos() * 2.0, t * 0.3 - 3.0, t.sin() * 2.0], color)
+        })
+        .collect::<Vec<_>>();
+    let helix = PointCloud::from_points(&helix_points);
+    let helix_id = storage.insert_mesh(helix.build(&renderer));
+
     let mut last_render_time = std::time::Instant::now();
     let mut fps_logger = FpsLogger::new();
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => match event {
-                DeviceEvent::MouseMotion { delta } => {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-                _ => {}
-            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -197,18 +189,19 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
                     storage.replace_texture(
                         depth_texture_id,
                         EmptyTexture::new_depth().build(&renderer),
                     );
-                    screen.width = physical_size.width as f32;
-                    screen.height = physical_size.height as f32;
-                    screen_handle.update(&renderer, &storage, &screen);
+                    globals.resize(physical_size.width, physical_size.height);
                 }
                 WindowEvent::RedrawRequested => {
                     let now = std::time::Instant::now();
@@ -217,18 +210,40 @@ fn main() {
 
                     fps_logger.log(now, dt);
 
+                    globals.advance(dt);
+                    globals_handle.update(&renderer, &storage, &globals);
+
                     camera_controller.update_camera(&mut camera, dt);
                     camera_handle.update(&renderer, &storage, &camera);
 
                     let line = LineRenderCommand {
                         pipeline_id,
                         mesh_id: line_id,
-                        bind_groups: const_vec![camera_bind_group.0, screen_bind_group.0,],
+                        bind_groups: const_vec![
+                            camera_bind_group.0,
+                            globals_bind_group.0,
+                            style_bind_group.0,
+                        ],
                     };
+                    let zigzag = LineRenderCommand {
+                        pipeline_id,
+                        mesh_id: zigzag_id,
+                        bind_groups: const_vec![
+                            camera_bind_group.0,
+                            globals_bind_group.0,
+                            style_bind_group.0,
+                        ],
+                    };
+                    let helix = points_pipeline.command(
+                        helix_id,
+                        camera_bind_group,
+                        globals_bind_group,
+                        point_style_bind_group,
+                    );
 
                     let current_frame_context = match renderer.current_frame() {
                         Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                             renderer.resize(None);
                             return;
                         }
@@ -252,6 +267,8 @@ fn main() {
                         let mut render_pass =
                             phase.render_pass(&mut encoder, &current_frame_storage);
                         line.execute(&mut render_pass, &current_frame_storage);
+                        zigzag.execute(&mut render_pass, &current_frame_storage);
+                        helix.execute(&mut render_pass, &current_frame_storage);
                     }
                     let commands = encoder.finish();
 
@@ -1,4 +1,3 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -64,20 +63,17 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TimeBindGroup>(&renderer);
-
     let pipeline = PipelineBuilder {
         shader_path: "./examples/raymarching/raymarching.wgsl",
         label: None,
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<CameraBindGroup>(),
-                storage.get_bind_group_layout::<TimeBindGroup>(),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TimeBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -113,23 +109,12 @@ fn main() {
 
     let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
 
-    let phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: Operations {
-                load: LoadOp::Clear(Color::TRANSPARENT),
-                store: StoreOp::Store,
-            },
-        },],
-        Some(DepthStencil {
-            view_id: depth_texture_id,
-            depth_ops: Some(Operations {
-                load: LoadOp::Clear(1.0),
-                store: StoreOp::Store,
-            }),
-            stencil_ops: None,
-        }),
-    );
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
         position: (-10.0, 0.0, 0.0).into(),
@@ -157,11 +142,10 @@ fn main() {
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => {
-                if let DeviceEvent::MouseMotion { delta } = event {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -182,11 +166,14 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
                     storage.replace_texture(
                         depth_texture_id,
                         EmptyTexture::new_depth().build(&renderer),
@@ -207,7 +194,7 @@ fn main() {
 
                     let current_frame_context = match renderer.current_frame() {
                         Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                             renderer.resize(None);
                             return;
                         }
@@ -234,7 +221,11 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
-                        bind_groups: const_vec![camera_bind_group.0, time_bind_group.0],
+                        viewport: None,
+                        bind_groups: const_vec![
+                            BindGroupMeta::new(0, camera_bind_group.0),
+                            BindGroupMeta::new(1, time_bind_group.0),
+                        ],
                     };
 
                     {
@@ -0,0 +1,194 @@
+use winit::{
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::WindowBuilder,
+};
+use zero::prelude::*;
+
+fn main() {
+    env_logger::init();
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
+    let mut storage = RenderStorage::default();
+
+    // Tab switches between the forward and the deferred path
+    let mut render_path = RenderPathRenderer::new(
+        &renderer,
+        &mut storage,
+        TextureId::WINDOW_VIEW_ID,
+        renderer.surface_format(),
+        RenderPath::Forward,
+    );
+
+    let mut camera = Camera::Perspective(PerspectiveCamera {
+        position: (-10.0, 2.0, 0.0).into(),
+        yaw: Deg(0.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: renderer.size().width as f32 / renderer.size().height as f32,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
+    let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
+    let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
+
+    let mut camera_controller = CameraController::new(5.0, 0.7);
+
+    let lights = PointLights {
+        lights: vec![
+            PointLight::new((-1.0, 9.0, 5.0), (1.0, 1.0, 1.0), 1.0, 0.109, 0.032),
+            PointLight::new((4.0, 3.0, -4.0), (1.0, 0.6, 0.2), 1.0, 0.109, 0.032),
+        ],
+    };
+    let lights_handle = PointLightsHandle::new(&mut storage, lights.build(&renderer));
+    let lights_bind_group = PointLightsBindGroup::new(&renderer, &mut storage, &lights_handle);
+
+    let mut object = |mesh: Mesh, transform: Transform, material: ColorMaterial| {
+        let mesh_id = storage.insert_mesh(mesh.build(&renderer));
+        let transform_handle = TransformHandle::new(&mut storage, transform.build(&renderer));
+        let material_handle = ColorMaterialHandle::new(&mut storage, material.build(&renderer));
+        let object = RenderPathObject {
+            mesh_id,
+            material: ColorMaterialBindGroup::new(&renderer, &mut storage, &material_handle),
+            transform: TransformBindGroup::new(&renderer, &mut storage, &transform_handle),
+        };
+        (object, transform_handle)
+    };
+
+    let (floor, _) = object(
+        Cube::new(9.0, 1.0, 5.0).into(),
+        Transform {
+            translation: (0.0, 0.0, 0.0).into(),
+            rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
+            scale: (3.0, 1.0, 3.0).into(),
+        },
+        ColorMaterial {
+            ambient: [0.4, 0.4, 0.4],
+            diffuse: [0.6, 0.6, 0.6],
+            specular: [1.0, 1.0, 1.0],
+            shininess: 32.0,
+        },
+    );
+    let mut box_transform = Transform {
+        translation: (0.0, 2.0, 1.0).into(),
+        rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(0.0)),
+        scale: (1.0, 1.0, 1.0).into(),
+    };
+    let (green_box, box_transform_handle) = object(
+        Cube::new(1.0, 1.0, 1.0).into(),
+        box_transform,
+        ColorMaterial {
+            ambient: [0.4, 0.9, 0.4],
+            diffuse: [0.4, 0.9, 0.4],
+            specular: [0.1, 0.1, 0.1],
+            shininess: 1.0,
+        },
+    );
+    let objects = [floor, green_box];
+
+    let mut last_render_time = std::time::Instant::now();
+    _ = event_loop.run(|event, target| {
+        target.set_control_flow(ControlFlow::Poll);
+        match event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => camera_controller.set_mouse_active(*state == ElementState::Pressed),
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: key,
+                            state,
+                            ..
+                        },
+                    ..
+                } => match key {
+                    Key::Named(NamedKey::Escape) => target.exit(),
+                    Key::Named(NamedKey::Tab) if *state == ElementState::Pressed => {
+                        let path = match render_path.path() {
+                            RenderPath::Forward => RenderPath::Deferred,
+                            RenderPath::Deferred => RenderPath::Forward,
+                        };
+                        println!("Render path: {path:?}");
+                        render_path.set_path(path);
+                    }
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
+                },
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
+                    camera.resize(physical_size.width, physical_size.height);
+                    render_path.resize(&renderer, &mut storage);
+                }
+                WindowEvent::RedrawRequested => {
+                    let now = std::time::Instant::now();
+                    let dt = now - last_render_time;
+                    last_render_time = now;
+
+                    camera_controller.update_camera(&mut camera, dt);
+                    camera_handle.update(&renderer, &storage, &camera);
+
+                    box_transform.rotation = box_transform.rotation
+                        * Quaternion::from_axis_angle(
+                            Vector3::unit_y(),
+                            Deg(-dt.as_secs_f32() * 30.0),
+                        );
+                    box_transform_handle.update(&renderer, &storage, &box_transform);
+
+                    let current_frame_context = match renderer.current_frame() {
+                        Ok(cfc) => cfc,
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                            renderer.resize(None);
+                            return;
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            target.exit();
+                            return;
+                        }
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            return;
+                        }
+                    };
+
+                    let current_frame_storage = CurrentFrameStorage {
+                        storage: &storage,
+                        current_frame_view: current_frame_context.view(),
+                    };
+
+                    let mut encoder = renderer.create_encoder();
+                    render_path.render(
+                        &mut encoder,
+                        &current_frame_storage,
+                        &objects,
+                        camera_bind_group,
+                        lights_bind_group,
+                    );
+
+                    let commands = encoder.finish();
+                    renderer.submit(std::iter::once(commands));
+                    current_frame_context.present();
+                }
+                _ => {}
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => {}
+        }
+    });
+}
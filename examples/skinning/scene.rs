@@ -0,0 +1,311 @@
+use winit::{
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::WindowBuilder,
+};
+use zero::{const_vec, prelude::*};
+
+struct FpsLogger {
+    last_log: std::time::Instant,
+}
+
+impl FpsLogger {
+    fn new() -> Self {
+        Self {
+            last_log: std::time::Instant::now(),
+        }
+    }
+
+    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration) {
+        if 1.0 <= (now - self.last_log).as_secs_f32() {
+            println!(
+                "Frame time: {:.2}ms(FPS: {:.2})",
+                dt.as_secs_f64() * 1000.0,
+                1.0 / dt.as_secs_f64()
+            );
+            self.last_log = now;
+        }
+    }
+}
+
+const COLUMN_WIDTH: f32 = 1.0;
+const COLUMN_HEIGHT: f32 = 4.0;
+const COLUMN_SEGMENTS: u32 = 8;
+
+/// Square column along Y axis skinned to two joints.
+/// Lower joint sits at the base, upper joint in the middle.
+fn column() -> (SkinnedMesh, Skeleton) {
+    let extent = COLUMN_WIDTH / 2.0;
+    let sides = [
+        ([extent, extent], [extent, -extent], [1.0, 0.0, 0.0]),
+        ([extent, -extent], [-extent, -extent], [0.0, 0.0, -1.0]),
+        ([-extent, -extent], [-extent, extent], [-1.0, 0.0, 0.0]),
+        ([-extent, extent], [extent, extent], [0.0, 0.0, 1.0]),
+    ];
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (a, b, normal) in sides {
+        let base = vertices.len() as u32;
+        for level in 0..=COLUMN_SEGMENTS {
+            let t = level as f32 / COLUMN_SEGMENTS as f32;
+            let y = t * COLUMN_HEIGHT;
+            for (i, corner) in [a, b].into_iter().enumerate() {
+                vertices.push(MeshVertexSkinned {
+                    position: [corner[0], y, corner[1]],
+                    tex_coords: [i as f32, t],
+                    normal,
+                    joints: [0, 1, 0, 0],
+                    weights: [1.0 - t, t, 0.0, 0.0],
+                });
+            }
+        }
+        for level in 0..COLUMN_SEGMENTS {
+            let i = base + level * 2;
+            indices.extend_from_slice(&[i, i + 1, i + 3, i, i + 3, i + 2]);
+        }
+    }
+
+    let joint = |parent, y: f32, model_y: f32| Joint {
+        parent,
+        transform: Transform {
+            translation: (0.0, y, 0.0).into(),
+            rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
+            scale: (1.0, 1.0, 1.0).into(),
+        },
+        inverse_bind_matrix: Matrix4::from_translation((0.0, -model_y, 0.0).into()),
+    };
+    let skeleton = Skeleton {
+        joints: vec![
+            joint(None, 0.0, 0.0),
+            joint(Some(0), COLUMN_HEIGHT / 2.0, COLUMN_HEIGHT / 2.0),
+        ],
+    };
+
+    let mesh = SkinnedMesh {
+        name: "column".to_string(),
+        vertices,
+        indices,
+    };
+    (mesh, skeleton)
+}
+
+fn main() {
+    env_logger::init();
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
+    let mut storage = RenderStorage::default();
+
+    let skinning_pipeline = PipelineBuilder {
+        shader_path: "./examples/skinning/skinning.wgsl",
+        label: Some("skinning_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<SkeletonBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[MeshVertexSkinned::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let skinning_pipeline_id = storage.insert_pipeline(skinning_pipeline);
+
+    let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
+
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
+
+    let mut camera = Camera::Perspective(PerspectiveCamera {
+        position: (-8.0, 2.0, 0.0).into(),
+        yaw: Deg(0.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: renderer.size().width as f32 / renderer.size().height as f32,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
+    let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
+    let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
+
+    let mut camera_controller = CameraController::new(5.0, 0.7);
+
+    let material = ColorMaterial {
+        ambient: [0.4, 0.4, 0.9],
+        diffuse: [0.4, 0.4, 0.9],
+        specular: [0.1, 0.1, 0.1],
+        shininess: 1.0,
+    };
+    let material_handle = ColorMaterialHandle::new(&mut storage, material.build(&renderer));
+    let material_bind_group =
+        ColorMaterialBindGroup::new(&renderer, &mut storage, &material_handle);
+
+    let (column_mesh, mut skeleton) = column();
+    let column_id = storage.insert_mesh(column_mesh.build(&renderer));
+
+    let skeleton_handle = SkeletonHandle::new(&mut storage, skeleton.build(&renderer));
+    let skeleton_bind_group = SkeletonBindGroup::new(&renderer, &mut storage, &skeleton_handle);
+
+    let column_transform = Transform {
+        translation: (0.0, 0.0, 0.0).into(),
+        rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(0.0)),
+        scale: (1.0, 1.0, 1.0).into(),
+    };
+    let column_transform_handle =
+        TransformHandle::new(&mut storage, column_transform.build(&renderer));
+    let column_transform_bind_group =
+        TransformBindGroup::new(&renderer, &mut storage, &column_transform_handle);
+
+    let mut fixed_timestep = FixedTimestep::new(std::time::Duration::from_secs_f32(1.0 / 60.0));
+    let mut animation_time = 0.0;
+    let mut last_render_time = std::time::Instant::now();
+    let mut fps_logger = FpsLogger::new();
+    _ = event_loop.run(|event, target| {
+        target.set_control_flow(ControlFlow::Poll);
+        match event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => camera_controller.set_mouse_active(*state == ElementState::Pressed),
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: key,
+                            state,
+                            ..
+                        },
+                    ..
+                } => match key {
+                    Key::Named(NamedKey::Escape) => target.exit(),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
+                },
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
+                    camera.resize(physical_size.width, physical_size.height);
+                    storage.replace_texture(
+                        depth_texture_id,
+                        EmptyTexture::new_depth().build(&renderer),
+                    );
+                }
+                WindowEvent::RedrawRequested => {
+                    let now = std::time::Instant::now();
+                    let dt = now - last_render_time;
+                    last_render_time = now;
+
+                    fps_logger.log(now, dt);
+
+                    fixed_timestep.advance(dt, |step| {
+                        camera_controller.update_camera(&mut camera, step);
+                        animation_time += step.as_secs_f32();
+                    });
+                    camera_handle.update(&renderer, &storage, &camera);
+
+                    let bend = f32::sin(animation_time) * 60.0;
+                    skeleton.joints[1].transform.rotation =
+                        Quaternion::from_axis_angle(Vector3::unit_z(), Deg(bend));
+                    skeleton_handle.update(&renderer, &storage, &skeleton);
+
+                    let current_frame_context = match renderer.current_frame() {
+                        Ok(cfc) => cfc,
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                            renderer.resize(None);
+                            return;
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            target.exit();
+                            return;
+                        }
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            return;
+                        }
+                    };
+
+                    let current_frame_storage = CurrentFrameStorage {
+                        storage: &storage,
+                        current_frame_view: current_frame_context.view(),
+                    };
+
+                    let mut encoder = renderer.create_encoder();
+
+                    let column = MeshRenderCommand {
+                        pipeline_id: skinning_pipeline_id,
+                        mesh_id: column_id,
+                        index_slice: None,
+                        vertex_slice: None,
+                        scissor_rect: None,
+                        viewport: None,
+                        bind_groups: const_vec![
+                            BindGroupMeta::new(0, material_bind_group.0),
+                            BindGroupMeta::new(1, column_transform_bind_group.0),
+                            BindGroupMeta::new(2, camera_bind_group.0),
+                            BindGroupMeta::new(3, skeleton_bind_group.0),
+                        ],
+                    };
+
+                    {
+                        let mut render_pass =
+                            phase.render_pass(&mut encoder, &current_frame_storage);
+                        column.execute(&mut render_pass, &current_frame_storage);
+                    }
+
+                    let commands = encoder.finish();
+                    renderer.submit(std::iter::once(commands));
+                    current_frame_context.present();
+                }
+                _ => {}
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,72 @@
+// Vertex shader
+
+struct TransformUniform {
+  transform: mat4x4<f32>,
+  rotate: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> transform: TransformUniform;
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(2) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(3) @binding(0)
+var<storage, read> joints: array<mat4x4<f32>>;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+  @location(3) joints: vec4<u32>,
+  @location(4) weights: vec4<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) world_normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+) -> VertexOutput {
+  let skin = vertex.weights.x * joints[vertex.joints.x] +
+             vertex.weights.y * joints[vertex.joints.y] +
+             vertex.weights.z * joints[vertex.joints.z] +
+             vertex.weights.w * joints[vertex.joints.w];
+
+  let world_position = transform.transform * skin * vec4<f32>(vertex.position, 1.0);
+  let world_normal = transform.rotate * skin * vec4<f32>(vertex.normal, 0.0);
+
+  var out: VertexOutput;
+  out.clip_position = camera.view_projection * world_position;
+  out.world_normal = normalize(world_normal.xyz);
+  return out;
+}
+
+// Fragment shader
+
+struct MaterialProperties {
+    ambient: vec3<f32>,
+    diffuse: vec3<f32>,
+    specular: vec3<f32>,
+    shininess: f32,
+};
+@group(0) @binding(0)
+var<uniform> properties: MaterialProperties;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let light_dir = normalize(vec3<f32>(-1.0, 2.0, 1.0));
+  let diffuse_strength = max(dot(vertex.world_normal, light_dir), 0.0);
+  let color = properties.ambient * 0.3 + properties.diffuse * diffuse_strength;
+  return vec4<f32>(color, 1.0);
+}
@@ -1,4 +1,3 @@
-use wgpu::StoreOp;
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -36,21 +35,31 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     let mut storage = RenderStorage::default();
 
-    storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    storage.register_bind_group_layout::<SkyboxBindGroup>(&renderer);
-    storage.register_bind_group_layout::<TransformBindGroup>(&renderer);
-
+    // Optional path to an equirectangular image (e.g. `.hdr`) to use instead of the cube map
+    let equirect_path = std::env::args().nth(1);
+
+    let (shader_path, skybox_layout) = if equirect_path.is_some() {
+        (
+            "./examples/skybox/skybox_equirect.wgsl",
+            &storage.get_bind_group_layout::<SkyboxEquirectBindGroup>(&renderer),
+        )
+    } else {
+        (
+            "./examples/skybox/skybox.wgsl",
+            &storage.get_bind_group_layout::<SkyboxBindGroup>(&renderer),
+        )
+    };
     let skybox_pipeline = PipelineBuilder {
-        shader_path: "./examples/skybox/skybox.wgsl",
+        shader_path,
         label: Some("skybox_pipeline"),
         layout_descriptor: Some(&PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
-                storage.get_bind_group_layout::<SkyboxBindGroup>(),
-                storage.get_bind_group_layout::<CameraBindGroup>(),
+                skybox_layout,
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
             ],
             push_constant_ranges: &[],
         }),
@@ -61,7 +70,11 @@ fn main() {
             blend: None,
             write_mask: ColorWrites::ALL,
         })]),
-        fragment_entry_point: "fs_main",
+        fragment_entry_point: if renderer.needs_manual_gamma() {
+            "fs_main_gamma"
+        } else {
+            "fs_main"
+        },
         primitive: PrimitiveState {
             topology: PrimitiveTopology::TriangleList,
             strip_index_format: None,
@@ -78,16 +91,9 @@ fn main() {
     .build(&renderer);
     let skybox_pipeline_id = storage.insert_pipeline(skybox_pipeline);
 
-    let skybox_phase = RenderPhase::new(
-        const_vec![ColorAttachment {
-            view_id: ResourceId::WINDOW_VIEW_ID,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: StoreOp::Store,
-            },
-        }],
-        None,
-    );
+    let skybox_phase = RenderPhase::builder()
+        .color_load(TextureId::WINDOW_VIEW_ID)
+        .build();
 
     let mut camera = Camera::Perspective(PerspectiveCamera {
         position: (-10.0, 2.0, 0.0).into(),
@@ -103,29 +109,35 @@ fn main() {
 
     let mut camera_controller = CameraController::new(5.0, 0.7);
 
-    let skybox = Skybox::load([
-        "./res/skybox/right.jpg",
-        "./res/skybox/left.jpg",
-        "./res/skybox/top.jpg",
-        "./res/skybox/bottom.jpg",
-        "./res/skybox/front.jpg",
-        "./res/skybox/back.jpg",
-    ])
-    .unwrap();
+    let skybox = if let Some(path) = &equirect_path {
+        Skybox::load_equirect_2d(path).unwrap()
+    } else {
+        Skybox::load([
+            "./res/skybox/right.jpg",
+            "./res/skybox/left.jpg",
+            "./res/skybox/top.jpg",
+            "./res/skybox/bottom.jpg",
+            "./res/skybox/front.jpg",
+            "./res/skybox/back.jpg",
+        ])
+        .unwrap()
+    };
     let skybox_handle = SkyboxHandle::new(&mut storage, skybox.build(&renderer));
-    let skybox_bind_group = SkyboxBindGroup::new(&renderer, &mut storage, &skybox_handle);
+    let skybox_bind_group_id = if equirect_path.is_some() {
+        SkyboxEquirectBindGroup::new(&renderer, &mut storage, &skybox_handle).0
+    } else {
+        SkyboxBindGroup::new(&renderer, &mut storage, &skybox_handle).0
+    };
 
     let mut last_render_time = std::time::Instant::now();
     let mut fps_logger = FpsLogger::new();
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
-            Event::DeviceEvent { ref event, .. } => match event {
-                DeviceEvent::MouseMotion { delta } => {
-                    camera_controller.process_mouse(delta.0, delta.1);
-                }
-                _ => {}
-            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -146,11 +158,14 @@ fn main() {
                     ..
                 } => match key {
                     Key::Named(NamedKey::Escape) => target.exit(),
-                    k => _ = camera_controller.process_key(k.clone(), *state),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
                 },
-                WindowEvent::Resized(physical_size) => {
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
                     camera.resize(physical_size.width, physical_size.height);
-                    renderer.resize(Some(*physical_size));
                 }
                 WindowEvent::RedrawRequested => {
                     let now = std::time::Instant::now();
@@ -164,7 +179,7 @@ fn main() {
 
                     let current_frame_context = match renderer.current_frame() {
                         Ok(cfc) => cfc,
-                        Err(SurfaceError::Lost) => {
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
                             renderer.resize(None);
                             return;
                         }
@@ -191,7 +206,11 @@ fn main() {
                         index_slice: None,
                         vertex_slice: None,
                         scissor_rect: None,
-                        bind_groups: const_vec![skybox_bind_group.0, camera_bind_group.0],
+                        viewport: None,
+                        bind_groups: const_vec![
+                            BindGroupMeta::new(0, skybox_bind_group_id),
+                            BindGroupMeta::new(1, camera_bind_group.0),
+                        ],
                     };
 
                     {
@@ -0,0 +1,77 @@
+// Vertex shader
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) direction: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+) -> VertexOutput {
+
+  let position = camera.view_projection_without_translation * vec4<f32>(vertex.position, 1.0);
+  var out: VertexOutput;
+  out.clip_position = position.xyww;
+  out.direction = vertex.position.xyz;
+
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_equirect: texture_2d<f32>;
+@group(0) @binding(1)
+var s_equirect: sampler;
+
+const PI: f32 = 3.14159265359;
+
+// Longitude goes along U and latitude along V with +Y at the top
+fn equirect_uv(direction: vec3<f32>) -> vec2<f32> {
+  let d = normalize(direction);
+  let u = atan2(d.z, d.x) / (2.0 * PI) + 0.5;
+  let v = acos(clamp(d.y, -1.0, 1.0)) / PI;
+  return vec2<f32>(u, v);
+}
+
+// Explicit level avoids a seam where U wraps from 1 to 0
+fn sample_sky(direction: vec3<f32>) -> vec4<f32> {
+  return textureSampleLevel(t_equirect, s_equirect, equirect_uv(direction), 0.0);
+}
+
+// 0-1 sRGB gamma from 0-1 linear
+fn gamma_from_linear_rgb(rgb: vec3<f32>) -> vec3<f32> {
+  let cutoff = rgb < vec3<f32>(0.0031308);
+  let lower = rgb * vec3<f32>(12.92);
+  let higher = vec3<f32>(1.055) * pow(rgb, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+  return select(higher, lower, cutoff);
+}
+
+// Used with sRGB surfaces
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  return sample_sky(vertex.direction);
+}
+
+// Used with linear surfaces
+@fragment
+fn fs_main_gamma(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let color = sample_sky(vertex.direction);
+  return vec4<f32>(gamma_from_linear_rgb(color.rgb), color.a);
+}
@@ -0,0 +1,303 @@
+use winit::{
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::WindowBuilder,
+};
+use zero::{const_vec, prelude::*};
+
+struct FpsLogger {
+    last_log: std::time::Instant,
+}
+
+impl FpsLogger {
+    fn new() -> Self {
+        Self {
+            last_log: std::time::Instant::now(),
+        }
+    }
+
+    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration) {
+        if 1.0 <= (now - self.last_log).as_secs_f32() {
+            println!(
+                "Frame time: {:.2}ms(FPS: {:.2})",
+                dt.as_secs_f64() * 1000.0,
+                1.0 / dt.as_secs_f64()
+            );
+            self.last_log = now;
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
+    let mut storage = RenderStorage::default();
+
+    let color_pipeline = PipelineBuilder {
+        shader_path: "./examples/split_screen/color.wgsl",
+        label: Some("color_pipeline"),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &storage.get_bind_group_layout::<ColorMaterialBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+                &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
+            ],
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[MeshVertex::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point: "fs_main",
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState::default(),
+        multiview: None,
+    }
+    .build(&renderer);
+    let color_pipeline_id = storage.insert_pipeline(color_pipeline);
+
+    let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(&renderer));
+
+    let phase = RenderPhase::builder()
+        .color(TextureId::WINDOW_VIEW_ID)
+        .clear(Color::TRANSPARENT)
+        .depth(depth_texture_id)
+        .clear(1.0)
+        .build();
+
+    let half_aspect = renderer.size().width as f32 / 2.0 / renderer.size().height as f32;
+    let mut left_camera = Camera::Perspective(PerspectiveCamera {
+        position: (-10.0, 2.0, 0.0).into(),
+        yaw: Deg(0.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: half_aspect,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
+    let left_camera_handle = CameraHandle::new(&mut storage, left_camera.build(&renderer));
+    let left_camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &left_camera_handle);
+
+    let mut right_camera = Camera::Perspective(PerspectiveCamera {
+        position: (10.0, 2.0, 0.0).into(),
+        yaw: Deg(180.0).into(),
+        pitch: Deg(0.0).into(),
+        aspect: half_aspect,
+        fovy: Deg(90.0).into(),
+        znear: 0.1,
+        zfar: 100.0,
+    });
+    let right_camera_handle = CameraHandle::new(&mut storage, right_camera.build(&renderer));
+    let right_camera_bind_group =
+        CameraBindGroup::new(&renderer, &mut storage, &right_camera_handle);
+
+    let mut camera_controller = CameraController::new(5.0, 0.7);
+
+    let box_mesh: Mesh = Cube::new(9.0, 1.0, 5.0).into();
+    let box_id = storage.insert_mesh(box_mesh.build(&renderer));
+
+    let box_transform = Transform {
+        translation: (0.0, 0.0, 0.0).into(),
+        rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
+        scale: (1.0, 1.0, 1.0).into(),
+    };
+    let box_transform_handle = TransformHandle::new(&mut storage, box_transform.build(&renderer));
+    let box_transform_bind_group =
+        TransformBindGroup::new(&renderer, &mut storage, &box_transform_handle);
+
+    let box2_mesh: Mesh = Cube::new(1.0, 1.0, 1.0).into();
+    let box2_id = storage.insert_mesh(box2_mesh.build(&renderer));
+
+    let box2_transform = Transform {
+        translation: (0.0, 1.0, 1.0).into(),
+        rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)),
+        scale: (1.0, 1.0, 1.0).into(),
+    };
+    let box2_transform_handle = TransformHandle::new(&mut storage, box2_transform.build(&renderer));
+    let box2_transform_bind_group =
+        TransformBindGroup::new(&renderer, &mut storage, &box2_transform_handle);
+
+    let grey_material = ColorMaterial {
+        ambient: [0.4, 0.4, 0.4],
+        diffuse: [0.6, 0.6, 0.6],
+        specular: [1.0, 1.0, 1.0],
+        shininess: 32.0,
+    };
+    let grey_material_handle =
+        ColorMaterialHandle::new(&mut storage, grey_material.build(&renderer));
+    let grey_material_bind_group =
+        ColorMaterialBindGroup::new(&renderer, &mut storage, &grey_material_handle);
+
+    let green_material = ColorMaterial {
+        ambient: [0.4, 0.9, 0.4],
+        diffuse: [0.4, 0.9, 0.4],
+        specular: [0.1, 0.1, 0.1],
+        shininess: 1.0,
+    };
+    let green_material_handle =
+        ColorMaterialHandle::new(&mut storage, green_material.build(&renderer));
+    let green_material_bind_group =
+        ColorMaterialBindGroup::new(&renderer, &mut storage, &green_material_handle);
+
+    // Camera bind group is always at index 2 and is replaced per viewport
+    let commands = [
+        MeshRenderCommand {
+            pipeline_id: color_pipeline_id,
+            mesh_id: box_id,
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect: None,
+            viewport: None,
+            bind_groups: const_vec![
+                BindGroupMeta::new(0, grey_material_bind_group.0),
+                BindGroupMeta::new(1, box_transform_bind_group.0),
+                BindGroupMeta::new(2, left_camera_bind_group.0),
+            ],
+        },
+        MeshRenderCommand {
+            pipeline_id: color_pipeline_id,
+            mesh_id: box2_id,
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect: None,
+            viewport: None,
+            bind_groups: const_vec![
+                BindGroupMeta::new(0, green_material_bind_group.0),
+                BindGroupMeta::new(1, box2_transform_bind_group.0),
+                BindGroupMeta::new(2, left_camera_bind_group.0),
+            ],
+        },
+    ];
+
+    let mut last_render_time = std::time::Instant::now();
+    let mut fps_logger = FpsLogger::new();
+    _ = event_loop.run(|event, target| {
+        target.set_control_flow(ControlFlow::Poll);
+        match event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => camera_controller.process_mouse(delta.0, delta.1),
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => camera_controller.set_mouse_active(*state == ElementState::Pressed),
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: key,
+                            state,
+                            ..
+                        },
+                    ..
+                } => match key {
+                    Key::Named(NamedKey::Escape) => target.exit(),
+                    k => _ = camera_controller.process_key(k.into(), (*state).into()),
+                },
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    let physical_size = window.inner_size();
+                    if !renderer.handle_scale_factor_change(physical_size) {
+                        return;
+                    }
+                    left_camera.resize(physical_size.width / 2, physical_size.height);
+                    right_camera.resize(physical_size.width / 2, physical_size.height);
+                    right_camera_handle.update(&renderer, &storage, &right_camera);
+                    storage.replace_texture(
+                        depth_texture_id,
+                        EmptyTexture::new_depth().build(&renderer),
+                    );
+                }
+                WindowEvent::RedrawRequested => {
+                    let now = std::time::Instant::now();
+                    let dt = now - last_render_time;
+                    last_render_time = now;
+
+                    fps_logger.log(now, dt);
+
+                    camera_controller.update_camera(&mut left_camera, dt);
+                    left_camera_handle.update(&renderer, &storage, &left_camera);
+
+                    let current_frame_context = match renderer.current_frame() {
+                        Ok(cfc) => cfc,
+                        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                            renderer.resize(None);
+                            return;
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            target.exit();
+                            return;
+                        }
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            return;
+                        }
+                    };
+
+                    let current_frame_storage = CurrentFrameStorage {
+                        storage: &storage,
+                        current_frame_view: current_frame_context.view(),
+                    };
+
+                    let mut encoder = renderer.create_encoder();
+
+                    let half_width = renderer.size().width as f32 / 2.0;
+                    let height = renderer.size().height as f32;
+                    let viewports = [
+                        Viewport {
+                            camera_bind_group: left_camera_bind_group.0,
+                            rect: [0.0, 0.0, half_width, height],
+                        },
+                        Viewport {
+                            camera_bind_group: right_camera_bind_group.0,
+                            rect: [half_width, 0.0, half_width, height],
+                        },
+                    ];
+                    phase.execute_for_viewports(
+                        &mut encoder,
+                        &current_frame_storage,
+                        &commands,
+                        2,
+                        &viewports,
+                    );
+
+                    let commands = encoder.finish();
+                    renderer.submit(std::iter::once(commands));
+                    current_frame_context.present();
+                }
+                _ => {}
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => {}
+        }
+    });
+}
@@ -37,14 +37,9 @@ fn main() {
     // let event_loop = EventLoop::new().unwrap();
     // let window = WindowBuilder::new().build(&event_loop).unwrap();
     //
-    // let mut renderer = pollster::block_on(Renderer::new(&window));
+    // let mut renderer = pollster::block_on(Renderer::new_windowed(&window));
     // let mut render_system = RenderSystem::default();
     // let mut storage = RenderStorage::default();
-    //
-    // storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-    // storage.register_bind_group_layout::<StandartMaterialBindGroup>(&renderer);
-    // storage.register_bind_group_layout::<PointLightBindGroup>(&renderer);
-    // storage.register_bind_group_layout::<TransformBindGroup>(&renderer);
 
     // let standart_material_pipeline = PipelineBuilder {
     //     shader_path: "./examples/forward/standart_material.wgsl",
@@ -52,9 +47,9 @@ fn main() {
     //     layout_descriptor: Some(&PipelineLayoutDescriptor {
     //         label: None,
     //         bind_group_layouts: &[
-    //             storage.get_bind_group_layout::<StandartMaterialBindGroup>(),
-    //             storage.get_bind_group_layout::<TransformBindGroup>(),
-    //             storage.get_bind_group_layout::<CameraBindGroup>(),
+    //             &storage.get_bind_group_layout::<StandartMaterialBindGroup>(&renderer),
+    //             &storage.get_bind_group_layout::<TransformBindGroup>(&renderer),
+    //             &storage.get_bind_group_layout::<CameraBindGroup>(&renderer),
     //         ],
     //         push_constant_ranges: &[],
     //     }),
@@ -92,7 +87,7 @@ fn main() {
     //
     // let phase = RenderPhase::new(
     //     const_vec![ColorAttachment {
-    //         view_id: ResourceId::WINDOW_VIEW_ID,
+    //         view_id: TextureId::WINDOW_VIEW_ID,
     //         ops: Operations {
     //             load: LoadOp::Clear(Color::TRANSPARENT),
     //             store: StoreOp::Store,
@@ -228,7 +223,7 @@ fn main() {
     //                 ..
     //             } => match key {
     //                 Key::Named(NamedKey::Escape) => target.exit(),
-    //                 k => _ = camera_controller.process_key(k.clone(), *state),
+    //                 k => _ = camera_controller.process_key(k.into(), (*state).into()),
     //             },
     //             WindowEvent::Resized(physical_size) => {
     //                 camera.resize(physical_size.width, physical_size.height);
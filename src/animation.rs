@@ -0,0 +1,516 @@
+use crate::cgmath_imports::*;
+use crate::transform::Transform;
+use cgmath::{VectorSpace, Zero};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+    /// Catmull-Rom spline through neighbouring keyframes
+    Cubic,
+    /// Hermite spline with the tangents of the keyframes, same as
+    /// the glTF `CUBICSPLINE`
+    CubicSpline,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    /// Tangent of the spline arriving at the keyframe. Used only
+    /// by [`Interpolation::CubicSpline`].
+    pub in_tangent: T,
+    /// Tangent of the spline leaving the keyframe. Used only
+    /// by [`Interpolation::CubicSpline`].
+    pub out_tangent: T,
+}
+
+impl<T: Zero> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Self::with_tangents(time, T::zero(), value, T::zero())
+    }
+}
+
+impl<T> Keyframe<T> {
+    pub fn with_tangents(time: f32, in_tangent: T, value: T, out_tangent: T) -> Self {
+        Self {
+            time,
+            value,
+            in_tangent,
+            out_tangent,
+        }
+    }
+}
+
+/// Keyframes of one component of a [`Transform`] with their interpolation.
+/// Keyframes must be sorted by time.
+#[derive(Debug, Clone)]
+pub struct Channel<T> {
+    pub keyframes: Vec<Keyframe<T>>,
+    pub interpolation: Interpolation,
+}
+
+impl<T> Default for Channel<T> {
+    fn default() -> Self {
+        Self {
+            keyframes: vec![],
+            interpolation: Interpolation::default(),
+        }
+    }
+}
+
+impl<T> Channel<T> {
+    pub fn new(interpolation: Interpolation, keyframes: Vec<Keyframe<T>>) -> Self {
+        Self {
+            keyframes,
+            interpolation,
+        }
+    }
+
+    pub fn duration(&self) -> Option<f32> {
+        self.keyframes.last().map(|k| k.time)
+    }
+}
+
+/// Channels for each component of a [`Transform`].
+/// Empty channels keep the default value.
+#[derive(Debug, Clone, Default)]
+pub struct TransformAnimation {
+    pub translations: Channel<Vector3<f32>>,
+    pub rotations: Channel<Quaternion<f32>>,
+    pub scales: Channel<Vector3<f32>>,
+}
+
+impl TransformAnimation {
+    pub fn duration(&self) -> f32 {
+        [
+            self.translations.duration(),
+            self.rotations.duration(),
+            self.scales.duration(),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f32::max)
+    }
+
+    /// Samples the transform at the `time`. Rotations are interpolated
+    /// along the shortest path and normalized afterwards.
+    pub fn sample(&self, time: f32) -> Transform {
+        let translation =
+            sample_channel(&self.translations, time, same_sign).unwrap_or_else(Vector3::zero);
+        let rotation = sample_channel(&self.rotations, time, shortest_path)
+            .map(|r| r.normalize())
+            .unwrap_or_else(|| Quaternion::new(1.0, 0.0, 0.0, 0.0));
+        let scale = sample_channel(&self.scales, time, same_sign)
+            .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0));
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+}
+
+fn same_sign<T>(_: &T, _: &T) -> f32 {
+    1.0
+}
+
+/// `q` and `-q` are the same rotation. Returns the sign making `b` closer
+/// to `a`, so interpolation between them takes the shortest path.
+fn shortest_path(a: &Quaternion<f32>, b: &Quaternion<f32>) -> f32 {
+    if a.dot(*b) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Samples channel at the `time`. Values of the keyframes are multiplied
+/// by the `sign` relative to the previous value before interpolation.
+fn sample_channel<T>(channel: &Channel<T>, time: f32, sign: fn(&T, &T) -> f32) -> Option<T>
+where
+    T: VectorSpace<Scalar = f32>,
+{
+    let keyframes = channel.keyframes.as_slice();
+    let first = keyframes.first()?;
+    let last = keyframes.last()?;
+    if time <= first.time {
+        return Some(first.value);
+    }
+    if last.time <= time {
+        return Some(last.value);
+    }
+
+    // index of the first keyframe after the time
+    let next = keyframes.partition_point(|k| k.time <= time);
+    let current = next - 1;
+    let k0 = &keyframes[current];
+    let k1 = &keyframes[next];
+    let t = (time - k0.time) / (k1.time - k0.time);
+
+    let k1_sign = sign(&k0.value, &k1.value);
+    let value = match channel.interpolation {
+        Interpolation::Step => k0.value,
+        Interpolation::Linear => k0.value.lerp(k1.value * k1_sign, t),
+        Interpolation::Cubic => {
+            let p0 = keyframes[current.saturating_sub(1)].value;
+            let p0 = p0 * sign(&k0.value, &p0);
+            let p2 = k1.value * k1_sign;
+            let p3 = keyframes[(next + 1).min(keyframes.len() - 1)].value;
+            let p3 = p3 * sign(&p2, &p3);
+            catmull_rom(p0, k0.value, p2, p3, t)
+        }
+        Interpolation::CubicSpline => hermite(
+            k0.value,
+            k0.out_tangent * (k1.time - k0.time),
+            k1.value * k1_sign,
+            k1.in_tangent * (k1_sign * (k1.time - k0.time)),
+            t,
+        ),
+    };
+    Some(value)
+}
+
+fn catmull_rom<T: VectorSpace<Scalar = f32>>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Cubic Hermite spline from `p0` with tangent `m0`
+/// to `p1` with tangent `m1`
+fn hermite<T: VectorSpace<Scalar = f32>>(p0: T, m0: T, p1: T, m1: T, t: f32) -> T {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    p0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + m0 * (t3 - 2.0 * t2 + t)
+        + p1 * (3.0 * t2 - 2.0 * t3)
+        + m1 * (t3 - t2)
+}
+
+/// Set of named [`TransformAnimation`] played together
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub animations: HashMap<String, TransformAnimation>,
+    pub speed: f32,
+    pub looping: bool,
+    time: f32,
+    playing: bool,
+}
+
+impl Default for Clip {
+    fn default() -> Self {
+        Self {
+            animations: HashMap::new(),
+            speed: 1.0,
+            looping: false,
+            time: 0.0,
+            playing: false,
+        }
+    }
+}
+
+impl Clip {
+    pub fn new(animations: HashMap<String, TransformAnimation>) -> Self {
+        Self {
+            animations,
+            ..Default::default()
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.animations
+            .values()
+            .map(|a| a.duration())
+            .fold(0.0, f32::max)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.time = 0.0;
+    }
+
+    pub fn set_loop(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Advances clip time. Non looping clips stop at the end.
+    pub fn update(&mut self, dt: Duration) {
+        if !self.playing {
+            return;
+        }
+        let duration = self.duration();
+        self.time += dt.as_secs_f32() * self.speed;
+        if self.looping && 0.0 < duration {
+            self.time = self.time.rem_euclid(duration);
+        } else if duration <= self.time {
+            self.time = duration;
+            self.playing = false;
+        } else if self.time < 0.0 {
+            self.time = 0.0;
+            self.playing = false;
+        }
+    }
+
+    /// Samples named animation at the current clip time
+    pub fn sample(&self, name: &str) -> Option<Transform> {
+        self.animations.get(name).map(|a| a.sample(self.time))
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl Clip {
+    /// Loads every glTF animation as a separate clip.
+    /// Animations are keyed by the target node name or by the node index if the node
+    /// has no name.
+    pub fn load_gltf<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Vec<(String, Self)>, crate::model::Error> {
+        use gltf::animation::util::ReadOutputs;
+
+        let (document, buffers, _images) = gltf::import(path.as_ref())?;
+
+        let mut clips = Vec::new();
+        for animation in document.animations() {
+            let mut animations: HashMap<String, TransformAnimation> = HashMap::new();
+            for channel in animation.channels() {
+                let node = channel.target().node();
+                let name = node
+                    .name()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| node.index().to_string());
+                let target = animations.entry(name).or_default();
+
+                let cubic = channel.sampler().interpolation()
+                    == gltf::animation::Interpolation::CubicSpline;
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+
+                let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                let Some(times) = reader.read_inputs() else {
+                    continue;
+                };
+                let times: Vec<f32> = times.collect();
+                // cubic spline stores (in tangent, value, out tangent) triplets
+                fn keyframes<T: Copy + Zero>(
+                    times: &[f32],
+                    values: &[T],
+                    cubic: bool,
+                    interpolation: Interpolation,
+                ) -> Channel<T> {
+                    let keyframes = times
+                        .iter()
+                        .enumerate()
+                        .map(|(i, t)| {
+                            if cubic {
+                                let [in_tangent, value, out_tangent] =
+                                    [values[i * 3], values[i * 3 + 1], values[i * 3 + 2]];
+                                Keyframe::with_tangents(*t, in_tangent, value, out_tangent)
+                            } else {
+                                Keyframe::new(*t, values[i])
+                            }
+                        })
+                        .collect();
+                    Channel::new(interpolation, keyframes)
+                }
+
+                match reader.read_outputs() {
+                    Some(ReadOutputs::Translations(values)) => {
+                        let values: Vec<Vector3<f32>> = values.map(Into::into).collect();
+                        target.translations = keyframes(&times, &values, cubic, interpolation);
+                    }
+                    Some(ReadOutputs::Rotations(values)) => {
+                        let values: Vec<Quaternion<f32>> = values
+                            .into_f32()
+                            .map(|r| Quaternion::new(r[3], r[0], r[1], r[2]))
+                            .collect();
+                        target.rotations = keyframes(&times, &values, cubic, interpolation);
+                    }
+                    Some(ReadOutputs::Scales(values)) => {
+                        let values: Vec<Vector3<f32>> = values.map(Into::into).collect();
+                        target.scales = keyframes(&times, &values, cubic, interpolation);
+                    }
+                    _ => {}
+                }
+            }
+            let name = animation
+                .name()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| animation.index().to_string());
+            clips.push((name, Clip::new(animations)));
+        }
+        Ok(clips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(interpolation: Interpolation) -> TransformAnimation {
+        TransformAnimation {
+            translations: Channel::new(
+                interpolation,
+                vec![
+                    Keyframe::new(0.0, Vector3::new(0.0, 0.0, 0.0)),
+                    Keyframe::new(1.0, Vector3::new(2.0, 0.0, 0.0)),
+                    Keyframe::new(2.0, Vector3::new(2.0, 4.0, 0.0)),
+                ],
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transform_animation_step() {
+        let a = animation(Interpolation::Step);
+        assert_eq!(a.sample(0.5).translation, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(a.sample(1.5).translation, Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(a.sample(3.0).translation, Vector3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn transform_animation_linear() {
+        let a = animation(Interpolation::Linear);
+        assert_eq!(a.sample(0.5).translation, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(a.sample(1.5).translation, Vector3::new(2.0, 2.0, 0.0));
+        assert_eq!(a.sample(1.5).scale, Vector3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn transform_animation_cubic_hits_keyframes() {
+        let a = animation(Interpolation::Cubic);
+        assert_eq!(a.sample(1.0).translation, Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(a.duration(), 2.0);
+    }
+
+    #[test]
+    fn transform_animation_cubic_spline() {
+        let a = TransformAnimation {
+            translations: Channel::new(
+                Interpolation::CubicSpline,
+                vec![
+                    Keyframe::with_tangents(
+                        0.0,
+                        Vector3::zero(),
+                        Vector3::new(0.0, 0.0, 0.0),
+                        Vector3::new(0.0, 2.0, 0.0),
+                    ),
+                    Keyframe::with_tangents(
+                        2.0,
+                        Vector3::new(0.0, -2.0, 0.0),
+                        Vector3::new(4.0, 0.0, 0.0),
+                        Vector3::zero(),
+                    ),
+                ],
+            ),
+            ..Default::default()
+        };
+        assert_eq!(a.sample(0.0).translation, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(a.sample(2.0).translation, Vector3::new(4.0, 0.0, 0.0));
+        // Tangents scaled by the keyframe interval bend the path upwards
+        assert_eq!(a.sample(1.0).translation, Vector3::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn transform_animation_rotation_shortest_path() {
+        let from = Quaternion::from_angle_z(Deg(10.0));
+        let to = Quaternion::from_angle_z(Deg(30.0));
+        for interpolation in [Interpolation::Linear, Interpolation::Cubic] {
+            let a = TransformAnimation {
+                // Same rotations with the second one in the opposite hemisphere
+                rotations: Channel::new(
+                    interpolation,
+                    vec![Keyframe::new(0.0, from), Keyframe::new(1.0, -to)],
+                ),
+                ..Default::default()
+            };
+            let rotation = a.sample(0.5).rotation;
+            let expected = Quaternion::from_angle_z(Deg(20.0));
+            assert!(
+                rotation.dot(expected).abs() > 0.9999,
+                "{interpolation:?}: {rotation:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn transform_animation_mixed_interpolation() {
+        let a = TransformAnimation {
+            translations: Channel::new(
+                Interpolation::Step,
+                vec![
+                    Keyframe::new(0.0, Vector3::new(0.0, 0.0, 0.0)),
+                    Keyframe::new(1.0, Vector3::new(2.0, 0.0, 0.0)),
+                ],
+            ),
+            scales: Channel::new(
+                Interpolation::Linear,
+                vec![
+                    Keyframe::new(0.0, Vector3::new(1.0, 1.0, 1.0)),
+                    Keyframe::new(1.0, Vector3::new(3.0, 3.0, 3.0)),
+                ],
+            ),
+            ..Default::default()
+        };
+        let transform = a.sample(0.5);
+        assert_eq!(transform.translation, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(transform.scale, Vector3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn clip_loops() {
+        let mut clip = Clip::new(HashMap::from([(
+            "node".to_string(),
+            animation(Interpolation::Linear),
+        )]));
+        clip.set_loop(true);
+        clip.play();
+        clip.update(Duration::from_secs_f32(2.5));
+        assert!(clip.is_playing());
+        assert_eq!(clip.time(), 0.5);
+        assert_eq!(
+            clip.sample("node").unwrap().translation,
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn clip_stops_at_end() {
+        let mut clip = Clip::new(HashMap::from([(
+            "node".to_string(),
+            animation(Interpolation::Linear),
+        )]));
+        clip.speed = 2.0;
+        clip.play();
+        clip.update(Duration::from_secs_f32(1.5));
+        assert!(!clip.is_playing());
+        assert_eq!(clip.time(), 2.0);
+    }
+}
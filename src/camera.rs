@@ -1,10 +1,10 @@
+use crate::input::{GamepadState, InputKey, InputState};
+use crate::ray::Ray;
 use crate::render::prelude::*;
 use crate::{cgmath_imports::*, impl_simple_buffer};
 use cgmath::SquareMatrix;
 use std::f32::consts::FRAC_PI_2;
 use std::time::Duration;
-use winit::event::ElementState;
-use winit::keyboard::{Key, NamedKey};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
@@ -15,6 +15,10 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
 );
 
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+/// Stick values below this are treated as 0
+const GAMEPAD_DEAD_ZONE: f32 = 0.1;
+/// Scales right stick values to match mouse deltas
+const GAMEPAD_LOOK_SPEED: f32 = 10.0;
 
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -26,21 +30,26 @@ pub struct CameraUniform {
     view_projection_without_translation: [[f32; 4]; 4],
     position: [f32; 3],
     _pad: f32,
+    /// Same as `view_projection` unless updated with
+    /// [`CameraHandle::update_with_previous`]
+    prev_view_projection: [[f32; 4]; 4],
+    /// Sub pixel offset in clip space. Shaders add `jitter * clip.w`
+    /// to the clip position.
+    jitter: [f32; 2],
+    _pad2: [f32; 2],
 }
 
 impl From<&Camera> for CameraUniform {
     fn from(value: &Camera) -> Self {
         let view = value.view();
         let projection = value.projection();
-        let position = match value {
-            Camera::Perspective(c) => c.position,
-            Camera::Orthogonal(c) => c.position,
-        };
+        let position = value.position();
         let vp = projection * view;
         Self {
             view: view.into(),
             projection: projection.into(),
             view_projection: vp.into(),
+            prev_view_projection: vp.into(),
             view_projection_inverse: vp.invert().unwrap().into(),
             view_projection_without_translation: (projection * value.view_without_translation())
                 .into(),
@@ -87,6 +96,13 @@ impl Camera {
         }
     }
 
+    pub fn position(&self) -> Point3<f32> {
+        match self {
+            Camera::Perspective(c) => c.position,
+            Camera::Orthogonal(c) => c.position,
+        }
+    }
+
     pub fn view_without_translation(&self) -> Matrix4<f32> {
         let view = self.view();
         Matrix4::from(Matrix3::from_cols(
@@ -110,6 +126,38 @@ impl Camera {
         OPENGL_TO_WGPU_MATRIX * matrix
     }
 
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection() * self.view()
+    }
+
+    /// World space corners of the view frustum. First 4 are on the near plane.
+    pub fn frustum_corners(&self) -> [Point3<f32>; 8] {
+        let inverse = self
+            .view_projection()
+            .invert()
+            .unwrap_or(Matrix4::identity());
+        std::array::from_fn(|i| {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let z = if i < 4 { 0.0 } else { 1.0 };
+            Point3::from_homogeneous(inverse * cgmath::Vector4::new(x, y, z, 1.0))
+        })
+    }
+
+    /// World space ray through the `pixel` of the `viewport` of this camera.
+    /// Pixels are counted from the top left corner. Ray starts on the near plane.
+    pub fn screen_ray(&self, pixel: (f32, f32), viewport: (u32, u32)) -> Ray {
+        let inverse = self
+            .view_projection()
+            .invert()
+            .unwrap_or(Matrix4::identity());
+        let x = pixel.0 / viewport.0.max(1) as f32 * 2.0 - 1.0;
+        let y = 1.0 - pixel.1 / viewport.1.max(1) as f32 * 2.0;
+        let unproject = |z| Point3::from_homogeneous(inverse * cgmath::Vector4::new(x, y, z, 1.0));
+        let near = unproject(0.0);
+        Ray::new(near, unproject(1.0) - near)
+    }
+
     pub fn projection(&self) -> Matrix4<f32> {
         let matrix = match self {
             Camera::Perspective(c) => perspective(c.fovy, c.aspect, c.znear, c.zfar),
@@ -126,10 +174,53 @@ impl_simple_buffer!(
     CameraHandle,
     CameraBindGroup,
     { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
-    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
+    { ShaderStages::VERTEX | ShaderStages::FRAGMENT | ShaderStages::COMPUTE },
     { BufferBindingType::Uniform }
 );
 
+impl CameraHandle {
+    /// Same as `update`, but also writes view projection of the previous
+    /// frame used for motion vectors
+    pub fn update_with_previous(
+        &self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        camera: &Camera,
+        prev_view_projection: Matrix4<f32>,
+    ) {
+        self.update_jittered(
+            renderer,
+            storage,
+            camera,
+            prev_view_projection,
+            Vector2::new(0.0, 0.0),
+        );
+    }
+
+    /// Same as `update_with_previous`, but also writes sub pixel
+    /// `jitter` in clip space units. Matrices stay unjittered,
+    /// so motion vectors are not affected by it.
+    pub fn update_jittered(
+        &self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        camera: &Camera,
+        prev_view_projection: Matrix4<f32>,
+        jitter: Vector2<f32>,
+    ) {
+        let uniform = CameraUniform {
+            prev_view_projection: prev_view_projection.into(),
+            jitter: jitter.into(),
+            ..camera.into()
+        };
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.buffer_id),
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct CameraController {
     pub speed: f32,
@@ -143,6 +234,7 @@ pub struct CameraController {
     rotate_horizontal: f32,
     rotate_vertical: f32,
     mouse_active: bool,
+    gamepad: GamepadState,
 }
 
 impl CameraController {
@@ -154,38 +246,18 @@ impl CameraController {
         }
     }
 
-    pub fn process_key(&mut self, key: Key, state: ElementState) -> bool {
-        let pressed = if state == ElementState::Pressed { 1 } else { 0 };
+    pub fn process_key(&mut self, key: InputKey, state: InputState) -> bool {
+        let pressed = if state.is_pressed() { 1 } else { 0 };
         match key {
-            Key::Named(NamedKey::Space) => {
-                self.up = pressed;
-                true
-            }
-            Key::Named(NamedKey::Shift) => {
-                self.down = pressed;
-                true
-            }
-            Key::Character(c) => match c.as_str() {
-                "w" | "W" => {
-                    self.forward = pressed;
-                    true
-                }
-                "s" | "S" => {
-                    self.backward = pressed;
-                    true
-                }
-                "a" | "A" => {
-                    self.left = pressed;
-                    true
-                }
-                "d" | "D" => {
-                    self.right = pressed;
-                    true
-                }
-                _ => false,
-            },
-            _ => false,
+            InputKey::Space => self.up = pressed,
+            InputKey::Shift => self.down = pressed,
+            InputKey::Character('w') => self.forward = pressed,
+            InputKey::Character('s') => self.backward = pressed,
+            InputKey::Character('a') => self.left = pressed,
+            InputKey::Character('d') => self.right = pressed,
+            _ => return false,
         }
+        true
     }
 
     pub fn set_mouse_active(&mut self, active: bool) {
@@ -199,6 +271,18 @@ impl CameraController {
         }
     }
 
+    /// Left stick moves, right stick looks around and triggers move down/up.
+    /// State is kept until the next call.
+    pub fn process_gamepad(&mut self, state: &GamepadState) {
+        let dead_zone = |v: f32| if v.abs() < GAMEPAD_DEAD_ZONE { 0.0 } else { v };
+        self.gamepad = GamepadState {
+            left_stick: state.left_stick.map(dead_zone),
+            right_stick: state.right_stick.map(dead_zone),
+            left_trigger: dead_zone(state.left_trigger),
+            right_trigger: dead_zone(state.right_trigger),
+        };
+    }
+
     pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
         match camera {
             Camera::Perspective(camera) => {
@@ -208,14 +292,23 @@ impl CameraController {
                 let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
                 let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
 
-                camera.position +=
-                    forward * (self.forward - self.backward) as f32 * self.speed * dt;
-                camera.position += right * (self.right - self.left) as f32 * self.speed * dt;
+                let gamepad = &self.gamepad;
+                let forward_amount = (self.forward - self.backward) as f32 + gamepad.left_stick[1];
+                let right_amount = (self.right - self.left) as f32 + gamepad.left_stick[0];
+                let up_amount =
+                    (self.up - self.down) as f32 + gamepad.right_trigger - gamepad.left_trigger;
 
-                camera.position.y += (self.up - self.down) as f32 * self.speed * dt;
+                camera.position += forward * forward_amount * self.speed * dt;
+                camera.position += right * right_amount * self.speed * dt;
 
-                camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
-                camera.pitch += Rad(self.rotate_vertical) * self.sensitivity * dt;
+                camera.position.y += up_amount * self.speed * dt;
+
+                let rotate_horizontal =
+                    self.rotate_horizontal + gamepad.right_stick[0] * GAMEPAD_LOOK_SPEED;
+                let rotate_vertical =
+                    self.rotate_vertical + gamepad.right_stick[1] * GAMEPAD_LOOK_SPEED;
+                camera.yaw += Rad(rotate_horizontal) * self.sensitivity * dt;
+                camera.pitch += Rad(rotate_vertical) * self.sensitivity * dt;
 
                 self.rotate_horizontal = 0.0;
                 self.rotate_vertical = 0.0;
@@ -230,3 +323,94 @@ impl CameraController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_camera() -> Camera {
+        Camera::Perspective(PerspectiveCamera {
+            position: (0.0, 0.0, 0.0).into(),
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            aspect: 1.0,
+            fovy: Deg(90.0).into(),
+            znear: 0.1,
+            zfar: 100.0,
+        })
+    }
+
+    #[test]
+    fn camera_frustum_corners() {
+        let camera = test_camera();
+        let view_projection = camera.view_projection();
+        let corners = camera.frustum_corners();
+        for (i, corner) in corners.iter().enumerate() {
+            let clip = view_projection * corner.to_homogeneous();
+            let ndc = clip.truncate() / clip.w;
+            assert!((ndc.x.abs() - 1.0).abs() < 1e-3, "{i}: {ndc:?}");
+            assert!((ndc.y.abs() - 1.0).abs() < 1e-3, "{i}: {ndc:?}");
+            assert!(
+                (ndc.z - if i < 4 { 0.0 } else { 1.0 }).abs() < 1e-3,
+                "{i}: {ndc:?}"
+            );
+        }
+        // Camera looks along +X
+        assert!(corners.iter().all(|c| 0.0 < c.x));
+        assert!(corners[0].x < corners[4].x);
+    }
+
+    #[test]
+    fn camera_screen_ray() {
+        let camera = test_camera();
+        let center = camera.screen_ray((50.0, 50.0), (100, 100));
+        assert!((center.direction - Vector3::unit_x()).magnitude() < 1e-4);
+
+        // Rays through projected points pass through them
+        let view_projection = camera.view_projection();
+        for point in [Point3::new(5.0, 1.0, 2.0), Point3::new(20.0, -8.0, -3.0)] {
+            let clip = view_projection * point.to_homogeneous();
+            let pixel = (
+                (clip.x / clip.w + 1.0) * 50.0,
+                (1.0 - clip.y / clip.w) * 50.0,
+            );
+            let ray = camera.screen_ray(pixel, (100, 100));
+            assert!(ray.distance_to_point(point) < 1e-3, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn camera_controller_gamepad_move() {
+        let mut camera = test_camera();
+        let mut controller = CameraController::new(1.0, 1.0);
+        controller.process_gamepad(&GamepadState {
+            left_stick: [0.0, 1.0],
+            right_trigger: 1.0,
+            ..Default::default()
+        });
+        controller.update_camera(&mut camera, Duration::from_secs(1));
+
+        let Camera::Perspective(c) = camera else {
+            unreachable!()
+        };
+        assert_eq!(c.position, Point3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn camera_controller_gamepad_dead_zone() {
+        let mut camera = test_camera();
+        let mut controller = CameraController::new(1.0, 1.0);
+        controller.process_gamepad(&GamepadState {
+            left_stick: [0.05, -0.05],
+            right_stick: [0.05, 0.05],
+            ..Default::default()
+        });
+        controller.update_camera(&mut camera, Duration::from_secs(1));
+
+        let Camera::Perspective(c) = camera else {
+            unreachable!()
+        };
+        assert_eq!(c.position, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(c.yaw, Rad(0.0));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::const_vec;
+use crate::fullscreen::{BlitSourceBindGroup, FullscreenTriangle};
+use crate::impl_simple_volume_texture_bind_group;
+use crate::mesh::ProceduralCommand;
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::texture::{GpuTexture, VolumeTexture};
+use crate::utils::ConstVec;
+use image::{GenericImageView, ImageError};
+use log::info;
+use std::path::Path;
+
+const COLOR_GRADE_SHADER: &str = include_str!("shaders/color_grade.wgsl");
+
+/// Format of the color grading LUT texture
+pub const COLOR_GRADE_LUT_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ColorGradeError {
+    #[error("Error reading LUT: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error loading LUT image: {0}")]
+    ImageLoad(#[from] ImageError),
+    #[error("Error parsing LUT at line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("LUT image of {width}x{height} is not a strip of square slices")]
+    InvalidImageSize { width: u32, height: u32 },
+    #[error("Unsupported LUT file: {0}")]
+    UnsupportedFile(String),
+}
+
+/// 3D color lookup table applied by the [`ColorGradePipeline`].
+/// Texels are ordered with red changing fastest, then green, then blue.
+#[derive(Debug, Clone)]
+pub struct ColorGrade {
+    /// Number of entries along each axis
+    pub size: u32,
+    pub texels: Vec<[f32; 4]>,
+}
+
+impl ColorGrade {
+    /// LUT which does not change colors
+    pub fn identity(size: u32) -> Self {
+        let max = (size.max(2) - 1) as f32;
+        let texels = (0..size * size * size)
+            .map(|i| {
+                let r = i % size;
+                let g = i / size % size;
+                let b = i / (size * size);
+                [r as f32 / max, g as f32 / max, b as f32 / max, 1.0]
+            })
+            .collect();
+        Self { size, texels }
+    }
+
+    /// Loads LUT from the `.cube` file or from the image.
+    /// Images must be a horizontal strip of `size` slices of `size` x `size`
+    /// pixels, with blue selecting the slice, red going right and green going down.
+    pub fn load_lut<P: AsRef<Path>>(path: P) -> Result<Self, ColorGradeError> {
+        let path = path.as_ref();
+        info!("Loading color grading LUT from {:#?}", path);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("cube") => {
+                Self::parse_cube(&std::fs::read_to_string(path)?)
+            }
+            Some(_) => Self::from_image(&image::open(path)?),
+            None => Err(ColorGradeError::UnsupportedFile(path.display().to_string())),
+        }
+    }
+
+    /// Parses LUT in the Adobe `.cube` format. Only 3D LUTs
+    /// with the default [0, 1] domain are supported.
+    pub fn parse_cube(source: &str) -> Result<Self, ColorGradeError> {
+        let mut size = None;
+        let mut texels = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let parse_error = |message: &str| ColorGradeError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("LUT_3D_SIZE") => {
+                    let s = words
+                        .next()
+                        .and_then(|s| s.parse::<u32>().ok())
+                        .filter(|s| 2 <= *s)
+                        .ok_or_else(|| parse_error("invalid LUT_3D_SIZE"))?;
+                    size = Some(s);
+                }
+                Some("LUT_1D_SIZE") => return Err(parse_error("1D LUTs are not supported")),
+                Some("TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX") => {}
+                Some(_) => {
+                    let values = line
+                        .split_whitespace()
+                        .map(|v| v.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| parse_error("invalid value"))?;
+                    let [r, g, b] = values[..] else {
+                        return Err(parse_error("expected 3 values"));
+                    };
+                    texels.push([r, g, b, 1.0]);
+                }
+                None => {}
+            }
+        }
+
+        let size = size.ok_or(ColorGradeError::Parse {
+            line: 0,
+            message: "missing LUT_3D_SIZE".to_string(),
+        })?;
+        if texels.len() != (size * size * size) as usize {
+            return Err(ColorGradeError::Parse {
+                line: 0,
+                message: format!(
+                    "expected {} entries, found {}",
+                    size * size * size,
+                    texels.len()
+                ),
+            });
+        }
+        Ok(Self { size, texels })
+    }
+
+    /// Converts image strip into the LUT. See [`ColorGrade::load_lut`] for the layout.
+    pub fn from_image(image: &image::DynamicImage) -> Result<Self, ColorGradeError> {
+        let (width, height) = image.dimensions();
+        let size = height;
+        if size < 2 || width != size * size {
+            return Err(ColorGradeError::InvalidImageSize { width, height });
+        }
+        let image = image.to_rgba32f();
+        let texels = (0..size * size * size)
+            .map(|i| {
+                let r = i % size;
+                let g = i / size % size;
+                let b = i / (size * size);
+                let [r, g, b, _] = image.get_pixel(b * size + r, g).0;
+                [r, g, b, 1.0]
+            })
+            .collect();
+        Ok(Self { size, texels })
+    }
+}
+
+impl GpuResource for ColorGrade {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let texels: Vec<u16> = self
+            .texels
+            .iter()
+            .flatten()
+            .map(|c| half::f16::from_f32(*c).to_bits())
+            .collect();
+        VolumeTexture {
+            dimensions: (self.size, self.size, self.size),
+            format: COLOR_GRADE_LUT_FORMAT,
+            data: Some(bytemuck::cast_slice(&texels).to_vec()),
+            filtered: true,
+        }
+        .build(renderer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColorGradeHandle {
+    pub texture_id: TextureId,
+}
+
+impl ResourceHandle for ColorGradeHandle {
+    type OriginalResource<'a> = ColorGrade;
+    type ResourceType = GpuTexture;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            texture_id: storage.insert_texture(resource),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_texture(self.texture_id, resource);
+    }
+}
+
+impl_simple_volume_texture_bind_group!(
+    ColorGradeHandle,
+    ColorGradeBindGroup,
+    { TextureSampleType::Float { filterable: true } },
+    { SamplerBindingType::Filtering }
+);
+
+/// Applies [`ColorGrade`] LUT to the source texture with a [`FullscreenTriangle`].
+/// Meant to be the last pass before presenting, after tone mapping.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorGradePipeline {
+    pub pipeline_id: PipelineId,
+}
+
+impl ColorGradePipeline {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage, format: TextureFormat) -> Self {
+        let shader = create_shader_module(renderer, "color_grade.wgsl", COLOR_GRADE_SHADER.into());
+        let pipeline = PipelineBuilder {
+            shader_path: "color_grade.wgsl",
+            label: Some("color_grade_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<BlitSourceBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<ColorGradeBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_pipeline(pipeline),
+        }
+    }
+
+    /// Command drawing graded `source` over the whole render target
+    pub fn command(
+        &self,
+        source: BlitSourceBindGroup,
+        lut: ColorGradeBindGroup,
+    ) -> ProceduralCommand {
+        FullscreenTriangle::command(
+            self.pipeline_id,
+            const_vec![
+                BindGroupMeta::new(0, source.0),
+                BindGroupMeta::new(1, lut.0),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_grade_parse_cube() {
+        let source = "
+            # comment
+            TITLE \"test\"
+            LUT_3D_SIZE 2
+            DOMAIN_MIN 0 0 0
+            0 0 0
+            1 0 0
+            0 1 0
+            1 1 0
+            0 0 1
+            1 0 1
+            0 1 1
+            1 1 1
+        ";
+        let lut = ColorGrade::parse_cube(source).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.texels, ColorGrade::identity(2).texels);
+    }
+
+    #[test]
+    fn color_grade_parse_cube_errors() {
+        assert!(matches!(
+            ColorGrade::parse_cube("LUT_3D_SIZE 2\n0 0 0\n"),
+            Err(ColorGradeError::Parse { line: 0, .. })
+        ));
+        assert!(matches!(
+            ColorGrade::parse_cube("LUT_3D_SIZE 2\n0 0\n"),
+            Err(ColorGradeError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn color_grade_from_image() {
+        let image = image::RgbaImage::from_fn(4, 2, |x, y| {
+            let (r, g, b) = (x % 2, y, x / 2);
+            image::Rgba([(r * 255) as u8, (g * 255) as u8, (b * 255) as u8, 255])
+        });
+        let lut = ColorGrade::from_image(&image.into()).unwrap();
+        assert_eq!(lut.texels, ColorGrade::identity(2).texels);
+
+        let image = image::RgbaImage::new(3, 2);
+        assert!(ColorGrade::from_image(&image.into()).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::const_vec;
+use crate::impl_simple_texture_bind_group;
+use crate::mesh::MeshRenderCommand;
+use crate::render::prelude::*;
+use crate::texture::{EmptyTexture, GpuTexture};
+use crate::utils::ConstVec;
+
+/// Format of the cone marching depth levels
+pub const CONE_MARCHER_DEPTH_FORMAT: TextureFormat = TextureFormat::R32Float;
+/// Size of the coarsest depth level. Each next level is twice as big.
+pub const CONE_MARCHER_BASE_SIZE: u32 = 16;
+
+/// Single square depth level of the [`ConeMarcher`]
+#[derive(Debug, Clone, Copy)]
+pub struct ConeDepth {
+    pub size: u32,
+}
+
+impl GpuResource for ConeDepth {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        EmptyTexture {
+            dimensions: Some((self.size, self.size)),
+            format: CONE_MARCHER_DEPTH_FORMAT,
+            filtered: false,
+            compare: None,
+        }
+        .build(renderer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConeDepthHandle {
+    pub texture_id: TextureId,
+}
+
+impl ResourceHandle for ConeDepthHandle {
+    type OriginalResource<'a> = ConeDepth;
+    type ResourceType = GpuTexture;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            texture_id: storage.insert_texture(resource),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_texture(self.texture_id, resource);
+    }
+}
+
+impl_simple_texture_bind_group!(
+    ConeDepthHandle,
+    ConeDepthBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: false } },
+    { SamplerBindingType::NonFiltering }
+);
+
+/// Chain of depth levels for cone marching SDF scenes.
+/// Every prepass renders into the next level while sampling the previous one
+/// to start marching from the already found distance. The final pass
+/// samples the finest level.
+///
+/// The SDF itself lives in the user shaders: the prepass pipeline has to
+/// write the distance into [`CONE_MARCHER_DEPTH_FORMAT`] target and
+/// both prepass and final pipelines have to bind [`ConeDepthBindGroup`].
+#[derive(Debug)]
+pub struct ConeMarcher {
+    pub handles: Vec<ConeDepthHandle>,
+    pub bind_groups: Vec<ConeDepthBindGroup>,
+    /// Phases for all levels except the first one, which is never rendered into
+    pub phases: Vec<RenderPhase>,
+}
+
+impl ConeMarcher {
+    /// Size of the depth level at `level` index
+    pub fn level_size(level: u32) -> u32 {
+        CONE_MARCHER_BASE_SIZE << level
+    }
+
+    /// Number of levels needed for the finest level
+    /// to be at least half of the target resolution
+    pub fn levels_for_resolution(width: u32, height: u32) -> u32 {
+        let target = (width.max(height) / 2).max(CONE_MARCHER_BASE_SIZE * 2);
+        let mut levels = 1;
+        while Self::level_size(levels - 1) < target {
+            levels += 1;
+        }
+        levels
+    }
+
+    /// Creates `levels` depth levels with bind groups and phases.
+    /// Needs at least 2 levels.
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage, levels: u32) -> Self {
+        assert!(2 <= levels, "ConeMarcher needs at least 2 levels");
+
+        let handles: Vec<_> = (0..levels)
+            .map(|level| {
+                let depth = ConeDepth {
+                    size: Self::level_size(level),
+                };
+                ConeDepthHandle::new(storage, depth.build(renderer))
+            })
+            .collect();
+        let bind_groups = handles
+            .iter()
+            .map(|handle| ConeDepthBindGroup::new(renderer, storage, handle))
+            .collect();
+        let phases = handles[1..]
+            .iter()
+            .map(|handle| {
+                RenderPhase::new(
+                    const_vec![ColorAttachment {
+                        view_id: handle.texture_id,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::TRANSPARENT),
+                            store: StoreOp::Store,
+                        },
+                    }],
+                    None,
+                )
+            })
+            .collect();
+
+        Self {
+            handles,
+            bind_groups,
+            phases,
+        }
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.handles.len() as u32
+    }
+
+    /// Bind group of the finest level for the final pass
+    pub fn final_bind_group(&self) -> ConeDepthBindGroup {
+        *self.bind_groups.last().unwrap()
+    }
+
+    /// Prepass commands in the same order as `phases`.
+    /// Each command binds the previous level at `depth_group` index
+    /// in addition to the shared `bind_groups`.
+    pub fn prepass_commands(
+        &self,
+        pipeline_id: PipelineId,
+        mesh_id: MeshId,
+        bind_groups: &[BindGroupMeta],
+        depth_group: u32,
+    ) -> Vec<MeshRenderCommand> {
+        self.bind_groups[..self.bind_groups.len() - 1]
+            .iter()
+            .map(|depth| MeshRenderCommand {
+                pipeline_id,
+                mesh_id,
+                index_slice: None,
+                vertex_slice: None,
+                scissor_rect: None,
+                viewport: None,
+                bind_groups: bind_groups
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once(BindGroupMeta::new(depth_group, depth.0)))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cone_marcher_levels_for_resolution() {
+        // 16, 32, 64, 128, 256, 512
+        assert_eq!(ConeMarcher::levels_for_resolution(1024, 768), 6);
+        assert_eq!(ConeMarcher::levels_for_resolution(1280, 720), 7);
+        assert_eq!(ConeMarcher::levels_for_resolution(1, 1), 2);
+    }
+}
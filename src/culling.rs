@@ -0,0 +1,352 @@
+use crate::cgmath_imports::*;
+use crate::render::prelude::*;
+use crate::transform::Transform;
+use crate::utils::ConstVec;
+use wgpu::util::DrawIndexedIndirectArgs;
+
+/// Number of instances processed by one compute workgroup.
+/// Culling shader should use the same workgroup size.
+pub const CULLING_WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CullingInstance {
+    transform: [[f32; 4]; 4],
+    aabb_min: [f32; 4],
+    aabb_max: [f32; 4],
+}
+
+impl CullingInstance {
+    /// Creates instance with the local space AABB of the mesh
+    pub fn new(transform: &Transform, aabb_min: [f32; 3], aabb_max: [f32; 3]) -> Self {
+        Self {
+            transform: Matrix4::from(transform).into(),
+            aabb_min: [aabb_min[0], aabb_min[1], aabb_min[2], 1.0],
+            aabb_max: [aabb_max[0], aabb_max[1], aabb_max[2], 1.0],
+        }
+    }
+}
+
+/// Instances of one mesh culled on the GPU.
+/// Culling compute pass writes indices of visible instances and
+/// number of instances to draw into the indirect buffer.
+#[derive(Debug)]
+pub struct GpuCulling {
+    pub instances: Vec<CullingInstance>,
+    /// Number of indices of the mesh being instanced
+    pub index_count: u32,
+}
+
+impl GpuCulling {
+    fn indirect_args(&self) -> DrawIndexedIndirectArgs {
+        DrawIndexedIndirectArgs {
+            index_count: self.index_count,
+            instance_count: 0,
+            first_index: 0,
+            base_vertex: 0,
+            first_instance: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GpuCullingResources {
+    instances_buffer: Buffer,
+    visible_buffer: Buffer,
+    indirect_buffer: Buffer,
+}
+
+impl GpuResource for GpuCulling {
+    type ResourceType = GpuCullingResources;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let instances_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("culling_instances_buffer"),
+            contents: bytemuck::cast_slice(&self.instances),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let visible_buffer = renderer.device().create_buffer(&BufferDescriptor {
+            label: Some("culling_visible_buffer"),
+            size: (std::mem::size_of::<u32>() * self.instances.len().max(1)) as BufferAddress,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let indirect_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("culling_indirect_buffer"),
+            contents: self.indirect_args().as_bytes(),
+            usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+        });
+
+        Self::ResourceType {
+            instances_buffer,
+            visible_buffer,
+            indirect_buffer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GpuCullingHandle {
+    pub instances_buffer_id: BufferId,
+    pub visible_buffer_id: BufferId,
+    pub indirect_buffer_id: BufferId,
+}
+
+impl GpuCullingHandle {
+    /// Resets number of visible instances. Needs to be called every
+    /// frame before the culling pass.
+    pub fn reset(&self, renderer: &Renderer, storage: &RenderStorage, original: &GpuCulling) {
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.indirect_buffer_id),
+            0,
+            original.indirect_args().as_bytes(),
+        );
+    }
+}
+
+impl ResourceHandle for GpuCullingHandle {
+    type OriginalResource<'a> = GpuCulling;
+    type ResourceType = GpuCullingResources;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            instances_buffer_id: storage.insert_buffer(resource.instances_buffer),
+            visible_buffer_id: storage.insert_buffer(resource.visible_buffer),
+            indirect_buffer_id: storage.insert_buffer(resource.indirect_buffer),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_buffer(self.instances_buffer_id, resource.instances_buffer);
+        storage.replace_buffer(self.visible_buffer_id, resource.visible_buffer);
+        storage.replace_buffer(self.indirect_buffer_id, resource.indirect_buffer);
+    }
+
+    /// Writes instances data. Number of instances must not change.
+    fn update(&self, renderer: &Renderer, storage: &RenderStorage, original: &GpuCulling) {
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.instances_buffer_id),
+            0,
+            bytemuck::cast_slice(&original.instances),
+        );
+    }
+}
+
+fn storage_buffer_entry(
+    binding: u32,
+    visibility: ShaderStages,
+    read_only: bool,
+) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Bind group used by the culling compute pass
+/// - binding 0: instances (read)
+/// - binding 1: visible instances indices (read_write)
+/// - binding 2: indirect draw args (read_write)
+#[derive(Debug, Clone, Copy)]
+pub struct GpuCullingBindGroup(pub BindGroupId);
+
+impl GpuCullingBindGroup {
+    fn create_bind_group(
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        resource: &GpuCullingHandle,
+    ) -> BindGroup {
+        renderer.device().create_bind_group(&BindGroupDescriptor {
+            layout: &storage.get_bind_group_layout::<Self>(renderer),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: storage
+                        .get_buffer(resource.instances_buffer_id)
+                        .as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: storage
+                        .get_buffer(resource.visible_buffer_id)
+                        .as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: storage
+                        .get_buffer(resource.indirect_buffer_id)
+                        .as_entire_binding(),
+                },
+            ],
+            label: Some(std::any::type_name::<Self>()),
+        })
+    }
+}
+
+impl AssetBindGroup for GpuCullingBindGroup {
+    type ResourceHandle = GpuCullingHandle;
+
+    fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+        renderer
+            .device()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    storage_buffer_entry(0, ShaderStages::COMPUTE, true),
+                    storage_buffer_entry(1, ShaderStages::COMPUTE, false),
+                    storage_buffer_entry(2, ShaderStages::COMPUTE, false),
+                ],
+                label: Some(std::any::type_name::<Self>()),
+            })
+    }
+
+    fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) -> Self {
+        let bind_group = Self::create_bind_group(renderer, storage, resource);
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![],
+                buffers: vec![
+                    resource.instances_buffer_id,
+                    resource.visible_buffer_id,
+                    resource.indirect_buffer_id,
+                ],
+            },
+        ))
+    }
+
+    fn replace(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) {
+        let bind_group = Self::create_bind_group(renderer, storage, resource);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![],
+                buffers: vec![
+                    resource.instances_buffer_id,
+                    resource.visible_buffer_id,
+                    resource.indirect_buffer_id,
+                ],
+            },
+        );
+    }
+}
+
+/// Bind group used by the vertex shader to find instance data
+/// - binding 0: instances (read)
+/// - binding 1: visible instances indices (read)
+#[derive(Debug, Clone, Copy)]
+pub struct GpuCullingInstancesBindGroup(pub BindGroupId);
+
+impl GpuCullingInstancesBindGroup {
+    fn create_bind_group(
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        resource: &GpuCullingHandle,
+    ) -> BindGroup {
+        renderer.device().create_bind_group(&BindGroupDescriptor {
+            layout: &storage.get_bind_group_layout::<Self>(renderer),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: storage
+                        .get_buffer(resource.instances_buffer_id)
+                        .as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: storage
+                        .get_buffer(resource.visible_buffer_id)
+                        .as_entire_binding(),
+                },
+            ],
+            label: Some(std::any::type_name::<Self>()),
+        })
+    }
+}
+
+impl AssetBindGroup for GpuCullingInstancesBindGroup {
+    type ResourceHandle = GpuCullingHandle;
+
+    fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+        renderer
+            .device()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    storage_buffer_entry(0, ShaderStages::VERTEX, true),
+                    storage_buffer_entry(1, ShaderStages::VERTEX, true),
+                ],
+                label: Some(std::any::type_name::<Self>()),
+            })
+    }
+
+    fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) -> Self {
+        let bind_group = Self::create_bind_group(renderer, storage, resource);
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![],
+                buffers: vec![resource.instances_buffer_id, resource.visible_buffer_id],
+            },
+        ))
+    }
+
+    fn replace(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) {
+        let bind_group = Self::create_bind_group(renderer, storage, resource);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![],
+                buffers: vec![resource.instances_buffer_id, resource.visible_buffer_id],
+            },
+        );
+    }
+}
+
+/// Dispatches culling compute pipeline for all instances.
+#[derive(Debug, Clone)]
+pub struct CullingCommand {
+    pub pipeline_id: ComputePipelineId,
+    pub num_instances: u32,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupId>,
+}
+
+impl CullingCommand {
+    pub fn execute(&self, encoder: &mut CommandEncoder, storage: &RenderStorage) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("culling_pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(storage.get_compute_pipeline(self.pipeline_id));
+        for (i, bg) in self.bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(i as u32, storage.get_bind_group(*bg), &[]);
+        }
+        compute_pass.dispatch_workgroups(self.num_instances.div_ceil(CULLING_WORKGROUP_SIZE), 1, 1);
+    }
+}
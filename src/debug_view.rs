@@ -0,0 +1,106 @@
+use crate::input::InputKey;
+use crate::{impl_simple_buffer, render::prelude::*};
+
+/// Render target shown by the debug view pass
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Position,
+    /// Normals remapped from [-1, 1] to [0, 1]
+    Normal,
+    Albedo,
+    /// Linearized scene depth
+    Depth,
+    ShadowMap,
+    /// Lit image, debug view pass is not needed
+    #[default]
+    Final,
+}
+
+impl DebugView {
+    const ALL: [DebugView; 6] = [
+        DebugView::Position,
+        DebugView::Normal,
+        DebugView::Albedo,
+        DebugView::Depth,
+        DebugView::ShadowMap,
+        DebugView::Final,
+    ];
+
+    /// Next view in the cycle. Goes back to `Position` after `Final`.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Maps number keys 1..=6 to the views in declaration order
+    pub fn from_key(key: InputKey) -> Option<Self> {
+        match key {
+            InputKey::Character(c @ '1'..='6') => Some(Self::ALL[c as usize - '1' as usize]),
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugViewPassUniform {
+    view: u32,
+    znear: f32,
+    zfar: f32,
+    _pad: f32,
+}
+
+impl From<&DebugViewPass> for DebugViewPassUniform {
+    fn from(value: &DebugViewPass) -> Self {
+        Self {
+            view: value.view as u32,
+            znear: value.znear,
+            zfar: value.zfar,
+            ..Default::default()
+        }
+    }
+}
+
+/// Settings of the pass drawing one of the deferred rendering targets
+/// fullscreen. Near and far planes of the camera are used to linearize depth.
+#[derive(Debug)]
+pub struct DebugViewPass {
+    pub view: DebugView,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl_simple_buffer!(
+    DebugViewPass,
+    DebugViewPassUniform,
+    DebugViewPassResources,
+    DebugViewPassHandle,
+    DebugViewPassBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_view_next() {
+        assert_eq!(DebugView::Position.next(), DebugView::Normal);
+        assert_eq!(DebugView::Final.next(), DebugView::Position);
+    }
+
+    #[test]
+    fn debug_view_from_key() {
+        assert_eq!(
+            DebugView::from_key(InputKey::Character('1')),
+            Some(DebugView::Position)
+        );
+        assert_eq!(
+            DebugView::from_key(InputKey::Character('6')),
+            Some(DebugView::Final)
+        );
+        assert_eq!(DebugView::from_key(InputKey::Character('7')), None);
+        assert_eq!(DebugView::from_key(InputKey::Space), None);
+    }
+}
@@ -1,5 +1,7 @@
 use std::{borrow::Cow, collections::HashMap, num::NonZeroU64};
 
+use log::trace;
+
 use wgpu::BufferDescriptor;
 
 use crate::{
@@ -11,7 +13,7 @@ use crate::{
 };
 
 pub struct EguiRenderContext {
-    mesh_id: ResourceId,
+    mesh_id: MeshId,
     index_buffer_slices: Vec<std::ops::Range<u64>>,
     vertex_buffer_slices: Vec<std::ops::Range<u64>>,
 
@@ -58,7 +60,7 @@ impl EguiRenderContext {
             self.create_or_update_texture(renderer, storage, texture_id, imgae_delta);
         }
         for texture_id in textures_delta.free {
-            println!("freeing texture: {:?}", texture_id);
+            trace!("Freeing egui texture: {:?}", texture_id);
             // TODO
             // self.free_texture(f);
         }
@@ -71,65 +73,16 @@ impl EguiRenderContext {
         texture_id: egui::TextureId,
         image_delta: egui::epaint::ImageDelta,
     ) {
-        let width = image_delta.image.width() as u32;
-        let height = image_delta.image.height() as u32;
-
-        let texture_size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-
-        let data_color32 = match &image_delta.image {
-            egui::epaint::ImageData::Color(image) => {
-                assert_eq!(
-                    width as usize * height as usize,
-                    image.pixels.len(),
-                    "Mismatch between texture size and texel count"
-                );
-                Cow::Borrowed(&image.pixels)
-            }
-            egui::epaint::ImageData::Font(image) => {
-                assert_eq!(
-                    width as usize * height as usize,
-                    image.pixels.len(),
-                    "Mismatch between texture size and texel count"
-                );
-                Cow::Owned(image.srgba_pixels(None).collect::<Vec<_>>())
-            }
-        };
-        let data_bytes: &[u8] = bytemuck::cast_slice(data_color32.as_slice());
-
-        if let Some(pos) = image_delta.pos {
-            // update the existing texture
-            let (texture_handle, _texture_bind_group) = self
+        let texture_handle = image_delta.pos.map(|_| {
+            &self
                 .textures
                 .get(&texture_id)
-                .expect("Tried to update a texture that has not been allocated yet.");
-            let origin = wgpu::Origin3d {
-                x: pos[0] as u32,
-                y: pos[1] as u32,
-                z: 0,
-            };
-            let texture = EguiTexture {
-                texture: data_bytes,
-                size: texture_size,
-                origin,
-            };
-            texture_handle.update(renderer, storage, &texture);
-        } else {
-            // allocate a new texture
-            let origin = wgpu::Origin3d::ZERO;
-            let texture = EguiTexture {
-                texture: data_bytes,
-                size: texture_size,
-                origin,
-            };
-            let texture_handle = EguiTextureHandle::new(storage, texture.build(renderer));
-            let texture_bind_group = EguiTextureBindGroup::new(renderer, storage, &texture_handle);
-            self.textures
-                .insert(texture_id, (texture_handle, texture_bind_group));
-        };
+                .expect("Tried to update a texture that has not been allocated yet.")
+                .0
+        });
+        if let Some(texture) = write_image_delta(renderer, storage, texture_handle, &image_delta) {
+            self.textures.insert(texture_id, texture);
+        }
     }
 
     pub fn update_meshes(
@@ -233,7 +186,7 @@ impl EguiRenderContext {
 
     pub fn create_commands(
         &self,
-        pipeline_id: ResourceId,
+        pipeline_id: PipelineId,
         primitives: &[egui::epaint::ClippedPrimitive],
     ) -> Vec<MeshRenderCommand> {
         primitives
@@ -268,9 +221,10 @@ impl EguiRenderContext {
                                 index_slice: Some(index_slice.clone()),
                                 vertex_slice: Some(vertex_slice.clone()),
                                 scissor_rect: Some([rect.x, rect.y, rect.width, rect.height]),
+                                viewport: None,
                                 bind_groups: const_vec![
-                                    self.uniform_buffer_bind_group.0,
-                                    texture_bind_group.0
+                                    BindGroupMeta::new(0, self.uniform_buffer_bind_group.0),
+                                    BindGroupMeta::new(1, texture_bind_group.0),
                                 ],
                             })
                         }
@@ -282,6 +236,73 @@ impl EguiRenderContext {
     }
 }
 
+/// Writes egui image delta into the provided texture if the delta is partial.
+/// Otherwise allocates a new texture and returns it.
+pub(crate) fn write_image_delta(
+    renderer: &Renderer,
+    storage: &mut RenderStorage,
+    texture_handle: Option<&EguiTextureHandle>,
+    image_delta: &egui::epaint::ImageDelta,
+) -> Option<(EguiTextureHandle, EguiTextureBindGroup)> {
+    let width = image_delta.image.width() as u32;
+    let height = image_delta.image.height() as u32;
+
+    let texture_size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let data_color32 = match &image_delta.image {
+        egui::epaint::ImageData::Color(image) => {
+            assert_eq!(
+                width as usize * height as usize,
+                image.pixels.len(),
+                "Mismatch between texture size and texel count"
+            );
+            Cow::Borrowed(&image.pixels)
+        }
+        egui::epaint::ImageData::Font(image) => {
+            assert_eq!(
+                width as usize * height as usize,
+                image.pixels.len(),
+                "Mismatch between texture size and texel count"
+            );
+            Cow::Owned(image.srgba_pixels(None).collect::<Vec<_>>())
+        }
+    };
+    let data_bytes: &[u8] = bytemuck::cast_slice(data_color32.as_slice());
+
+    if let Some(pos) = image_delta.pos {
+        // update the existing texture
+        let texture_handle =
+            texture_handle.expect("Tried to update a texture that has not been allocated yet.");
+        let origin = wgpu::Origin3d {
+            x: pos[0] as u32,
+            y: pos[1] as u32,
+            z: 0,
+        };
+        let texture = EguiTexture {
+            texture: data_bytes,
+            size: texture_size,
+            origin,
+        };
+        texture_handle.update(renderer, storage, &texture);
+        None
+    } else {
+        // allocate a new texture
+        let origin = wgpu::Origin3d::ZERO;
+        let texture = EguiTexture {
+            texture: data_bytes,
+            size: texture_size,
+            origin,
+        };
+        let texture_handle = EguiTextureHandle::new(storage, texture.build(renderer));
+        let texture_bind_group = EguiTextureBindGroup::new(renderer, storage, &texture_handle);
+        Some((texture_handle, texture_bind_group))
+    }
+}
+
 struct ScissorRect {
     x: u32,
     y: u32,
@@ -411,7 +432,7 @@ impl<'a> GpuResource for EguiTexture<'a> {
 
 #[derive(Debug, Clone, Copy)]
 pub struct EguiTextureHandle {
-    pub texture_id: ResourceId,
+    pub texture_id: TextureId,
 }
 
 impl ResourceHandle for EguiTextureHandle {
@@ -0,0 +1,196 @@
+use crate::const_vec;
+use crate::impl_simple_texture_bind_group;
+use crate::mesh::{GpuMesh, ProceduralCommand};
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::texture::TextureVertex;
+use crate::utils::ConstVec;
+
+const BLIT_SHADER: &str = include_str!("shaders/blit.wgsl");
+
+/// Two triangles covering the whole screen in clip space.
+/// Texture coordinates start at the top left corner unless `flip_v` is set.
+/// [`FullscreenTriangle`] is cheaper for passes that don't need vertex buffers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FullscreenQuad {
+    pub flip_v: bool,
+}
+
+impl FullscreenQuad {
+    /// Quad showing texture with `source` origin in the orientation of the renderer
+    pub fn new(renderer: &Renderer, source: UvOrigin) -> Self {
+        Self {
+            flip_v: source.flips_v(renderer.uv_origin()),
+        }
+    }
+
+    fn vertices(&self) -> [([f32; 3], [f32; 2]); 4] {
+        let (top, bottom) = if self.flip_v { (1.0, 0.0) } else { (0.0, 1.0) };
+        [
+            ([-1.0, 1.0, 0.0], [0.0, top]),
+            ([-1.0, -1.0, 0.0], [0.0, bottom]),
+            ([1.0, 1.0, 0.0], [1.0, top]),
+            ([1.0, -1.0, 0.0], [1.0, bottom]),
+        ]
+    }
+}
+
+impl GpuResource for FullscreenQuad {
+    type ResourceType = GpuMesh;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let vertices: Vec<TextureVertex> = self.vertices().into_iter().map(Into::into).collect();
+
+        let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("fullscreen_quad_vertex_buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let indices: [u32; 6] = [0, 1, 2, 2, 1, 3];
+
+        let index_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("fullscreen_quad_index_buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: BufferUsages::INDEX,
+        });
+
+        GpuMesh {
+            vertex_buffer,
+            index_buffer: Some(index_buffer),
+            num_elements: indices.len() as u32,
+        }
+    }
+}
+
+/// Single triangle covering the whole screen. Draws 3 vertices
+/// without any vertex buffer, so positions have to be generated
+/// in the vertex shader:
+/// ```wgsl
+/// @vertex
+/// fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+///   let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+///   var out: VertexOutput;
+///   out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+///   out.tex_coords = uv;
+///   return out;
+/// }
+/// ```
+/// Triangle has counter clockwise winding and texture coordinates
+/// start at the top left corner.
+#[derive(Debug, Clone, Copy)]
+pub struct FullscreenTriangle;
+
+impl FullscreenTriangle {
+    pub const VERTEX_COUNT: u32 = 3;
+
+    /// Command drawing the triangle with the `pipeline_id` pipeline
+    pub fn command(
+        pipeline_id: PipelineId,
+        bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupMeta>,
+    ) -> ProceduralCommand {
+        ProceduralCommand {
+            pipeline_id,
+            vertex_count: Self::VERTEX_COUNT,
+            bind_groups,
+        }
+    }
+}
+
+/// Texture sampled by the [`BlitPipeline`]. Can point to any
+/// filterable float texture in the storage.
+#[derive(Debug, Clone, Copy)]
+pub struct BlitSourceHandle {
+    pub texture_id: TextureId,
+}
+
+impl_simple_texture_bind_group!(
+    BlitSourceHandle,
+    BlitSourceBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: true } },
+    { SamplerBindingType::Filtering }
+);
+
+/// Copies source texture into the render target with a [`FullscreenTriangle`].
+/// Useful as the last step of post processing chains.
+/// Sources with [`UvOrigin`] different from the renderer one are flipped vertically.
+#[derive(Debug, Clone, Copy)]
+pub struct BlitPipeline {
+    pub pipeline_id: PipelineId,
+}
+
+impl BlitPipeline {
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        format: TextureFormat,
+        source: UvOrigin,
+    ) -> Self {
+        let shader = create_shader_module(renderer, "blit.wgsl", BLIT_SHADER.into());
+        let pipeline = PipelineBuilder {
+            shader_path: "blit.wgsl",
+            label: Some("blit_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<BlitSourceBindGroup>(renderer)
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: if source.flips_v(renderer.uv_origin()) {
+                "fs_main_flip_v"
+            } else {
+                "fs_main"
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_pipeline(pipeline),
+        }
+    }
+
+    /// Command drawing `source` over the whole render target
+    pub fn command(&self, source: BlitSourceBindGroup) -> ProceduralCommand {
+        FullscreenTriangle::command(
+            self.pipeline_id,
+            const_vec![BindGroupMeta::new(0, source.0)],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fullscreen_quad_flip_v() {
+        let quad = FullscreenQuad::default().vertices();
+        let flipped = FullscreenQuad { flip_v: true }.vertices();
+        for (v, f) in quad.iter().zip(flipped.iter()) {
+            assert_eq!(v.0, f.0);
+            assert_eq!(v.1[0], f.1[0]);
+            assert_eq!(v.1[1], 1.0 - f.1[1]);
+        }
+    }
+}
@@ -1,6 +1,10 @@
-use crate::mesh::GpuMesh;
+use crate::impl_simple_texture_bind_group;
 use crate::render::prelude::*;
-use crate::texture::{GpuTexture, TextureVertex};
+use crate::texture::GpuTexture;
+
+/// Format of the optional velocity target. Stores screen space motion
+/// in texture coordinates, so previous position is `uv - velocity`.
+pub const GBUFFER_VELOCITY_FORMAT: TextureFormat = TextureFormat::Rg16Float;
 
 #[derive(Debug)]
 pub struct GBufferTexture {
@@ -55,16 +59,76 @@ pub struct GBuffer {
     pub position: GBufferTexture,
     pub normal: GBufferTexture,
     pub albedo: GBufferTexture,
+    pub velocity: Option<GBufferTexture>,
 }
 
 impl GBuffer {
+    /// Same format for all targets
     pub fn new(format: TextureFormat) -> Self {
+        Self::with_formats(format, format, format)
+    }
+
+    /// Formats must be sampled as floats, because [`GBufferBindGroup`]
+    /// binds all targets as unfilterable float textures.
+    pub fn with_formats(
+        position: TextureFormat,
+        normal: TextureFormat,
+        albedo: TextureFormat,
+    ) -> Self {
+        for format in [position, normal, albedo] {
+            assert!(
+                matches!(
+                    format.sample_type(None, None),
+                    Some(TextureSampleType::Float { .. })
+                ),
+                "GBuffer target format {:?} is not float sampled",
+                format
+            );
+        }
         Self {
-            position: GBufferTexture::new(format),
-            normal: GBufferTexture::new(format),
-            albedo: GBufferTexture::new(format),
+            position: GBufferTexture::new(position),
+            normal: GBufferTexture::new(normal),
+            albedo: GBufferTexture::new(albedo),
+            velocity: None,
         }
     }
+
+    /// Adds [`GBUFFER_VELOCITY_FORMAT`] target after albedo.
+    /// Geometry pass writes the difference between this and previous
+    /// frame positions into it, which needs previous frame matrices from
+    /// [`crate::camera::CameraHandle::update_with_previous`] and
+    /// [`crate::transform::TransformHandle::update_with_previous`].
+    pub fn with_velocity(mut self) -> Self {
+        self.velocity = Some(GBufferTexture::new(GBUFFER_VELOCITY_FORMAT));
+        self
+    }
+
+    /// 16 bytes per pixel instead of 48 with `Rgba32Float` targets.
+    /// Normals need to be octahedral encoded into 2 components
+    /// and albedo alpha has to be in [0, 1] range.
+    pub fn compact() -> Self {
+        Self::with_formats(
+            TextureFormat::Rgba32Float,
+            TextureFormat::Rg16Float,
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    /// Color targets for the pipelines writing into the g-buffer
+    /// in position, normal, albedo and optional velocity order
+    pub fn color_targets(&self) -> Vec<Option<ColorTargetState>> {
+        [&self.position, &self.normal, &self.albedo]
+            .into_iter()
+            .chain(self.velocity.as_ref())
+            .map(|target| {
+                Some(ColorTargetState {
+                    format: target.format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -72,62 +136,42 @@ pub struct GBufferResource {
     position_texture: GpuTexture,
     normal_texture: GpuTexture,
     albedo_texture: GpuTexture,
-    mesh: GpuMesh,
+    velocity_texture: Option<GpuTexture>,
 }
 
 impl GpuResource for GBuffer {
     type ResourceType = GBufferResource;
 
     fn build(&self, renderer: &Renderer) -> Self::ResourceType {
-        let vertices: Vec<TextureVertex> = vec![
-            ([-1.0, 1.0, 0.0], [0.0, 0.0]),
-            ([-1.0, -1.0, 0.0], [0.0, 1.0]),
-            ([1.0, 1.0, 0.0], [1.0, 0.0]),
-            ([1.0, -1.0, 0.0], [1.0, 1.0]),
-        ]
-        .into_iter()
-        .map(Into::into)
-        .collect();
-
-        let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
-            label: Some("gbuffer_vertex_buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: BufferUsages::VERTEX,
-        });
-
-        let indices = vec![0, 1, 2, 2, 1, 3];
-
-        let index_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
-            label: Some("gbuffer_index_buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: BufferUsages::INDEX,
-        });
-
         let position_texture = self.position.build(renderer);
         let normal_texture = self.normal.build(renderer);
         let albedo_texture = self.albedo.build(renderer);
-
-        let mesh = GpuMesh {
-            vertex_buffer,
-            index_buffer: Some(index_buffer),
-            num_elements: 6,
-        };
+        let velocity_texture = self.velocity.as_ref().map(|v| v.build(renderer));
 
         Self::ResourceType {
             position_texture,
             normal_texture,
             albedo_texture,
-            mesh,
+            velocity_texture,
         }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct GBufferHandle {
-    pub position_texture_id: ResourceId,
-    pub normal_texture_id: ResourceId,
-    pub albedo_texture_id: ResourceId,
-    pub mesh_id: ResourceId,
+    pub position_texture_id: TextureId,
+    pub normal_texture_id: TextureId,
+    pub albedo_texture_id: TextureId,
+    pub velocity_texture_id: Option<TextureId>,
+}
+
+impl GBufferHandle {
+    /// Handle to the velocity target if g-buffer was created
+    /// with [`GBuffer::with_velocity`]
+    pub fn velocity(&self) -> Option<GBufferVelocityHandle> {
+        self.velocity_texture_id
+            .map(|texture_id| GBufferVelocityHandle { texture_id })
+    }
 }
 
 impl ResourceHandle for GBufferHandle {
@@ -139,7 +183,9 @@ impl ResourceHandle for GBufferHandle {
             position_texture_id: storage.insert_texture(resource.position_texture),
             normal_texture_id: storage.insert_texture(resource.normal_texture),
             albedo_texture_id: storage.insert_texture(resource.albedo_texture),
-            mesh_id: storage.insert_mesh(resource.mesh),
+            velocity_texture_id: resource
+                .velocity_texture
+                .map(|texture| storage.insert_texture(texture)),
         }
     }
 
@@ -147,12 +193,30 @@ impl ResourceHandle for GBufferHandle {
         storage.replace_texture(self.position_texture_id, resource.position_texture);
         storage.replace_texture(self.normal_texture_id, resource.normal_texture);
         storage.replace_texture(self.albedo_texture_id, resource.albedo_texture);
-        storage.replace_mesh(self.mesh_id, resource.mesh);
+        if let (Some(id), Some(texture)) = (self.velocity_texture_id, resource.velocity_texture) {
+            storage.replace_texture(id, texture);
+        }
     }
 }
 
+/// Velocity target of the [`GBuffer`]
 #[derive(Debug, Clone, Copy)]
-pub struct GBufferBindGroup(pub ResourceId);
+pub struct GBufferVelocityHandle {
+    pub texture_id: TextureId,
+}
+
+impl_simple_texture_bind_group!(
+    GBufferVelocityHandle,
+    GBufferVelocityBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: false } },
+    { SamplerBindingType::NonFiltering }
+);
+
+/// All targets are bound as unfilterable float textures,
+/// which is compatible with any float sampled format.
+#[derive(Debug, Clone, Copy)]
+pub struct GBufferBindGroup(pub BindGroupId);
 
 impl AssetBindGroup for GBufferBindGroup {
     type ResourceHandle = GBufferHandle;
@@ -220,7 +284,7 @@ impl AssetBindGroup for GBufferBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) -> Self {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
         let position = storage.get_texture(resource.position_texture_id);
         let normal = storage.get_texture(resource.normal_texture_id);
         let albedo = storage.get_texture(resource.albedo_texture_id);
@@ -256,7 +320,17 @@ impl AssetBindGroup for GBufferBindGroup {
             label: None,
         });
 
-        Self(storage.insert_bind_group(bind_group))
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![
+                    resource.position_texture_id,
+                    resource.normal_texture_id,
+                    resource.albedo_texture_id,
+                ],
+                buffers: vec![],
+            },
+        ))
     }
 
     fn replace(
@@ -265,7 +339,7 @@ impl AssetBindGroup for GBufferBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
         let position = storage.get_texture(resource.position_texture_id);
         let normal = storage.get_texture(resource.normal_texture_id);
         let albedo = storage.get_texture(resource.albedo_texture_id);
@@ -301,6 +375,58 @@ impl AssetBindGroup for GBufferBindGroup {
             label: None,
         });
 
-        storage.replace_bind_group(self.0, bind_group);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![
+                    resource.position_texture_id,
+                    resource.normal_texture_id,
+                    resource.albedo_texture_id,
+                ],
+                buffers: vec![],
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gbuffer_color_targets() {
+        let g_buffer = GBuffer::compact();
+        let formats: Vec<_> = g_buffer
+            .color_targets()
+            .into_iter()
+            .map(|t| t.unwrap().format)
+            .collect();
+        assert_eq!(
+            formats,
+            [
+                TextureFormat::Rgba32Float,
+                TextureFormat::Rg16Float,
+                TextureFormat::Rgba8Unorm,
+            ]
+        );
+    }
+
+    #[test]
+    fn gbuffer_velocity_target() {
+        let g_buffer = GBuffer::compact().with_velocity();
+        let targets = g_buffer.color_targets();
+        assert_eq!(targets.len(), 4);
+        assert_eq!(targets[3].as_ref().unwrap().format, GBUFFER_VELOCITY_FORMAT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn gbuffer_non_float_format() {
+        GBuffer::with_formats(
+            TextureFormat::Rgba32Float,
+            TextureFormat::Rg32Uint,
+            TextureFormat::Rgba8Unorm,
+        );
     }
 }
@@ -0,0 +1,363 @@
+use crate::camera::Camera;
+use crate::cgmath_imports::*;
+use crate::line::{Line, LineVertex};
+use crate::ray::Ray;
+use crate::transform::Transform;
+use cgmath::{EuclideanSpace, One};
+
+/// Number of segments of the rotation handle circles
+const GIZMO_CIRCLE_SEGMENTS: usize = 32;
+/// Size of the arrow heads and scale boxes relative to the handle length
+const GIZMO_TIP_SIZE: f32 = 0.08;
+/// Scale drags starting closer to the gizmo center than this are ignored
+const GIZMO_MIN_SCALE_START: f32 = 1e-4;
+
+const GIZMO_AXIS_COLORS: [[f32; 4]; 3] = [
+    [1.0, 0.2, 0.2, 1.0],
+    [0.2, 1.0, 0.2, 1.0],
+    [0.2, 0.4, 1.0, 1.0],
+];
+const GIZMO_ACTIVE_COLOR: [f32; 4] = [1.0, 1.0, 0.2, 1.0];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn unit(&self) -> Vector3<f32> {
+        match self {
+            GizmoAxis::X => Vector3::unit_x(),
+            GizmoAxis::Y => Vector3::unit_y(),
+            GizmoAxis::Z => Vector3::unit_z(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GizmoDrag {
+    axis: GizmoAxis,
+    /// World space direction of the axis at the start of the drag
+    direction: Vector3<f32>,
+    /// Distance along the axis or angle around it at the start of the drag
+    start: f32,
+    transform: Transform,
+}
+
+/// Interactive translate, rotate and scale handles at a [`Transform`].
+/// Handles follow the local axes of the transform and keep the same
+/// size on the screen. Mouse ray is tested against them on the CPU.
+#[derive(Debug)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    /// Length of the handles relative to the distance to the camera
+    pub size: f32,
+    /// Max distance between the mouse ray and a handle at which
+    /// the handle can be grabbed, relative to the handle length
+    pub pick_radius: f32,
+    mouse_pressed: bool,
+    /// Mouse was pressed since the last update
+    grab: bool,
+    hovered: Option<GizmoAxis>,
+    drag: Option<GizmoDrag>,
+}
+
+impl Gizmo {
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            size: 0.15,
+            pick_radius: 0.08,
+            mouse_pressed: false,
+            grab: false,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// Needs to be called when the mouse button is pressed and released.
+    /// Pressing over a handle starts the drag, releasing ends it.
+    pub fn set_mouse_pressed(&mut self, pressed: bool) {
+        self.grab |= pressed && !self.mouse_pressed;
+        self.mouse_pressed = pressed;
+    }
+
+    /// Handle under the mouse or the one being dragged
+    pub fn active_axis(&self) -> Option<GizmoAxis> {
+        self.drag.map(|drag| drag.axis).or(self.hovered)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Length of the handles in world units
+    pub fn handle_length(&self, camera: &Camera, transform: &Transform) -> f32 {
+        match camera {
+            Camera::Perspective(c) => {
+                self.size * (c.position - Point3::from_vec(transform.translation)).magnitude()
+            }
+            Camera::Orthogonal(c) => self.size * (c.top - c.bottom),
+        }
+    }
+
+    /// Updates the hovered handle and applies the drag to the `transform`.
+    /// `mouse_ray` is usually created with [`Camera::screen_ray`].
+    /// Returns `true` if the `transform` was changed.
+    pub fn update(&mut self, camera: &Camera, mouse_ray: &Ray, transform: &mut Transform) -> bool {
+        let grab = std::mem::take(&mut self.grab);
+        if !self.mouse_pressed {
+            self.drag = None;
+        }
+        if let Some(drag) = self.drag {
+            return self.apply_drag(&drag, mouse_ray, transform);
+        }
+
+        let length = self.handle_length(camera, transform);
+        self.hovered = self.pick(mouse_ray, transform, length);
+        if let (true, Some(axis)) = (grab, self.hovered) {
+            let direction = axis_direction(transform, axis);
+            self.drag = self
+                .drag_param(mouse_ray, transform, direction)
+                .map(|start| GizmoDrag {
+                    axis,
+                    direction,
+                    start,
+                    transform: *transform,
+                });
+        }
+        false
+    }
+
+    /// Closest to the ray origin handle within the `pick_radius`
+    fn pick(&self, ray: &Ray, transform: &Transform, length: f32) -> Option<GizmoAxis> {
+        let origin = Point3::from_vec(transform.translation);
+        let radius = self.pick_radius * length;
+        GizmoAxis::ALL
+            .into_iter()
+            .filter_map(|axis| {
+                let direction = axis_direction(transform, axis);
+                let t = match self.mode {
+                    GizmoMode::Translate | GizmoMode::Scale => {
+                        let (t, s) = ray.closest_to_line(origin, direction)?;
+                        let handle_point = origin + direction * s.clamp(0.0, length);
+                        (ray.distance_to_point(handle_point) < radius).then_some(t)?
+                    }
+                    GizmoMode::Rotate => {
+                        let t = ray.intersect_plane(origin, direction)?;
+                        let distance = (ray.at(t) - origin).magnitude();
+                        ((distance - length).abs() < radius).then_some(t)?
+                    }
+                };
+                Some((axis, t))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(axis, _)| axis)
+    }
+
+    /// Distance along the handle axis for translation and scale,
+    /// angle around it for rotation
+    fn drag_param(&self, ray: &Ray, transform: &Transform, direction: Vector3<f32>) -> Option<f32> {
+        let origin = Point3::from_vec(transform.translation);
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                ray.closest_to_line(origin, direction).map(|(_, s)| s)
+            }
+            GizmoMode::Rotate => {
+                let t = ray.intersect_plane(origin, direction)?;
+                let (u, v) = plane_basis(direction);
+                let offset = ray.at(t) - origin;
+                Some(offset.dot(v).atan2(offset.dot(u)))
+            }
+        }
+    }
+
+    fn apply_drag(&self, drag: &GizmoDrag, ray: &Ray, transform: &mut Transform) -> bool {
+        let Some(param) = self.drag_param(ray, &drag.transform, drag.direction) else {
+            return false;
+        };
+        let start = &drag.transform;
+        match self.mode {
+            GizmoMode::Translate => {
+                transform.translation = start.translation + drag.direction * (param - drag.start);
+            }
+            GizmoMode::Rotate => {
+                let delta = Quaternion::from_axis_angle(drag.direction, Rad(param - drag.start));
+                transform.rotation = delta * normalized_rotation(start);
+            }
+            GizmoMode::Scale => {
+                if drag.start.abs() < GIZMO_MIN_SCALE_START {
+                    return false;
+                }
+                let i = drag.axis as usize;
+                transform.scale[i] = start.scale[i] * param / drag.start;
+            }
+        }
+        true
+    }
+
+    /// Lines of the handles for the current mode. Number of vertices
+    /// depends on the mode, so the line mesh needs to be replaced
+    /// when the mode changes.
+    pub fn line(&self, camera: &Camera, transform: &Transform) -> Line {
+        let origin = Point3::from_vec(transform.translation);
+        let length = self.handle_length(camera, transform);
+        let tip = GIZMO_TIP_SIZE * length;
+        let active = self.active_axis();
+
+        let mut vertices = Vec::new();
+        for axis in GizmoAxis::ALL {
+            let color = if active == Some(axis) {
+                GIZMO_ACTIVE_COLOR
+            } else {
+                GIZMO_AXIS_COLORS[axis as usize]
+            };
+            let direction = axis_direction(transform, axis);
+            let (u, v) = plane_basis(direction);
+            let end = origin + direction * length;
+            let segment = |a: Point3<f32>, b: Point3<f32>| {
+                LineVertex::segment(a.into(), b.into(), color, color)
+            };
+            match self.mode {
+                GizmoMode::Translate => {
+                    vertices.push(segment(origin, end));
+                    let base = end - direction * (2.0 * tip);
+                    for side in [u, v, -u, -v] {
+                        vertices.push(segment(end, base + side * tip));
+                    }
+                }
+                GizmoMode::Rotate => {
+                    let points: Vec<_> = (0..GIZMO_CIRCLE_SEGMENTS)
+                        .map(|i| {
+                            let angle =
+                                i as f32 / GIZMO_CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                            let point = origin + (u * angle.cos() + v * angle.sin()) * length;
+                            (point.into(), color)
+                        })
+                        .collect();
+                    vertices.extend(Line::polyline(&points, true).vertices);
+                }
+                GizmoMode::Scale => {
+                    vertices.push(segment(origin, end));
+                    let points: Vec<_> = [u + v, u - v, -u - v, -u + v]
+                        .into_iter()
+                        .map(|corner| ((end + corner * tip).into(), color))
+                        .collect();
+                    vertices.extend(Line::polyline(&points, true).vertices);
+                }
+            }
+        }
+        Line { vertices }
+    }
+}
+
+/// Default `Transform` rotation is a zero quaternion, which
+/// rotates nothing, but also can not be multiplied with.
+fn normalized_rotation(transform: &Transform) -> Quaternion<f32> {
+    if transform.rotation.magnitude2() < f32::EPSILON {
+        Quaternion::one()
+    } else {
+        transform.rotation.normalize()
+    }
+}
+
+/// World space direction of the local `axis` of the `transform`
+fn axis_direction(transform: &Transform, axis: GizmoAxis) -> Vector3<f32> {
+    normalized_rotation(transform) * axis.unit()
+}
+
+/// Two orthonormal vectors perpendicular to the `normal`
+fn plane_basis(normal: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let u = normal.cross(helper).normalize();
+    (u, normal.cross(u))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::PerspectiveCamera;
+
+    fn camera() -> Camera {
+        Camera::Perspective(PerspectiveCamera {
+            position: (0.0, 0.0, 5.0).into(),
+            yaw: Deg(-90.0).into(),
+            pitch: Deg(0.0).into(),
+            aspect: 1.0,
+            fovy: Deg(60.0).into(),
+            znear: 0.1,
+            zfar: 100.0,
+        })
+    }
+
+    fn ray_to(camera: &Camera, x: f32, y: f32) -> Ray {
+        let origin = camera.position();
+        Ray::new(origin, Point3::new(x, y, 0.0) - origin)
+    }
+
+    #[test]
+    fn gizmo_translate_drag() {
+        let camera = camera();
+        let mut transform = Transform::default();
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        assert!((gizmo.handle_length(&camera, &transform) - 0.75).abs() < 1e-6);
+
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 0.5, 0.0), &mut transform));
+        assert_eq!(gizmo.active_axis(), Some(GizmoAxis::X));
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 0.5, 0.5), &mut transform));
+        assert_eq!(gizmo.active_axis(), None);
+
+        // Pressing away from the handles does not grab them while moving over
+        gizmo.set_mouse_pressed(true);
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 0.5, 0.5), &mut transform));
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 0.5, 0.0), &mut transform));
+        assert!(!gizmo.is_dragging());
+        gizmo.set_mouse_pressed(false);
+
+        gizmo.set_mouse_pressed(true);
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 0.5, 0.0), &mut transform));
+        assert!(gizmo.is_dragging());
+        assert!(gizmo.update(&camera, &ray_to(&camera, 1.5, 0.0), &mut transform));
+        assert!((transform.translation - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+
+        gizmo.set_mouse_pressed(false);
+        assert!(!gizmo.update(&camera, &ray_to(&camera, 2.0, 0.0), &mut transform));
+        assert!(!gizmo.is_dragging());
+        assert!((transform.translation - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn gizmo_rotate_drag() {
+        let camera = camera();
+        let mut transform = Transform::default();
+        let mut gizmo = Gizmo::new(GizmoMode::Rotate);
+
+        gizmo.set_mouse_pressed(true);
+        gizmo.update(&camera, &ray_to(&camera, 0.75, 0.0), &mut transform);
+        assert_eq!(gizmo.active_axis(), Some(GizmoAxis::Z));
+        assert!(gizmo.update(&camera, &ray_to(&camera, 0.0, 0.75), &mut transform));
+        let x = transform.rotation * Vector3::unit_x();
+        assert!((x - Vector3::unit_y()).magnitude() < 1e-4);
+
+        assert_eq!(
+            gizmo.line(&camera, &transform).vertices.len(),
+            3 * GIZMO_CIRCLE_SEGMENTS
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use crate::impl_simple_buffer;
+use crate::render::prelude::*;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlobalsUniform {
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    _pad: u32,
+    resolution: [f32; 2],
+}
+
+impl From<&Globals> for GlobalsUniform {
+    fn from(value: &Globals) -> Self {
+        Self {
+            time: value.time,
+            delta_time: value.delta_time,
+            frame: value.frame,
+            resolution: value.resolution,
+            ..Default::default()
+        }
+    }
+}
+
+/// Per frame values available to all shader stages.
+/// Matches this WGSL struct:
+/// ```wgsl
+/// struct Globals {
+///   time: f32,
+///   delta_time: f32,
+///   frame: u32,
+///   resolution: vec2<f32>,
+/// };
+/// ```
+#[derive(Debug, Default)]
+pub struct Globals {
+    /// Seconds since the start
+    pub time: f32,
+    /// Seconds since the last frame
+    pub delta_time: f32,
+    /// Number of advanced frames
+    pub frame: u32,
+    /// Render target size in pixels
+    pub resolution: [f32; 2],
+}
+
+impl Globals {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            resolution: [width as f32, height as f32],
+            ..Default::default()
+        }
+    }
+
+    /// Moves to the next frame. Should be called once per frame.
+    pub fn advance(&mut self, dt: Duration) {
+        self.delta_time = dt.as_secs_f32();
+        self.time += self.delta_time;
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.resolution = [width as f32, height as f32];
+    }
+}
+
+impl_simple_buffer!(
+    Globals,
+    GlobalsUniform,
+    GlobalsResources,
+    GlobalsHandle,
+    GlobalsBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::VERTEX | ShaderStages::FRAGMENT | ShaderStages::COMPUTE },
+    { BufferBindingType::Uniform }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globals_advance() {
+        let mut globals = Globals::new(800, 600);
+        globals.advance(Duration::from_millis(500));
+        globals.advance(Duration::from_millis(250));
+        assert_eq!(globals.frame, 2);
+        assert_eq!(globals.delta_time, 0.25);
+        assert_eq!(globals.time, 0.75);
+        assert_eq!(globals.resolution, [800.0, 600.0]);
+    }
+
+    #[test]
+    fn globals_uniform_size() {
+        // vec2 in WGSL is 8 byte aligned, so resolution starts at 16
+        assert_eq!(std::mem::size_of::<GlobalsUniform>(), 24);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::render::prelude::*;
+use crate::texture_buffer::TextureBuffer;
+use log::info;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecorderError {
+    #[error("Recording requires headless renderer")]
+    NotHeadless,
+    #[error("Could not read back frame {0}")]
+    Readback(usize),
+    #[error("Could not write frame: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not save frame: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Destination of the recorded frames
+pub enum RecorderOutput {
+    /// Every frame is saved as `{directory}/{prefix}{frame_index:05}.png`
+    ImageSequence { directory: PathBuf, prefix: String },
+    /// Raw RGBA8 frames are written one after another. Can be piped
+    /// into an encoder, e.g. `ffmpeg -f rawvideo -pix_fmt rgba -s WxH -i - out.mp4`.
+    Raw(Box<dyn Write>),
+}
+
+/// Renders a number of frames with the headless renderer
+/// and writes every one of them to the [`RecorderOutput`]
+pub struct HeadlessRecorder<'a> {
+    renderer: &'a Renderer<'a>,
+    storage: &'a mut RenderStorage,
+    texture_buffer: TextureBuffer,
+    output: RecorderOutput,
+}
+
+impl<'a> HeadlessRecorder<'a> {
+    pub fn new(
+        renderer: &'a Renderer<'a>,
+        storage: &'a mut RenderStorage,
+        output: RecorderOutput,
+    ) -> Result<Self, RecorderError> {
+        if renderer.surface_texture().is_none() {
+            return Err(RecorderError::NotHeadless);
+        }
+        if let RecorderOutput::ImageSequence { directory, .. } = &output {
+            std::fs::create_dir_all(directory)?;
+        }
+        let size = renderer.size();
+        let texture_buffer = TextureBuffer::new(renderer, size.width, size.height);
+        Ok(Self {
+            renderer,
+            storage,
+            texture_buffer,
+            output,
+        })
+    }
+
+    /// Calls `frame` for every frame index in `0..frame_count` and writes
+    /// the result. `frame` needs to render and submit the whole frame
+    /// into [`Renderer::current_frame`].
+    pub fn record<F>(&mut self, frame_count: usize, mut frame: F) -> Result<(), RecorderError>
+    where
+        F: FnMut(usize, &Renderer, &mut RenderStorage),
+    {
+        for frame_index in 0..frame_count {
+            frame(frame_index, self.renderer, self.storage);
+
+            self.texture_buffer
+                .copy_render_surface_to_texture(self.renderer);
+            let image = self
+                .texture_buffer
+                .read_image_buffer(self.renderer)
+                .ok_or(RecorderError::Readback(frame_index))?;
+
+            match &mut self.output {
+                RecorderOutput::ImageSequence { directory, prefix } => {
+                    image.save(directory.join(format!("{prefix}{frame_index:05}.png")))?
+                }
+                RecorderOutput::Raw(writer) => writer.write_all(image.as_raw())?,
+            }
+        }
+        if let RecorderOutput::Raw(writer) = &mut self.output {
+            writer.flush()?;
+        }
+        info!("Recorded {frame_count} frames");
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use crate::cgmath_imports::*;
+use crate::mesh::{Mesh, MeshVertex};
+use image::ImageError;
+use log::info;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeightmapOptions {
+    /// Number of times the texture repeats across the whole terrain
+    pub uv_tiling: f32,
+    /// Normals averaged from neighboring samples. Otherwise every
+    /// triangle gets its own vertices with the face normal.
+    pub smooth_normals: bool,
+}
+
+impl Default for HeightmapOptions {
+    fn default() -> Self {
+        Self {
+            uv_tiling: 1.0,
+            smooth_normals: true,
+        }
+    }
+}
+
+impl Mesh {
+    /// Terrain mesh from the grayscale image. See [`Mesh::from_heights`].
+    pub fn from_heightmap<P: AsRef<Path>>(
+        path: P,
+        horizontal_scale: f32,
+        height_scale: f32,
+    ) -> Result<Self, ImageError> {
+        Self::from_heightmap_with_options(
+            path,
+            horizontal_scale,
+            height_scale,
+            &HeightmapOptions::default(),
+        )
+    }
+
+    pub fn from_heightmap_with_options<P: AsRef<Path>>(
+        path: P,
+        horizontal_scale: f32,
+        height_scale: f32,
+        options: &HeightmapOptions,
+    ) -> Result<Self, ImageError> {
+        info!("Loading heightmap from {:#?}", path.as_ref());
+        let image = image::open(path)?.to_luma32f();
+        let (width, depth) = image.dimensions();
+        Ok(Self::from_heights(
+            image.as_raw(),
+            width,
+            depth,
+            horizontal_scale,
+            height_scale,
+            options,
+        ))
+    }
+
+    /// Terrain mesh centered at the origin from `width` x `depth` grid of
+    /// heights in [0, 1] range. Samples are `horizontal_scale` apart along
+    /// X and Z axes and heights are multiplied by `height_scale`.
+    /// Texture coordinates follow the grid, so the first row has `v` = 0.
+    pub fn from_heights(
+        heights: &[f32],
+        width: u32,
+        depth: u32,
+        horizontal_scale: f32,
+        height_scale: f32,
+        options: &HeightmapOptions,
+    ) -> Self {
+        let (width, depth) = (width.max(2) as usize, depth.max(2) as usize);
+        let height = |x: usize, z: usize| heights.get(z * width + x).copied().unwrap_or(0.0);
+        let position = |x: usize, z: usize| {
+            Vector3::new(
+                (x as f32 - (width - 1) as f32 / 2.0) * horizontal_scale,
+                height(x, z) * height_scale,
+                (z as f32 - (depth - 1) as f32 / 2.0) * horizontal_scale,
+            )
+        };
+        let tex_coords = |x: usize, z: usize| {
+            [
+                x as f32 / (width - 1) as f32 * options.uv_tiling,
+                z as f32 / (depth - 1) as f32 * options.uv_tiling,
+            ]
+        };
+        // Central differences, one sided at the edges
+        let normal = |x: usize, z: usize| {
+            let (x0, x1) = (x.saturating_sub(1), (x + 1).min(width - 1));
+            let (z0, z1) = (z.saturating_sub(1), (z + 1).min(depth - 1));
+            let dx = (height(x1, z) - height(x0, z)) * height_scale
+                / ((x1 - x0) as f32 * horizontal_scale);
+            let dz = (height(x, z1) - height(x, z0)) * height_scale
+                / ((z1 - z0) as f32 * horizontal_scale);
+            Vector3::new(-dx, 1.0, -dz).normalize()
+        };
+
+        let indices: Vec<u32> = (0..depth - 1)
+            .flat_map(|z| {
+                (0..width - 1).flat_map(move |x| {
+                    let top_left = (z * width + x) as u32;
+                    let top_right = top_left + 1;
+                    let bottom_left = top_left + width as u32;
+                    let bottom_right = bottom_left + 1;
+                    [
+                        top_right,
+                        bottom_left,
+                        bottom_right,
+                        top_right,
+                        top_left,
+                        bottom_left,
+                    ]
+                })
+            })
+            .collect();
+
+        let grid = |i: u32| (i as usize % width, i as usize / width);
+        let (mut vertices, indices): (Vec<MeshVertex>, Vec<u32>) = if options.smooth_normals {
+            let vertices = (0..width * depth)
+                .map(|i| {
+                    let (x, z) = grid(i as u32);
+                    (position(x, z).into(), tex_coords(x, z), normal(x, z).into()).into()
+                })
+                .collect();
+            (vertices, indices)
+        } else {
+            let vertices = indices
+                .chunks_exact(3)
+                .flat_map(|triangle| {
+                    let [a, b, c] = [0, 1, 2].map(|i| {
+                        let (x, z) = grid(triangle[i]);
+                        (position(x, z), tex_coords(x, z))
+                    });
+                    let face_normal = (b.0 - a.0).cross(c.0 - a.0).normalize();
+                    [a, b, c].map(|(p, uv)| (p.into(), uv, face_normal.into()).into())
+                })
+                .collect();
+            let flat_indices = (0..indices.len() as u32).collect();
+            (vertices, flat_indices)
+        };
+
+        MeshVertex::calc_tangents_and_bitangents(&mut vertices, &indices);
+
+        Self {
+            name: "heightmap".to_string(),
+            vertices,
+            indices,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::prelude::FrontFace;
+
+    #[test]
+    fn heightmap_flat() {
+        let mesh = Mesh::from_heights(&[0.0; 9], 3, 3, 1.0, 10.0, &HeightmapOptions::default());
+        assert_eq!(mesh.vertices.len(), 9);
+        assert_eq!(mesh.indices.len(), 2 * 2 * 6);
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Ccw));
+        assert_eq!(mesh.vertices[0].position, [-1.0, 0.0, -1.0]);
+        assert_eq!(mesh.vertices[8].tex_coords, [1.0, 1.0]);
+        assert!(mesh.vertices.iter().all(|v| v.normal == [0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn heightmap_slope() {
+        // Rises along X by 1 per sample
+        let heights = [0.0, 0.5, 1.0, 0.0, 0.5, 1.0];
+        let options = HeightmapOptions {
+            uv_tiling: 4.0,
+            smooth_normals: false,
+        };
+        let mesh = Mesh::from_heights(&heights, 3, 2, 1.0, 2.0, &options);
+        assert_eq!(mesh.vertices.len(), 2 * 6);
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Ccw));
+        let expected = Vector3::new(-1.0, 1.0, 0.0).normalize();
+        for v in mesh.vertices.iter() {
+            assert!((Vector3::from(v.normal) - expected).magnitude() < 1e-6);
+        }
+        assert!(mesh.vertices.iter().any(|v| v.tex_coords == [4.0, 4.0]));
+    }
+}
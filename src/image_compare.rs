@@ -0,0 +1,211 @@
+use crate::render::prelude::*;
+use crate::texture_buffer::TextureBuffer;
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Setting this environment variable makes [`assert_render_matches`]
+/// overwrite reference images instead of comparing with them
+pub const UPDATE_REFERENCES_ENV: &str = "ZERO_UPDATE_REFERENCES";
+
+/// Side of the square window of the [`ssim`]
+const SSIM_WINDOW: u32 = 8;
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+/// Mean structural similarity of the RGB channels of two images. 1 for
+/// identical images, closer to 0 the more they differ. Images with
+/// different sizes have similarity of 0.
+pub fn ssim(a: &RgbaImage, b: &RgbaImage) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 0.0;
+    }
+    (0..3)
+        .map(|channel| channel_ssim(a, b, channel))
+        .sum::<f32>()
+        / 3.0
+}
+
+/// Mean structural similarity of one channel of two images of the same size
+fn channel_ssim(a: &RgbaImage, b: &RgbaImage, channel: usize) -> f32 {
+    let value = |pixel: &Rgba<u8>| pixel[channel] as f32 / 255.0;
+    let (width, height) = a.dimensions();
+    let window = SSIM_WINDOW.min(width).min(height);
+    if window == 0 {
+        return 1.0;
+    }
+    // Windows overlap by a half
+    let step = (window / 2).max(1);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in (0..=height - window).step_by(step as usize) {
+        for x in (0..=width - window).step_by(step as usize) {
+            let samples = || {
+                (y..y + window)
+                    .flat_map(move |y| (x..x + window).map(move |x| (x, y)))
+                    .map(|(x, y)| (value(a.get_pixel(x, y)), value(b.get_pixel(x, y))))
+            };
+            let n = (window * window) as f32;
+            let (sum_a, sum_b) = samples().fold((0.0, 0.0), |(sa, sb), (a, b)| (sa + a, sb + b));
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let (var_a, var_b, covar) = samples().fold((0.0, 0.0, 0.0), |(va, vb, c), (a, b)| {
+                let (da, db) = (a - mean_a, b - mean_b);
+                (va + da * da, vb + db * db, c + da * db)
+            });
+            let (var_a, var_b, covar) = (var_a / n, var_b / n, covar / n);
+
+            total += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covar + SSIM_C2))
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (var_a + var_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    total / windows as f32
+}
+
+/// Per channel absolute difference of two images of the same size.
+/// Alpha is kept opaque, so the difference is visible in any viewer.
+pub fn diff_image(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(a.width(), a.height(), |x, y| {
+        let (a, b) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        Rgba([
+            a[0].abs_diff(b[0]),
+            a[1].abs_diff(b[1]),
+            a[2].abs_diff(b[2]),
+            u8::MAX,
+        ])
+    })
+}
+
+/// Path next to the `reference` with the `suffix` added to the file name
+fn sibling_path(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    reference.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+/// Loads the `reference` image. If `update` is set, the reference is
+/// overwritten with the `actual` image instead and `None` is returned.
+/// Panics if the reference does not exist and `update` is not set.
+fn reference_image(reference: &Path, actual: &RgbaImage, update: bool) -> Option<RgbaImage> {
+    if update {
+        if let Some(parent) = reference.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        actual.save(reference).unwrap();
+        return None;
+    }
+    if !reference.exists() {
+        panic!("Reference {reference:?} does not exist. Set {UPDATE_REFERENCES_ENV} to create it");
+    }
+    let expected = image::open(reference)
+        .unwrap_or_else(|e| panic!("Could not load reference {reference:?}: {e}"))
+        .to_rgba8();
+    Some(expected)
+}
+
+/// Renders the scene with the headless `renderer` and compares the result with
+/// the `reference` image. `scene` needs to create its resources in the provided
+/// storage, render and submit the whole frame into [`Renderer::current_frame`].
+/// Panics if [`ssim`] of the images is lower than `1.0 - tolerance` and saves
+/// the render and the difference next to the reference as `*.actual.png` and
+/// `*.diff.png`. Missing reference panics, unless [`UPDATE_REFERENCES_ENV`]
+/// is set, in which case the reference is created from the render.
+pub fn assert_render_matches<F, P>(renderer: &Renderer, scene: F, reference: P, tolerance: f32)
+where
+    F: FnOnce(&Renderer, &mut RenderStorage),
+    P: AsRef<Path>,
+{
+    let reference = reference.as_ref();
+    let size = renderer.size();
+    let texture_buffer = TextureBuffer::new(renderer, size.width, size.height);
+
+    let mut storage = RenderStorage::default();
+    scene(renderer, &mut storage);
+    texture_buffer.copy_render_surface_to_texture(renderer);
+    let actual = texture_buffer
+        .read_image_buffer(renderer)
+        .expect("Could not read back rendered image");
+
+    let update = std::env::var_os(UPDATE_REFERENCES_ENV).is_some();
+    let Some(expected) = reference_image(reference, &actual, update) else {
+        return;
+    };
+    let similarity = ssim(&actual, &expected);
+    if similarity < 1.0 - tolerance {
+        let actual_path = sibling_path(reference, "actual");
+        actual.save(&actual_path).unwrap();
+        let mut message = format!(
+            "Render does not match {reference:?}: similarity {similarity} is lower than {}. Render saved to {actual_path:?}",
+            1.0 - tolerance
+        );
+        if actual.dimensions() == expected.dimensions() {
+            let diff_path = sibling_path(reference, "diff");
+            diff_image(&actual, &expected).save(&diff_path).unwrap();
+            message.push_str(&format!(", difference saved to {diff_path:?}"));
+        }
+        panic!("{message}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker(size: u32, cell: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            let value = if (x / cell + y / cell).is_multiple_of(2) {
+                255
+            } else {
+                0
+            };
+            Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn image_compare_ssim() {
+        let a = checker(32, 4);
+        assert!((ssim(&a, &a) - 1.0).abs() < 1e-6);
+
+        let mut noisy = a.clone();
+        noisy.put_pixel(5, 5, Rgba([128, 128, 128, 255]));
+        let noisy_similarity = ssim(&a, &noisy);
+        assert!(0.95 < noisy_similarity && noisy_similarity < 1.0);
+
+        let shifted = checker(32, 8);
+        assert!(ssim(&a, &shifted) < noisy_similarity);
+
+        assert_eq!(ssim(&a, &checker(16, 4)), 0.0);
+    }
+
+    #[test]
+    fn image_compare_ssim_color() {
+        // Same luma in both images, only the colors differ
+        let red = RgbaImage::from_fn(16, 16, |x, _| Rgba([255 * (x % 2) as u8, 0, 0, 255]));
+        let green = RgbaImage::from_fn(16, 16, |x, _| Rgba([0, 76 * (x % 2) as u8, 0, 255]));
+        assert!(ssim(&red, &green) < 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn image_compare_missing_reference() {
+        reference_image(
+            Path::new("tests/reference/missing.png"),
+            &checker(4, 2),
+            false,
+        );
+    }
+
+    #[test]
+    fn image_compare_diff() {
+        let a = checker(4, 2);
+        let diff = diff_image(&a, &a);
+        assert!(diff.pixels().all(|p| *p == Rgba([0, 0, 0, 255])));
+        assert_eq!(
+            sibling_path(Path::new("tests/reference/sky.png"), "diff"),
+            Path::new("tests/reference/sky.diff.png")
+        );
+    }
+}
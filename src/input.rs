@@ -0,0 +1,133 @@
+/// Engine owned keys, independent of the windowing library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKey {
+    /// Printable character. Always lowercase.
+    Character(char),
+    Space,
+    Shift,
+    Control,
+    Alt,
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    /// Key without a variant, including characters
+    /// made of multiple code points (e.g. dead key compositions)
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputState {
+    Pressed,
+    Released,
+}
+
+impl InputState {
+    pub fn is_pressed(&self) -> bool {
+        *self == InputState::Pressed
+    }
+}
+
+/// Snapshot of the gamepad axes
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GamepadState {
+    /// Stick values are in [-1, 1] range with positive y pointing up
+    pub left_stick: [f32; 2],
+    pub right_stick: [f32; 2],
+    /// Trigger values are in [0, 1] range
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+}
+
+#[cfg(feature = "gamepad")]
+impl From<&gilrs::Gamepad<'_>> for GamepadState {
+    fn from(value: &gilrs::Gamepad<'_>) -> Self {
+        use gilrs::{Axis, Button};
+        let trigger = |button| value.button_data(button).map_or(0.0, |d| d.value());
+        Self {
+            left_stick: [value.value(Axis::LeftStickX), value.value(Axis::LeftStickY)],
+            right_stick: [
+                value.value(Axis::RightStickX),
+                value.value(Axis::RightStickY),
+            ],
+            left_trigger: trigger(Button::LeftTrigger2),
+            right_trigger: trigger(Button::RightTrigger2),
+        }
+    }
+}
+
+#[cfg(feature = "winit_input")]
+impl From<&winit::keyboard::Key> for InputKey {
+    fn from(value: &winit::keyboard::Key) -> Self {
+        use winit::keyboard::{Key as WKey, NamedKey};
+        match value {
+            WKey::Character(c) => {
+                let mut chars = c.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => InputKey::Character(c.to_ascii_lowercase()),
+                    _ => InputKey::Unknown,
+                }
+            }
+            WKey::Named(NamedKey::Space) => InputKey::Space,
+            WKey::Named(NamedKey::Shift) => InputKey::Shift,
+            WKey::Named(NamedKey::Control) => InputKey::Control,
+            WKey::Named(NamedKey::Alt) => InputKey::Alt,
+            WKey::Named(NamedKey::Escape) => InputKey::Escape,
+            WKey::Named(NamedKey::Enter) => InputKey::Enter,
+            WKey::Named(NamedKey::Tab) => InputKey::Tab,
+            WKey::Named(NamedKey::Backspace) => InputKey::Backspace,
+            WKey::Named(NamedKey::ArrowUp) => InputKey::ArrowUp,
+            WKey::Named(NamedKey::ArrowDown) => InputKey::ArrowDown,
+            WKey::Named(NamedKey::ArrowLeft) => InputKey::ArrowLeft,
+            WKey::Named(NamedKey::ArrowRight) => InputKey::ArrowRight,
+            _ => InputKey::Unknown,
+        }
+    }
+}
+
+#[cfg(feature = "winit_input")]
+impl From<winit::event::ElementState> for InputState {
+    fn from(value: winit::event::ElementState) -> Self {
+        match value {
+            winit::event::ElementState::Pressed => InputState::Pressed,
+            winit::event::ElementState::Released => InputState::Released,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "winit_input")]
+mod tests {
+    use super::*;
+    use winit::keyboard::{Key as WKey, NamedKey};
+
+    #[test]
+    fn key_from_winit_character() {
+        let key = WKey::Character("W".into());
+        assert_eq!(InputKey::from(&key), InputKey::Character('w'));
+        let key = WKey::Character("e\u{301}".into());
+        assert_eq!(InputKey::from(&key), InputKey::Unknown);
+    }
+
+    #[test]
+    fn key_from_winit_named() {
+        assert_eq!(
+            InputKey::from(&WKey::Named(NamedKey::Space)),
+            InputKey::Space
+        );
+        assert_eq!(
+            InputKey::from(&WKey::Named(NamedKey::F1)),
+            InputKey::Unknown
+        );
+    }
+
+    #[test]
+    fn input_state_from_winit() {
+        let state = InputState::from(winit::event::ElementState::Pressed);
+        assert!(state.is_pressed());
+    }
+}
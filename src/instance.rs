@@ -0,0 +1,141 @@
+use crate::cgmath_imports::*;
+use crate::render::prelude::*;
+use crate::transform::Transform;
+
+/// First shader location used by [`InstanceData`] attributes.
+/// Follows [`crate::mesh::MeshVertex`] attributes.
+pub const INSTANCE_SHADER_LOCATION: u32 = 5;
+
+/// Per instance data stored in a vertex buffer at slot 1.
+/// Matches this WGSL input:
+/// ```wgsl
+/// struct InstanceInput {
+///   @location(5) model_0: vec4<f32>,
+///   @location(6) model_1: vec4<f32>,
+///   @location(7) model_2: vec4<f32>,
+///   @location(8) model_3: vec4<f32>,
+///   @location(9) color: vec4<f32>,
+///   @location(10) material_index: u32,
+/// };
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    /// Not used by the engine. Shaders can use it to
+    /// select material properties per instance.
+    pub material_index: u32,
+}
+
+impl InstanceData {
+    pub fn new(transform: &Transform, color: [f32; 4], material_index: u32) -> Self {
+        Self {
+            model: Matrix4::from(transform).into(),
+            color,
+            material_index,
+        }
+    }
+}
+
+impl VertexLayout for InstanceData {
+    fn layout<'a>() -> VertexBufferLayout<'a> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &[
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: INSTANCE_SHADER_LOCATION,
+                    format: VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as BufferAddress,
+                    shader_location: INSTANCE_SHADER_LOCATION + 1,
+                    format: VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as BufferAddress,
+                    shader_location: INSTANCE_SHADER_LOCATION + 2,
+                    format: VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as BufferAddress,
+                    shader_location: INSTANCE_SHADER_LOCATION + 3,
+                    format: VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 16]>() as BufferAddress,
+                    shader_location: INSTANCE_SHADER_LOCATION + 4,
+                    format: VertexFormat::Float32x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 20]>() as BufferAddress,
+                    shader_location: INSTANCE_SHADER_LOCATION + 5,
+                    format: VertexFormat::Uint32,
+                },
+            ],
+        }
+    }
+}
+
+/// Instance buffer drawn with [`crate::mesh::InstancedMeshCommand`]
+#[derive(Debug, Default)]
+pub struct Instances {
+    pub instances: Vec<InstanceData>,
+}
+
+impl GpuResource for Instances {
+    type ResourceType = Buffer;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("instances_buffer"),
+            contents: bytemuck::cast_slice(&self.instances),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InstancesHandle {
+    pub buffer_id: BufferId,
+}
+
+impl ResourceHandle for InstancesHandle {
+    type OriginalResource<'a> = Instances;
+    type ResourceType = Buffer;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            buffer_id: storage.insert_buffer(resource),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_buffer(self.buffer_id, resource);
+    }
+
+    /// Writes instances data. Number of instances must not grow.
+    fn update(&self, renderer: &Renderer, storage: &RenderStorage, original: &Instances) {
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.buffer_id),
+            0,
+            bytemuck::cast_slice(&original.instances),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_data_layout() {
+        let layout = InstanceData::layout();
+        assert_eq!(layout.array_stride, 84);
+        let last = layout.attributes.last().unwrap();
+        assert_eq!(last.offset, 80);
+        assert_eq!(last.shader_location, 10);
+    }
+}
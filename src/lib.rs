@@ -1,39 +1,95 @@
+pub mod animation;
 pub mod camera;
+pub mod color_grade;
+pub mod cone_marcher;
+pub mod culling;
+pub mod debug_view;
 #[cfg(feature = "egui")]
 pub mod egui;
+pub mod fullscreen;
 pub mod gbuffer;
+pub mod gizmo;
+pub mod globals;
+pub mod headless_recorder;
+pub mod heightmap;
+#[cfg(feature = "image_compare")]
+pub mod image_compare;
+pub mod input;
+pub mod instance;
 pub mod light;
+pub mod light_tiles;
 pub mod line;
+pub mod marching_cubes;
 pub mod material;
 pub mod mesh;
+pub mod mesh_optimize;
 pub mod model;
+pub mod outline;
+pub mod picking;
+pub mod point_cloud;
+pub mod ray;
 pub mod render;
+pub mod render_path;
+#[cfg(feature = "scene")]
+pub mod scene;
 pub mod shadow_map;
 pub mod shapes;
+pub mod skeleton;
 pub mod skybox;
+pub mod ssr;
+pub mod taa;
+#[cfg(feature = "egui")]
+pub mod text;
 pub mod texture;
 pub mod texture_buffer;
 pub mod transform;
 pub mod utils;
+pub mod volumetric_light;
 
 pub mod prelude {
     use super::*;
 
+    pub use animation::*;
     pub use camera::*;
+    pub use color_grade::*;
+    pub use cone_marcher::*;
+    pub use culling::*;
+    pub use debug_view::*;
+    pub use fullscreen::*;
     pub use gbuffer::*;
+    pub use gizmo::*;
+    pub use globals::*;
+    pub use headless_recorder::*;
+    pub use heightmap::*;
+    pub use input::*;
+    pub use instance::*;
     pub use light::*;
+    pub use light_tiles::*;
     pub use line::*;
+    pub use marching_cubes::*;
     pub use material::*;
     pub use mesh::*;
+    pub use mesh_optimize::*;
     pub use model::*;
+    pub use outline::*;
+    pub use picking::*;
+    pub use point_cloud::*;
+    pub use ray::*;
     pub use render::prelude::*;
+    pub use render_path::*;
+    #[cfg(feature = "scene")]
+    pub use scene::*;
     pub use shadow_map::*;
     pub use shapes::*;
+    pub use skeleton::*;
     pub use skybox::*;
+    pub use ssr::*;
+    pub use taa::*;
     pub use texture::*;
     pub use texture_buffer::*;
     pub use transform::*;
     pub use utils::*;
+    pub use volumetric_light::*;
 
     pub use cgmath_imports::*;
     pub use wgpu;
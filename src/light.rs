@@ -46,6 +46,70 @@ impl_simple_buffer!(
     { BufferBindingType::Uniform }
 );
 
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AmbientLightUniform {
+    color: [f32; 3],
+    intensity: f32,
+    sky_color: [f32; 3],
+    _pad1: u32,
+    ground_color: [f32; 3],
+    _pad2: u32,
+}
+
+impl From<&AmbientLight> for AmbientLightUniform {
+    fn from(value: &AmbientLight) -> Self {
+        Self {
+            color: value.color.into(),
+            intensity: value.intensity,
+            sky_color: value.sky_color.into(),
+            ground_color: value.ground_color.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Global ambient term added to every surface independent of its material.
+/// Color is tinted by the hemisphere: surfaces facing up get the `sky_color`
+/// and facing down the `ground_color`. In the shader:
+/// `color * intensity * mix(ground_color, sky_color, normal.y * 0.5 + 0.5)`.
+#[derive(Debug, Clone)]
+pub struct AmbientLight {
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub sky_color: Vector3<f32>,
+    pub ground_color: Vector3<f32>,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self::new((1.0, 1.0, 1.0), 0.1)
+    }
+}
+
+impl AmbientLight {
+    /// Ambient light without the hemisphere tint
+    pub fn new<C: Into<Vector3<f32>>>(color: C, intensity: f32) -> Self {
+        Self {
+            color: color.into(),
+            intensity,
+            sky_color: Vector3::new(1.0, 1.0, 1.0),
+            ground_color: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl_simple_buffer!(
+    AmbientLight,
+    AmbientLightUniform,
+    AmbientLightResources,
+    AmbientLightHandle,
+    AmbientLightBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PointLightUniform {
@@ -56,7 +120,7 @@ pub struct PointLightUniform {
     constant: f32,
     linear: f32,
     quadratic: f32,
-    _pad3: u32,
+    intensity: f32,
 }
 
 impl From<&PointLight> for PointLightUniform {
@@ -67,11 +131,15 @@ impl From<&PointLight> for PointLightUniform {
             constant: value.constant,
             linear: value.linear,
             quadratic: value.quadratic,
+            intensity: value.intensity,
             ..Default::default()
         }
     }
 }
 
+/// Attenuation at which [`PointLight::with_range`] light reaches its range
+pub const LIGHT_RANGE_CUTOFF: f32 = 1.0 / 256.0;
+
 #[derive(Debug, Clone)]
 pub struct PointLight {
     pub position: Vector3<f32>,
@@ -79,6 +147,8 @@ pub struct PointLight {
     pub constant: f32,
     pub linear: f32,
     pub quadratic: f32,
+    /// Multiplier of the light color
+    pub intensity: f32,
 }
 
 impl PointLight {
@@ -95,6 +165,43 @@ impl PointLight {
             constant,
             linear,
             quadratic,
+            intensity: 1.0,
+        }
+    }
+
+    /// Light with inverse square falloff which drops to the [`LIGHT_RANGE_CUTOFF`]
+    /// at the `range` distance. Brighter lights fall off faster, so
+    /// the range does not depend on the `intensity`.
+    pub fn with_range<P: Into<Vector3<f32>>, C: Into<Vector3<f32>>>(
+        position: P,
+        color: C,
+        intensity: f32,
+        range: f32,
+    ) -> Self {
+        // Solves intensity / (1 + quadratic * range^2) = cutoff
+        let quadratic = (intensity / LIGHT_RANGE_CUTOFF - 1.0).max(0.0) / (range * range);
+        Self {
+            position: position.into(),
+            color: color.into(),
+            constant: 1.0,
+            linear: 0.0,
+            quadratic,
+            intensity,
+        }
+    }
+
+    /// Distance at which the light attenuation multiplied by the intensity
+    /// drops to `cutoff`. `None` if the light never gets dimmer than `cutoff`.
+    pub fn radius(&self, cutoff: f32) -> Option<f32> {
+        // Solves intensity / (constant + linear * d + quadratic * d^2) = cutoff
+        let c = self.constant - self.intensity / cutoff;
+        if 0.0 < self.quadratic {
+            let discriminant = self.linear * self.linear - 4.0 * self.quadratic * c;
+            Some(((-self.linear + discriminant.sqrt()) / (2.0 * self.quadratic)).max(0.0))
+        } else if 0.0 < self.linear {
+            Some((-c / self.linear).max(0.0))
+        } else {
+            None
         }
     }
 }
@@ -110,9 +217,10 @@ impl_simple_buffer!(
     { BufferBindingType::Uniform }
 );
 
-const MAX_LIGHTS: usize = 10;
+/// Maximum number of lights in the [`PointLights`]
+pub const MAX_LIGHTS: usize = 256;
 #[repr(C)]
-#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PointLightsUniform {
     // using i32 because of the wgsl
     lights_num: i32,
@@ -122,6 +230,12 @@ pub struct PointLightsUniform {
     lights: [PointLightUniform; MAX_LIGHTS],
 }
 
+impl Default for PointLightsUniform {
+    fn default() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+}
+
 impl From<&PointLights> for PointLightsUniform {
     fn from(value: &PointLights) -> Self {
         // TODO refactor this
@@ -156,6 +270,37 @@ impl_simple_buffer!(
     PointLightsHandle,
     PointLightsBindGroup,
     { BufferUsages::STORAGE | BufferUsages::COPY_DST },
-    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
+    { ShaderStages::VERTEX | ShaderStages::FRAGMENT | ShaderStages::COMPUTE },
     { BufferBindingType::Storage { read_only: true } }
 );
+
+impl PointLightsHandle {
+    /// Storage buffer with [`PointLightsUniform`]
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_light_radius() {
+        let light = PointLight::new((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), 1.0, 0.0, 1.0);
+        assert_eq!(light.radius(0.01), Some(99.0f32.sqrt()));
+        let light = PointLight::new((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), 1.0, 1.0, 0.0);
+        assert_eq!(light.radius(0.5), Some(1.0));
+        let light = PointLight::new((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), 1.0, 0.0, 0.0);
+        assert_eq!(light.radius(0.5), None);
+    }
+
+    #[test]
+    fn point_light_with_range() {
+        for intensity in [0.5, 1.0, 10.0] {
+            let light = PointLight::with_range((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), intensity, 8.0);
+            let radius = light.radius(LIGHT_RANGE_CUTOFF).unwrap();
+            assert!((radius - 8.0).abs() < 1e-3, "{intensity}: {radius}");
+        }
+    }
+}
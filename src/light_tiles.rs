@@ -0,0 +1,455 @@
+use crate::camera::CameraBindGroup;
+use crate::const_vec;
+use crate::light::{PointLightsHandle, LIGHT_RANGE_CUTOFF};
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::utils::ConstVec;
+
+const LIGHT_TILES_SHADER: &str = include_str!("shaders/light_tiles.wgsl");
+
+/// Number of tiles along each axis processed by one compute workgroup.
+/// Light tiles shader should use the same workgroup size.
+pub const LIGHT_TILES_WORKGROUP_SIZE: u32 = 8;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightTilesUniform {
+    tile_size: u32,
+    max_lights_per_tile: u32,
+    tiles_x: u32,
+    tiles_y: u32,
+    width: u32,
+    height: u32,
+    attenuation_cutoff: f32,
+    _pad: u32,
+}
+
+impl From<&LightTiles> for LightTilesUniform {
+    fn from(value: &LightTiles) -> Self {
+        let (tiles_x, tiles_y) = value.tiles();
+        Self {
+            tile_size: value.tile_size,
+            max_lights_per_tile: value.max_lights_per_tile,
+            tiles_x,
+            tiles_y,
+            width: value.width,
+            height: value.height,
+            attenuation_cutoff: value.attenuation_cutoff,
+            ..Default::default()
+        }
+    }
+}
+
+/// Screen space tiles with lists of point lights affecting them.
+/// The [`LightTilesPipeline`] compute pass bins lights into tiles, so the
+/// lighting pass only evaluates lights of the tile the pixel is in.
+///
+/// Tiles are stored as an `array<u32>` where every tile takes
+/// `max_lights_per_tile + 1` elements: number of lights followed by the
+/// light indices. Tile of the pixel at (x, y) is
+/// `(y / tile_size) * tiles_x + x / tile_size`.
+#[derive(Debug, Clone, Copy)]
+pub struct LightTiles {
+    /// Size of the tile side in pixels
+    pub tile_size: u32,
+    /// Lights over this limit are ignored by the tile
+    pub max_lights_per_tile: u32,
+    /// Size of the render target in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Attenuation at which the light is considered to have no effect.
+    /// Defines the light radius, see [`crate::light::PointLight::radius`].
+    pub attenuation_cutoff: f32,
+}
+
+impl LightTiles {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            tile_size: 16,
+            max_lights_per_tile: 64,
+            width,
+            height,
+            attenuation_cutoff: LIGHT_RANGE_CUTOFF,
+        }
+    }
+
+    /// Number of tiles along X and Y axes
+    pub fn tiles(&self) -> (u32, u32) {
+        let tile_size = self.tile_size.max(1);
+        (
+            self.width.div_ceil(tile_size).max(1),
+            self.height.div_ceil(tile_size).max(1),
+        )
+    }
+
+    /// Size of the tiles buffer in bytes
+    pub fn tiles_buffer_size(&self) -> BufferAddress {
+        let (tiles_x, tiles_y) = self.tiles();
+        let tile = (self.max_lights_per_tile + 1) as BufferAddress;
+        tiles_x as BufferAddress * tiles_y as BufferAddress * tile * 4
+    }
+
+    /// Tiles need to be rebuilt with
+    /// [`ResourceHandle::replace`] after the resize.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+#[derive(Debug)]
+pub struct LightTilesResources {
+    settings_buffer: Buffer,
+    tiles_buffer: Buffer,
+}
+
+impl GpuResource for LightTiles {
+    type ResourceType = LightTilesResources;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let settings_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("light_tiles_settings_buffer"),
+            contents: bytemuck::cast_slice(&[LightTilesUniform::from(self)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let tiles_buffer = renderer.device().create_buffer(&BufferDescriptor {
+            label: Some("light_tiles_buffer"),
+            size: self.tiles_buffer_size(),
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        Self::ResourceType {
+            settings_buffer,
+            tiles_buffer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LightTilesHandle {
+    pub settings_buffer_id: BufferId,
+    pub tiles_buffer_id: BufferId,
+}
+
+impl ResourceHandle for LightTilesHandle {
+    type OriginalResource<'a> = LightTiles;
+    type ResourceType = LightTilesResources;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            settings_buffer_id: storage.insert_buffer(resource.settings_buffer),
+            tiles_buffer_id: storage.insert_buffer(resource.tiles_buffer),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_buffer(self.settings_buffer_id, resource.settings_buffer);
+        storage.replace_buffer(self.tiles_buffer_id, resource.tiles_buffer);
+    }
+
+    /// Writes settings. Number of tiles and lights per tile must not change.
+    fn update(&self, renderer: &Renderer, storage: &RenderStorage, original: &LightTiles) {
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.settings_buffer_id),
+            0,
+            bytemuck::cast_slice(&[LightTilesUniform::from(original)]),
+        );
+    }
+}
+
+fn light_tiles_bind_group_layout(
+    renderer: &Renderer,
+    visibility: ShaderStages,
+    read_only: bool,
+    label: &str,
+) -> BindGroupLayout {
+    let buffer_entry = |binding, ty| BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    renderer
+        .device()
+        .create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                buffer_entry(0, BufferBindingType::Uniform),
+                buffer_entry(1, BufferBindingType::Storage { read_only }),
+                buffer_entry(2, BufferBindingType::Storage { read_only: true }),
+            ],
+            label: Some(label),
+        })
+}
+
+fn light_tiles_bind_group(
+    renderer: &Renderer,
+    storage: &RenderStorage,
+    layout: &BindGroupLayout,
+    resource: &(LightTilesHandle, PointLightsHandle),
+    label: &str,
+) -> BindGroup {
+    let (tiles, lights) = resource;
+    renderer.device().create_bind_group(&BindGroupDescriptor {
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: storage
+                    .get_buffer(tiles.settings_buffer_id)
+                    .as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: storage
+                    .get_buffer(tiles.tiles_buffer_id)
+                    .as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: storage.get_buffer(lights.buffer_id()).as_entire_binding(),
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+fn light_tiles_dependencies(
+    resource: &(LightTilesHandle, PointLightsHandle),
+) -> BindGroupDependencies {
+    let (tiles, lights) = resource;
+    BindGroupDependencies {
+        textures: vec![],
+        buffers: vec![
+            tiles.settings_buffer_id,
+            tiles.tiles_buffer_id,
+            lights.buffer_id(),
+        ],
+    }
+}
+
+/// Bind group used by the light binning compute pass
+/// - binding 0: tiles settings
+/// - binding 1: tiles (read_write)
+/// - binding 2: point lights (read)
+#[derive(Debug, Clone, Copy)]
+pub struct LightTilesComputeBindGroup(pub BindGroupId);
+
+impl AssetBindGroup for LightTilesComputeBindGroup {
+    type ResourceHandle = (LightTilesHandle, PointLightsHandle);
+
+    fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+        light_tiles_bind_group_layout(
+            renderer,
+            ShaderStages::COMPUTE,
+            false,
+            "light_tiles_compute_bind_group_layout",
+        )
+    }
+
+    fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) -> Self {
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
+        let bind_group = light_tiles_bind_group(
+            renderer,
+            storage,
+            layout,
+            resource,
+            "light_tiles_compute_bind_group",
+        );
+        Self(
+            storage.insert_bind_group_with_dependencies(
+                bind_group,
+                light_tiles_dependencies(resource),
+            ),
+        )
+    }
+
+    fn replace(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) {
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
+        let bind_group = light_tiles_bind_group(
+            renderer,
+            storage,
+            layout,
+            resource,
+            "light_tiles_compute_bind_group",
+        );
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            light_tiles_dependencies(resource),
+        );
+    }
+}
+
+/// Bind group used by the lighting pass to find lights of the pixel
+/// - binding 0: tiles settings
+/// - binding 1: tiles (read)
+/// - binding 2: point lights (read)
+#[derive(Debug, Clone, Copy)]
+pub struct LightTilesBindGroup(pub BindGroupId);
+
+impl AssetBindGroup for LightTilesBindGroup {
+    type ResourceHandle = (LightTilesHandle, PointLightsHandle);
+
+    fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+        light_tiles_bind_group_layout(
+            renderer,
+            ShaderStages::FRAGMENT,
+            true,
+            "light_tiles_bind_group_layout",
+        )
+    }
+
+    fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) -> Self {
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
+        let bind_group = light_tiles_bind_group(
+            renderer,
+            storage,
+            layout,
+            resource,
+            "light_tiles_bind_group",
+        );
+        Self(
+            storage.insert_bind_group_with_dependencies(
+                bind_group,
+                light_tiles_dependencies(resource),
+            ),
+        )
+    }
+
+    fn replace(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) {
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
+        let bind_group = light_tiles_bind_group(
+            renderer,
+            storage,
+            layout,
+            resource,
+            "light_tiles_bind_group",
+        );
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            light_tiles_dependencies(resource),
+        );
+    }
+}
+
+/// Compute pipeline binning point lights into [`LightTiles`].
+/// Lights are bounded by the screen space rectangle of their radius,
+/// so tiles can contain lights which are hidden by the scene depth.
+#[derive(Debug, Clone, Copy)]
+pub struct LightTilesPipeline {
+    pub pipeline_id: ComputePipelineId,
+}
+
+impl LightTilesPipeline {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage) -> Self {
+        let defines = ShaderDefines::default()
+            .define("WORKGROUP_SIZE", format!("{LIGHT_TILES_WORKGROUP_SIZE}u"));
+        let shader = create_shader_module(
+            renderer,
+            "light_tiles.wgsl",
+            defines.apply(LIGHT_TILES_SHADER),
+        );
+        let pipeline = ComputePipelineBuilder {
+            shader_path: "light_tiles.wgsl",
+            label: Some("light_tiles_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<LightTilesComputeBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            entry_point: "cs_main",
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_compute_pipeline(pipeline),
+        }
+    }
+
+    /// Command binning lights into all `tiles`
+    pub fn command(
+        &self,
+        tiles: &LightTiles,
+        tiles_bind_group: LightTilesComputeBindGroup,
+        camera: CameraBindGroup,
+    ) -> LightTilesCommand {
+        LightTilesCommand {
+            pipeline_id: self.pipeline_id,
+            tiles: tiles.tiles(),
+            bind_groups: const_vec![tiles_bind_group.0, camera.0],
+        }
+    }
+}
+
+/// Dispatches light binning compute pipeline for all tiles.
+/// Needs to run after lights or camera change and before the lighting pass.
+#[derive(Debug, Clone)]
+pub struct LightTilesCommand {
+    pub pipeline_id: ComputePipelineId,
+    /// Number of tiles along X and Y axes
+    pub tiles: (u32, u32),
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupId>,
+}
+
+impl LightTilesCommand {
+    pub fn execute(&self, encoder: &mut CommandEncoder, storage: &RenderStorage) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("light_tiles_pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(storage.get_compute_pipeline(self.pipeline_id));
+        for (i, bg) in self.bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(i as u32, storage.get_bind_group(*bg), &[]);
+        }
+        compute_pass.dispatch_workgroups(
+            self.tiles.0.div_ceil(LIGHT_TILES_WORKGROUP_SIZE),
+            self.tiles.1.div_ceil(LIGHT_TILES_WORKGROUP_SIZE),
+            1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_tiles_count() {
+        let mut tiles = LightTiles::new(1920, 1080);
+        assert_eq!(tiles.tiles(), (120, 68));
+        tiles.tile_size = 32;
+        tiles.max_lights_per_tile = 15;
+        assert_eq!(tiles.tiles(), (60, 34));
+        assert_eq!(tiles.tiles_buffer_size(), 60 * 34 * 16 * 4);
+        tiles.resize(0, 0);
+        assert_eq!(tiles.tiles(), (1, 1));
+    }
+}
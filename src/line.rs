@@ -1,7 +1,15 @@
+use crate::impl_simple_buffer;
 use crate::mesh::GpuMesh;
 use crate::prelude::ConstVec;
 use crate::render::prelude::*;
 
+/// Number of triangles used for each round cap
+pub const LINE_ROUND_CAP_TRIANGLES: u32 = 8;
+/// Vertices drawn per line segment: 6 for the segment body,
+/// 3 for the join at the end and triangles of both round caps.
+/// Unused parts collapse into degenerate triangles in the shader.
+pub const LINE_VERTEX_COUNT: u32 = 6 + 3 + 2 * LINE_ROUND_CAP_TRIANGLES * 3;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LineVertex {
@@ -9,6 +17,31 @@ pub struct LineVertex {
     pub position_b: [f32; 3],
     pub color_a: [f32; 4],
     pub color_b: [f32; 4],
+    /// Point before `position_a` in the polyline.
+    /// Equals `position_a` if segment starts the line.
+    pub position_prev: [f32; 3],
+    /// Point after `position_b` in the polyline.
+    /// Equals `position_b` if segment ends the line.
+    pub position_next: [f32; 3],
+}
+
+impl LineVertex {
+    /// Standalone segment with caps on both ends
+    pub fn segment(
+        position_a: [f32; 3],
+        position_b: [f32; 3],
+        color_a: [f32; 4],
+        color_b: [f32; 4],
+    ) -> Self {
+        Self {
+            position_a,
+            position_b,
+            color_a,
+            color_b,
+            position_prev: position_a,
+            position_next: position_b,
+        }
+    }
 }
 
 impl VertexLayout for LineVertex {
@@ -37,6 +70,16 @@ impl VertexLayout for LineVertex {
                     shader_location: 3,
                     format: VertexFormat::Float32x4,
                 },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 14]>() as BufferAddress,
+                    shader_location: 4,
+                    format: VertexFormat::Float32x3,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 17]>() as BufferAddress,
+                    shader_location: 5,
+                    format: VertexFormat::Float32x3,
+                },
             ],
         }
     }
@@ -47,6 +90,107 @@ pub struct Line {
     pub vertices: Vec<LineVertex>,
 }
 
+impl Line {
+    /// Connected line through all `points`. Each point is a position
+    /// with a color. Closed polyline also connects the last point to the first one.
+    pub fn polyline(points: &[([f32; 3], [f32; 4])], closed: bool) -> Self {
+        let n = points.len();
+        let segments = if closed { n } else { n.saturating_sub(1) };
+        let vertices = (0..segments)
+            .map(|i| {
+                let (position_a, color_a) = points[i];
+                let (position_b, color_b) = points[(i + 1) % n];
+                let position_prev = if 0 < i || closed {
+                    points[(i + n - 1) % n].0
+                } else {
+                    position_a
+                };
+                let position_next = if i + 2 < n || closed {
+                    points[(i + 2) % n].0
+                } else {
+                    position_b
+                };
+                LineVertex {
+                    position_a,
+                    position_b,
+                    color_a,
+                    color_b,
+                    position_prev,
+                    position_next,
+                }
+            })
+            .collect();
+        Self { vertices }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    /// Extends line ends by half of the width
+    Square,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Falls back to bevel for very sharp angles
+    #[default]
+    Miter,
+    Bevel,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineStyleUniform {
+    width: f32,
+    cap: u32,
+    join: u32,
+    _pad: f32,
+}
+
+/// Screen space style of the lines
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle {
+    /// Width in pixels
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            cap: LineCap::default(),
+            join: LineJoin::default(),
+        }
+    }
+}
+
+impl From<&LineStyle> for LineStyleUniform {
+    fn from(value: &LineStyle) -> Self {
+        Self {
+            width: value.width,
+            cap: value.cap as u32,
+            join: value.join as u32,
+            ..Default::default()
+        }
+    }
+}
+
+impl_simple_buffer!(
+    LineStyle,
+    LineStyleUniform,
+    LineStyleResources,
+    LineStyleHandle,
+    LineStyleBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::VERTEX },
+    { BufferBindingType::Uniform }
+);
+
 impl GpuResource for Line {
     type ResourceType = GpuMesh;
 
@@ -54,7 +198,7 @@ impl GpuResource for Line {
         let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
             label: Some("vertex_buffer"),
             contents: bytemuck::cast_slice(&self.vertices),
-            usage: BufferUsages::VERTEX,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
         });
 
         Self::ResourceType {
@@ -67,14 +211,15 @@ impl GpuResource for Line {
 
 #[derive(Debug, Clone)]
 pub struct LineRenderCommand {
-    pub pipeline_id: ResourceId,
-    pub mesh_id: ResourceId,
-    pub bind_groups: ConstVec<MAX_BIND_GROUPS, ResourceId>,
+    pub pipeline_id: PipelineId,
+    pub mesh_id: MeshId,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupId>,
 }
 
 impl RenderCommand for LineRenderCommand {
     fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
-        render_pass.set_pipeline(storage.get_pipeline(self.pipeline_id));
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        render_pass.set_pipeline(&pipeline.pipeline);
         for (i, bg) in self.bind_groups.iter().enumerate() {
             render_pass.set_bind_group(i as u32, storage.get_bind_group(*bg), &[]);
         }
@@ -82,6 +227,54 @@ impl RenderCommand for LineRenderCommand {
         let mesh = storage.get_mesh(self.mesh_id);
         render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
 
-        render_pass.draw(0..6, 0..mesh.num_elements);
+        // Every line segment is an instance
+        let mut stats = RenderStats {
+            bind_group_switches: self.bind_groups.iter().count(),
+            pipeline_switches: 1,
+            ..Default::default()
+        };
+        stats.record_instanced_draw(pipeline.topology, LINE_VERTEX_COUNT, mesh.num_elements);
+        storage.record_stats(stats);
+        render_pass.draw(0..LINE_VERTEX_COUNT, 0..mesh.num_elements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    #[test]
+    fn line_polyline_open() {
+        let line = Line::polyline(
+            &[
+                ([0.0, 0.0, 0.0], WHITE),
+                ([1.0, 0.0, 0.0], WHITE),
+                ([1.0, 1.0, 0.0], WHITE),
+            ],
+            false,
+        );
+        assert_eq!(line.vertices.len(), 2);
+        assert_eq!(line.vertices[0].position_prev, [0.0, 0.0, 0.0]);
+        assert_eq!(line.vertices[0].position_next, [1.0, 1.0, 0.0]);
+        assert_eq!(line.vertices[1].position_prev, [0.0, 0.0, 0.0]);
+        assert_eq!(line.vertices[1].position_next, [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn line_polyline_closed() {
+        let line = Line::polyline(
+            &[
+                ([0.0, 0.0, 0.0], WHITE),
+                ([1.0, 0.0, 0.0], WHITE),
+                ([1.0, 1.0, 0.0], WHITE),
+            ],
+            true,
+        );
+        assert_eq!(line.vertices.len(), 3);
+        assert_eq!(line.vertices[0].position_prev, [1.0, 1.0, 0.0]);
+        assert_eq!(line.vertices[2].position_b, [0.0, 0.0, 0.0]);
+        assert_eq!(line.vertices[2].position_next, [1.0, 0.0, 0.0]);
     }
 }
@@ -0,0 +1,188 @@
+use crate::cgmath_imports::*;
+use crate::mesh::{Mesh, MeshVertex};
+use cgmath::{ElementWise, EuclideanSpace};
+use std::collections::HashMap;
+
+/// Corners of a unit cell
+const CELL_CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+
+/// Cell split into tetrahedra around the `0-6` diagonal. All cells are split
+/// the same way, so diagonals of the shared faces match and the surface
+/// has no cracks.
+const CELL_TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 6, 1, 2],
+    [0, 6, 2, 3],
+    [0, 6, 3, 7],
+    [0, 6, 7, 4],
+    [0, 6, 4, 5],
+    [0, 6, 5, 1],
+];
+
+/// Polygonizes the zero level of the signed distance function `sdf` inside of the
+/// `bounds` (min, max) split into `resolution` cells along each axis. Negative
+/// distances are inside. Every cell is split into tetrahedra, which needs no case
+/// tables and has no ambiguous cases. Vertices on the same grid edge are shared,
+/// normals come from the `sdf` gradient. Triangles are counter clockwise
+/// when looking from the outside.
+pub fn marching_cubes<F>(sdf: F, bounds: ([f32; 3], [f32; 3]), resolution: [u32; 3]) -> Mesh
+where
+    F: Fn(Point3<f32>) -> f32,
+{
+    let min = Vector3::from(bounds.0);
+    let [nx, ny, nz] = resolution.map(|r| r.max(1) as usize);
+    let cell = (Vector3::from(bounds.1) - min)
+        .div_element_wise(Vector3::new(nx as f32, ny as f32, nz as f32));
+
+    let grid_index = |x: usize, y: usize, z: usize| x + (nx + 1) * (y + (ny + 1) * z);
+    let grid_point = |i: usize| {
+        let (x, y, z) = (
+            i % (nx + 1),
+            i / (nx + 1) % (ny + 1),
+            i / ((nx + 1) * (ny + 1)),
+        );
+        Point3::from_vec(min + cell.mul_element_wise(Vector3::new(x as f32, y as f32, z as f32)))
+    };
+    let values: Vec<f32> = (0..(nx + 1) * (ny + 1) * (nz + 1))
+        .map(|i| sdf(grid_point(i)))
+        .collect();
+
+    // Central differences over half of the cell
+    let h = cell / 2.0;
+    let normal = |p: Point3<f32>| {
+        let gradient = Vector3::new(
+            sdf(p + Vector3::unit_x() * h.x) - sdf(p - Vector3::unit_x() * h.x),
+            sdf(p + Vector3::unit_y() * h.y) - sdf(p - Vector3::unit_y() * h.y),
+            sdf(p + Vector3::unit_z() * h.z) - sdf(p - Vector3::unit_z() * h.z),
+        );
+        if gradient.magnitude2() == 0.0 {
+            gradient
+        } else {
+            gradient.normalize()
+        }
+    };
+
+    let edge_position = |a: usize, b: usize| {
+        let (va, vb) = (values[a], values[b]);
+        let t = if va == vb { 0.5 } else { va / (va - vb) };
+        let (pa, pb) = (grid_point(a), grid_point(b));
+        pa + (pb - pa) * t
+    };
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut edge_vertices: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut edge_vertex = |a: usize, b: usize| {
+        let key = (a.min(b), a.max(b));
+        *edge_vertices.entry(key).or_insert_with(|| {
+            let position = edge_position(key.0, key.1);
+            vertices.push(MeshVertex::new(
+                position.into(),
+                [0.0; 2],
+                normal(position).into(),
+            ));
+            (vertices.len() - 1) as u32
+        })
+    };
+
+    for z in 0..nz {
+        for y in 0..ny {
+            for x in 0..nx {
+                let corners = CELL_CORNERS.map(|[cx, cy, cz]| grid_index(x + cx, y + cy, z + cz));
+                for tetrahedron in CELL_TETRAHEDRA {
+                    let (inside, outside): (Vec<usize>, Vec<usize>) = tetrahedron
+                        .iter()
+                        .map(|&c| corners[c])
+                        .partition(|&i| values[i] < 0.0);
+                    // Edges crossing the surface in the order around the polygon
+                    let mut polygon: Vec<(usize, usize)> = match inside.len() {
+                        1 => outside.iter().map(|&o| (inside[0], o)).collect(),
+                        3 => inside.iter().map(|&i| (i, outside[0])).collect(),
+                        2 => vec![
+                            (inside[0], outside[0]),
+                            (inside[0], outside[1]),
+                            (inside[1], outside[1]),
+                            (inside[1], outside[0]),
+                        ],
+                        _ => continue,
+                    };
+
+                    // Triangle normal needs to point from the inside corners to the outside ones
+                    let centroid = |points: &[usize]| {
+                        points
+                            .iter()
+                            .map(|&i| grid_point(i).to_vec())
+                            .sum::<Vector3<f32>>()
+                            / points.len() as f32
+                    };
+                    let outward = centroid(&outside) - centroid(&inside);
+                    let [p0, p1, p2] =
+                        [polygon[0], polygon[1], polygon[2]].map(|(a, b)| edge_position(a, b));
+                    if (p1 - p0).cross(p2 - p0).dot(outward) < 0.0 {
+                        polygon.reverse();
+                    }
+
+                    let polygon: Vec<u32> =
+                        polygon.iter().map(|&(a, b)| edge_vertex(a, b)).collect();
+                    for pair in polygon[1..].windows(2) {
+                        indices.extend([polygon[0], pair[0], pair[1]]);
+                    }
+                }
+            }
+        }
+    }
+
+    Mesh {
+        name: "marching_cubes".to_string(),
+        vertices,
+        indices,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::FrontFace;
+
+    #[test]
+    fn marching_cubes_sphere() {
+        let sphere = |p: Point3<f32>| p.to_vec().magnitude() - 1.0;
+        let mesh = marching_cubes(sphere, ([-1.5; 3], [1.5; 3]), [12, 12, 12]);
+        assert!(mesh.validate().is_ok());
+        assert!(!mesh.indices.is_empty());
+
+        let cell = 3.0 / 12.0;
+        for v in mesh.vertices.iter() {
+            let position = Vector3::from(v.position);
+            assert!((position.magnitude() - 1.0).abs() < cell / 2.0, "{v:?}");
+            assert!(
+                0.99 < Vector3::from(v.normal).dot(position.normalize()),
+                "{v:?}"
+            );
+        }
+        // Closed surface, every edge is shared by exactly two triangles
+        let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+        for t in mesh.indices.chunks_exact(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(edges.values().all(|&count| count == 2));
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Ccw));
+    }
+
+    #[test]
+    fn marching_cubes_empty() {
+        let mesh = marching_cubes(|_| 1.0, ([-1.0; 3], [1.0; 3]), [4, 4, 4]);
+        assert!(mesh.vertices.is_empty());
+        assert!(mesh.indices.is_empty());
+    }
+}
@@ -13,9 +13,23 @@ pub struct MaterialPropertiesUniform {
     specular: [f32; 3],
     _pad3: f32,
     shininess: f32,
-    _pad4: f32,
-    _pad5: f32,
-    _pad6: f32,
+    // using u32 because of the wgsl
+    flip_normal_y: u32,
+    parallax_mode: u32,
+    parallax_scale: f32,
+}
+
+/// How the height texture of the [`Material`] offsets texture coordinates
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParallaxMode {
+    /// Height texture is ignored
+    #[default]
+    Off,
+    /// Single sample offset along the view direction. Cheap, but
+    /// breaks down at grazing angles.
+    Offset,
+    /// Height texture is ray marched along the view direction
+    Occlusion,
 }
 
 #[derive(Debug)]
@@ -23,10 +37,18 @@ pub struct Material {
     pub name: String,
     pub diffuse_texture: ImageTexture,
     pub normal_texture: ImageTexture,
+    /// Used by the [`ParallaxMode`] other than [`ParallaxMode::Off`]
+    pub height_texture: ImageTexture,
     pub ambient: [f32; 3],
     pub diffuse: [f32; 3],
     pub specular: [f32; 3],
     pub shininess: f32,
+    /// Normal texture uses DirectX convention with green channel
+    /// pointing down. Defaults to the OpenGL convention.
+    pub flip_normal_y: bool,
+    pub parallax_mode: ParallaxMode,
+    /// Depth of the height texture in texture coordinates
+    pub parallax_scale: f32,
 }
 
 impl Material {
@@ -36,6 +58,9 @@ impl Material {
             diffuse: self.diffuse,
             specular: self.specular,
             shininess: self.shininess,
+            flip_normal_y: self.flip_normal_y as u32,
+            parallax_mode: self.parallax_mode as u32,
+            parallax_scale: self.parallax_scale,
             ..Default::default()
         }
     }
@@ -46,6 +71,7 @@ pub struct MaterialResource {
     buffer: Buffer,
     diffuse_texture: GpuTexture,
     normal_texture: GpuTexture,
+    height_texture: GpuTexture,
 }
 
 impl GpuResource for Material {
@@ -54,6 +80,7 @@ impl GpuResource for Material {
     fn build(&self, renderer: &Renderer) -> Self::ResourceType {
         let diffuse_texture = self.diffuse_texture.build(renderer);
         let normal_texture = self.normal_texture.build(renderer);
+        let height_texture = self.height_texture.build(renderer);
 
         let properties = self.to_uniform();
 
@@ -67,15 +94,17 @@ impl GpuResource for Material {
             buffer,
             diffuse_texture,
             normal_texture,
+            height_texture,
         }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct MaterialHandle {
-    pub buffer_id: ResourceId,
-    pub diffuse_texture_id: ResourceId,
-    pub normal_texture_id: ResourceId,
+    pub buffer_id: BufferId,
+    pub diffuse_texture_id: TextureId,
+    pub normal_texture_id: TextureId,
+    pub height_texture_id: TextureId,
 }
 
 impl ResourceHandle for MaterialHandle {
@@ -87,6 +116,7 @@ impl ResourceHandle for MaterialHandle {
             buffer_id: storage.insert_buffer(resource.buffer),
             diffuse_texture_id: storage.insert_texture(resource.diffuse_texture),
             normal_texture_id: storage.insert_texture(resource.normal_texture),
+            height_texture_id: storage.insert_texture(resource.height_texture),
         }
     }
 
@@ -94,6 +124,7 @@ impl ResourceHandle for MaterialHandle {
         storage.replace_buffer(self.buffer_id, resource.buffer);
         storage.replace_texture(self.diffuse_texture_id, resource.diffuse_texture);
         storage.replace_texture(self.normal_texture_id, resource.normal_texture);
+        storage.replace_texture(self.height_texture_id, resource.height_texture);
     }
 
     fn update(
@@ -111,7 +142,7 @@ impl ResourceHandle for MaterialHandle {
 }
 
 #[derive(Debug, Clone, Copy, Hash)]
-pub struct MaterialBindGroup(pub ResourceId);
+pub struct MaterialBindGroup(pub BindGroupId);
 
 impl AssetBindGroup for MaterialBindGroup {
     type ResourceHandle = MaterialHandle;
@@ -163,6 +194,22 @@ impl AssetBindGroup for MaterialBindGroup {
                         },
                         count: None,
                     },
+                    BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: TextureViewDimension::D2,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
                 ],
                 label: Some("material_bind_group_layout"),
             })
@@ -173,10 +220,11 @@ impl AssetBindGroup for MaterialBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) -> Self {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
         let buffer = storage.get_buffer(resource.buffer_id);
         let diffuse_texture = storage.get_texture(resource.diffuse_texture_id);
         let normal_texture = storage.get_texture(resource.normal_texture_id);
+        let height_texture = storage.get_texture(resource.height_texture_id);
 
         let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
             layout,
@@ -201,11 +249,29 @@ impl AssetBindGroup for MaterialBindGroup {
                     binding: 4,
                     resource: buffer.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&height_texture.view),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::Sampler(&height_texture.sampler),
+                },
             ],
             label: None,
         });
 
-        Self(storage.insert_bind_group(bind_group))
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![
+                    resource.diffuse_texture_id,
+                    resource.normal_texture_id,
+                    resource.height_texture_id,
+                ],
+                buffers: vec![resource.buffer_id],
+            },
+        ))
     }
 
     fn replace(
@@ -214,10 +280,11 @@ impl AssetBindGroup for MaterialBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
         let buffer = storage.get_buffer(resource.buffer_id);
         let diffuse_texture = storage.get_texture(resource.diffuse_texture_id);
         let normal_texture = storage.get_texture(resource.normal_texture_id);
+        let height_texture = storage.get_texture(resource.height_texture_id);
 
         let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
             layout,
@@ -242,11 +309,30 @@ impl AssetBindGroup for MaterialBindGroup {
                     binding: 4,
                     resource: buffer.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&height_texture.view),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::Sampler(&height_texture.sampler),
+                },
             ],
             label: None,
         });
 
-        storage.replace_bind_group(self.0, bind_group);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![
+                    resource.diffuse_texture_id,
+                    resource.normal_texture_id,
+                    resource.height_texture_id,
+                ],
+                buffers: vec![resource.buffer_id],
+            },
+        );
     }
 }
 
@@ -262,6 +348,23 @@ impl From<&ColorMaterial> for MaterialPropertiesUniform {
     }
 }
 
+/// Converts sRGB encoded channel in [0, 1] range into linear space
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn srgb8_to_linear(color: [u8; 3]) -> [f32; 3] {
+    color.map(|c| srgb_to_linear(c as f32 / 255.0))
+}
+
+/// Material with constant colors.
+/// Colors are in linear space, same as the lighting math in the shaders.
+/// Conversion into sRGB happens when writing into an sRGB surface.
+/// Use [`ColorMaterial::from_srgb8`] for colors picked in sRGB (e.g. from a color picker).
 #[derive(Debug)]
 pub struct ColorMaterial {
     pub ambient: [f32; 3],
@@ -270,6 +373,23 @@ pub struct ColorMaterial {
     pub shininess: f32,
 }
 
+impl ColorMaterial {
+    /// Creates material from sRGB encoded 8 bit colors
+    pub fn from_srgb8(
+        ambient: [u8; 3],
+        diffuse: [u8; 3],
+        specular: [u8; 3],
+        shininess: f32,
+    ) -> Self {
+        Self {
+            ambient: srgb8_to_linear(ambient),
+            diffuse: srgb8_to_linear(diffuse),
+            specular: srgb8_to_linear(specular),
+            shininess,
+        }
+    }
+}
+
 impl_simple_buffer!(
     ColorMaterial,
     MaterialPropertiesUniform,
@@ -280,3 +400,23 @@ impl_simple_buffer!(
     { ShaderStages::FRAGMENT },
     { BufferBindingType::Uniform }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_to_linear_bounds() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn color_material_from_srgb8() {
+        let material = ColorMaterial::from_srgb8([0; 3], [128; 3], [255; 3], 1.0);
+        assert_eq!(material.ambient, [0.0; 3]);
+        // sRGB mid grey is ~21.6% in linear space
+        assert!((material.diffuse[0] - 0.2158).abs() < 1e-3);
+        assert!((material.specular[0] - 1.0).abs() < 1e-6);
+    }
+}
@@ -3,7 +3,13 @@ use std::ops::Range;
 use crate::cgmath_imports::*;
 use crate::prelude::ConstVec;
 use crate::render::prelude::*;
+use crate::transform::Transform;
+use cgmath::{Matrix, SquareMatrix};
+use wgpu::COPY_BUFFER_ALIGNMENT;
 
+/// Vertex of the [`Mesh`]. Tangent and bitangent are only needed for
+/// normal mapping and can be computed for the whole mesh with
+/// [`MeshVertex::calc_tangents_and_bitangents`].
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct MeshVertex {
@@ -16,16 +22,34 @@ pub struct MeshVertex {
 
 impl From<([f32; 3], [f32; 2], [f32; 3])> for MeshVertex {
     fn from(data: ([f32; 3], [f32; 2], [f32; 3])) -> Self {
-        Self {
-            position: data.0,
-            tex_coords: data.1,
-            normal: data.2,
-            ..Default::default()
-        }
+        Self::new(data.0, data.1, data.2)
     }
 }
 
 impl MeshVertex {
+    /// Vertex with zero tangent and bitangent
+    pub const fn new(position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]) -> Self {
+        Self {
+            position,
+            tex_coords,
+            normal,
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
+        }
+    }
+
+    /// Same vertex with the provided tangent and bitangent,
+    /// e.g. computed analytically by a procedural generator
+    pub const fn with_tangents(self, tangent: [f32; 3], bitangent: [f32; 3]) -> Self {
+        Self {
+            tangent,
+            bitangent,
+            ..self
+        }
+    }
+
+    /// Accumulates tangents and bitangents of all triangles in the
+    /// `indices` into their vertices and normalizes them
     pub fn calc_tangents_and_bitangents(vertices: &mut [MeshVertex], indices: &[u32]) {
         for c in indices.chunks(3) {
             let v0 = vertices[c[0] as usize];
@@ -69,6 +93,21 @@ impl MeshVertex {
             v.bitangent = Vector3::from(v.bitangent).normalize().into();
         }
     }
+
+    /// Layout with only the position attribute at location 0.
+    /// Used by passes that only need positions (e.g. depth and shadow passes)
+    /// with the same vertex buffer.
+    pub fn position_only_layout<'a>() -> VertexBufferLayout<'a> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &[VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: VertexFormat::Float32x3,
+            }],
+        }
+    }
 }
 
 impl VertexLayout for MeshVertex {
@@ -107,6 +146,9 @@ impl VertexLayout for MeshVertex {
     }
 }
 
+/// Mesh without index buffer draws `num_elements` vertices in order,
+/// indexed mesh draws `num_elements` indices. Mesh with zero elements
+/// draws nothing and no draw call is made for it.
 #[derive(Debug)]
 pub struct GpuMesh {
     pub vertex_buffer: Buffer,
@@ -114,6 +156,12 @@ pub struct GpuMesh {
     pub num_elements: u32,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum MeshError {
+    #[error("Index {index} is out of range of {vertices} vertices")]
+    IndexOutOfRange { index: u32, vertices: usize },
+}
+
 #[derive(Debug)]
 pub struct Mesh {
     pub name: String,
@@ -121,23 +169,179 @@ pub struct Mesh {
     pub indices: Vec<u32>,
 }
 
+impl Mesh {
+    /// Creates mesh checking that all indices point to existing vertices
+    pub fn new(
+        name: impl Into<String>,
+        vertices: Vec<MeshVertex>,
+        indices: Vec<u32>,
+    ) -> Result<Self, MeshError> {
+        let mesh = Self {
+            name: name.into(),
+            vertices,
+            indices,
+        };
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Checks that all indices point to existing vertices
+    pub fn validate(&self) -> Result<(), MeshError> {
+        match self
+            .indices
+            .iter()
+            .find(|i| self.vertices.len() <= **i as usize)
+        {
+            Some(index) => Err(MeshError::IndexOutOfRange {
+                index: *index,
+                vertices: self.vertices.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Bakes transforms into vertices of all meshes and concatenates them
+    /// into one mesh, so static meshes sharing a material can be drawn
+    /// with a single draw call. Normals are transformed with the normal
+    /// matrix, so non uniform scales are handled correctly.
+    pub fn merge(meshes: &[(Mesh, Transform)]) -> Mesh {
+        let mut vertices = Vec::with_capacity(meshes.iter().map(|(m, _)| m.vertices.len()).sum());
+        let mut indices = Vec::with_capacity(meshes.iter().map(|(m, _)| m.indices.len()).sum());
+
+        for (mesh, transform) in meshes {
+            let model = Matrix4::from(transform);
+            let model_3x3 =
+                Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate());
+            let normal_matrix = model_3x3
+                .invert()
+                .map(|m| m.transpose())
+                .unwrap_or(model_3x3);
+
+            let offset = vertices.len() as u32;
+            vertices.extend(mesh.vertices.iter().map(|v| {
+                MeshVertex {
+                    position: (model * Vector3::from(v.position).extend(1.0))
+                        .truncate()
+                        .into(),
+                    tex_coords: v.tex_coords,
+                    normal: normalize_or_zero(normal_matrix * Vector3::from(v.normal)).into(),
+                    tangent: normalize_or_zero(model_3x3 * Vector3::from(v.tangent)).into(),
+                    bitangent: normalize_or_zero(model_3x3 * Vector3::from(v.bitangent)).into(),
+                }
+            }));
+
+            // Mirroring transforms flip triangles inside out
+            if model_3x3.determinant() < 0.0 {
+                indices.extend(
+                    mesh.indices
+                        .chunks(3)
+                        .flat_map(|c| c.iter().rev())
+                        .map(|i| i + offset),
+                );
+            } else {
+                indices.extend(mesh.indices.iter().map(|i| i + offset));
+            }
+        }
+
+        Self {
+            name: "merged".to_string(),
+            vertices,
+            indices,
+        }
+    }
+
+    /// Winding of the front faces. See [`detect_front_face`].
+    pub fn detect_front_face(&self) -> Option<FrontFace> {
+        detect_front_face(
+            &self.indices,
+            |i| self.vertices[i as usize].position.into(),
+            |i| self.vertices[i as usize].normal.into(),
+        )
+    }
+
+    /// Reverses order of vertices in every triangle
+    pub fn flip_winding(&mut self) {
+        flip_winding(&mut self.indices);
+    }
+}
+
+/// Winding of the front faces of `indices` triangles. Geometric normals of
+/// the triangles are compared with their vertex normals and the majority wins.
+/// Returns `None` if normals are missing or there is no majority.
+pub fn detect_front_face(
+    indices: &[u32],
+    position: impl Fn(u32) -> Vector3<f32>,
+    normal: impl Fn(u32) -> Vector3<f32>,
+) -> Option<FrontFace> {
+    let score: i64 = indices
+        .chunks_exact(3)
+        .map(|t| {
+            let face_normal =
+                (position(t[1]) - position(t[0])).cross(position(t[2]) - position(t[0]));
+            let vertex_normal = normal(t[0]) + normal(t[1]) + normal(t[2]);
+            let d = face_normal.dot(vertex_normal);
+            if 0.0 < d {
+                1
+            } else if d < 0.0 {
+                -1
+            } else {
+                0
+            }
+        })
+        .sum();
+    match score.cmp(&0) {
+        std::cmp::Ordering::Greater => Some(FrontFace::Ccw),
+        std::cmp::Ordering::Less => Some(FrontFace::Cw),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Reverses order of vertices in every triangle of `indices`
+pub fn flip_winding(indices: &mut [u32]) {
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+}
+
+fn normalize_or_zero(v: Vector3<f32>) -> Vector3<f32> {
+    if v.magnitude2() == 0.0 {
+        v
+    } else {
+        v.normalize()
+    }
+}
+
 impl GpuResource for Mesh {
     type ResourceType = GpuMesh;
 
     fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        self.build_with_usage(renderer, BufferUsages::empty())
+    }
+}
+
+impl Mesh {
+    /// Same as `build`, but adds `extra_usages` to the vertex and index
+    /// buffers, e.g. `STORAGE` for meshes updated by compute shaders or
+    /// `COPY_SRC` for readback. Updates through [`MeshHandle`] keep them.
+    pub fn build_with_usage(&self, renderer: &Renderer, extra_usages: BufferUsages) -> GpuMesh {
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.validate() {
+            panic!("Invalid mesh {}: {}", self.name, e);
+        }
+
         let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
             label: Some("vertex_buffer"),
             contents: bytemuck::cast_slice(&self.vertices),
-            usage: BufferUsages::VERTEX,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST | extra_usages,
         });
 
         let index_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
             label: Some("index_buffer"),
             contents: bytemuck::cast_slice(&self.indices),
-            usage: BufferUsages::INDEX,
+            usage: BufferUsages::INDEX | BufferUsages::COPY_DST | extra_usages,
         });
 
-        Self::ResourceType {
+        GpuMesh {
             vertex_buffer,
             index_buffer: Some(index_buffer),
             num_elements: self.indices.len() as u32,
@@ -145,55 +349,614 @@ impl GpuResource for Mesh {
     }
 }
 
+/// Writes `data` into the buffer, reallocating it with a bigger
+/// size and the same usages if `data` does not fit.
+/// Size of the `data` must be a multiple of [`COPY_BUFFER_ALIGNMENT`].
+fn write_or_grow_buffer(renderer: &Renderer, buffer: &mut Buffer, label: &str, data: &[u8]) {
+    assert!(
+        (data.len() as u64).is_multiple_of(COPY_BUFFER_ALIGNMENT),
+        "{label} data size {} is not a multiple of COPY_BUFFER_ALIGNMENT ({COPY_BUFFER_ALIGNMENT})",
+        data.len()
+    );
+    if buffer.size() < data.len() as u64 {
+        let size = (buffer.size() * 2).max(data.len() as u64);
+        *buffer = renderer.device().create_buffer(&BufferDescriptor {
+            label: Some(label),
+            usage: buffer.usage(),
+            size,
+            mapped_at_creation: false,
+        });
+    }
+    renderer.queue().write_buffer(buffer, 0, data);
+}
+
+/// Handle to the [`GpuMesh`] in the storage which allows
+/// to update mesh geometry without rebuilding it.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshHandle {
+    pub mesh_id: MeshId,
+}
+
+impl MeshHandle {
+    /// Writes new vertices into the mesh vertex buffer. Buffer grows if
+    /// vertices do not fit. For non indexed meshes also updates number of
+    /// elements to draw. Size of the vertices in bytes must be a multiple
+    /// of [`COPY_BUFFER_ALIGNMENT`].
+    pub fn update_vertices<V: bytemuck::Pod>(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        vertices: &[V],
+    ) {
+        let mesh = storage.get_mesh_mut(self.mesh_id);
+        write_or_grow_buffer(
+            renderer,
+            &mut mesh.vertex_buffer,
+            "vertex_buffer",
+            bytemuck::cast_slice(vertices),
+        );
+        if mesh.index_buffer.is_none() {
+            mesh.num_elements = vertices.len() as u32;
+        }
+    }
+
+    /// Writes new indices into the mesh index buffer and updates number of
+    /// elements to draw. Buffer grows if indices do not fit.
+    pub fn update_indices(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        indices: &[u32],
+    ) {
+        let mesh = storage.get_mesh_mut(self.mesh_id);
+        let data = bytemuck::cast_slice(indices);
+        match &mut mesh.index_buffer {
+            Some(index_buffer) => {
+                write_or_grow_buffer(renderer, index_buffer, "index_buffer", data)
+            }
+            None => {
+                // Keeps extra usages the mesh was built with
+                let usage =
+                    (mesh.vertex_buffer.usage() - BufferUsages::VERTEX) | BufferUsages::INDEX;
+                mesh.index_buffer =
+                    Some(renderer.device().create_buffer_init(&BufferInitDescriptor {
+                        label: Some("index_buffer"),
+                        contents: data,
+                        usage,
+                    }))
+            }
+        }
+        mesh.num_elements = indices.len() as u32;
+    }
+}
+
+impl ResourceHandle for MeshHandle {
+    type OriginalResource<'a> = Mesh;
+    type ResourceType = GpuMesh;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            mesh_id: storage.insert_mesh(resource),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_mesh(self.mesh_id, resource);
+    }
+}
+
+/// Draw call made for a [`GpuMesh`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MeshDraw {
+    Vertices(Range<u32>),
+    Indices(Range<u32>),
+    Nothing,
+}
+
+impl MeshDraw {
+    /// Slices are in bytes. `index_slice` is only used by indexed meshes,
+    /// `vertex_slice` only by non indexed meshes with known `vertex_stride`.
+    fn new(
+        indexed: bool,
+        num_elements: u32,
+        index_slice: Option<&Range<u64>>,
+        vertex_slice: Option<&Range<u64>>,
+        vertex_stride: Option<BufferAddress>,
+    ) -> Self {
+        let count = match (index_slice, vertex_slice, vertex_stride) {
+            (Some(slice), _, _) if indexed => {
+                ((slice.end - slice.start) / std::mem::size_of::<u32>() as u64) as u32
+            }
+            (_, Some(slice), Some(stride)) if !indexed && stride != 0 => {
+                ((slice.end - slice.start) / stride) as u32
+            }
+            _ => num_elements,
+        };
+        match (count, indexed) {
+            (0, _) => Self::Nothing,
+            (_, true) => Self::Indices(0..count),
+            (_, false) => Self::Vertices(0..count),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MeshRenderCommand {
-    pub pipeline_id: ResourceId,
-    pub mesh_id: ResourceId,
+    pub pipeline_id: PipelineId,
+    pub mesh_id: MeshId,
     pub index_slice: Option<Range<u64>>,
     pub vertex_slice: Option<Range<u64>>,
     pub scissor_rect: Option<[u32; 4]>,
-    pub bind_groups: ConstVec<MAX_BIND_GROUPS, ResourceId>,
+    /// Viewport as `[x, y, width, height, min_depth, max_depth]`
+    pub viewport: Option<[f32; 6]>,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupMeta>,
 }
 
 impl RenderCommand for MeshRenderCommand {
     fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
-        render_pass.set_pipeline(storage.get_pipeline(self.pipeline_id));
-        for (i, bg) in self.bind_groups.iter().enumerate() {
-            render_pass.set_bind_group(i as u32, storage.get_bind_group(*bg), &[]);
+        let mut state = PassState::default();
+        self.execute_with_state(render_pass, storage, &mut state);
+        storage.record_stats(state.stats());
+    }
+}
+
+impl MeshRenderCommand {
+    /// Executes the command without setting the pipeline, bind groups
+    /// and mesh buffers already set by the previous commands.
+    /// Stats are added to the `state`.
+    pub fn execute_with_state<'a>(
+        &self,
+        render_pass: &mut RenderPass<'a>,
+        storage: &'a CurrentFrameStorage,
+        state: &mut PassState,
+    ) {
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        pipeline.assert_bind_group_count(self.bind_groups.len());
+        let mut stats = RenderStats::default();
+        // Bind groups stay valid only up to the first group with a different
+        // layout. Layouts derived from the shader can not be compared.
+        let invalidate_from = match state.pipeline_id().map(|id| storage.get_gpu_pipeline(id)) {
+            Some(previous)
+                if previous.bind_group_layouts.is_some()
+                    && pipeline.bind_group_layouts.is_some() =>
+            {
+                pipeline
+                    .layout_mismatch(previous)
+                    .unwrap_or(MAX_BIND_GROUPS)
+            }
+            _ => 0,
+        };
+        if state.set_pipeline(self.pipeline_id, invalidate_from) {
+            render_pass.set_pipeline(&pipeline.pipeline);
+            stats.pipeline_switches += 1;
+        }
+        for bg in self.bind_groups.iter() {
+            if state.set_bind_group(*bg) {
+                render_pass.set_bind_group(
+                    bg.index,
+                    storage.get_bind_group(bg.bind_group_id),
+                    bg.dynamic_offsets(),
+                );
+                stats.bind_group_switches += 1;
+            }
         }
 
-        if let Some(scissor_rect) = self.scissor_rect {
-            render_pass.set_scissor_rect(
-                scissor_rect[0],
-                scissor_rect[1],
-                scissor_rect[2],
-                scissor_rect[3],
-            )
+        if let Some([x, y, width, height]) = state.set_scissor_rect(self.scissor_rect) {
+            render_pass.set_scissor_rect(x, y, width, height);
+        }
+        if let Some([x, y, width, height, min_depth, max_depth]) = state.set_viewport(self.viewport)
+        {
+            render_pass.set_viewport(x, y, width, height, min_depth, max_depth);
         }
 
         let mesh = storage.get_mesh(self.mesh_id);
+        let draw = MeshDraw::new(
+            mesh.index_buffer.is_some(),
+            mesh.num_elements,
+            self.index_slice.as_ref(),
+            self.vertex_slice.as_ref(),
+            pipeline.vertex_stride,
+        );
+        if draw == MeshDraw::Nothing {
+            state.record_stats(stats);
+            return;
+        }
 
-        if let Some(vertex_slice) = &self.vertex_slice {
-            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(vertex_slice.clone()));
-        } else {
-            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        if state.set_vertex_buffer(self.mesh_id, self.vertex_slice.as_ref()) {
+            if let Some(vertex_slice) = &self.vertex_slice {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(vertex_slice.clone()));
+            } else {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            }
         }
 
+        match (draw, &mesh.index_buffer) {
+            (MeshDraw::Indices(indices), Some(index_buffer)) => {
+                if state.set_index_buffer(self.mesh_id, self.index_slice.as_ref()) {
+                    let slice = match &self.index_slice {
+                        Some(index_slice) => index_buffer.slice(index_slice.clone()),
+                        None => index_buffer.slice(..),
+                    };
+                    render_pass.set_index_buffer(slice, IndexFormat::Uint32);
+                }
+                stats.record_draw(pipeline.topology, indices.len() as u32);
+                render_pass.draw_indexed(indices, 0, 0..1);
+            }
+            (MeshDraw::Vertices(vertices), _) => {
+                stats.record_draw(pipeline.topology, vertices.len() as u32);
+                render_pass.draw(vertices, 0..1);
+            }
+            _ => {}
+        }
+        state.record_stats(stats);
+    }
+}
+
+/// Draws a mesh with arguments read from the indirect buffer.
+/// Indexed meshes expect `DrawIndexedIndirectArgs` and non indexed
+/// meshes expect `DrawIndirectArgs` at the start of the buffer.
+#[derive(Debug, Clone)]
+pub struct IndirectMeshCommand {
+    pub pipeline_id: PipelineId,
+    pub mesh_id: MeshId,
+    pub indirect_buffer_id: BufferId,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupMeta>,
+}
+
+impl RenderCommand for IndirectMeshCommand {
+    fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        pipeline.assert_bind_group_count(self.bind_groups.len());
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for bg in self.bind_groups.iter() {
+            render_pass.set_bind_group(
+                bg.index,
+                storage.get_bind_group(bg.bind_group_id),
+                bg.dynamic_offsets(),
+            );
+        }
+
+        // Number of vertices is on the GPU, so only the draw call is known
+        storage.record_stats(RenderStats {
+            draw_calls: 1,
+            bind_group_switches: self.bind_groups.len(),
+            pipeline_switches: 1,
+            ..Default::default()
+        });
+
+        let mesh = storage.get_mesh(self.mesh_id);
+        let indirect_buffer = storage.get_buffer(self.indirect_buffer_id);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
         if let Some(index_buffer) = &mesh.index_buffer {
-            if let Some(index_slice) = &self.index_slice {
-                render_pass
-                    .set_index_buffer(index_buffer.slice(index_slice.clone()), IndexFormat::Uint32);
-                // slice is in bytes so we divide by size of u32 to get
-                // number of actual indices
-                let s = (index_slice.end - index_slice.start) as u32;
-                let s = s / std::mem::size_of::<u32>() as u32;
-                render_pass.draw_indexed(0..s, 0, 0..1);
-            } else {
+            render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+            render_pass.draw_indexed_indirect(indirect_buffer, 0);
+        } else {
+            render_pass.draw_indirect(indirect_buffer, 0);
+        }
+    }
+}
+
+/// Draws `instance_count` instances of the mesh. Per instance data is read
+/// from the `instance_buffer_id` vertex buffer bound at slot 1
+/// (e.g. [`crate::instance::Instances`]).
+#[derive(Debug, Clone)]
+pub struct InstancedMeshCommand {
+    pub pipeline_id: PipelineId,
+    pub mesh_id: MeshId,
+    pub instance_buffer_id: BufferId,
+    pub instance_count: u32,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupMeta>,
+}
+
+impl RenderCommand for InstancedMeshCommand {
+    fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        pipeline.assert_bind_group_count(self.bind_groups.len());
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for bg in self.bind_groups.iter() {
+            render_pass.set_bind_group(
+                bg.index,
+                storage.get_bind_group(bg.bind_group_id),
+                bg.dynamic_offsets(),
+            );
+        }
+
+        let mesh = storage.get_mesh(self.mesh_id);
+        let draw = MeshDraw::new(
+            mesh.index_buffer.is_some(),
+            mesh.num_elements,
+            None,
+            None,
+            None,
+        );
+        if draw == MeshDraw::Nothing || self.instance_count == 0 {
+            return;
+        }
+
+        let mut stats = RenderStats {
+            bind_group_switches: self.bind_groups.len(),
+            pipeline_switches: 1,
+            ..Default::default()
+        };
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, storage.get_buffer(self.instance_buffer_id).slice(..));
+        match (draw, &mesh.index_buffer) {
+            (MeshDraw::Indices(indices), Some(index_buffer)) => {
                 render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
-                render_pass.draw_indexed(0..mesh.num_elements, 0, 0..1);
+                stats.record_instanced_draw(
+                    pipeline.topology,
+                    indices.len() as u32,
+                    self.instance_count,
+                );
+                render_pass.draw_indexed(indices, 0, 0..self.instance_count);
             }
-        } else {
-            render_pass.draw(0..mesh.num_elements, 0..1);
+            (MeshDraw::Vertices(vertices), _) => {
+                stats.record_instanced_draw(
+                    pipeline.topology,
+                    vertices.len() as u32,
+                    self.instance_count,
+                );
+                render_pass.draw(vertices, 0..self.instance_count);
+            }
+            _ => {}
         }
+        storage.record_stats(stats);
+    }
+}
+
+/// Draws `vertex_count` vertices without any vertex or index buffer bound.
+/// Vertex shader has to generate geometry from `@builtin(vertex_index)`.
+#[derive(Debug, Clone)]
+pub struct ProceduralCommand {
+    pub pipeline_id: PipelineId,
+    pub vertex_count: u32,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupMeta>,
+}
+
+impl RenderCommand for ProceduralCommand {
+    fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        pipeline.assert_bind_group_count(self.bind_groups.len());
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for bg in self.bind_groups.iter() {
+            render_pass.set_bind_group(
+                bg.index,
+                storage.get_bind_group(bg.bind_group_id),
+                bg.dynamic_offsets(),
+            );
+        }
+        let mut stats = RenderStats {
+            bind_group_switches: self.bind_groups.len(),
+            pipeline_switches: 1,
+            ..Default::default()
+        };
+        stats.record_draw(pipeline.topology, self.vertex_count);
+        storage.record_stats(stats);
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshVertexSkinned {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub joints: [u16; 4],
+    pub weights: [f32; 4],
+}
+
+impl VertexLayout for MeshVertexSkinned {
+    fn layout<'a>() -> VertexBufferLayout<'a> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &[
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x3,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as BufferAddress,
+                    shader_location: 1,
+                    format: VertexFormat::Float32x2,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as BufferAddress,
+                    shader_location: 2,
+                    format: VertexFormat::Float32x3,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as BufferAddress,
+                    shader_location: 3,
+                    format: VertexFormat::Uint16x4,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 10]>() as BufferAddress,
+                    shader_location: 4,
+                    format: VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Mesh with per vertex joint indices and weights.
+/// Joint indices point into the [`crate::skeleton::Skeleton`] joints.
+#[derive(Debug)]
+pub struct SkinnedMesh {
+    pub name: String,
+    pub vertices: Vec<MeshVertexSkinned>,
+    pub indices: Vec<u32>,
+}
+
+impl SkinnedMesh {
+    /// Winding of the front faces. See [`detect_front_face`].
+    pub fn detect_front_face(&self) -> Option<FrontFace> {
+        detect_front_face(
+            &self.indices,
+            |i| self.vertices[i as usize].position.into(),
+            |i| self.vertices[i as usize].normal.into(),
+        )
+    }
+
+    /// Reverses order of vertices in every triangle
+    pub fn flip_winding(&mut self) {
+        flip_winding(&mut self.indices);
+    }
+}
+
+impl GpuResource for SkinnedMesh {
+    type ResourceType = GpuMesh;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("skinned_vertex_buffer"),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        let index_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("skinned_index_buffer"),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+        });
+
+        Self::ResourceType {
+            vertex_buffer,
+            index_buffer: Some(index_buffer),
+            num_elements: self.indices.len() as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Mesh {
+        Mesh {
+            name: "triangle".to_string(),
+            vertices: vec![
+                ([0.0, 0.0, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]).into(),
+                ([1.0, 0.0, 0.0], [1.0, 0.0], [0.0, 0.0, 1.0]).into(),
+                ([0.0, 1.0, 0.0], [0.0, 1.0], [0.0, 0.0, 1.0]).into(),
+            ],
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn mesh_vertex_tangents() {
+        let mut mesh = triangle();
+        MeshVertex::calc_tangents_and_bitangents(&mut mesh.vertices, &mesh.indices);
+        let expected = MeshVertex::new([1.0, 0.0, 0.0], [1.0, 0.0], [0.0, 0.0, 1.0])
+            .with_tangents([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_eq!(
+            bytemuck::bytes_of(&mesh.vertices[1]),
+            bytemuck::bytes_of(&expected)
+        );
+    }
+
+    #[test]
+    fn mesh_index_out_of_range() {
+        let mut mesh = triangle();
+        mesh.indices.push(3);
+        assert!(matches!(
+            mesh.validate(),
+            Err(MeshError::IndexOutOfRange {
+                index: 3,
+                vertices: 3
+            })
+        ));
+        assert!(Mesh::new("triangle", triangle().vertices, vec![0, 1, 2]).is_ok());
+    }
+
+    #[test]
+    fn mesh_draw_indexed() {
+        assert_eq!(
+            MeshDraw::new(true, 6, None, None, None),
+            MeshDraw::Indices(0..6)
+        );
+        assert_eq!(
+            MeshDraw::new(true, 6, Some(&(12..24)), None, None),
+            MeshDraw::Indices(0..3)
+        );
+        // Vertex slice does not change the number of indices
+        assert_eq!(
+            MeshDraw::new(true, 6, None, Some(&(0..32)), Some(32)),
+            MeshDraw::Indices(0..6)
+        );
+        // Zero length indices draw nothing
+        assert_eq!(MeshDraw::new(true, 0, None, None, None), MeshDraw::Nothing);
+        assert_eq!(
+            MeshDraw::new(true, 6, Some(&(12..12)), None, None),
+            MeshDraw::Nothing
+        );
+    }
+
+    #[test]
+    fn mesh_draw_non_indexed() {
+        assert_eq!(
+            MeshDraw::new(false, 3, None, None, None),
+            MeshDraw::Vertices(0..3)
+        );
+        // Index slice is ignored without index buffer
+        assert_eq!(
+            MeshDraw::new(false, 3, Some(&(0..4)), None, None),
+            MeshDraw::Vertices(0..3)
+        );
+        assert_eq!(MeshDraw::new(false, 0, None, None, None), MeshDraw::Nothing);
+    }
+
+    #[test]
+    fn mesh_draw_vertex_slice() {
+        // Vertices of 32 bytes, slice covers the second and third vertices
+        assert_eq!(
+            MeshDraw::new(false, 6, None, Some(&(32..96)), Some(32)),
+            MeshDraw::Vertices(0..2)
+        );
+        assert_eq!(
+            MeshDraw::new(false, 6, None, Some(&(32..32)), Some(32)),
+            MeshDraw::Nothing
+        );
+        // Without vertex buffers in the pipeline the slice size is unknown
+        assert_eq!(
+            MeshDraw::new(false, 6, None, Some(&(32..96)), None),
+            MeshDraw::Vertices(0..6)
+        );
+    }
+
+    #[test]
+    fn mesh_winding() {
+        let mut mesh = triangle();
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Ccw));
+        mesh.flip_winding();
+        assert_eq!(mesh.indices, [0, 2, 1]);
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Cw));
+        for v in mesh.vertices.iter_mut() {
+            v.normal = [0.0; 3];
+        }
+        assert_eq!(mesh.detect_front_face(), None);
+    }
+
+    #[test]
+    fn mesh_merge() {
+        let moved = Transform {
+            translation: Vector3::new(0.0, 0.0, 5.0),
+            ..Default::default()
+        };
+        let mirrored = Transform {
+            scale: Vector3::new(-1.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        let merged = Mesh::merge(&[(triangle(), moved), (triangle(), mirrored)]);
+
+        assert_eq!(merged.vertices.len(), 6);
+        assert_eq!(merged.indices, [0, 1, 2, 5, 4, 3]);
+        assert_eq!(merged.vertices[1].position, [1.0, 0.0, 5.0]);
+        assert_eq!(merged.vertices[4].position, [-1.0, 0.0, 0.0]);
+        assert_eq!(merged.vertices[4].normal, [0.0, 0.0, 1.0]);
     }
 }
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use crate::mesh::{Mesh, MeshVertex};
+
+/// Size of the simulated post transform cache used by [`optimize_vertex_cache`]
+pub const VERTEX_CACHE_SIZE: usize = 32;
+
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// Score of the vertex in the Forsyth algorithm.
+/// `cache_position` is `None` for vertices outside of the cache.
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        // Vertices of the last triangle get fixed score, so it is not
+        // favoured too much and the next triangle does not reuse all of them
+        Some(p) if p < 3 => LAST_TRIANGLE_SCORE,
+        Some(p) => {
+            let scale = 1.0 / (VERTEX_CACHE_SIZE - 3) as f32;
+            (1.0 - (p - 3) as f32 * scale)
+                .max(0.0)
+                .powf(CACHE_DECAY_POWER)
+        }
+    };
+    let valence_boost =
+        VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+    cache_score + valence_boost
+}
+
+/// Reorders triangles of `indices` to improve post transform vertex cache
+/// hit rate using Tom Forsyth's linear speed vertex cache optimization.
+/// `indices` must be a triangle list.
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "Number of indices {} is not a multiple of 3",
+        indices.len()
+    );
+    let triangle_count = indices.len() / 3;
+
+    let mut vertex_triangles = vec![Vec::new(); vertex_count];
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        for &v in triangle {
+            vertex_triangles[v as usize].push(t);
+        }
+    }
+
+    let mut vertex_scores: Vec<f32> = vertex_triangles
+        .iter()
+        .map(|t| vertex_score(None, t.len()))
+        .collect();
+    let triangle_score = |t: usize, vertex_scores: &[f32]| -> f32 {
+        indices[t * 3..t * 3 + 3]
+            .iter()
+            .map(|&v| vertex_scores[v as usize])
+            .sum()
+    };
+    let mut triangle_added = vec![false; triangle_count];
+
+    let mut result = Vec::with_capacity(triangle_count * 3);
+    let mut cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+    let mut best_triangle = None;
+    // Vertices of the added triangles. Candidates to continue from
+    // when nothing in the cache has triangles left.
+    let mut dead_end: Vec<u32> = Vec::with_capacity(triangle_count * 3);
+    // Triangles before this one are all added
+    let mut scan_start = 0;
+
+    while result.len() < triangle_count * 3 {
+        let t = match best_triangle {
+            Some(t) => t,
+            None => {
+                // Latest added vertex with triangles left, otherwise the
+                // first remaining triangle. Both keep the search linear.
+                let mut next = None;
+                while let Some(v) = dead_end.pop() {
+                    if let Some(&t) = vertex_triangles[v as usize].first() {
+                        next = Some(t);
+                        break;
+                    }
+                }
+                next.unwrap_or_else(|| {
+                    while triangle_added[scan_start] {
+                        scan_start += 1;
+                    }
+                    scan_start
+                })
+            }
+        };
+
+        triangle_added[t] = true;
+        let triangle = &indices[t * 3..t * 3 + 3];
+        result.extend_from_slice(triangle);
+        dead_end.extend_from_slice(triangle);
+
+        for &v in triangle {
+            vertex_triangles[v as usize].retain(|other| *other != t);
+        }
+
+        // Vertices of the added triangle go to the front of the cache
+        let old_cache = std::mem::take(&mut cache);
+        cache.extend_from_slice(triangle);
+        cache.extend(old_cache.iter().filter(|v| !triangle.contains(v)));
+
+        for (position, &v) in cache.iter().enumerate() {
+            let position = (position < VERTEX_CACHE_SIZE).then_some(position);
+            vertex_scores[v as usize] = vertex_score(position, vertex_triangles[v as usize].len());
+        }
+        cache.truncate(VERTEX_CACHE_SIZE);
+
+        best_triangle = None;
+        let mut best_score = f32::MIN;
+        for &v in cache.iter() {
+            for &other in vertex_triangles[v as usize].iter() {
+                let score = triangle_score(other, &vertex_scores);
+                if best_score < score {
+                    best_score = score;
+                    best_triangle = Some(other);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Average number of vertex cache misses per triangle for
+/// a FIFO cache of `cache_size` entries. Lower is better, regular grids
+/// can approach 0.5.
+pub fn average_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+    let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+    let mut misses = 0;
+    for &i in indices {
+        if !cache.contains(&i) {
+            misses += 1;
+            if cache.len() == cache_size {
+                cache.pop_front();
+            }
+            cache.push_back(i);
+        }
+    }
+    misses as f32 / triangle_count as f32
+}
+
+impl Mesh {
+    /// Merges identical vertices and points indices to the shared ones.
+    /// Meshes without indices get one index per original vertex.
+    pub fn deduplicate_vertices(&mut self) {
+        let original_indices = if self.indices.is_empty() {
+            (0..self.vertices.len() as u32).collect()
+        } else {
+            std::mem::take(&mut self.indices)
+        };
+
+        let mut unique: HashMap<[u32; 14], u32> = HashMap::new();
+        let mut vertices = Vec::new();
+        let remap: Vec<u32> = self
+            .vertices
+            .iter()
+            .map(|v| {
+                *unique
+                    .entry(bytemuck::cast::<MeshVertex, [u32; 14]>(*v))
+                    .or_insert_with(|| {
+                        vertices.push(*v);
+                        vertices.len() as u32 - 1
+                    })
+            })
+            .collect();
+
+        self.vertices = vertices;
+        self.indices = original_indices
+            .iter()
+            .map(|i| remap[*i as usize])
+            .collect();
+    }
+
+    /// Reorders triangles for the vertex cache with [`optimize_vertex_cache`]
+    /// and then vertices in the order of the first use, so vertex fetches
+    /// are mostly sequential. Meshes without indices are left as is.
+    pub fn optimize_vertex_order(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+        self.indices = optimize_vertex_cache(&self.indices, self.vertices.len());
+
+        let mut remap = vec![u32::MAX; self.vertices.len()];
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        for i in self.indices.iter_mut() {
+            let new = &mut remap[*i as usize];
+            if *new == u32::MAX {
+                *new = vertices.len() as u32;
+                vertices.push(self.vertices[*i as usize]);
+            }
+            *i = *new;
+        }
+        self.vertices = vertices;
+    }
+
+    /// Deduplicates vertices and optimizes their order for the GPU.
+    /// Vertices not referenced by any triangle are removed.
+    pub fn optimize(&mut self) {
+        self.deduplicate_vertices();
+        self.optimize_vertex_order();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of `n` x `n` quads where every triangle has its own vertices
+    fn triangle_soup(n: u32) -> Mesh {
+        let vertex = |x: u32, y: u32| -> MeshVertex {
+            ([x as f32, y as f32, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]).into()
+        };
+        let mut vertices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                vertices.extend([
+                    vertex(x, y),
+                    vertex(x + 1, y),
+                    vertex(x + 1, y + 1),
+                    vertex(x, y),
+                    vertex(x + 1, y + 1),
+                    vertex(x, y + 1),
+                ]);
+            }
+        }
+        Mesh {
+            name: "soup".to_string(),
+            vertices,
+            indices: vec![],
+        }
+    }
+
+    #[test]
+    fn mesh_deduplicate_vertices() {
+        let mut mesh = triangle_soup(4);
+        mesh.deduplicate_vertices();
+        assert_eq!(mesh.vertices.len(), 25);
+        assert_eq!(mesh.indices.len(), 4 * 4 * 6);
+        assert!(mesh.validate().is_ok());
+    }
+
+    #[test]
+    fn mesh_optimize_improves_cache() {
+        let mut mesh = triangle_soup(32);
+        mesh.deduplicate_vertices();
+        // Column major order of the triangles trashes the cache
+        let triangles: Vec<&[u32]> = mesh.indices.chunks(3).collect();
+        let mut shuffled = Vec::new();
+        for x in 0..64 {
+            for y in 0..32 {
+                shuffled.extend_from_slice(triangles[y * 64 + x]);
+            }
+        }
+        mesh.indices = shuffled;
+        let before = average_cache_miss_ratio(&mesh.indices, 16);
+
+        mesh.optimize();
+        let after = average_cache_miss_ratio(&mesh.indices, 16);
+        assert!(after < before, "{after} >= {before}");
+        assert!(after < 0.8, "{after}");
+        assert_eq!(mesh.vertices.len(), 33 * 33);
+        assert_eq!(mesh.indices.len(), 32 * 32 * 6);
+        assert!(mesh.validate().is_ok());
+    }
+    #[test]
+    fn mesh_optimize_non_indexed() {
+        let mut mesh = triangle_soup(2);
+        mesh.optimize_vertex_order();
+        assert_eq!(mesh.vertices.len(), 2 * 2 * 6);
+        assert!(mesh.indices.is_empty());
+    }
+
+    #[test]
+    fn optimize_vertex_cache_disconnected() {
+        // Every triangle has its own vertices, so the cache never has candidates
+        let indices: Vec<u32> = (0..3 * 1000).collect();
+        let mut optimized = optimize_vertex_cache(&indices, indices.len());
+        optimized.sort();
+        assert_eq!(optimized, indices);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a multiple of 3")]
+    fn optimize_vertex_cache_partial_triangle() {
+        optimize_vertex_cache(&[0, 1, 2, 0], 3);
+    }
+}
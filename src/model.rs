@@ -1,23 +1,87 @@
-use crate::material::Material;
-use crate::mesh::{Mesh, MeshVertex};
+use crate::camera::CameraBindGroup;
+use crate::material::{Material, ParallaxMode};
+use crate::mesh::{Mesh, MeshRenderCommand, MeshVertex};
 use crate::prelude::{MaterialBindGroup, MaterialHandle};
 use crate::render::prelude::*;
+use crate::shadow_map::ShadowMapDLightBindGroup;
 use crate::texture::{ImageTexture, TextureType};
+use crate::transform::TransformBindGroup;
+use crate::utils::Task;
 use image::ImageError;
-use log::info;
+use log::{info, warn};
+use std::path::Path;
 use tobj::{load_obj, LoadError, LoadOptions};
 
+#[cfg(feature = "gltf")]
+use crate::{
+    cgmath_imports::*,
+    mesh::{MeshVertexSkinned, SkinnedMesh},
+    skeleton::{Joint, Skeleton, MAX_JOINTS},
+    transform::Transform,
+};
+#[cfg(feature = "gltf")]
+use cgmath::SquareMatrix;
+#[cfg(feature = "gltf")]
+use std::collections::HashMap;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Error loading model: {0}")]
     ModelLoad(#[from] LoadError),
     #[error("Error loading image: {0}")]
     ImageLoad(#[from] ImageError),
+    #[cfg(feature = "gltf")]
+    #[error("Error loading gltf: {0}")]
+    GltfLoad(#[from] gltf::Error),
+}
+
+/// Winding used by most of the meshes. Defaults to `FrontFace::Ccw`.
+fn majority_front_face(front_faces: impl Iterator<Item = Option<FrontFace>>) -> FrontFace {
+    let cw: i32 = front_faces
+        .map(|f| match f {
+            Some(FrontFace::Cw) => 1,
+            Some(FrontFace::Ccw) => -1,
+            None => 0,
+        })
+        .sum();
+    if 0 < cw {
+        FrontFace::Cw
+    } else {
+        FrontFace::Ccw
+    }
+}
+
+fn flip_front_face(front_face: FrontFace) -> FrontFace {
+    match front_face {
+        FrontFace::Ccw => FrontFace::Cw,
+        FrontFace::Cw => FrontFace::Ccw,
+    }
+}
+
+/// Loads material texture. Missing or broken textures are
+/// replaced with placeholders.
+fn load_material_texture(
+    texture_base_dir: &Path,
+    material: &str,
+    texture: Option<&str>,
+    texture_type: TextureType,
+) -> ImageTexture {
+    let Some(texture) = texture else {
+        warn!("Material {material} has no {texture_type:?} texture, using placeholder");
+        return ImageTexture::placeholder(texture_type);
+    };
+    let path = texture_base_dir.join(texture);
+    ImageTexture::load(&path, texture_type).unwrap_or_else(|e| {
+        warn!(
+            "Could not load {texture_type:?} texture of material {material} from {path:?}: {e}, using placeholder"
+        );
+        ImageTexture::placeholder(texture_type)
+    })
 }
 
 #[derive(Debug)]
 pub struct ModelHadle {
-    pub mesh_id: ResourceId,
+    pub mesh_id: MeshId,
     pub material_bind_group: MaterialBindGroup,
 }
 
@@ -31,6 +95,9 @@ pub struct ModelMesh {
 pub struct Model {
     pub meshes: Vec<ModelMesh>,
     pub materials: Vec<Material>,
+    /// Winding of the front faces detected on load. Should be used as
+    /// `front_face` of the pipeline, or changed with [`Model::flip_winding`].
+    pub front_face: FrontFace,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,7 +107,17 @@ pub struct ModelMaterialHandle {
 }
 
 impl Model {
-    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+    /// Loads OBJ model. Textures are loaded relative to the model directory.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let texture_base_dir = path.as_ref().parent().expect("Directory has no parent");
+        Self::load_with_base(path.as_ref(), texture_base_dir)
+    }
+
+    /// Loads OBJ model. Textures are loaded relative to the `texture_base_dir`.
+    pub fn load_with_base<P: AsRef<Path>, B: AsRef<Path>>(
+        path: P,
+        texture_base_dir: B,
+    ) -> Result<Self, Error> {
         info!("loading model from {:#?}", path.as_ref());
         let (obj_models, obj_materials) = load_obj(
             path.as_ref(),
@@ -53,24 +130,45 @@ impl Model {
 
         let obj_materials = obj_materials?;
 
-        let containing_folder = path.as_ref().parent().expect("Directory has no parent");
-
         let mut materials = Vec::new();
         for mat in obj_materials {
-            let diffuse_path = containing_folder.join(mat.diffuse_texture.unwrap());
-            let diffuse_texture = ImageTexture::load(diffuse_path, TextureType::Diffuse)?;
+            let diffuse_texture = load_material_texture(
+                texture_base_dir.as_ref(),
+                &mat.name,
+                mat.diffuse_texture.as_deref(),
+                TextureType::Diffuse,
+            );
+            let normal_texture = load_material_texture(
+                texture_base_dir.as_ref(),
+                &mat.name,
+                mat.normal_texture.as_deref(),
+                TextureType::Normal,
+            );
 
-            let normal_path = containing_folder.join(mat.normal_texture.unwrap());
-            let normal_texture = ImageTexture::load(normal_path, TextureType::Normal)?;
+            // `disp` is not parsed by tobj. Height texture is optional,
+            // so there is no warning if it is missing.
+            let height_texture = match mat.unknown_param.get("disp") {
+                Some(texture) => load_material_texture(
+                    texture_base_dir.as_ref(),
+                    &mat.name,
+                    Some(texture),
+                    TextureType::Height,
+                ),
+                None => ImageTexture::placeholder_height(),
+            };
 
             materials.push(Material {
                 name: mat.name,
                 diffuse_texture,
                 normal_texture,
+                height_texture,
                 ambient: mat.ambient.unwrap(),
                 diffuse: mat.diffuse.unwrap(),
                 specular: mat.specular.unwrap(),
                 shininess: mat.shininess.unwrap(),
+                flip_normal_y: false,
+                parallax_mode: ParallaxMode::Off,
+                parallax_scale: 0.05,
             });
         }
 
@@ -107,9 +205,33 @@ impl Model {
             });
         }
 
-        Ok(Self { meshes, materials })
+        let front_face = majority_front_face(meshes.iter().map(|m| m.mesh.detect_front_face()));
+        Ok(Self {
+            meshes,
+            materials,
+            front_face,
+        })
     }
 
+    /// Reverses winding of all meshes, so models with clockwise
+    /// front faces can be drawn with `FrontFace::Ccw` pipelines
+    pub fn flip_winding(&mut self) {
+        for mesh in self.meshes.iter_mut() {
+            mesh.mesh.flip_winding();
+        }
+        self.front_face = flip_front_face(self.front_face);
+    }
+
+    /// Loads the model with all its textures on a separate thread.
+    /// Resulting model still needs to be built on the main thread.
+    pub fn load_async<P: Into<std::path::PathBuf>>(path: P) -> Task<Result<Self, Error>> {
+        let path = path.into();
+        Task::spawn(move || Self::load(path))
+    }
+
+    /// Inserts every submesh as a separate mesh, so there is one handle per
+    /// submesh in the order of `meshes`. Materials are inserted once and
+    /// shared by the handles of the submeshes using them.
     pub fn build(
         &self,
         renderer: &Renderer,
@@ -138,4 +260,269 @@ impl Model {
             .collect();
         (mmm, materials)
     }
+
+    /// Creates commands drawing every submesh of the model with its own material.
+    /// Material bind group is always bound to the group 0, followed by `bind_groups`
+    /// (e.g. transform and camera bind groups).
+    pub fn render_commands(
+        handles: &[ModelHadle],
+        pipeline_id: PipelineId,
+        bind_groups: &[BindGroupId],
+    ) -> Vec<MeshRenderCommand> {
+        Self::commands(handles, pipeline_id, |handle| {
+            std::iter::once(handle.material_bind_group.0)
+                .chain(bind_groups.iter().copied())
+                .collect()
+        })
+    }
+
+    /// Commands for the geometry pass with bind groups
+    /// - group 0: material
+    /// - group 1: transform
+    /// - group 2: camera
+    pub fn geometry_commands(
+        handles: &[ModelHadle],
+        pipeline_id: PipelineId,
+        transform: TransformBindGroup,
+        camera: CameraBindGroup,
+    ) -> Vec<MeshRenderCommand> {
+        Self::render_commands(handles, pipeline_id, &[transform.0, camera.0])
+    }
+
+    /// Commands for the shadow map pass. Materials are not used.
+    /// - group 0: transform
+    /// - group 1: shadow light
+    pub fn shadow_commands(
+        handles: &[ModelHadle],
+        pipeline_id: PipelineId,
+        transform: TransformBindGroup,
+        light: ShadowMapDLightBindGroup,
+    ) -> Vec<MeshRenderCommand> {
+        Self::commands(handles, pipeline_id, |_| vec![transform.0, light.0])
+    }
+
+    /// One command per handle with `bind_groups` bound in order starting from 0
+    fn commands(
+        handles: &[ModelHadle],
+        pipeline_id: PipelineId,
+        bind_groups: impl Fn(&ModelHadle) -> Vec<BindGroupId>,
+    ) -> Vec<MeshRenderCommand> {
+        handles
+            .iter()
+            .map(|handle| MeshRenderCommand {
+                pipeline_id,
+                mesh_id: handle.mesh_id,
+                index_slice: None,
+                vertex_slice: None,
+                scissor_rect: None,
+                viewport: None,
+                bind_groups: bind_groups(handle)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, id)| BindGroupMeta::new(i as u32, id))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Model with skinned meshes sharing one [`Skeleton`]
+#[cfg(feature = "gltf")]
+#[derive(Debug)]
+pub struct SkinnedModel {
+    pub meshes: Vec<SkinnedMesh>,
+    pub skeleton: Skeleton,
+    /// Winding of the front faces detected on load. Should be used as
+    /// `front_face` of the pipeline, or changed with [`SkinnedModel::flip_winding`].
+    pub front_face: FrontFace,
+}
+
+#[cfg(feature = "gltf")]
+impl SkinnedModel {
+    /// Loads all mesh primitives and the first skin of the glTF file.
+    /// Non joint nodes between skeleton joints are ignored.
+    pub fn load_gltf<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        info!("loading gltf model from {:#?}", path.as_ref());
+        let (document, buffers, _images) = gltf::import(path.as_ref())?;
+
+        let skins = document.skins().len();
+        if 1 < skins {
+            warn!("Model has {skins} skins, only the first one is used");
+        }
+
+        let mut skeleton = Skeleton::default();
+        if let Some(skin) = document.skins().next() {
+            let joint_nodes: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+            if MAX_JOINTS < joint_nodes.len() {
+                warn!(
+                    "Skin has {} joints, only the first {MAX_JOINTS} are used for skinning",
+                    joint_nodes.len()
+                );
+            }
+
+            let mut parents = HashMap::new();
+            for node in document.nodes() {
+                for child in node.children() {
+                    parents.insert(child.index(), node.index());
+                }
+            }
+
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            let inverse_bind_matrices: Vec<Matrix4<f32>> = reader
+                .read_inverse_bind_matrices()
+                .map(|matrices| matrices.map(Matrix4::from).collect())
+                .unwrap_or_else(|| vec![Matrix4::identity(); joint_nodes.len()]);
+
+            for (node, inverse_bind_matrix) in skin.joints().zip(inverse_bind_matrices) {
+                let parent = parents
+                    .get(&node.index())
+                    .and_then(|parent| joint_nodes.iter().position(|j| j == parent));
+                let (translation, rotation, scale) = node.transform().decomposed();
+                skeleton.joints.push(Joint {
+                    parent,
+                    transform: Transform {
+                        translation: translation.into(),
+                        rotation: Quaternion::new(
+                            rotation[3],
+                            rotation[0],
+                            rotation[1],
+                            rotation[2],
+                        ),
+                        scale: scale.into(),
+                    },
+                    inverse_bind_matrix,
+                });
+            }
+        }
+
+        let mut meshes = Vec::new();
+        for mesh in document.meshes() {
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                let positions: Vec<[f32; 3]> = reader
+                    .read_positions()
+                    .map(|positions| positions.collect())
+                    .unwrap_or_default();
+                let vertex_count = positions.len();
+                let normals: Vec<[f32; 3]> = reader
+                    .read_normals()
+                    .map(|normals| normals.collect())
+                    .unwrap_or_else(|| vec![[0.0; 3]; vertex_count]);
+                let tex_coords: Vec<[f32; 2]> = reader
+                    .read_tex_coords(0)
+                    .map(|tex_coords| tex_coords.into_f32().collect())
+                    .unwrap_or_else(|| vec![[0.0; 2]; vertex_count]);
+                let joints: Vec<[u16; 4]> = reader
+                    .read_joints(0)
+                    .map(|joints| joints.into_u16().collect())
+                    .unwrap_or_else(|| vec![[0; 4]; vertex_count]);
+                let weights: Vec<[f32; 4]> = reader
+                    .read_weights(0)
+                    .map(|weights| weights.into_f32().collect())
+                    .unwrap_or_else(|| vec![[1.0, 0.0, 0.0, 0.0]; vertex_count]);
+                let indices: Vec<u32> = reader
+                    .read_indices()
+                    .map(|indices| indices.into_u32().collect())
+                    .unwrap_or_else(|| (0..vertex_count as u32).collect());
+
+                let vertices = (0..vertex_count)
+                    .map(|i| MeshVertexSkinned {
+                        position: positions[i],
+                        tex_coords: tex_coords[i],
+                        normal: normals[i],
+                        joints: joints[i],
+                        weights: weights[i],
+                    })
+                    .collect();
+
+                meshes.push(SkinnedMesh {
+                    name: mesh.name().unwrap_or_default().to_string(),
+                    vertices,
+                    indices,
+                });
+            }
+        }
+
+        let front_face = majority_front_face(meshes.iter().map(|m| m.detect_front_face()));
+        Ok(Self {
+            meshes,
+            skeleton,
+            front_face,
+        })
+    }
+
+    /// Reverses winding of all meshes, so models with clockwise
+    /// front faces can be drawn with `FrontFace::Ccw` pipelines
+    pub fn flip_winding(&mut self) {
+        for mesh in self.meshes.iter_mut() {
+            mesh.flip_winding();
+        }
+        self.front_face = flip_front_face(self.front_face);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_load_with_base() {
+        let model = Model::load_with_base("./res/cube/cube.obj", "./res/cube").unwrap();
+        assert_eq!(model.materials.len(), 1);
+    }
+
+    #[test]
+    fn model_flip_winding() {
+        let mut model = Model::load("./res/cube/cube.obj").unwrap();
+        assert_eq!(model.front_face, FrontFace::Ccw);
+        model.flip_winding();
+        assert_eq!(model.front_face, FrontFace::Cw);
+        assert_eq!(
+            model.meshes[0].mesh.detect_front_face(),
+            Some(FrontFace::Cw)
+        );
+    }
+
+    #[test]
+    fn model_load_with_wrong_base() {
+        let model = Model::load_with_base("./res/cube/cube.obj", "./res/skybox").unwrap();
+        assert_eq!(
+            model.materials[0].diffuse_texture.dimensions(),
+            Some((1, 1))
+        );
+        assert_eq!(model.materials[0].normal_texture.dimensions(), Some((1, 1)));
+    }
+
+    #[test]
+    fn model_commands_bind_group_order() {
+        let handles = [ModelHadle {
+            mesh_id: ResourceId::from_index(0),
+            material_bind_group: MaterialBindGroup(ResourceId::from_index(1)),
+        }];
+        let pipeline_id = ResourceId::from_index(0);
+        let transform = TransformBindGroup(ResourceId::from_index(2));
+        let ids = |commands: Vec<MeshRenderCommand>| -> Vec<_> {
+            commands[0]
+                .bind_groups
+                .iter()
+                .map(|bg| (bg.index, bg.bind_group_id))
+                .collect()
+        };
+
+        let camera = CameraBindGroup(ResourceId::from_index(3));
+        let geometry = Model::geometry_commands(&handles, pipeline_id, transform, camera);
+        assert_eq!(
+            ids(geometry),
+            [
+                (0, handles[0].material_bind_group.0),
+                (1, transform.0),
+                (2, camera.0)
+            ]
+        );
+
+        let light = ShadowMapDLightBindGroup(ResourceId::from_index(4));
+        let shadow = Model::shadow_commands(&handles, pipeline_id, transform, light);
+        assert_eq!(ids(shadow), [(0, transform.0), (1, light.0)]);
+    }
 }
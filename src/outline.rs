@@ -0,0 +1,96 @@
+use crate::{impl_simple_buffer, render::prelude::*};
+
+/// Depth stencil format required by the outline passes
+pub const OUTLINE_DEPTH_STENCIL_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OutlineUniform {
+    color: [f32; 4],
+    width: f32,
+    _pad1: f32,
+    _pad2: f32,
+    _pad3: f32,
+}
+
+impl From<&Outline> for OutlineUniform {
+    fn from(value: &Outline) -> Self {
+        Self {
+            color: value.color,
+            width: value.width,
+            ..Default::default()
+        }
+    }
+}
+
+/// Outline drawn around selected meshes using stencil buffer.
+/// Selected meshes are first drawn with [`Outline::mask_depth_stencil`]
+/// which marks their pixels in the stencil buffer. Then meshes are drawn again
+/// extruded along normals by `width` with [`Outline::outline_depth_stencil`]
+/// which only passes for not marked pixels leaving only the outline.
+#[derive(Debug)]
+pub struct Outline {
+    pub color: [f32; 4],
+    pub width: f32,
+}
+
+impl Outline {
+    /// Depth stencil state for the pipeline drawing selected meshes.
+    /// Increments stencil value for every drawn pixel.
+    pub fn mask_depth_stencil() -> DepthStencilState {
+        let face = StencilFaceState {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::IncrementClamp,
+        };
+        DepthStencilState {
+            format: OUTLINE_DEPTH_STENCIL_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState {
+                front: face,
+                back: face,
+                read_mask: 0xff,
+                write_mask: 0xff,
+            },
+            bias: DepthBiasState::default(),
+        }
+    }
+
+    /// Depth stencil state for the pipeline drawing outlines.
+    /// Only passes where stencil value is equal to the stencil reference (0 by default),
+    /// so pixels covered by selected meshes are skipped. Ignores depth so outline
+    /// is visible through other objects.
+    pub fn outline_depth_stencil() -> DepthStencilState {
+        let face = StencilFaceState {
+            compare: CompareFunction::Equal,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+        DepthStencilState {
+            format: OUTLINE_DEPTH_STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Always,
+            stencil: StencilState {
+                front: face,
+                back: face,
+                read_mask: 0xff,
+                write_mask: 0x00,
+            },
+            bias: DepthBiasState::default(),
+        }
+    }
+}
+
+impl_simple_buffer!(
+    Outline,
+    OutlineUniform,
+    OutlineResources,
+    OutlineHandle,
+    OutlineBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
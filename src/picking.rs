@@ -0,0 +1,36 @@
+use crate::{impl_simple_buffer, render::prelude::*};
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectIdUniform {
+    id: u32,
+    _pad1: u32,
+    _pad2: u32,
+    _pad3: u32,
+}
+
+impl From<&ObjectId> for ObjectIdUniform {
+    fn from(value: &ObjectId) -> Self {
+        Self {
+            id: value.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Per object id written into the object id texture
+/// (see [`crate::texture::EmptyTexture::new_object_id`]) and read back with
+/// [`Renderer::read_pixel`] for picking. Id 0 is left for the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectId(pub u32);
+
+impl_simple_buffer!(
+    ObjectId,
+    ObjectIdUniform,
+    ObjectIdResources,
+    ObjectIdHandle,
+    ObjectIdBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
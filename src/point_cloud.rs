@@ -0,0 +1,287 @@
+use crate::camera::CameraBindGroup;
+use crate::const_vec;
+use crate::globals::GlobalsBindGroup;
+use crate::impl_simple_buffer;
+use crate::mesh::GpuMesh;
+use crate::prelude::ConstVec;
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+
+const POINT_CLOUD_SHADER: &str = include_str!("shaders/point_cloud.wgsl");
+
+/// Single point of the [`PointCloud`]. Each point is drawn as an instance.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl VertexLayout for PointVertex {
+    fn layout<'a>() -> VertexBufferLayout<'a> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &[
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x3,
+                },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as BufferAddress,
+                    shader_location: 1,
+                    format: VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PointCloud {
+    pub points: Vec<PointVertex>,
+}
+
+impl PointCloud {
+    /// Point cloud from positions with colors
+    pub fn from_points(points: &[([f32; 3], [f32; 4])]) -> Self {
+        Self {
+            points: points
+                .iter()
+                .map(|&(position, color)| PointVertex { position, color })
+                .collect(),
+        }
+    }
+}
+
+impl GpuResource for PointCloud {
+    type ResourceType = GpuMesh;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: Some("point_cloud_buffer"),
+            contents: bytemuck::cast_slice(&self.points),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        Self::ResourceType {
+            vertex_buffer,
+            index_buffer: None,
+            num_elements: self.points.len() as u32,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PointShape {
+    #[default]
+    Square,
+    Circle,
+}
+
+/// How points are rasterized by the [`PointCloudPipeline`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PointPrimitive {
+    /// [`PrimitiveTopology::PointList`] primitives. Always one pixel in size,
+    /// as wgpu has no way to set the point size.
+    Points,
+    /// Screen facing quads of [`PointStyle::size`] pixels
+    #[default]
+    Quads,
+}
+
+impl PointPrimitive {
+    /// Vertices drawn for every point
+    pub fn vertex_count(&self) -> u32 {
+        match self {
+            PointPrimitive::Points => 1,
+            PointPrimitive::Quads => 6,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointStyleUniform {
+    size: f32,
+    shape: u32,
+    _pad: [f32; 2],
+}
+
+/// Screen space style of the points
+#[derive(Debug, Clone, Copy)]
+pub struct PointStyle {
+    /// Size in pixels. Ignored by [`PointPrimitive::Points`].
+    pub size: f32,
+    /// Ignored by [`PointPrimitive::Points`]
+    pub shape: PointShape,
+}
+
+impl Default for PointStyle {
+    fn default() -> Self {
+        Self {
+            size: 4.0,
+            shape: PointShape::default(),
+        }
+    }
+}
+
+impl From<&PointStyle> for PointStyleUniform {
+    fn from(value: &PointStyle) -> Self {
+        Self {
+            size: value.size,
+            shape: value.shape as u32,
+            ..Default::default()
+        }
+    }
+}
+
+impl_simple_buffer!(
+    PointStyle,
+    PointStyleUniform,
+    PointStyleResources,
+    PointStyleHandle,
+    PointStyleBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::VERTEX | ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+/// Pipeline drawing [`PointCloud`] meshes with
+/// [`CameraBindGroup`], [`GlobalsBindGroup`] and [`PointStyleBindGroup`]
+#[derive(Debug, Clone, Copy)]
+pub struct PointCloudPipeline {
+    pub pipeline_id: PipelineId,
+    pub primitive: PointPrimitive,
+}
+
+impl PointCloudPipeline {
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        format: TextureFormat,
+        depth_format: Option<TextureFormat>,
+        primitive: PointPrimitive,
+    ) -> Self {
+        let shader = create_shader_module(renderer, "point_cloud.wgsl", POINT_CLOUD_SHADER.into());
+        let (topology, vertex_entry_point) = match primitive {
+            PointPrimitive::Points => (PrimitiveTopology::PointList, "vs_points"),
+            PointPrimitive::Quads => (PrimitiveTopology::TriangleList, "vs_quads"),
+        };
+        let pipeline = PipelineBuilder {
+            shader_path: "point_cloud.wgsl",
+            label: Some("point_cloud_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<GlobalsBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<PointStyleBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[PointVertex::layout()],
+            vertex_entry_point,
+            color_targets: Some(&[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: depth_format.map(|format| DepthStencilState {
+                format,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::LessEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_pipeline(pipeline),
+            primitive,
+        }
+    }
+
+    /// Command drawing `mesh_id` point cloud
+    pub fn command(
+        &self,
+        mesh_id: MeshId,
+        camera: CameraBindGroup,
+        globals: GlobalsBindGroup,
+        style: PointStyleBindGroup,
+    ) -> PointCloudRenderCommand {
+        PointCloudRenderCommand {
+            pipeline_id: self.pipeline_id,
+            mesh_id,
+            vertex_count: self.primitive.vertex_count(),
+            bind_groups: const_vec![camera.0, globals.0, style.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PointCloudRenderCommand {
+    pub pipeline_id: PipelineId,
+    pub mesh_id: MeshId,
+    /// Vertices drawn for every point, see [`PointPrimitive::vertex_count`]
+    pub vertex_count: u32,
+    pub bind_groups: ConstVec<MAX_BIND_GROUPS, BindGroupId>,
+}
+
+impl RenderCommand for PointCloudRenderCommand {
+    fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
+        let mesh = storage.get_mesh(self.mesh_id);
+        if mesh.num_elements == 0 {
+            return;
+        }
+
+        let pipeline = storage.get_gpu_pipeline(self.pipeline_id);
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for (i, bg) in self.bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, storage.get_bind_group(*bg), &[]);
+        }
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+
+        // Every point is an instance
+        let mut stats = RenderStats {
+            bind_group_switches: self.bind_groups.iter().count(),
+            pipeline_switches: 1,
+            ..Default::default()
+        };
+        stats.record_instanced_draw(pipeline.topology, self.vertex_count, mesh.num_elements);
+        storage.record_stats(stats);
+
+        render_pass.draw(0..self.vertex_count, 0..mesh.num_elements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_cloud_from_points() {
+        let cloud = PointCloud::from_points(&[
+            ([0.0, 1.0, 2.0], [1.0, 0.0, 0.0, 1.0]),
+            ([3.0, 4.0, 5.0], [0.0, 1.0, 0.0, 1.0]),
+        ]);
+        assert_eq!(cloud.points.len(), 2);
+        assert_eq!(cloud.points[1].position, [3.0, 4.0, 5.0]);
+        assert_eq!(cloud.points[1].color, [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(PointVertex::layout().array_stride, 28);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::cgmath_imports::*;
+
+/// Lines closer to parallel than this are treated as parallel
+const PARALLEL_EPSILON: f32 = 1e-6;
+
+/// Half line in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Normalized direction
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates ray with normalized `direction`
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Point at the distance `t` along the ray
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// Distance along the ray to the plane. `None` if the ray
+    /// is parallel to the plane or points away from it.
+    pub fn intersect_plane(&self, point: Point3<f32>, normal: Vector3<f32>) -> Option<f32> {
+        let denom = normal.dot(self.direction);
+        if denom.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let t = normal.dot(point - self.origin) / denom;
+        (0.0 <= t).then_some(t)
+    }
+
+    /// Parameters of the closest points between the ray and the infinite line
+    /// through `point` with normalized `direction`. First is the distance along the ray
+    /// (not clamped to the ray start), second is the distance along the line.
+    /// `None` if they are parallel.
+    pub fn closest_to_line(
+        &self,
+        point: Point3<f32>,
+        direction: Vector3<f32>,
+    ) -> Option<(f32, f32)> {
+        let b = self.direction.dot(direction);
+        let denom = 1.0 - b * b;
+        if denom.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let w = self.origin - point;
+        let d = self.direction.dot(w);
+        let e = direction.dot(w);
+        Some(((b * e - d) / denom, (e - b * d) / denom))
+    }
+
+    /// Distance along the ray to the axis aligned box. 0 if the ray
+    /// starts inside of it. `None` if the ray misses it.
+    pub fn intersect_aabb(&self, min: [f32; 3], max: [f32; 3]) -> Option<f32> {
+        let origin: [f32; 3] = self.origin.into();
+        let direction: [f32; 3] = self.direction.into();
+        let mut t_min = 0.0_f32;
+        let mut t_max = f32::INFINITY;
+        for i in 0..3 {
+            if direction[i].abs() < PARALLEL_EPSILON {
+                if origin[i] < min[i] || max[i] < origin[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[i] - origin[i]) / direction[i];
+            let t1 = (max[i] - origin[i]) / direction[i];
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+
+    /// Distance along the ray to the triangle. Both sides of the
+    /// triangle are hit. `None` if the ray misses it.
+    pub fn intersect_triangle(&self, triangle: [Point3<f32>; 3]) -> Option<f32> {
+        let edge_1 = triangle[1] - triangle[0];
+        let edge_2 = triangle[2] - triangle[0];
+        let p = self.direction.cross(edge_2);
+        let det = edge_1.dot(p);
+        if det.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let to_origin = self.origin - triangle[0];
+        let u = to_origin.dot(p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = to_origin.cross(edge_1);
+        let v = self.direction.dot(q) / det;
+        if v < 0.0 || 1.0 < u + v {
+            return None;
+        }
+        let t = edge_2.dot(q) / det;
+        (0.0 <= t).then_some(t)
+    }
+
+    /// Distance from the ray to the `point`
+    pub fn distance_to_point(&self, point: Point3<f32>) -> f32 {
+        let t = self.direction.dot(point - self.origin).max(0.0);
+        (point - self.at(t)).magnitude()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_intersect_plane() {
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -2.0, 0.0));
+        let t = ray
+            .intersect_plane(Point3::new(3.0, 1.0, 3.0), Vector3::unit_y())
+            .unwrap();
+        assert!((t - 4.0).abs() < 1e-6);
+        assert!(ray
+            .intersect_plane(Point3::new(0.0, 6.0, 0.0), Vector3::unit_y())
+            .is_none());
+        assert!(ray
+            .intersect_plane(Point3::new(0.0, 0.0, 0.0), Vector3::unit_x())
+            .is_none());
+    }
+
+    #[test]
+    fn ray_intersect_aabb() {
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.5), Vector3::unit_x());
+        let t = ray.intersect_aabb([-1.0; 3], [1.0; 3]).unwrap();
+        assert!((t - 4.0).abs() < 1e-6);
+        assert!(ray
+            .intersect_aabb([-1.0, 2.0, -1.0], [1.0, 3.0, 1.0])
+            .is_none());
+        assert!(ray
+            .intersect_aabb([-7.0, -1.0, -1.0], [-6.0, 1.0, 1.0])
+            .is_none());
+
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0));
+        assert_eq!(inside.intersect_aabb([-1.0; 3], [1.0; 3]), Some(0.0));
+    }
+
+    #[test]
+    fn ray_intersect_triangle() {
+        let triangle = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ];
+        let ray = Ray::new(Point3::new(0.5, 0.5, 3.0), -Vector3::unit_z());
+        let t = ray.intersect_triangle(triangle).unwrap();
+        assert!((t - 3.0).abs() < 1e-6);
+
+        let back = Ray::new(Point3::new(0.5, 0.5, -3.0), Vector3::unit_z());
+        assert!(back.intersect_triangle(triangle).is_some());
+
+        let miss = Ray::new(Point3::new(1.5, 1.5, 3.0), -Vector3::unit_z());
+        assert!(miss.intersect_triangle(triangle).is_none());
+        let away = Ray::new(Point3::new(0.5, 0.5, 3.0), Vector3::unit_z());
+        assert!(away.intersect_triangle(triangle).is_none());
+    }
+
+    #[test]
+    fn ray_closest_to_line() {
+        // Ray goes down the Y axis at x = 2, line is the X axis at z = 1
+        let ray = Ray::new(Point3::new(2.0, 3.0, 0.0), -Vector3::unit_y());
+        let (t, s) = ray
+            .closest_to_line(Point3::new(0.0, 0.0, 1.0), Vector3::unit_x())
+            .unwrap();
+        assert!((t - 3.0).abs() < 1e-6);
+        assert!((s - 2.0).abs() < 1e-6);
+        assert!(ray
+            .closest_to_line(Point3::new(0.0, 0.0, 1.0), Vector3::unit_y())
+            .is_none());
+        assert!((ray.distance_to_point(Point3::new(2.0, 0.0, 1.0)) - 1.0).abs() < 1e-6);
+    }
+}
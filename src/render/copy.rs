@@ -0,0 +1,186 @@
+use super::{
+    renderer::Renderer,
+    storage::RenderStorage,
+    storage::{BufferId, TextureId},
+    wgpu_imports::*,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CopyError {
+    #[error("Copy of {size} bytes does not fit source ({src_size}) or destination ({dst_size})")]
+    BufferOutOfBounds {
+        size: BufferAddress,
+        src_size: BufferAddress,
+        dst_size: BufferAddress,
+    },
+    #[error("Copy size {0} is not a multiple of 4 bytes")]
+    UnalignedSize(BufferAddress),
+    #[error("Texture sizes differ: {src:?} and {dst:?}")]
+    TextureSizeMismatch { src: Extent3d, dst: Extent3d },
+    #[error("Texture formats differ: {src:?} and {dst:?}")]
+    TextureFormatMismatch {
+        src: TextureFormat,
+        dst: TextureFormat,
+    },
+    #[error("Texture format {0:?} has no single block size")]
+    UnsupportedFormat(TextureFormat),
+    #[error("Source is missing COPY_SRC or destination is missing COPY_DST usage")]
+    MissingUsage,
+}
+
+/// Size of the texture row in the buffer aligned to the
+/// `COPY_BYTES_PER_ROW_ALIGNMENT`, as required by texture to buffer copies
+pub fn padded_bytes_per_row(width: u32, block_size: u32) -> u32 {
+    let unpadded = width * block_size;
+    unpadded.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Records copy of `size` bytes (whole source if `None`) from the start
+/// of `src_id` buffer into the start of `dst_id` buffer
+pub fn encode_copy_buffer(
+    encoder: &mut CommandEncoder,
+    storage: &RenderStorage,
+    src_id: BufferId,
+    dst_id: BufferId,
+    size: Option<BufferAddress>,
+) -> Result<(), CopyError> {
+    let src = storage.get_buffer(src_id);
+    let dst = storage.get_buffer(dst_id);
+    if !src.usage().contains(BufferUsages::COPY_SRC)
+        || !dst.usage().contains(BufferUsages::COPY_DST)
+    {
+        return Err(CopyError::MissingUsage);
+    }
+    let size = size.unwrap_or(src.size());
+    if src.size() < size || dst.size() < size {
+        return Err(CopyError::BufferOutOfBounds {
+            size,
+            src_size: src.size(),
+            dst_size: dst.size(),
+        });
+    }
+    if !size.is_multiple_of(4) {
+        return Err(CopyError::UnalignedSize(size));
+    }
+    encoder.copy_buffer_to_buffer(src, 0, dst, 0, size);
+    Ok(())
+}
+
+/// Records copy of the whole `src_id` texture into `dst_id` texture.
+/// Textures must have the same size and format.
+pub fn encode_copy_texture(
+    encoder: &mut CommandEncoder,
+    storage: &RenderStorage,
+    src_id: TextureId,
+    dst_id: TextureId,
+) -> Result<(), CopyError> {
+    let src = &storage.get_texture(src_id).texture;
+    let dst = &storage.get_texture(dst_id).texture;
+    if !src.usage().contains(TextureUsages::COPY_SRC)
+        || !dst.usage().contains(TextureUsages::COPY_DST)
+    {
+        return Err(CopyError::MissingUsage);
+    }
+    if src.size() != dst.size() {
+        return Err(CopyError::TextureSizeMismatch {
+            src: src.size(),
+            dst: dst.size(),
+        });
+    }
+    if src.format() != dst.format() {
+        return Err(CopyError::TextureFormatMismatch {
+            src: src.format(),
+            dst: dst.format(),
+        });
+    }
+    encoder.copy_texture_to_texture(src.as_image_copy(), dst.as_image_copy(), src.size());
+    Ok(())
+}
+
+/// Records copy of the first layer of `texture_id` texture into `buffer_id` buffer.
+/// Rows in the buffer are padded to [`padded_bytes_per_row`], which is returned.
+pub fn encode_copy_texture_to_buffer(
+    encoder: &mut CommandEncoder,
+    storage: &RenderStorage,
+    texture_id: TextureId,
+    buffer_id: BufferId,
+) -> Result<u32, CopyError> {
+    let texture = &storage.get_texture(texture_id).texture;
+    let buffer = storage.get_buffer(buffer_id);
+    if !texture.usage().contains(TextureUsages::COPY_SRC)
+        || !buffer.usage().contains(BufferUsages::COPY_DST)
+    {
+        return Err(CopyError::MissingUsage);
+    }
+    let block_size = texture
+        .format()
+        .block_copy_size(Some(TextureAspect::All))
+        .ok_or(CopyError::UnsupportedFormat(texture.format()))?;
+    let bytes_per_row = padded_bytes_per_row(texture.width(), block_size);
+    let size = bytes_per_row as BufferAddress * texture.height() as BufferAddress;
+    if buffer.size() < size {
+        return Err(CopyError::BufferOutOfBounds {
+            size,
+            src_size: size,
+            dst_size: buffer.size(),
+        });
+    }
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        Extent3d {
+            width: texture.width(),
+            height: texture.height(),
+            depth_or_array_layers: 1,
+        },
+    );
+    Ok(bytes_per_row)
+}
+
+impl<'window> Renderer<'window> {
+    /// Same as [`encode_copy_buffer`], but submits the copy immediately
+    pub fn copy_buffer(
+        &self,
+        storage: &RenderStorage,
+        src_id: BufferId,
+        dst_id: BufferId,
+        size: Option<BufferAddress>,
+    ) -> Result<(), CopyError> {
+        let mut encoder = self.create_encoder();
+        encode_copy_buffer(&mut encoder, storage, src_id, dst_id, size)?;
+        self.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    /// Same as [`encode_copy_texture`], but submits the copy immediately
+    pub fn copy_texture(
+        &self,
+        storage: &RenderStorage,
+        src_id: TextureId,
+        dst_id: TextureId,
+    ) -> Result<(), CopyError> {
+        let mut encoder = self.create_encoder();
+        encode_copy_texture(&mut encoder, storage, src_id, dst_id)?;
+        self.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_padded_bytes_per_row() {
+        assert_eq!(padded_bytes_per_row(64, 4), 256);
+        assert_eq!(padded_bytes_per_row(65, 4), 512);
+        assert_eq!(padded_bytes_per_row(1, 8), 256);
+    }
+}
@@ -1,5 +1,9 @@
+pub mod copy;
+pub mod parallel;
+pub mod phase_graph;
 pub mod pipeline_builder;
 pub mod render_phase;
+pub mod render_system;
 pub mod renderer;
 pub mod storage;
 pub mod traits;
@@ -7,8 +11,12 @@ pub mod traits;
 pub mod prelude {
     use super::*;
 
+    pub use copy::*;
+    pub use parallel::*;
+    pub use phase_graph::*;
     pub use pipeline_builder::*;
     pub use render_phase::*;
+    pub use render_system::*;
     pub use renderer::*;
     pub use storage::*;
     pub use traits::*;
@@ -21,20 +29,22 @@ pub mod wgpu_imports {
     pub use wgpu::{
         AddressMode, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
         BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
-        BlendComponent, BlendState, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
-        BufferUsages, Color, ColorTargetState, ColorWrites, CommandBuffer, CommandEncoder,
-        CommandEncoderDescriptor, CompareFunction, DepthBiasState, DepthStencilState, Device,
-        DeviceDescriptor, Extent3d, Face, Features, FilterMode, FragmentState, FrontFace,
-        ImageCopyTexture, ImageDataLayout, IndexFormat, Instance, Limits, LoadOp, Maintain,
-        MapMode, MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor, PolygonMode,
-        PowerPreference, PresentMode, PrimitiveState, PrimitiveTopology, Queue, RenderPass,
-        RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
-        RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, Sampler,
-        SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+        BlendComponent, BlendFactor, BlendOperation, BlendState, Buffer, BufferAddress,
+        BufferBinding, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
+        ColorWrites, CommandBuffer, CommandEncoder, CommandEncoderDescriptor, CompareFunction,
+        ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, DepthBiasState,
+        DepthStencilState, Device, DeviceDescriptor, Extent3d, Face, Features, FilterMode,
+        FragmentState, FrontFace, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, IndexFormat,
+        Instance, Limits, LoadOp, Maintain, MapMode, MultisampleState, Operations, Origin3d,
+        PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
+        PrimitiveTopology, Queue, RenderPass, RenderPassColorAttachment,
+        RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+        RenderPipelineDescriptor, RequestAdapterOptions, Sampler, SamplerBindingType,
+        SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages,
         StencilFaceState, StencilOperation, StencilState, StoreOp, Surface, SurfaceConfiguration,
         SurfaceError, SurfaceTexture, Texture, TextureAspect, TextureDescriptor, TextureDimension,
         TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
         TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
-        VertexStepMode,
+        VertexStepMode, COPY_BYTES_PER_ROW_ALIGNMENT,
     };
 }
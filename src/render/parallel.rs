@@ -0,0 +1,36 @@
+use super::prelude::*;
+
+/// Work recorded into its own command encoder. Usually begins
+/// one or more render phases and executes their commands.
+pub type EncodeJob<'a> = dyn Fn(&mut CommandEncoder, &CurrentFrameStorage) + Sync + 'a;
+
+/// Records every job into a separate encoder and returns command buffers
+/// in the order of `jobs`. With the `rayon` feature jobs are recorded
+/// in parallel, otherwise one after another.
+/// Only recording is parallel: submitting the result in the returned
+/// order executes jobs on the GPU in the order they were given, so jobs
+/// depending on results of other jobs need to come after them.
+pub fn encode_parallel(
+    renderer: &Renderer,
+    current_frame_storage: &CurrentFrameStorage,
+    jobs: &[&EncodeJob],
+) -> Vec<CommandBuffer> {
+    let device = renderer.device();
+    let encode = |job: &&EncodeJob| {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("parallel_encoder"),
+        });
+        job(&mut encoder, current_frame_storage);
+        encoder.finish()
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        jobs.par_iter().map(encode).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        jobs.iter().map(encode).collect()
+    }
+}
@@ -0,0 +1,110 @@
+use super::prelude::*;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PhaseGraphError {
+    #[error("Phases {0:?} depend on each other")]
+    Cycle(Vec<usize>),
+}
+
+/// Orders phases so every phase runs after all phases writing the
+/// resources it reads (see [`RenderPhase::reads`] and [`RenderPhase::writes`]).
+/// Phases writing the same resource keep their relative order, as do
+/// independent phases. Returns indices into `phases` in execution order.
+///
+/// Reads are resolved within one frame. Resources intentionally read before
+/// they are written again (e.g. history of the previous frame) should not
+/// be declared as reads.
+pub fn sort_phases(phases: &[&RenderPhase]) -> Result<Vec<usize>, PhaseGraphError> {
+    let n = phases.len();
+    let mut dependencies = vec![Vec::new(); n];
+    for (i, phase) in phases.iter().enumerate() {
+        for resource in phase.reads() {
+            for (j, writer) in phases.iter().enumerate() {
+                if i != j && writer.writes().any(|w| w == resource) {
+                    dependencies[i].push(j);
+                }
+            }
+        }
+        for resource in phase.writes() {
+            for (j, writer) in phases[..i].iter().enumerate() {
+                if writer.writes().any(|w| w == resource) {
+                    dependencies[i].push(j);
+                }
+            }
+        }
+    }
+
+    // Kahn's algorithm always taking the earliest ready phase,
+    // so the result is the original order when there are no conflicts
+    let mut done = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    while order.len() < n {
+        let ready = (0..n).find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d]));
+        match ready {
+            Some(i) => {
+                done[i] = true;
+                order.push(i);
+            }
+            None => {
+                return Err(PhaseGraphError::Cycle(
+                    (0..n).filter(|&i| !done[i]).collect(),
+                ))
+            }
+        }
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::const_vec;
+    use crate::utils::ConstVec;
+
+    fn phase(writes: TextureId, reads: &[TextureId]) -> RenderPhase {
+        let mut phase = RenderPhase::new(
+            const_vec![ColorAttachment {
+                view_id: writes,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            }],
+            None,
+        );
+        phase.set_reads(reads.to_vec());
+        phase
+    }
+
+    #[test]
+    fn phase_graph_sort() {
+        let shadow_map = ResourceId::from_index(0);
+        let g_buffer = ResourceId::from_index(1);
+        let window = TextureId::WINDOW_VIEW_ID;
+
+        // Lighting is added before the shadow phase it samples
+        let lighting = phase(window, &[shadow_map, g_buffer]);
+        let geometry = phase(g_buffer, &[]);
+        let shadow = phase(shadow_map, &[]);
+        let skybox = phase(window, &[]);
+
+        let order = sort_phases(&[&lighting, &geometry, &shadow, &skybox]).unwrap();
+        assert_eq!(order, vec![1, 2, 0, 3]);
+
+        let order = sort_phases(&[&geometry, &shadow, &lighting, &skybox]).unwrap();
+        assert_eq!(order, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn phase_graph_cycle() {
+        let a = ResourceId::from_index(0);
+        let b = ResourceId::from_index(1);
+        let first = phase(a, &[b]);
+        let second = phase(b, &[a]);
+        let independent = phase(ResourceId::from_index(2), &[]);
+        assert_eq!(
+            sort_phases(&[&first, &second, &independent]),
+            Err(PhaseGraphError::Cycle(vec![0, 1]))
+        );
+    }
+}
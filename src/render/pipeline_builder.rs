@@ -1,6 +1,236 @@
-use super::{renderer::Renderer, wgpu_imports::*};
-use log::info;
-use std::{fs::File, io::Read, num::NonZeroU32};
+use super::{renderer::Renderer, storage::RenderStorage, wgpu_imports::*};
+use log::{debug, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Read,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShaderIncludeError {
+    #[error("Could not read shader {path:?} (included from {stack:?}): {error}")]
+    Io {
+        path: PathBuf,
+        stack: Vec<PathBuf>,
+        error: std::io::Error,
+    },
+    #[error("Shader include cycle: {0:?}")]
+    Cycle(Vec<PathBuf>),
+}
+
+/// Reads the shader and replaces every `#include "path"` line with the contents
+/// of the file at `path` relative to the including shader. Every file is
+/// included only once, so shared structs can be included from multiple files.
+pub fn preprocess_shader<P: AsRef<Path>>(shader_path: P) -> Result<String, ShaderIncludeError> {
+    let mut included = HashSet::new();
+    let mut stack = Vec::new();
+    let mut output = String::new();
+    preprocess_file(shader_path.as_ref(), &mut stack, &mut included, &mut output)?;
+    Ok(output)
+}
+
+fn preprocess_file(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut HashSet<PathBuf>,
+    output: &mut String,
+) -> Result<(), ShaderIncludeError> {
+    let io_error = |stack: &Vec<PathBuf>, error| ShaderIncludeError::Io {
+        path: path.to_path_buf(),
+        stack: stack.clone(),
+        error,
+    };
+    let canonical = path.canonicalize().map_err(|e| io_error(stack, e))?;
+    if stack.contains(&canonical) {
+        let mut cycle = stack.clone();
+        cycle.push(canonical);
+        return Err(ShaderIncludeError::Cycle(cycle));
+    }
+    if !included.insert(canonical.clone()) {
+        return Ok(());
+    }
+
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| io_error(stack, e))?;
+
+    stack.push(canonical);
+    let directory = path.parent().unwrap_or(Path::new(""));
+    for line in contents.split_inclusive('\n') {
+        let include = line
+            .trim()
+            .strip_prefix("#include")
+            .map(|rest| rest.trim().trim_matches('"'));
+        match include {
+            Some(include) => {
+                preprocess_file(&directory.join(include), stack, included, output)?;
+                if !output.ends_with('\n') {
+                    output.push('\n');
+                }
+            }
+            None => output.push_str(line),
+        }
+    }
+    stack.pop();
+    Ok(())
+}
+
+pub(crate) fn read_shader_source(shader_path: &str) -> String {
+    preprocess_shader(shader_path).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub(crate) fn create_shader_module(
+    renderer: &Renderer,
+    shader_path: &str,
+    contents: String,
+) -> ShaderModule {
+    let shader_label = format!("shader: {}", shader_path);
+    let shader = ShaderModuleDescriptor {
+        label: Some(&shader_label),
+        source: ShaderSource::Wgsl(contents.into()),
+    };
+    renderer.device().create_shader_module(shader)
+}
+
+/// Values replacing initializers of the `const` declarations in the shader
+/// source, so one shader can be specialized into multiple pipelines.
+/// `const MAX_LIGHTS: u32 = 16u;` with `MAX_LIGHTS` defined as `64u` becomes
+/// `const MAX_LIGHTS: u32 = 64u;`, so the shader stays valid WGSL without defines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShaderDefines(HashMap<String, String>);
+
+impl ShaderDefines {
+    pub fn define(mut self, name: &str, value: impl ToString) -> Self {
+        self.0.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Source with initializers of defined constants replaced
+    pub fn apply(&self, source: &str) -> String {
+        let mut replaced = 0;
+        let result = source
+            .split_inclusive('\n')
+            .map(|line| {
+                let Some(declaration) = line.trim_start().strip_prefix("const ") else {
+                    return line.to_string();
+                };
+                let name = declaration
+                    .trim_start()
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default();
+                // First `;` after the `=`, so trailing comments are kept as is
+                let eq = line.find('=');
+                let semicolon = eq.and_then(|eq| line[eq..].find(';').map(|i| eq + i));
+                match (self.0.get(name), eq, semicolon) {
+                    (Some(value), Some(eq), Some(semicolon)) => {
+                        replaced += 1;
+                        format!("{} {value}{}", &line[..=eq], &line[semicolon..])
+                    }
+                    _ => line.to_string(),
+                }
+            })
+            .collect();
+        if replaced != self.0.len() {
+            warn!(
+                "Some of the shader defines {:?} have no matching constants",
+                self.0
+            );
+        }
+        result
+    }
+}
+
+/// Index format for strip topologies. All meshes use `Uint32` indices
+/// and list topologies must not have strip index format set.
+fn strip_index_format(topology: PrimitiveTopology) -> Option<IndexFormat> {
+    match topology {
+        PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip => {
+            Some(IndexFormat::Uint32)
+        }
+        PrimitiveTopology::PointList
+        | PrimitiveTopology::LineList
+        | PrimitiveTopology::TriangleList => None,
+    }
+}
+
+/// Render pipeline with information about its layout
+#[derive(Debug)]
+pub struct GpuPipeline {
+    pub pipeline: RenderPipeline,
+    pub label: Option<String>,
+    /// Number of bind groups in the pipeline layout.
+    /// `None` for pipelines with the layout derived from the shader.
+    pub bind_group_count: Option<usize>,
+    /// Format of the depth stencil attachment. `None` for pipelines without depth.
+    pub depth_format: Option<TextureFormat>,
+    pub depth_write_enabled: bool,
+    pub topology: PrimitiveTopology,
+    /// Stride of the first vertex buffer. `None` for pipelines without vertex buffers.
+    pub vertex_stride: Option<BufferAddress>,
+    /// Ids of the bind group layouts of the pipeline layout.
+    /// `None` for pipelines with the layout derived from the shader.
+    pub bind_group_layouts: Option<Vec<wgpu::Id<BindGroupLayout>>>,
+}
+
+impl GpuPipeline {
+    /// Panics if the pipeline layout expects different number of bind groups
+    pub fn assert_bind_group_count(&self, count: usize) {
+        if let Some(expected) = self.bind_group_count {
+            assert!(
+                expected == count,
+                "Pipeline {:?} expects {} bind groups, but {} were provided",
+                self.label.as_deref().unwrap_or("unlabeled"),
+                expected,
+                count,
+            );
+        }
+    }
+
+    /// Panics if the pipeline expects different depth stencil attachment
+    /// format than the phase it is used in has
+    pub fn assert_depth_format(&self, phase_depth_format: Option<TextureFormat>) {
+        assert!(
+            self.depth_format == phase_depth_format,
+            "Pipeline {:?} uses {:?} depth format, but the render phase depth attachment is {:?}",
+            self.label.as_deref().unwrap_or("unlabeled"),
+            self.depth_format,
+            phase_depth_format,
+        );
+    }
+
+    /// Panics if the pipeline writes depth. Used for phases
+    /// with read only depth attachment.
+    pub fn assert_depth_read_only(&self) {
+        assert!(
+            !self.depth_write_enabled,
+            "Pipeline {:?} writes depth, but the render phase depth attachment is read only",
+            self.label.as_deref().unwrap_or("unlabeled"),
+        );
+    }
+
+    /// Index of the first bind group with a layout different from the one
+    /// in the `other` pipeline. Pipelines with layouts derived from
+    /// the shader can not be compared and are considered compatible.
+    pub fn layout_mismatch(&self, other: &GpuPipeline) -> Option<usize> {
+        match (&self.bind_group_layouts, &other.bind_group_layouts) {
+            (Some(a), Some(b)) => first_mismatch(a, b),
+            _ => None,
+        }
+    }
+}
+
+/// Index of the first differing element. Slices of different
+/// length differ at the end of the shorter one.
+fn first_mismatch<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (a.len() != b.len()).then_some(a.len().min(b.len())))
+}
 
 pub struct PipelineBuilder<'a> {
     pub shader_path: &'a str,
@@ -10,6 +240,7 @@ pub struct PipelineBuilder<'a> {
     pub vertex_entry_point: &'a str,
     pub color_targets: Option<&'a [Option<ColorTargetState>]>,
     pub fragment_entry_point: &'a str,
+    /// `strip_index_format` is ignored and derived from the `topology`
     pub primitive: PrimitiveState,
     pub depth_stencil: Option<DepthStencilState>,
     pub multisample: MultisampleState,
@@ -17,46 +248,220 @@ pub struct PipelineBuilder<'a> {
 }
 
 impl<'a> PipelineBuilder<'a> {
-    pub fn build(self, renderer: &Renderer) -> RenderPipeline {
-        info!("Building pipilene: {}", self.shader_path);
+    pub fn build(self, renderer: &Renderer) -> GpuPipeline {
+        let shader = create_shader_module(
+            renderer,
+            self.shader_path,
+            read_shader_source(self.shader_path),
+        );
+        self.build_with_module(renderer, &shader)
+    }
+
+    /// Same as `build`, but applies `defines` to the shader source
+    pub fn build_with_defines(self, renderer: &Renderer, defines: &ShaderDefines) -> GpuPipeline {
+        let shader = create_shader_module(
+            renderer,
+            self.shader_path,
+            defines.apply(&read_shader_source(self.shader_path)),
+        );
+        self.build_with_module(renderer, &shader)
+    }
+
+    /// Same as `build`, but reuses shader module from the `storage` cache
+    pub fn build_cached(self, renderer: &Renderer, storage: &RenderStorage) -> GpuPipeline {
+        let shader = storage.get_shader_module(renderer, self.shader_path);
+        self.build_with_module(renderer, &shader)
+    }
+
+    pub(crate) fn build_with_module(
+        self,
+        renderer: &Renderer,
+        shader: &ShaderModule,
+    ) -> GpuPipeline {
+        debug!("Building pipeline: {}", self.shader_path);
 
         let layout = self
             .layout_descriptor
             .map(|d| renderer.device().create_pipeline_layout(d));
 
-        let mut contents = String::new();
-        {
-            let mut file = File::open(self.shader_path).unwrap();
-            file.read_to_string(&mut contents).unwrap();
-        }
-        let shader_label = format!("shader: {}", self.shader_path);
-        let shader = ShaderModuleDescriptor {
-            label: Some(&shader_label),
-            source: ShaderSource::Wgsl(contents.into()),
-        };
-        let shader = renderer.device().create_shader_module(shader);
-
         let fragment = self.color_targets.map(|targets| FragmentState {
-            module: &shader,
+            module: shader,
             entry_point: self.fragment_entry_point,
             targets,
         });
 
-        renderer
+        let depth_format = self.depth_stencil.as_ref().map(|d| d.format);
+        let depth_write_enabled = self
+            .depth_stencil
+            .as_ref()
+            .is_some_and(|d| d.depth_write_enabled);
+        let pipeline = renderer
             .device()
             .create_render_pipeline(&RenderPipelineDescriptor {
                 label: self.label,
                 layout: layout.as_ref(),
                 vertex: VertexState {
-                    module: &shader,
+                    module: shader,
                     entry_point: self.vertex_entry_point,
                     buffers: self.vertex_layouts,
                 },
                 fragment,
-                primitive: self.primitive,
+                primitive: PrimitiveState {
+                    strip_index_format: strip_index_format(self.primitive.topology),
+                    ..self.primitive
+                },
                 depth_stencil: self.depth_stencil,
                 multisample: self.multisample,
                 multiview: self.multiview,
+            });
+
+        GpuPipeline {
+            pipeline,
+            label: self.label.map(str::to_string),
+            bind_group_count: self.layout_descriptor.map(|d| d.bind_group_layouts.len()),
+            depth_format,
+            depth_write_enabled,
+            topology: self.primitive.topology,
+            vertex_stride: self.vertex_layouts.first().map(|l| l.array_stride),
+            bind_group_layouts: self.layout_descriptor.map(|d| {
+                d.bind_group_layouts
+                    .iter()
+                    .map(|layout| layout.global_id())
+                    .collect()
+            }),
+        }
+    }
+}
+
+pub struct ComputePipelineBuilder<'a> {
+    pub shader_path: &'a str,
+    pub label: Option<&'a str>,
+    pub layout_descriptor: Option<&'a PipelineLayoutDescriptor<'a>>,
+    pub entry_point: &'a str,
+}
+
+impl<'a> ComputePipelineBuilder<'a> {
+    pub fn build(self, renderer: &Renderer) -> ComputePipeline {
+        let shader = create_shader_module(
+            renderer,
+            self.shader_path,
+            read_shader_source(self.shader_path),
+        );
+        self.build_with_module(renderer, &shader)
+    }
+
+    /// Same as `build`, but applies `defines` to the shader source
+    pub fn build_with_defines(
+        self,
+        renderer: &Renderer,
+        defines: &ShaderDefines,
+    ) -> ComputePipeline {
+        let shader = create_shader_module(
+            renderer,
+            self.shader_path,
+            defines.apply(&read_shader_source(self.shader_path)),
+        );
+        self.build_with_module(renderer, &shader)
+    }
+
+    /// Same as `build`, but reuses shader module from the `storage` cache
+    pub fn build_cached(self, renderer: &Renderer, storage: &RenderStorage) -> ComputePipeline {
+        let shader = storage.get_shader_module(renderer, self.shader_path);
+        self.build_with_module(renderer, &shader)
+    }
+
+    pub fn build_with_module(self, renderer: &Renderer, shader: &ShaderModule) -> ComputePipeline {
+        debug!("Building compute pipeline: {}", self.shader_path);
+
+        let layout = self
+            .layout_descriptor
+            .map(|d| renderer.device().create_pipeline_layout(d));
+
+        renderer
+            .device()
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: self.label,
+                layout: layout.as_ref(),
+                module: shader,
+                entry_point: self.entry_point,
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_strip_index_format() {
+        for topology in [
+            PrimitiveTopology::PointList,
+            PrimitiveTopology::LineList,
+            PrimitiveTopology::TriangleList,
+        ] {
+            assert_eq!(strip_index_format(topology), None);
+        }
+        for topology in [
+            PrimitiveTopology::LineStrip,
+            PrimitiveTopology::TriangleStrip,
+        ] {
+            assert_eq!(strip_index_format(topology), Some(IndexFormat::Uint32));
+        }
+    }
+
+    #[test]
+    fn pipeline_preprocess_shader() {
+        let dir = std::env::temp_dir().join(format!("zero_shader_include_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        let write = |name: &str, contents: &str| std::fs::write(dir.join(name), contents).unwrap();
+        write("common/camera.wgsl", "struct Camera {};");
+        write(
+            "common/light.wgsl",
+            "#include \"camera.wgsl\"\nstruct Light {};\n",
+        );
+        write(
+            "main.wgsl",
+            "#include \"common/camera.wgsl\"\n#include \"common/light.wgsl\"\nfn main() {}\n",
+        );
+        assert_eq!(
+            preprocess_shader(dir.join("main.wgsl")).unwrap(),
+            "struct Camera {};\nstruct Light {};\nfn main() {}\n"
+        );
+
+        write("a.wgsl", "#include \"b.wgsl\"\n");
+        write("b.wgsl", "#include \"a.wgsl\"\n");
+        match preprocess_shader(dir.join("a.wgsl")) {
+            Err(ShaderIncludeError::Cycle(stack)) => assert_eq!(stack.len(), 3),
+            other => panic!("{other:?}"),
+        }
+
+        write("missing.wgsl", "#include \"nothing.wgsl\"\n");
+        match preprocess_shader(dir.join("missing.wgsl")) {
+            Err(ShaderIncludeError::Io { stack, .. }) => assert_eq!(stack.len(), 1),
+            other => panic!("{other:?}"),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pipeline_shader_defines() {
+        let source = "const MAX_LIGHTS: u32 = 16u;\n  const MAX_LIGHTS_PER_TILE = 4u; // tiles; per axis\nlet a = MAX_LIGHTS;\n";
+        let defines = ShaderDefines::default()
+            .define("MAX_LIGHTS", "64u")
+            .define("MAX_LIGHTS_PER_TILE", 8);
+        assert_eq!(
+            defines.apply(source),
+            "const MAX_LIGHTS: u32 = 64u;\n  const MAX_LIGHTS_PER_TILE = 8; // tiles; per axis\nlet a = MAX_LIGHTS;\n"
+        );
+        assert_eq!(ShaderDefines::default().apply(source), source);
+    }
+
+    #[test]
+    fn pipeline_layout_mismatch() {
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 4, 3]), Some(1));
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 2]), Some(2));
+        assert_eq!(first_mismatch(&[1], &[1, 2]), Some(1));
+        assert_eq!(first_mismatch::<u32>(&[], &[]), None);
+    }
+}
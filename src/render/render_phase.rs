@@ -1,25 +1,87 @@
-use super::renderer::MAX_COLOR_ATTACHMENTS;
-use super::storage::CurrentFrameStorage;
-use super::{storage::ResourceId, wgpu_imports::*};
+use super::renderer::{MAX_BIND_GROUPS, MAX_COLOR_ATTACHMENTS};
+use super::storage::{CurrentFrameStorage, RenderStats, RenderStorage};
+use super::{
+    storage::{BindGroupId, MeshId, PipelineId, TextureId},
+    wgpu_imports::*,
+};
+use crate::mesh::MeshRenderCommand;
 use crate::utils::ConstVec;
+use std::ops::Range;
 
 #[derive(Debug)]
 pub struct ColorAttachment {
-    pub view_id: ResourceId,
+    pub view_id: TextureId,
     pub ops: Operations<Color>,
 }
 
 #[derive(Debug)]
 pub struct DepthStencil {
-    pub view_id: ResourceId,
+    pub view_id: TextureId,
+    /// `None` makes the depth aspect read only
     pub depth_ops: Option<Operations<f32>>,
     pub stencil_ops: Option<Operations<u32>>,
 }
 
+/// How a phase uses the depth attachment it shares with other phases.
+/// In a deferred frame the geometry phase clears depth, forward phases
+/// with opaque objects load it and keep writing, skybox and transparent
+/// phases only test against it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthUsage {
+    /// Clears depth to the value and writes it. Only the first
+    /// phase rendering into the attachment should clear it.
+    Clear(f32),
+    /// Tests against and writes into the depth of the earlier phases
+    Load,
+    /// Tests against the depth of the earlier phases without writing it.
+    /// Pipelines of the phase need `depth_write_enabled: false`. Phase is
+    /// ordered after the writers by [`super::phase_graph::sort_phases`].
+    ReadOnly,
+}
+
+impl DepthStencil {
+    /// Depth attachment without stencil operations. Written depth is stored,
+    /// [`RenderPhase::set_depth_store_op`] can discard it in the last phase using it.
+    pub fn new(view_id: TextureId, usage: DepthUsage) -> Self {
+        let depth_ops = match usage {
+            DepthUsage::Clear(value) => Some(LoadOp::Clear(value)),
+            DepthUsage::Load => Some(LoadOp::Load),
+            DepthUsage::ReadOnly => None,
+        }
+        .map(|load| Operations {
+            load,
+            store: StoreOp::Store,
+        });
+        Self {
+            view_id,
+            depth_ops,
+            stencil_ops: None,
+        }
+    }
+
+    pub fn depth_usage(&self) -> DepthUsage {
+        match self.depth_ops.map(|ops| ops.load) {
+            Some(LoadOp::Clear(value)) => DepthUsage::Clear(value),
+            Some(LoadOp::Load) => DepthUsage::Load,
+            None => DepthUsage::ReadOnly,
+        }
+    }
+}
+
+/// Attachments of one render pass. Phases do not own commands or encoders:
+/// passes are started on the caller encoder with [`RenderPhase::render_pass`],
+/// so any other encoder work (copies, compute dispatches, readbacks) can be
+/// recorded between phases at the point it is needed.
+/// [`crate::render::render_system::RenderSystem`] keeps such work as custom
+/// phases in order with the render phases.
 #[derive(Debug, Default)]
 pub struct RenderPhase {
     color_attachments: ConstVec<MAX_COLOR_ATTACHMENTS, ColorAttachment>,
     depth_stencil: Option<DepthStencil>,
+    scissor_rect: Option<[u32; 4]>,
+    viewport: Option<[f32; 6]>,
+    reads: Vec<TextureId>,
+    sort_commands: bool,
 }
 
 impl RenderPhase {
@@ -30,6 +92,157 @@ impl RenderPhase {
         Self {
             color_attachments,
             depth_stencil,
+            scissor_rect: None,
+            viewport: None,
+            reads: Vec::new(),
+            sort_commands: false,
+        }
+    }
+
+    pub fn builder() -> RenderPhaseBuilder {
+        RenderPhaseBuilder::default()
+    }
+
+    /// Sets clear color of the color attachment at `index`
+    pub fn set_clear_color(&mut self, index: usize, color: Color) {
+        self.set_load_op(index, LoadOp::Clear(color));
+    }
+
+    /// Sets load operation of the color attachment at `index`
+    pub fn set_load_op(&mut self, index: usize, load: LoadOp<Color>) {
+        self.color_attachments.as_mut_slice()[index].ops.load = load;
+    }
+
+    /// Sets store operation of the color attachment at `index`.
+    /// [`StoreOp::Discard`] saves bandwidth for attachments not read afterwards.
+    pub fn set_store_op(&mut self, index: usize, store: StoreOp) {
+        self.color_attachments.as_mut_slice()[index].ops.store = store;
+    }
+
+    /// Sets store operation of the depth aspect. Does nothing
+    /// if the phase has no depth attachment or depth operations.
+    pub fn set_depth_store_op(&mut self, store: StoreOp) {
+        if let Some(ops) = self
+            .depth_stencil
+            .as_mut()
+            .and_then(|ds| ds.depth_ops.as_mut())
+        {
+            ops.store = store;
+        }
+    }
+
+    /// Sets store operation of the stencil aspect. Does nothing
+    /// if the phase has no depth attachment or stencil operations.
+    pub fn set_stencil_store_op(&mut self, store: StoreOp) {
+        if let Some(ops) = self
+            .depth_stencil
+            .as_mut()
+            .and_then(|ds| ds.stencil_ops.as_mut())
+        {
+            ops.store = store;
+        }
+    }
+
+    /// Sets textures sampled by the commands of the phase.
+    /// Used by [`super::phase_graph::sort_phases`] to order phases.
+    pub fn set_reads(&mut self, reads: Vec<TextureId>) {
+        self.reads = reads;
+    }
+
+    /// Sets reads to the textures referenced by `bind_groups`, see
+    /// [`RenderStorage::bind_group_dependencies`]. Bind groups without
+    /// recorded dependencies are skipped.
+    pub fn set_reads_from_bind_groups(
+        &mut self,
+        storage: &RenderStorage,
+        bind_groups: &[BindGroupId],
+    ) {
+        self.reads = bind_groups
+            .iter()
+            .filter_map(|id| storage.bind_group_dependencies(*id))
+            .flat_map(|dependencies| dependencies.textures.iter().copied())
+            .collect();
+    }
+
+    /// Textures sampled by the commands of the phase and
+    /// the read only depth attachment
+    pub fn reads(&self) -> impl Iterator<Item = TextureId> + '_ {
+        let depth = self
+            .depth_stencil
+            .as_ref()
+            .filter(|ds| ds.depth_ops.is_none() && ds.stencil_ops.is_none())
+            .map(|ds| ds.view_id);
+        self.reads.iter().copied().chain(depth)
+    }
+
+    /// Attachments the phase renders into
+    pub fn writes(&self) -> impl Iterator<Item = TextureId> + '_ {
+        let depth = self
+            .depth_stencil
+            .as_ref()
+            .filter(|ds| ds.depth_ops.is_some() || ds.stencil_ops.is_some())
+            .map(|ds| ds.view_id);
+        self.color_attachments
+            .iter()
+            .map(|attachment| attachment.view_id)
+            .chain(depth)
+    }
+
+    /// Sets scissor rectangle as `[x, y, width, height]` applied at the
+    /// beginning of the pass. Rectangle must be inside of the attachments.
+    pub fn set_scissor_rect(&mut self, scissor_rect: Option<[u32; 4]>) {
+        self.scissor_rect = scissor_rect;
+    }
+
+    /// Sets viewport as `[x, y, width, height, min_depth, max_depth]`
+    /// applied at the beginning of the pass.
+    pub fn set_viewport(&mut self, viewport: Option<[f32; 6]>) {
+        self.viewport = viewport;
+    }
+
+    /// Sorts commands by pipeline and bind groups before execution, so
+    /// consecutive commands share state and redundant binds are skipped.
+    /// Commands with the same state keep submission order. Disabled by
+    /// default, as transparent objects need to be drawn in the submitted order.
+    pub fn sort_commands(&mut self, sort: bool) {
+        self.sort_commands = sort;
+    }
+
+    /// Format of the depth stencil attachment. `None` if the phase has no
+    /// depth attachment or it is the window view, as its format is unknown.
+    pub fn depth_format(&self, storage: &CurrentFrameStorage) -> Option<TextureFormat> {
+        self.depth_stencil
+            .as_ref()
+            .filter(|depth_stencil| depth_stencil.view_id != TextureId::WINDOW_VIEW_ID)
+            .map(|depth_stencil| storage.get_texture(depth_stencil.view_id).texture.format())
+    }
+
+    /// Usage of the depth attachment. `None` if the phase has no depth attachment.
+    pub fn depth_usage(&self) -> Option<DepthUsage> {
+        self.depth_stencil.as_ref().map(DepthStencil::depth_usage)
+    }
+
+    /// Panics with the pipeline name if any of the pipelines has
+    /// different depth format than the depth attachment of the phase,
+    /// or writes depth while the attachment is read only.
+    /// Without this check the mismatch is a wgpu validation error
+    /// at the draw call. Depth format of the window view is not checked.
+    pub fn assert_pipelines(
+        &self,
+        storage: &CurrentFrameStorage,
+        pipelines: impl IntoIterator<Item = PipelineId>,
+    ) {
+        let depth_format = self.depth_format(storage);
+        let check_format = depth_format.is_some() || self.depth_stencil.is_none();
+        let read_only = self.depth_usage() == Some(DepthUsage::ReadOnly);
+        for pipeline in pipelines {
+            let pipeline = storage.get_gpu_pipeline(pipeline);
+            if check_format {
+                pipeline.assert_depth_format(depth_format);
+            }
+            if read_only {
+                pipeline.assert_depth_read_only();
+            }
         }
     }
 
@@ -38,12 +251,402 @@ impl RenderPhase {
         encoder: &'a mut CommandEncoder,
         current_frame_storage: &'a CurrentFrameStorage,
     ) -> RenderPass<'a> {
-        encoder.begin_render_pass(&RenderPassDescriptor {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: None,
             color_attachments: &self.color_attachments(current_frame_storage),
             depth_stencil_attachment: self.depth_stencil_attachment(current_frame_storage),
             ..Default::default()
-        })
+        });
+        // Commands with their own scissor or viewport override these
+        // until the next command without them
+        if let Some([x, y, width, height]) = self.scissor_rect {
+            render_pass.set_scissor_rect(x, y, width, height);
+        }
+        if let Some([x, y, width, height, min_depth, max_depth]) = self.viewport {
+            render_pass.set_viewport(x, y, width, height, min_depth, max_depth);
+        }
+        render_pass
+    }
+}
+
+/// Chained construction of the [`RenderPhase`]:
+/// ```ignore
+/// let phase = RenderPhase::builder()
+///     .color(TextureId::WINDOW_VIEW_ID)
+///     .clear(Color::BLACK)
+///     .depth(depth_texture_id)
+///     .clear(1.0)
+///     .build();
+/// ```
+/// All attachments store their content, store operations can be
+/// changed on the built phase (e.g. [`RenderPhase::set_store_op`]).
+#[derive(Debug, Default)]
+pub struct RenderPhaseBuilder {
+    color_attachments: ConstVec<MAX_COLOR_ATTACHMENTS, ColorAttachment>,
+    depth_stencil: Option<DepthStencil>,
+}
+
+impl RenderPhaseBuilder {
+    /// Adds color attachment. Load operation is chosen by the next call.
+    pub fn color(self, view_id: TextureId) -> ColorAttachmentBuilder {
+        ColorAttachmentBuilder {
+            phase: self,
+            view_id,
+        }
+    }
+
+    /// Adds color attachment keeping its content
+    pub fn color_load(self, view_id: TextureId) -> Self {
+        self.color(view_id).load()
+    }
+
+    /// Sets depth attachment. Usage is chosen by the next call.
+    pub fn depth(self, view_id: TextureId) -> DepthAttachmentBuilder {
+        DepthAttachmentBuilder {
+            phase: self,
+            view_id,
+        }
+    }
+
+    /// Sets depth attachment testing against and writing into the existing depth
+    pub fn depth_load(self, view_id: TextureId) -> Self {
+        self.depth(view_id).load()
+    }
+
+    /// Sets depth attachment only testing against the existing depth
+    pub fn depth_read_only(self, view_id: TextureId) -> Self {
+        self.depth(view_id).read_only()
+    }
+
+    pub fn build(self) -> RenderPhase {
+        RenderPhase::new(self.color_attachments, self.depth_stencil)
+    }
+
+    fn with_color(mut self, view_id: TextureId, load: LoadOp<Color>) -> Self {
+        self.color_attachments.push(ColorAttachment {
+            view_id,
+            ops: Operations {
+                load,
+                store: StoreOp::Store,
+            },
+        });
+        self
+    }
+
+    fn with_depth(mut self, view_id: TextureId, usage: DepthUsage) -> Self {
+        self.depth_stencil = Some(DepthStencil::new(view_id, usage));
+        self
+    }
+}
+
+/// Color attachment waiting for its load operation
+#[derive(Debug)]
+pub struct ColorAttachmentBuilder {
+    phase: RenderPhaseBuilder,
+    view_id: TextureId,
+}
+
+impl ColorAttachmentBuilder {
+    pub fn clear(self, color: Color) -> RenderPhaseBuilder {
+        self.phase.with_color(self.view_id, LoadOp::Clear(color))
+    }
+
+    pub fn load(self) -> RenderPhaseBuilder {
+        self.phase.with_color(self.view_id, LoadOp::Load)
+    }
+}
+
+/// Depth attachment waiting for its [`DepthUsage`]
+#[derive(Debug)]
+pub struct DepthAttachmentBuilder {
+    phase: RenderPhaseBuilder,
+    view_id: TextureId,
+}
+
+impl DepthAttachmentBuilder {
+    pub fn clear(self, depth: f32) -> RenderPhaseBuilder {
+        self.usage(DepthUsage::Clear(depth))
+    }
+
+    pub fn load(self) -> RenderPhaseBuilder {
+        self.usage(DepthUsage::Load)
+    }
+
+    pub fn read_only(self) -> RenderPhaseBuilder {
+        self.usage(DepthUsage::ReadOnly)
+    }
+
+    pub fn usage(self, usage: DepthUsage) -> RenderPhaseBuilder {
+        self.phase.with_depth(self.view_id, usage)
+    }
+}
+
+/// Bind group with the index of the group it is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindGroupMeta {
+    pub index: u32,
+    pub bind_group_id: BindGroupId,
+    /// Offset for bind groups with dynamic offset (e.g. `DynamicTransformBindGroup`)
+    pub dynamic_offset: Option<u32>,
+}
+
+impl BindGroupMeta {
+    pub const fn new(index: u32, bind_group_id: BindGroupId) -> Self {
+        Self {
+            index,
+            bind_group_id,
+            dynamic_offset: None,
+        }
+    }
+
+    pub const fn with_dynamic_offset(index: u32, bind_group_id: BindGroupId, offset: u32) -> Self {
+        Self {
+            index,
+            bind_group_id,
+            dynamic_offset: Some(offset),
+        }
+    }
+
+    /// Offsets to pass to `set_bind_group`
+    pub fn dynamic_offsets(&self) -> &[u32] {
+        self.dynamic_offset.as_slice()
+    }
+}
+
+/// Part of the render target drawn from the point of view of one camera
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub camera_bind_group: BindGroupId,
+    /// Viewport rectangle in pixels as `[x, y, width, height]`
+    pub rect: [f32; 4],
+}
+
+/// Mesh buffer slice bound to the render pass. `None` slice is the whole buffer.
+type MeshSlice = (MeshId, Option<Range<u64>>);
+
+/// Pipeline, bind groups and mesh buffers set on the render pass by the
+/// previous commands. Used to skip binding the same state again.
+#[derive(Debug, Default, Clone)]
+pub struct PassState {
+    pipeline_id: Option<PipelineId>,
+    bind_groups: [Option<BindGroupMeta>; MAX_BIND_GROUPS],
+    vertex_buffer: Option<MeshSlice>,
+    index_buffer: Option<MeshSlice>,
+    default_scissor_rect: Option<[u32; 4]>,
+    default_viewport: Option<[f32; 6]>,
+    /// Scissor rect of the previous command. `None` if the default is set.
+    scissor_rect: Option<[u32; 4]>,
+    /// Viewport of the previous command. `None` if the default is set.
+    viewport: Option<[f32; 6]>,
+    /// Stats of the executed commands
+    stats: RenderStats,
+}
+
+impl PassState {
+    /// Returns `true` if the pipeline needs to be set. Bind groups from
+    /// `invalidate_from` index onwards are forgotten, as they were bound
+    /// with a different layout.
+    pub fn set_pipeline(&mut self, pipeline_id: PipelineId, invalidate_from: usize) -> bool {
+        if self.pipeline_id == Some(pipeline_id) {
+            return false;
+        }
+        self.pipeline_id = Some(pipeline_id);
+        for bg in self.bind_groups.iter_mut().skip(invalidate_from) {
+            *bg = None;
+        }
+        true
+    }
+
+    /// Returns `true` if the bind group needs to be set
+    pub fn set_bind_group(&mut self, bind_group: BindGroupMeta) -> bool {
+        let slot = &mut self.bind_groups[bind_group.index as usize];
+        if *slot == Some(bind_group) {
+            return false;
+        }
+        *slot = Some(bind_group);
+        true
+    }
+
+    /// Returns `true` if the vertex buffer of the mesh needs to be set.
+    /// Vertex buffers are not affected by pipeline changes.
+    pub fn set_vertex_buffer(&mut self, mesh_id: MeshId, slice: Option<&Range<u64>>) -> bool {
+        Self::set_mesh_slice(&mut self.vertex_buffer, mesh_id, slice)
+    }
+
+    /// Returns `true` if the index buffer of the mesh needs to be set
+    pub fn set_index_buffer(&mut self, mesh_id: MeshId, slice: Option<&Range<u64>>) -> bool {
+        Self::set_mesh_slice(&mut self.index_buffer, mesh_id, slice)
+    }
+
+    fn set_mesh_slice(
+        bound: &mut Option<MeshSlice>,
+        mesh_id: MeshId,
+        slice: Option<&Range<u64>>,
+    ) -> bool {
+        let new = (mesh_id, slice.cloned());
+        if bound.as_ref() == Some(&new) {
+            return false;
+        }
+        *bound = Some(new);
+        true
+    }
+
+    /// Sets scissor rect and viewport restored after commands with their own ones
+    pub fn set_default_rects(
+        &mut self,
+        scissor_rect: Option<[u32; 4]>,
+        viewport: Option<[f32; 6]>,
+    ) {
+        self.default_scissor_rect = scissor_rect;
+        self.default_viewport = viewport;
+    }
+
+    /// Returns scissor rect which needs to be set for the command with the
+    /// `scissor_rect`. Command without one restores the default.
+    pub fn set_scissor_rect(&mut self, scissor_rect: Option<[u32; 4]>) -> Option<[u32; 4]> {
+        Self::set_rect(
+            &mut self.scissor_rect,
+            scissor_rect,
+            self.default_scissor_rect,
+        )
+    }
+
+    /// Returns viewport which needs to be set for the command with the
+    /// `viewport`. Command without one restores the default.
+    pub fn set_viewport(&mut self, viewport: Option<[f32; 6]>) -> Option<[f32; 6]> {
+        Self::set_rect(&mut self.viewport, viewport, self.default_viewport)
+    }
+
+    fn set_rect<T: Copy + PartialEq>(
+        current: &mut Option<T>,
+        rect: Option<T>,
+        default: Option<T>,
+    ) -> Option<T> {
+        if *current == rect {
+            return None;
+        }
+        *current = rect;
+        rect.or(default)
+    }
+
+    /// Pipeline set by the previous command
+    pub fn pipeline_id(&self) -> Option<PipelineId> {
+        self.pipeline_id
+    }
+
+    /// Adds `stats` of the executed command
+    pub fn record_stats(&mut self, stats: RenderStats) {
+        self.stats += stats;
+    }
+
+    /// Stats of all commands executed with this state
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+}
+
+impl RenderPhase {
+    /// Commands in the execution order
+    fn ordered_commands<'c>(
+        &self,
+        commands: &'c [MeshRenderCommand],
+    ) -> Vec<&'c MeshRenderCommand> {
+        let mut ordered: Vec<_> = commands.iter().collect();
+        if self.sort_commands {
+            let bind_groups = |c: &'c MeshRenderCommand| {
+                c.bind_groups
+                    .iter()
+                    .map(|bg| (bg.index, bg.bind_group_id, bg.dynamic_offset))
+            };
+            ordered.sort_by(|a, b| {
+                a.pipeline_id
+                    .cmp(&b.pipeline_id)
+                    .then_with(|| bind_groups(a).cmp(bind_groups(b)))
+                    .then_with(|| a.mesh_id.cmp(&b.mesh_id))
+            });
+        }
+        ordered
+    }
+
+    /// State of the new pass. Commands overriding scissor rect or viewport
+    /// restore the ones of the phase, or the whole attachment if the phase
+    /// has none. Size of the window view is unknown, so phases rendering only
+    /// into it need their own scissor rect and viewport for this.
+    fn pass_state(&self, storage: &CurrentFrameStorage) -> PassState {
+        let size = self
+            .color_attachments
+            .iter()
+            .map(|attachment| attachment.view_id)
+            .chain(self.depth_stencil.as_ref().map(|ds| ds.view_id))
+            .find(|view_id| *view_id != TextureId::WINDOW_VIEW_ID)
+            .map(|view_id| storage.get_texture(view_id).texture.size());
+        let mut state = PassState::default();
+        state.set_default_rects(
+            self.scissor_rect
+                .or(size.map(|size| [0, 0, size.width, size.height])),
+            self.viewport
+                .or(size.map(|size| [0.0, 0.0, size.width as f32, size.height as f32, 0.0, 1.0])),
+        );
+        state
+    }
+
+    /// Begins render pass and executes all commands. Pipelines and
+    /// bind groups shared by consecutive commands are set once.
+    /// Debug builds check pipelines with [`RenderPhase::assert_pipelines`].
+    pub fn execute(
+        &self,
+        encoder: &mut CommandEncoder,
+        current_frame_storage: &CurrentFrameStorage,
+        commands: &[MeshRenderCommand],
+    ) {
+        if cfg!(debug_assertions) {
+            self.assert_pipelines(
+                current_frame_storage,
+                commands.iter().map(|c| c.pipeline_id),
+            );
+        }
+        let mut render_pass = self.render_pass(encoder, current_frame_storage);
+        let mut state = self.pass_state(current_frame_storage);
+        for command in self.ordered_commands(commands) {
+            command.execute_with_state(&mut render_pass, current_frame_storage, &mut state);
+        }
+        current_frame_storage.record_stats(state.stats());
+    }
+
+    /// Begins render pass and executes all commands once for every viewport.
+    /// Bind group with the `camera_slot` group index of every command is
+    /// replaced with the viewport camera bind group.
+    pub fn execute_for_viewports(
+        &self,
+        encoder: &mut CommandEncoder,
+        current_frame_storage: &CurrentFrameStorage,
+        commands: &[MeshRenderCommand],
+        camera_slot: u32,
+        viewports: &[Viewport],
+    ) {
+        if cfg!(debug_assertions) {
+            self.assert_pipelines(
+                current_frame_storage,
+                commands.iter().map(|c| c.pipeline_id),
+            );
+        }
+        let mut render_pass = self.render_pass(encoder, current_frame_storage);
+        let mut state = self.pass_state(current_frame_storage);
+        let commands = self.ordered_commands(commands);
+        for viewport in viewports {
+            for command in commands.iter() {
+                let mut command = (*command).clone();
+                for bg in command.bind_groups.iter_mut() {
+                    if bg.index == camera_slot {
+                        bg.bind_group_id = viewport.camera_bind_group;
+                    }
+                }
+                let [x, y, width, height] = viewport.rect;
+                command.viewport = Some([x, y, width, height, 0.0, 1.0]);
+                command.execute_with_state(&mut render_pass, current_frame_storage, &mut state);
+            }
+        }
+        current_frame_storage.record_stats(state.stats());
     }
 }
 
@@ -70,12 +673,324 @@ impl RenderPhase {
         storage: &'a CurrentFrameStorage,
     ) -> Option<RenderPassDepthStencilAttachment<'a>> {
         self.depth_stencil.as_ref().map(|depth_stencil| {
-            let view = storage.get_view(depth_stencil.view_id);
+            // Operations for the aspect texture does not have are not allowed,
+            // so they are dropped. This way the same phase description can be used
+            // with depth only and depth stencil textures.
+            let format = self.depth_format(storage);
             RenderPassDepthStencilAttachment {
-                view,
-                depth_ops: depth_stencil.depth_ops,
-                stencil_ops: depth_stencil.stencil_ops,
+                view: storage.get_view(depth_stencil.view_id),
+                depth_ops: depth_stencil
+                    .depth_ops
+                    .filter(|_| format.is_none_or(|f| f.has_depth_aspect())),
+                stencil_ops: depth_stencil
+                    .stencil_ops
+                    .filter(|_| format.is_none_or(|f| f.has_stencil_aspect())),
             }
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::const_vec;
+    use crate::render::renderer::Renderer;
+    use crate::render::storage::ResourceId;
+
+    fn id<T>(index: usize) -> ResourceId<T> {
+        ResourceId::from_index(index)
+    }
+
+    #[test]
+    fn render_phase_store_ops() {
+        let ops = Operations {
+            load: LoadOp::Load,
+            store: StoreOp::Store,
+        };
+        let mut phase = RenderPhase::new(
+            const_vec![ColorAttachment {
+                view_id: TextureId::WINDOW_VIEW_ID,
+                ops,
+            }],
+            Some(DepthStencil {
+                view_id: TextureId::WINDOW_VIEW_ID,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+        );
+        phase.set_store_op(0, StoreOp::Discard);
+        phase.set_depth_store_op(StoreOp::Discard);
+        phase.set_stencil_store_op(StoreOp::Discard);
+
+        assert_eq!(phase.color_attachments[0].ops.store, StoreOp::Discard);
+        let depth_stencil = phase.depth_stencil.unwrap();
+        assert_eq!(depth_stencil.depth_ops.unwrap().store, StoreOp::Discard);
+        assert!(depth_stencil.stencil_ops.is_none());
+    }
+
+    #[test]
+    fn render_phase_depth_usage() {
+        let view_id = ResourceId::from_index(0);
+        for usage in [
+            DepthUsage::Clear(1.0),
+            DepthUsage::Load,
+            DepthUsage::ReadOnly,
+        ] {
+            let phase =
+                RenderPhase::new(ConstVec::default(), Some(DepthStencil::new(view_id, usage)));
+            assert_eq!(phase.depth_usage(), Some(usage));
+            // Read only depth orders the phase after the depth writers
+            assert_eq!(
+                phase.reads().any(|id| id == view_id),
+                usage == DepthUsage::ReadOnly
+            );
+            assert_eq!(
+                phase.writes().any(|id| id == view_id),
+                usage != DepthUsage::ReadOnly
+            );
+        }
+        assert_eq!(RenderPhase::default().depth_usage(), None);
+    }
+
+    #[test]
+    fn render_phase_window_depth_format() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let storage = RenderStorage::default();
+        let current_frame = renderer.current_frame().unwrap();
+        let current_frame_storage = CurrentFrameStorage {
+            storage: &storage,
+            current_frame_view: current_frame.view(),
+        };
+        let phase = RenderPhase::builder()
+            .depth(TextureId::WINDOW_VIEW_ID)
+            .clear(1.0)
+            .build();
+        assert_eq!(phase.depth_format(&current_frame_storage), None);
+        phase.assert_pipelines(&current_frame_storage, []);
+    }
+
+    #[test]
+    fn render_phase_builder() {
+        let color = id(0);
+        let phase = RenderPhase::builder()
+            .color(TextureId::WINDOW_VIEW_ID)
+            .clear(Color::BLACK)
+            .color_load(color)
+            .depth(id(1))
+            .clear(1.0)
+            .build();
+        let attachments = phase.color_attachments.as_slice();
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].view_id, TextureId::WINDOW_VIEW_ID);
+        assert_eq!(attachments[0].ops.load, LoadOp::Clear(Color::BLACK));
+        assert_eq!(attachments[1].view_id, color);
+        assert_eq!(attachments[1].ops.load, LoadOp::Load);
+        assert!(attachments.iter().all(|a| a.ops.store == StoreOp::Store));
+        assert_eq!(phase.depth_usage(), Some(DepthUsage::Clear(1.0)));
+
+        let phase = RenderPhase::builder()
+            .color_load(color)
+            .depth_read_only(id(1))
+            .build();
+        assert_eq!(phase.depth_usage(), Some(DepthUsage::ReadOnly));
+        assert_eq!(RenderPhase::builder().build().depth_usage(), None);
+    }
+
+    #[test]
+    fn render_phase_sort_commands() {
+        let command = |pipeline: usize, bind_group: usize| MeshRenderCommand {
+            pipeline_id: id(pipeline),
+            mesh_id: id(0),
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect: None,
+            viewport: None,
+            bind_groups: const_vec![BindGroupMeta::new(0, id(bind_group))],
+        };
+        let commands = [command(1, 0), command(0, 1), command(1, 0), command(0, 0)];
+        let order = |phase: &RenderPhase| -> Vec<_> {
+            phase
+                .ordered_commands(&commands)
+                .into_iter()
+                .map(|c| (c.pipeline_id, c.bind_groups[0].bind_group_id))
+                .collect()
+        };
+
+        let mut phase = RenderPhase::default();
+        assert_eq!(
+            order(&phase),
+            [
+                (id(1), id(0)),
+                (id(0), id(1)),
+                (id(1), id(0)),
+                (id(0), id(0))
+            ]
+        );
+        phase.sort_commands(true);
+        assert_eq!(
+            order(&phase),
+            [
+                (id(0), id(0)),
+                (id(0), id(1)),
+                (id(1), id(0)),
+                (id(1), id(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn pass_state_skips_redundant_binds() {
+        let mut state = PassState::default();
+        assert!(state.set_pipeline(id(0), 0));
+        assert!(!state.set_pipeline(id(0), 0));
+        assert!(state.set_bind_group(BindGroupMeta::new(0, id(1))));
+        assert!(state.set_bind_group(BindGroupMeta::new(1, id(2))));
+        assert!(!state.set_bind_group(BindGroupMeta::new(1, id(2))));
+        assert!(state.set_bind_group(BindGroupMeta::with_dynamic_offset(1, id(2), 256)));
+
+        // Layout of the group 1 differs in the new pipeline
+        assert!(state.set_pipeline(id(3), 1));
+        assert!(!state.set_bind_group(BindGroupMeta::new(0, id(1))));
+        assert!(state.set_bind_group(BindGroupMeta::with_dynamic_offset(1, id(2), 256)));
+
+        assert!(state.set_vertex_buffer(id(4), None));
+        assert!(!state.set_vertex_buffer(id(4), None));
+        assert!(state.set_vertex_buffer(id(4), Some(&(0..64))));
+        assert!(!state.set_vertex_buffer(id(4), Some(&(0..64))));
+        assert!(state.set_index_buffer(id(4), None));
+        assert!(state.set_index_buffer(id(5), None));
+        // Mesh buffers stay bound after the pipeline change
+        assert!(state.set_pipeline(id(0), 0));
+        assert!(!state.set_vertex_buffer(id(4), Some(&(0..64))));
+    }
+
+    #[test]
+    fn pass_state_restores_default_rects() {
+        let mut state = PassState::default();
+        state.set_default_rects(Some([0, 0, 8, 8]), None);
+        assert_eq!(state.set_scissor_rect(None), None);
+        assert_eq!(
+            state.set_scissor_rect(Some([0, 0, 4, 4])),
+            Some([0, 0, 4, 4])
+        );
+        assert_eq!(state.set_scissor_rect(Some([0, 0, 4, 4])), None);
+        // Override of the previous command is replaced by the default
+        assert_eq!(state.set_scissor_rect(None), Some([0, 0, 8, 8]));
+        assert_eq!(state.set_scissor_rect(None), None);
+
+        let viewport = [0.0, 0.0, 4.0, 4.0, 0.0, 1.0];
+        assert_eq!(state.set_viewport(Some(viewport)), Some(viewport));
+        // Without default there is nothing to restore
+        assert_eq!(state.set_viewport(None), None);
+        assert_eq!(state.set_viewport(Some(viewport)), Some(viewport));
+    }
+
+    #[test]
+    fn render_phase_restores_scissor_rect() {
+        use crate::mesh::GpuMesh;
+        use crate::render::prelude::*;
+        use crate::texture::EmptyTexture;
+
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(8, 8)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+        let target_id = storage.insert_texture(
+            EmptyTexture {
+                dimensions: None,
+                format: TextureFormat::Rgba8Unorm,
+                filtered: false,
+                compare: None,
+            }
+            .build(&renderer),
+        );
+        let shader = renderer
+            .device()
+            .create_shader_module(ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(
+                    r#"
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+  let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4<f32>(uv * 2.0 - 1.0, 0.5, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+  return vec4<f32>(1.0);
+}
+"#
+                    .into(),
+                ),
+            });
+        let pipeline_id = storage.insert_pipeline(
+            PipelineBuilder {
+                shader_path: "fullscreen",
+                label: None,
+                layout_descriptor: None,
+                vertex_layouts: &[],
+                vertex_entry_point: "vs_main",
+                color_targets: Some(&[Some(TextureFormat::Rgba8Unorm.into())]),
+                fragment_entry_point: "fs_main",
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+            }
+            .build_with_module(&renderer, &shader),
+        );
+        let mesh_id = storage.insert_mesh(GpuMesh {
+            vertex_buffer: renderer.device().create_buffer(&BufferDescriptor {
+                label: None,
+                size: 4,
+                usage: BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            }),
+            index_buffer: None,
+            num_elements: 3,
+        });
+        let command = |scissor_rect| MeshRenderCommand {
+            pipeline_id,
+            mesh_id,
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect,
+            viewport: None,
+            bind_groups: ConstVec::default(),
+        };
+        let phase = RenderPhase::builder()
+            .color(target_id)
+            .clear(Color::BLACK)
+            .build();
+
+        let current_frame = renderer.current_frame().unwrap();
+        let current_frame_storage = CurrentFrameStorage {
+            storage: &storage,
+            current_frame_view: current_frame.view(),
+        };
+        let mut encoder = renderer.create_encoder();
+        phase.execute(
+            &mut encoder,
+            &current_frame_storage,
+            &[command(Some([0, 0, 4, 8])), command(None)],
+        );
+        renderer.submit(std::iter::once(encoder.finish()));
+
+        // Second command draws into the whole target again
+        let target = &storage.get_texture(target_id).texture;
+        assert_eq!(renderer.read_pixel(target, 1, 4), Some(u32::MAX));
+        assert_eq!(renderer.read_pixel(target, 6, 4), Some(u32::MAX));
+
+        // Stats of the whole phase are recorded at once
+        let stats = storage.frame_stats();
+        assert_eq!(stats.draw_calls, 2);
+        assert_eq!(stats.triangles, 2);
+        assert_eq!(stats.pipeline_switches, 1);
+    }
+}
@@ -0,0 +1,368 @@
+use super::prelude::*;
+use crate::mesh::MeshRenderCommand;
+
+/// Index of the phase in the [`RenderSystem`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhaseId(usize);
+
+/// Arbitrary encoder work (copies, compute dispatches, readbacks)
+/// recorded at its place between the render phases
+pub type CustomPhase = Box<dyn FnMut(&mut CommandEncoder, &CurrentFrameStorage)>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderSystemError {
+    #[error("Could not get current frame: {0}")]
+    Surface(#[from] SurfaceError),
+    #[error("Could not order phases: {0}")]
+    PhaseGraph(#[from] PhaseGraphError),
+}
+
+enum Phase {
+    Render {
+        phase: Box<RenderPhase>,
+        commands: Vec<MeshRenderCommand>,
+    },
+    Custom {
+        name: String,
+        record: CustomPhase,
+    },
+}
+
+impl std::fmt::Debug for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Render { phase, commands } => f
+                .debug_struct("Render")
+                .field("phase", phase)
+                .field("commands", &commands.len())
+                .finish(),
+            Self::Custom { name, .. } => f.debug_struct("Custom").field("name", name).finish(),
+        }
+    }
+}
+
+/// Phases of the frame. Render phases are ordered by [`sort_phases`].
+/// Custom phases do not declare resources they use, so they stay where
+/// they were added and render phases are only reordered between them.
+#[derive(Debug, Default)]
+pub struct RenderSystem {
+    phases: Vec<Phase>,
+    /// Execution order, sorted again after a phase is added
+    order: Option<Vec<usize>>,
+}
+
+impl RenderSystem {
+    /// Adds render phase without commands, see [`RenderSystem::set_commands`]
+    pub fn add_phase(&mut self, phase: RenderPhase) -> PhaseId {
+        self.phases.push(Phase::Render {
+            phase: Box::new(phase),
+            commands: vec![],
+        });
+        self.order = None;
+        PhaseId(self.phases.len() - 1)
+    }
+
+    /// Adds phase recording custom work into the frame encoder
+    pub fn add_custom_phase(&mut self, name: impl Into<String>, record: CustomPhase) -> PhaseId {
+        self.phases.push(Phase::Custom {
+            name: name.into(),
+            record,
+        });
+        self.order = None;
+        PhaseId(self.phases.len() - 1)
+    }
+
+    /// Sets commands executed by the render phase. Panics if
+    /// the phase was added with [`RenderSystem::add_custom_phase`].
+    pub fn set_commands(&mut self, phase_id: PhaseId, commands: Vec<MeshRenderCommand>) {
+        match &mut self.phases[phase_id.0] {
+            Phase::Render { commands: c, .. } => *c = commands,
+            Phase::Custom { name, .. } => panic!("Phase {name:?} is a custom phase"),
+        }
+    }
+
+    /// Sorts phases if the order is not known yet. Indices in the
+    /// returned [`PhaseGraphError::Cycle`] are indices of [`PhaseId`]s.
+    fn sort(&mut self) -> Result<&[usize], PhaseGraphError> {
+        if self.order.is_none() {
+            let mut order = Vec::with_capacity(self.phases.len());
+            let mut start = 0;
+            for segment in self
+                .phases
+                .split(|phase| matches!(phase, Phase::Custom { .. }))
+            {
+                let render_phases: Vec<&RenderPhase> = segment
+                    .iter()
+                    .filter_map(|phase| match phase {
+                        Phase::Render { phase, .. } => Some(phase.as_ref()),
+                        Phase::Custom { .. } => None,
+                    })
+                    .collect();
+                let segment_order = sort_phases(&render_phases).map_err(|e| match e {
+                    PhaseGraphError::Cycle(c) => {
+                        PhaseGraphError::Cycle(c.into_iter().map(|i| start + i).collect())
+                    }
+                })?;
+                order.extend(segment_order.into_iter().map(|i| start + i));
+                // custom phase separating this segment from the next one
+                start += segment.len();
+                if start < self.phases.len() {
+                    order.push(start);
+                    start += 1;
+                }
+            }
+            self.order = Some(order);
+        }
+        Ok(self.order.as_deref().unwrap())
+    }
+
+    /// Records all phases into the `encoder` in their execution order
+    pub fn record(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        storage: &CurrentFrameStorage,
+    ) -> Result<(), PhaseGraphError> {
+        self.sort()?;
+        for &i in self.order.as_deref().unwrap() {
+            match &mut self.phases[i] {
+                Phase::Render { phase, commands } => phase.execute(encoder, storage, commands),
+                Phase::Custom { record, .. } => record(encoder, storage),
+            }
+        }
+        Ok(())
+    }
+
+    /// Records all phases into one encoder, submits it and presents
+    /// the frame. Finishes the frame of the `storage` and returns its stats.
+    pub fn run(
+        &mut self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+    ) -> Result<RenderStats, RenderSystemError> {
+        self.sort()?;
+        let current_frame = renderer.current_frame()?;
+        let current_frame_storage = CurrentFrameStorage {
+            storage,
+            current_frame_view: current_frame.view(),
+        };
+        let mut encoder = renderer.create_encoder();
+        self.record(&mut encoder, &current_frame_storage)?;
+        renderer.submit(std::iter::once(encoder.finish()));
+        current_frame.present();
+        Ok(storage.end_frame())
+    }
+
+    /// Same as [`RenderSystem::run`], but every phase is recorded into its
+    /// own encoder with [`encode_parallel`]. Custom phases are recorded on
+    /// the calling thread. Command buffers are submitted in phase order.
+    pub fn run_parallel(
+        &mut self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+    ) -> Result<RenderStats, RenderSystemError> {
+        self.sort()?;
+        let current_frame = renderer.current_frame()?;
+        let current_frame_storage = CurrentFrameStorage {
+            storage,
+            current_frame_view: current_frame.view(),
+        };
+        let order = self.order.as_deref().unwrap();
+
+        let mut buffers: Vec<Option<CommandBuffer>> = order
+            .iter()
+            .map(|&i| match &mut self.phases[i] {
+                Phase::Render { .. } => None,
+                Phase::Custom { record, .. } => {
+                    let mut encoder = renderer.create_encoder();
+                    record(&mut encoder, &current_frame_storage);
+                    Some(encoder.finish())
+                }
+            })
+            .collect();
+
+        let jobs: Vec<_> = order
+            .iter()
+            .filter_map(|&i| match &self.phases[i] {
+                Phase::Render { phase, commands } => Some(
+                    move |encoder: &mut CommandEncoder, storage: &CurrentFrameStorage| {
+                        phase.execute(encoder, storage, commands)
+                    },
+                ),
+                Phase::Custom { .. } => None,
+            })
+            .collect();
+        let jobs: Vec<&EncodeJob> = jobs.iter().map(|job| job as &EncodeJob).collect();
+        let mut render_buffers =
+            encode_parallel(renderer, &current_frame_storage, &jobs).into_iter();
+        for buffer in buffers.iter_mut().filter(|buffer| buffer.is_none()) {
+            *buffer = render_buffers.next();
+        }
+
+        renderer.submit(buffers.into_iter().flatten());
+        current_frame.present();
+        Ok(storage.end_frame())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::const_vec;
+    use crate::texture::EmptyTexture;
+    use crate::utils::ConstVec;
+    use std::rc::Rc;
+
+    fn clear_phase(view_id: TextureId, color: Color) -> RenderPhase {
+        RenderPhase::new(
+            const_vec![ColorAttachment {
+                view_id,
+                ops: Operations {
+                    load: LoadOp::Clear(color),
+                    store: StoreOp::Store,
+                },
+            }],
+            None,
+        )
+    }
+
+    #[test]
+    fn render_system_custom_phase_order() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+        let target_id = storage.insert_texture(
+            EmptyTexture {
+                dimensions: None,
+                format: TextureFormat::Rgba8Unorm,
+                filtered: false,
+                compare: None,
+            }
+            .build(&renderer),
+        );
+        let size = storage.get_texture(target_id).texture.size();
+        let snapshot = Rc::new(renderer.device().create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+            view_formats: &[],
+        }));
+
+        let mut system = RenderSystem::default();
+        system.add_phase(clear_phase(target_id, Color::RED));
+        let custom_snapshot = snapshot.clone();
+        system.add_custom_phase(
+            "snapshot",
+            Box::new(move |encoder, storage| {
+                encoder.copy_texture_to_texture(
+                    storage.get_texture(target_id).texture.as_image_copy(),
+                    custom_snapshot.as_image_copy(),
+                    size,
+                );
+            }),
+        );
+        system.add_phase(clear_phase(target_id, Color::GREEN));
+        let stats = system.run(&renderer, &mut storage).unwrap();
+        assert_eq!(stats, storage.last_frame_stats());
+
+        // Snapshot is taken between the phases
+        let red = u32::from_ne_bytes([255, 0, 0, 255]);
+        let green = u32::from_ne_bytes([0, 255, 0, 255]);
+        assert_eq!(renderer.read_pixel(&snapshot, 1, 1), Some(red));
+        let target = &storage.get_texture(target_id).texture;
+        assert_eq!(renderer.read_pixel(target, 1, 1), Some(green));
+    }
+
+    #[test]
+    fn render_system_run_parallel_order() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+        let target_id = storage.insert_texture(
+            EmptyTexture {
+                dimensions: None,
+                format: TextureFormat::Rgba8Unorm,
+                filtered: false,
+                compare: None,
+            }
+            .build(&renderer),
+        );
+        let size = storage.get_texture(target_id).texture.size();
+        let snapshot = Rc::new(renderer.device().create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+            view_formats: &[],
+        }));
+
+        let mut system = RenderSystem::default();
+        system.add_phase(clear_phase(target_id, Color::BLUE));
+        system.add_phase(clear_phase(target_id, Color::RED));
+        let custom_snapshot = snapshot.clone();
+        system.add_custom_phase(
+            "snapshot",
+            Box::new(move |encoder, storage| {
+                encoder.copy_texture_to_texture(
+                    storage.get_texture(target_id).texture.as_image_copy(),
+                    custom_snapshot.as_image_copy(),
+                    size,
+                );
+            }),
+        );
+        system.add_phase(clear_phase(target_id, Color::GREEN));
+        system.run_parallel(&renderer, &mut storage).unwrap();
+
+        let red = u32::from_ne_bytes([255, 0, 0, 255]);
+        let green = u32::from_ne_bytes([0, 255, 0, 255]);
+        assert_eq!(renderer.read_pixel(&snapshot, 1, 1), Some(red));
+        let target = &storage.get_texture(target_id).texture;
+        assert_eq!(renderer.read_pixel(target, 1, 1), Some(green));
+    }
+
+    #[test]
+    fn render_system_sorts_between_custom_phases() {
+        let (a, b, c) = (
+            ResourceId::from_index(0),
+            ResourceId::from_index(1),
+            ResourceId::from_index(2),
+        );
+        let mut reads_a = clear_phase(b, Color::RED);
+        reads_a.set_reads(vec![a]);
+
+        let mut system = RenderSystem::default();
+        system.add_phase(reads_a);
+        system.add_phase(clear_phase(a, Color::RED));
+        system.add_custom_phase("custom", Box::new(|_, _| {}));
+        system.add_phase(clear_phase(c, Color::RED));
+        assert_eq!(system.sort().unwrap(), &[1, 0, 2, 3]);
+
+        // Cycle is reported with the phase ids
+        let mut reads_c = clear_phase(a, Color::RED);
+        reads_c.set_reads(vec![c]);
+        let mut reads_a = clear_phase(c, Color::RED);
+        reads_a.set_reads(vec![a]);
+        system.add_phase(reads_c);
+        system.add_phase(reads_a);
+        assert_eq!(
+            system.sort().unwrap_err(),
+            PhaseGraphError::Cycle(vec![4, 5])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "custom phase")]
+    fn render_system_custom_phase_commands() {
+        let mut system = RenderSystem::default();
+        let id = system.add_custom_phase("custom", Box::new(|_, _| {}));
+        system.set_commands(id, vec![]);
+    }
+}
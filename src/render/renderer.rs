@@ -1,8 +1,9 @@
 use super::wgpu_imports::*;
-use log::info;
+use log::debug;
+use wgpu::{
+    Adapter, AdapterInfo, CompositeAlphaMode, InstanceDescriptor, TextureFormatFeatureFlags,
+};
 use winit::dpi::PhysicalSize;
-
-#[cfg(not(feature = "headless"))]
 use winit::window::Window;
 
 pub const MAX_BIND_GROUPS: usize = 4;
@@ -12,8 +13,7 @@ pub const MAX_COLOR_ATTACHMENTS: usize = 8;
 #[derive(Debug)]
 pub struct CurrentFrameContext {
     view: TextureView,
-    #[cfg(not(feature = "headless"))]
-    output: SurfaceTexture,
+    output: Option<SurfaceTexture>,
 }
 
 impl CurrentFrameContext {
@@ -21,9 +21,119 @@ impl CurrentFrameContext {
         &self.view
     }
 
-    #[cfg(not(feature = "headless"))]
+    /// Presents the frame to the window. Does nothing for headless renderer.
     pub fn present(self) {
-        self.output.present();
+        if let Some(output) = self.output {
+            output.present();
+        }
+    }
+}
+
+/// Corner of a render target where sampled texture coordinates start
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UvOrigin {
+    /// Convention of wgpu on all backends
+    #[default]
+    TopLeft,
+    /// Convention of OpenGL. Images produced with it
+    /// look upside down when sampled with the other one.
+    BottomLeft,
+}
+
+impl UvOrigin {
+    /// Returns true if V texture coordinate has to be flipped to sample
+    /// texture with `self` origin in the `target` orientation
+    pub fn flips_v(self, target: UvOrigin) -> bool {
+        self != target
+    }
+}
+
+/// Target the renderer draws the [`TextureId::WINDOW_VIEW_ID`](super::storage::TextureId) into
+#[derive(Debug)]
+enum RenderTarget<'window> {
+    Surface {
+        surface: Surface<'window>,
+        config: SurfaceConfiguration,
+    },
+    Offscreen {
+        texture: Texture,
+    },
+}
+
+const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+fn create_offscreen_texture(device: &Device, size: PhysicalSize<u32>) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
+        label: Some("surface_texture"),
+        view_formats: &[],
+    })
+}
+
+/// Highest sample count supported by texture format with these flags
+fn max_sample_count(flags: TextureFormatFeatureFlags) -> u32 {
+    [16, 8, 4, 2]
+        .into_iter()
+        .find(|count| flags.sample_count_supported(*count))
+        .unwrap_or(1)
+}
+
+/// What the adapter and surface the [`Renderer`] was created with support.
+/// Features and limits are the ones of the adapter, so they can be
+/// supported even if the device was not created with them.
+#[derive(Debug, Clone)]
+pub struct RendererCapabilities {
+    pub adapter_info: AdapterInfo,
+    pub features: Features,
+    pub limits: Limits,
+    /// Empty for headless renderer
+    pub present_modes: Vec<PresentMode>,
+    /// Empty for headless renderer
+    pub surface_formats: Vec<TextureFormat>,
+    /// Max MSAA sample count for the surface format
+    pub max_sample_count: u32,
+}
+
+impl RendererCapabilities {
+    fn new(adapter: &Adapter, surface: Option<&Surface>, surface_format: TextureFormat) -> Self {
+        let (present_modes, surface_formats) = match surface {
+            Some(surface) => {
+                let capabilities = surface.get_capabilities(adapter);
+                (capabilities.present_modes, capabilities.formats)
+            }
+            None => (vec![], vec![]),
+        };
+        let flags = adapter.get_texture_format_features(surface_format).flags;
+
+        Self {
+            adapter_info: adapter.get_info(),
+            features: adapter.features(),
+            limits: adapter.limits(),
+            present_modes,
+            surface_formats,
+            max_sample_count: max_sample_count(flags),
+        }
+    }
+
+    pub fn supports_present_mode(&self, present_mode: PresentMode) -> bool {
+        self.present_modes.contains(&present_mode)
+    }
+
+    pub fn supports_sample_count(&self, count: u32) -> bool {
+        count <= self.max_sample_count && count.is_power_of_two()
+    }
+
+    pub fn supports_timestamps(&self) -> bool {
+        self.features.contains(Features::TIMESTAMP_QUERY)
     }
 }
 
@@ -32,24 +142,14 @@ impl CurrentFrameContext {
 pub struct Renderer<'window> {
     device: Device,
     queue: Queue,
-
-    #[cfg(not(feature = "headless"))]
-    surface: Surface<'window>,
-    #[cfg(not(feature = "headless"))]
-    config: SurfaceConfiguration,
-
-    #[cfg(feature = "headless")]
-    texture: Texture,
-
+    target: RenderTarget<'window>,
     size: PhysicalSize<u32>,
+    capabilities: RendererCapabilities,
 }
 
 impl<'window> Renderer<'window> {
     /// Creates new [`Renderer`] instance attached to the provided window
-    #[cfg(not(feature = "headless"))]
-    pub async fn new(window: &'window Window) -> Renderer<'window> {
-        use wgpu::{CompositeAlphaMode, InstanceDescriptor};
-
+    pub async fn new_windowed(window: &'window Window) -> Renderer<'window> {
         let instance = Instance::new(InstanceDescriptor {
             backends: Backends::VULKAN,
             ..Default::default()
@@ -67,62 +167,43 @@ impl<'window> Renderer<'window> {
             .await
             .unwrap();
 
-        let (device, queue) = adapter
-            .request_device(
-                &DeviceDescriptor {
-                    required_features: Features::empty(),
-                    required_limits: Limits {
-                        max_bind_groups: MAX_BIND_GROUPS as u32,
-                        ..Default::default()
-                    },
-                    label: Some("device_descriptor"),
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
-        info!("Renderer device: {:#?}, queue: {:#?}", device, queue);
-
-        let formats = surface.get_capabilities(&adapter).formats;
-
-        let config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: formats[0],
-            width: size.width,
-            height: size.height,
-            present_mode: PresentMode::AutoNoVsync,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: CompositeAlphaMode::Auto,
-            view_formats: vec![formats[0]],
-        };
-        surface.configure(&device, &config);
+        Self::from_adapter(&adapter, Some(surface), size).await
+    }
 
-        Self {
-            device,
-            queue,
-            surface,
-            config,
-            size,
-        }
+    /// Creates new headless [`Renderer`] instance. Frames are rendered into
+    /// an offscreen texture with provided size available with [`Renderer::surface_texture`].
+    pub async fn new_headless(width: u32, height: u32) -> Renderer<'static> {
+        Self::try_new_headless(width, height).await.unwrap()
     }
 
-    /// Creates new headless [`Renderer`] instance with internal texture with provided size
-    #[cfg(feature = "headless")]
-    pub async fn new(width: u32, height: u32) -> Self {
-        let instance = Instance::new(Backends::VULKAN);
+    /// Same as [`Renderer::new_headless`], but returns `None`
+    /// if there is no adapter available
+    pub async fn try_new_headless(width: u32, height: u32) -> Option<Renderer<'static>> {
+        let instance = Instance::new(InstanceDescriptor {
+            backends: Backends::all(),
+            ..Default::default()
+        });
 
         let adapter = instance
             .request_adapter(&RequestAdapterOptions::default())
-            .await
-            .unwrap();
+            .await?;
 
+        Some(Renderer::from_adapter(&adapter, None, PhysicalSize { width, height }).await)
+    }
+
+    /// Creates renderer drawing into the surface if provided or into
+    /// an offscreen texture otherwise
+    async fn from_adapter(
+        adapter: &Adapter,
+        surface: Option<Surface<'window>>,
+        size: PhysicalSize<u32>,
+    ) -> Renderer<'window> {
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
-                    features: Features::empty(),
-                    limits: Limits {
-                        max_bind_groups: MAX_BIND_GROUPS,
+                    required_features: Features::empty(),
+                    required_limits: Limits {
+                        max_bind_groups: MAX_BIND_GROUPS as u32,
                         ..Default::default()
                     },
                     label: Some("device_descriptor"),
@@ -132,39 +213,49 @@ impl<'window> Renderer<'window> {
             .await
             .unwrap();
 
-        info!("Renderer device: {:#?}, queue: {:#?}", device, queue);
-
-        let size = PhysicalSize { width, height };
-
-        let desc = TextureDescriptor {
-            size: Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
-            label: Some("surface_texture"),
+        debug!("Renderer device: {:#?}, queue: {:#?}", device, queue);
+
+        let (target, capabilities) = match surface {
+            Some(surface) => {
+                let formats = surface.get_capabilities(adapter).formats;
+                let capabilities = RendererCapabilities::new(adapter, Some(&surface), formats[0]);
+
+                let config = SurfaceConfiguration {
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    format: formats[0],
+                    width: size.width,
+                    height: size.height,
+                    present_mode: PresentMode::AutoNoVsync,
+                    desired_maximum_frame_latency: 2,
+                    alpha_mode: CompositeAlphaMode::Auto,
+                    view_formats: vec![formats[0]],
+                };
+                surface.configure(&device, &config);
+                (RenderTarget::Surface { surface, config }, capabilities)
+            }
+            None => (
+                RenderTarget::Offscreen {
+                    texture: create_offscreen_texture(&device, size),
+                },
+                RendererCapabilities::new(adapter, None, OFFSCREEN_FORMAT),
+            ),
         };
-        let texture = device.create_texture(&desc);
 
-        Self {
+        Renderer {
             device,
             queue,
-            texture,
+            target,
             size,
+            capabilities,
         }
     }
 
-    /// Size of current surface
+    /// Device for creating GPU resources in custom passes
     pub fn device(&self) -> &Device {
         &self.device
     }
 
-    /// Size of current surface
+    /// Queue for writing buffers and textures in custom passes
     pub fn queue(&self) -> &Queue {
         &self.queue
     }
@@ -174,19 +265,48 @@ impl<'window> Renderer<'window> {
         &self.size
     }
 
-    #[cfg(not(feature = "headless"))]
-    pub fn surface_format(&self) -> TextureFormat {
-        self.config.format
+    /// Configuration of the window surface.
+    /// Headless renderer has no surface, so it returns `None`.
+    pub fn config(&self) -> Option<&SurfaceConfiguration> {
+        match &self.target {
+            RenderTarget::Surface { config, .. } => Some(config),
+            RenderTarget::Offscreen { .. } => None,
+        }
     }
 
-    #[cfg(feature = "headless")]
+    /// Format of the [`TextureId::WINDOW_VIEW_ID`](super::storage::TextureId) texture
     pub fn surface_format(&self) -> TextureFormat {
-        TextureFormat::Rgba8UnormSrgb
+        match &self.target {
+            RenderTarget::Surface { config, .. } => config.format,
+            RenderTarget::Offscreen { .. } => OFFSCREEN_FORMAT,
+        }
+    }
+
+    /// Capabilities of the adapter and surface
+    pub fn capabilities(&self) -> &RendererCapabilities {
+        &self.capabilities
     }
 
-    #[cfg(feature = "headless")]
-    pub fn surface_texture(&self) -> &Texture {
-        &self.texture
+    /// Texture coordinates origin of the render targets.
+    /// wgpu uses the same convention for all backends,
+    /// so only textures produced outside of it can differ.
+    pub fn uv_origin(&self) -> UvOrigin {
+        UvOrigin::TopLeft
+    }
+
+    /// Returns true if the surface format is not sRGB and shaders writing into
+    /// the surface need to convert linear colors into gamma space themselves.
+    /// By convention such shaders provide `fs_main_gamma` entry point next to `fs_main`.
+    pub fn needs_manual_gamma(&self) -> bool {
+        !self.surface_format().is_srgb()
+    }
+
+    /// Offscreen texture of the headless renderer
+    pub fn surface_texture(&self) -> Option<&Texture> {
+        match &self.target {
+            RenderTarget::Surface { .. } => None,
+            RenderTarget::Offscreen { texture } => Some(texture),
+        }
     }
 
     /// Reconfigures current surface with new size if provided.
@@ -200,54 +320,69 @@ impl<'window> Renderer<'window> {
         self.resize_surface(self.size);
     }
 
-    #[cfg(not(feature = "headless"))]
-    fn resize_surface(&mut self, new_size: PhysicalSize<u32>) {
-        self.size = new_size;
-        self.config.width = new_size.width;
-        self.config.height = new_size.height;
-
-        self.surface.configure(&self.device, &self.config);
+    /// Handles `WindowEvent::ScaleFactorChanged`. Window keeps its logical size,
+    /// so its physical size changes with the scale factor and the surface needs
+    /// the same reconfigure as after the resize. `new_inner_size` is the physical
+    /// size after the change (`Window::inner_size`). Works for `WindowEvent::Resized`
+    /// as well, so both events can be handled in one place.
+    /// Returns `true` if the surface was reconfigured, then the size dependent
+    /// resources (depth textures, camera aspect) need to be updated too.
+    pub fn handle_scale_factor_change(&mut self, new_inner_size: PhysicalSize<u32>) -> bool {
+        if new_inner_size == self.size || new_inner_size.width == 0 || new_inner_size.height == 0 {
+            return false;
+        }
+        self.resize_surface(new_inner_size);
+        true
     }
 
-    #[cfg(feature = "headless")]
     fn resize_surface(&mut self, new_size: PhysicalSize<u32>) {
         self.size = new_size;
-
-        let desc = TextureDescriptor {
-            size: Extent3d {
-                width: self.size.width,
-                height: self.size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
-            label: Some("surface_texture"),
-        };
-        self.texture = self.device.create_texture(&desc);
+        match &mut self.target {
+            RenderTarget::Surface { surface, config } => {
+                config.width = new_size.width;
+                config.height = new_size.height;
+                surface.configure(&self.device, config);
+            }
+            RenderTarget::Offscreen { texture } => {
+                *texture = create_offscreen_texture(&self.device, new_size);
+            }
+        }
     }
 
-    /// Returns context for the current frame
-    #[cfg(not(feature = "headless"))]
+    /// Returns context for the current frame. On [`SurfaceError::Outdated`]
+    /// the surface is reconfigured and the frame is requested once more.
+    /// Remaining errors should be handled by the caller:
+    /// - [`SurfaceError::Lost`] or [`SurfaceError::Outdated`]: call [`Renderer::resize`]
+    ///   with `None` and skip the frame
+    /// - [`SurfaceError::Timeout`]: skip the frame, the next one usually succeeds
+    /// - [`SurfaceError::OutOfMemory`]: exit the application
     pub fn current_frame(&self) -> Result<CurrentFrameContext, SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        let context = CurrentFrameContext { view, output };
+        let context = match &self.target {
+            RenderTarget::Surface { surface, config } => {
+                let output = match surface.get_current_texture() {
+                    // Surface changed since it was configured, e.g. during resize
+                    Err(SurfaceError::Outdated) => {
+                        surface.configure(&self.device, config);
+                        surface.get_current_texture()?
+                    }
+                    output => output?,
+                };
+                let view = output
+                    .texture
+                    .create_view(&TextureViewDescriptor::default());
+                CurrentFrameContext {
+                    view,
+                    output: Some(output),
+                }
+            }
+            RenderTarget::Offscreen { texture } => CurrentFrameContext {
+                view: texture.create_view(&TextureViewDescriptor::default()),
+                output: None,
+            },
+        };
         Ok(context)
     }
 
-    /// Returns context for the current frame
-    #[cfg(feature = "headless")]
-    pub fn current_frame(&self) -> CurrentFrameContext {
-        CurrentFrameContext {
-            view: self.texture.create_view(&TextureViewDescriptor::default()),
-        }
-    }
-
     /// Creates command encoder
     pub fn create_encoder(&self) -> CommandEncoder {
         self.device
@@ -260,4 +395,119 @@ impl<'window> Renderer<'window> {
     pub fn submit<I: IntoIterator<Item = CommandBuffer>>(&self, command_buffers: I) {
         self.queue.submit(command_buffers);
     }
+
+    /// Reads single `u32` texel from the texture (e.g. object id texture).
+    /// Texture must have 4 byte texel format and `COPY_SRC` usage.
+    /// Blocks until the GPU finishes all submitted work.
+    /// Returns `None` if the texel is outside of the texture.
+    pub fn read_pixel(&self, texture: &Texture, x: u32, y: u32) -> Option<u32> {
+        if texture.width() <= x || texture.height() <= y {
+            return None;
+        }
+
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("read_pixel_buffer"),
+            size: COPY_BYTES_PER_ROW_ALIGNMENT as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.create_encoder();
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d { x, y, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(MapMode::Read, |_| {});
+        self.device.poll(Maintain::Wait);
+        let data = slice.get_mapped_range();
+        Some(u32::from_ne_bytes([data[0], data[1], data[2], data[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renderer_max_sample_count() {
+        assert_eq!(max_sample_count(TextureFormatFeatureFlags::empty()), 1);
+        assert_eq!(
+            max_sample_count(
+                TextureFormatFeatureFlags::MULTISAMPLE_X2
+                    | TextureFormatFeatureFlags::MULTISAMPLE_X4
+            ),
+            4
+        );
+    }
+
+    #[test]
+    fn renderer_scale_factor_change() {
+        let Some(mut renderer) = pollster::block_on(Renderer::try_new_headless(64, 32)) else {
+            return;
+        };
+        assert!(!renderer.handle_scale_factor_change(PhysicalSize::new(64, 32)));
+        assert!(!renderer.handle_scale_factor_change(PhysicalSize::new(0, 64)));
+        assert_eq!(*renderer.size(), PhysicalSize::new(64, 32));
+
+        assert!(renderer.handle_scale_factor_change(PhysicalSize::new(128, 64)));
+        assert_eq!(*renderer.size(), PhysicalSize::new(128, 64));
+        let texture = renderer.surface_texture().unwrap();
+        assert_eq!((texture.width(), texture.height()), (128, 64));
+    }
+    #[test]
+    fn renderer_read_pixel() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let texture = renderer.device().create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 4,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R32Uint,
+            usage: TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let ids: Vec<u32> = (0..8).collect();
+        renderer.queue().write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&ids),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(16),
+                rows_per_image: None,
+            },
+            texture.size(),
+        );
+
+        assert_eq!(renderer.read_pixel(&texture, 0, 0), Some(0));
+        assert_eq!(renderer.read_pixel(&texture, 3, 1), Some(7));
+        assert_eq!(renderer.read_pixel(&texture, 4, 0), None);
+        assert_eq!(renderer.read_pixel(&texture, 0, 2), None);
+    }
 }
@@ -1,13 +1,88 @@
-use super::{renderer::Renderer, traits::*, wgpu_imports::*};
+use super::{
+    pipeline_builder::{create_shader_module, read_shader_source, GpuPipeline},
+    renderer::Renderer,
+    traits::*,
+    wgpu_imports::*,
+};
 use crate::{mesh::GpuMesh, texture::GpuTexture, utils::sparse_set::SparseSet};
-use std::{collections::HashMap, ops::Deref};
+use log::{debug, trace, warn};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
 
-/// Id assighed to any resource
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ResourceId(usize);
+/// Id assighed to any resource. Typed by the kind of the resource it
+/// refers to, so ids of different kinds can not be mixed up.
+pub struct ResourceId<T>(usize, PhantomData<fn() -> T>);
 
-impl ResourceId {
-    pub const WINDOW_VIEW_ID: ResourceId = ResourceId(usize::MAX);
+pub type BufferId = ResourceId<Buffer>;
+pub type TextureId = ResourceId<GpuTexture>;
+pub type MeshId = ResourceId<GpuMesh>;
+pub type BindGroupId = ResourceId<BindGroup>;
+pub type PipelineId = ResourceId<GpuPipeline>;
+pub type ComputePipelineId = ResourceId<ComputePipeline>;
+
+impl<T> ResourceId<T> {
+    const fn new(index: usize) -> Self {
+        Self(index, PhantomData)
+    }
+
+    #[cfg(test)]
+    pub(crate) const fn from_index(index: usize) -> Self {
+        Self::new(index)
+    }
+}
+
+impl TextureId {
+    pub const WINDOW_VIEW_ID: TextureId = TextureId::new(usize::MAX);
+}
+
+// Implemented by hand, as derives would require `T` to implement the traits
+
+impl<T> std::fmt::Debug for ResourceId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ResourceId").field(&self.0).finish()
+    }
+}
+
+impl<T> Clone for ResourceId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ResourceId<T> {}
+
+impl<T> PartialEq for ResourceId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for ResourceId<T> {}
+
+impl<T> PartialOrd for ResourceId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for ResourceId<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T> Hash for ResourceId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
 }
 
 pub struct CurrentFrameStorage<'a> {
@@ -16,8 +91,8 @@ pub struct CurrentFrameStorage<'a> {
 }
 
 impl<'a> CurrentFrameStorage<'a> {
-    pub fn get_view(&self, id: ResourceId) -> &TextureView {
-        if id == ResourceId::WINDOW_VIEW_ID {
+    pub fn get_view(&self, id: TextureId) -> &TextureView {
+        if id == TextureId::WINDOW_VIEW_ID {
             self.current_frame_view
         } else {
             &self.storage.get_texture(id).view
@@ -39,8 +114,120 @@ pub struct RenderStorage {
     textures: SparseSet<GpuTexture>,
     meshes: SparseSet<GpuMesh>,
     bind_groups: SparseSet<BindGroup>,
-    pipelines: SparseSet<RenderPipeline>,
-    layouts: HashMap<&'static str, BindGroupLayout>,
+    bind_group_dependencies: HashMap<BindGroupId, BindGroupDependencies>,
+    pipelines: SparseSet<GpuPipeline>,
+    compute_pipelines: SparseSet<ComputePipeline>,
+    /// Layouts are shared through `Arc`, so callers keep
+    /// their layout while new ones are registered.
+    layouts: Mutex<HashMap<&'static str, Arc<BindGroupLayout>>>,
+    shaders: Mutex<ShaderCache>,
+    /// Stats are recorded by commands through the shared
+    /// reference, so they are atomic.
+    frame_stats: FrameStats,
+    last_frame_stats: RenderStats,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineReplaceError {
+    #[error("There is no pipeline with id {0:?}")]
+    NotFound(PipelineId),
+    #[error("Pipeline {label:?} can not be replaced: layout of the bind group {index} has changed, so bind groups created for the old layout are incompatible with it")]
+    LayoutChanged { label: String, index: usize },
+}
+
+/// Resources a bind group was created from. Textures and buffers
+/// are stored separately, as their ids come from different sets.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BindGroupDependencies {
+    pub textures: Vec<TextureId>,
+    pub buffers: Vec<BufferId>,
+}
+
+/// Shader modules keyed by path and hash of the source
+#[derive(Debug, Default)]
+struct ShaderCache {
+    modules: HashMap<(String, u64), Arc<ShaderModule>>,
+    stats: ShaderCacheStats,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderCacheStats {
+    /// Number of cached shader modules
+    pub modules: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Work submitted by render commands during one frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: usize,
+    pub triangles: usize,
+    pub bind_group_switches: usize,
+    pub pipeline_switches: usize,
+}
+
+impl RenderStats {
+    /// Records draw call of `count` vertices or indices
+    pub fn record_draw(&mut self, topology: PrimitiveTopology, count: u32) {
+        self.record_instanced_draw(topology, count, 1);
+    }
+
+    /// Records draw call of `instances` instances with
+    /// `count` vertices or indices each
+    pub fn record_instanced_draw(
+        &mut self,
+        topology: PrimitiveTopology,
+        count: u32,
+        instances: u32,
+    ) {
+        let triangles = match topology {
+            PrimitiveTopology::TriangleList => count as usize / 3,
+            PrimitiveTopology::TriangleStrip => count.saturating_sub(2) as usize,
+            _ => 0,
+        };
+        self.draw_calls += 1;
+        self.triangles += triangles * instances as usize;
+    }
+}
+
+/// [`RenderStats`] of the current frame
+#[derive(Debug, Default)]
+struct FrameStats {
+    draw_calls: AtomicUsize,
+    triangles: AtomicUsize,
+    bind_group_switches: AtomicUsize,
+    pipeline_switches: AtomicUsize,
+}
+
+impl FrameStats {
+    fn add(&self, stats: RenderStats) {
+        self.draw_calls
+            .fetch_add(stats.draw_calls, Ordering::Relaxed);
+        self.triangles.fetch_add(stats.triangles, Ordering::Relaxed);
+        self.bind_group_switches
+            .fetch_add(stats.bind_group_switches, Ordering::Relaxed);
+        self.pipeline_switches
+            .fetch_add(stats.pipeline_switches, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> RenderStats {
+        RenderStats {
+            draw_calls: self.draw_calls.load(Ordering::Relaxed),
+            triangles: self.triangles.load(Ordering::Relaxed),
+            bind_group_switches: self.bind_group_switches.load(Ordering::Relaxed),
+            pipeline_switches: self.pipeline_switches.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.triangles += rhs.triangles;
+        self.bind_group_switches += rhs.bind_group_switches;
+        self.pipeline_switches += rhs.pipeline_switches;
+    }
 }
 
 impl Default for RenderStorage {
@@ -56,91 +243,256 @@ impl RenderStorage {
             textures: SparseSet::new(),
             meshes: SparseSet::new(),
             bind_groups: SparseSet::new(),
+            bind_group_dependencies: HashMap::new(),
             pipelines: SparseSet::new(),
-            layouts: HashMap::new(),
+            compute_pipelines: SparseSet::new(),
+            layouts: Mutex::new(HashMap::new()),
+            shaders: Mutex::new(ShaderCache::default()),
+            frame_stats: FrameStats::default(),
+            last_frame_stats: RenderStats::default(),
         }
     }
 
-    pub fn insert_pipeline(&mut self, pipeline: RenderPipeline) -> ResourceId {
-        ResourceId(self.pipelines.insert(pipeline))
+    pub fn insert_pipeline(&mut self, pipeline: GpuPipeline) -> PipelineId {
+        ResourceId::new(self.pipelines.insert(pipeline))
+    }
+
+    pub fn insert_compute_pipeline(&mut self, pipeline: ComputePipeline) -> ComputePipelineId {
+        ResourceId::new(self.compute_pipelines.insert(pipeline))
+    }
+
+    pub fn insert_buffer(&mut self, buffer: Buffer) -> BufferId {
+        ResourceId::new(self.buffers.insert(buffer))
     }
 
-    pub fn insert_buffer(&mut self, buffer: Buffer) -> ResourceId {
-        ResourceId(self.buffers.insert(buffer))
+    pub fn insert_texture(&mut self, texture: GpuTexture) -> TextureId {
+        ResourceId::new(self.textures.insert(texture))
     }
 
-    pub fn insert_texture(&mut self, texture: GpuTexture) -> ResourceId {
-        ResourceId(self.textures.insert(texture))
+    /// Every mesh id refers to exactly one [`GpuMesh`]. Resources made of
+    /// several meshes (e.g. [`crate::model::Model`]) insert each mesh
+    /// separately and draw them with one command per mesh.
+    pub fn insert_mesh(&mut self, mesh: GpuMesh) -> MeshId {
+        ResourceId::new(self.meshes.insert(mesh))
     }
 
-    pub fn insert_mesh(&mut self, mesh: GpuMesh) -> ResourceId {
-        ResourceId(self.meshes.insert(mesh))
+    pub fn insert_bind_group(&mut self, bind_group: BindGroup) -> BindGroupId {
+        ResourceId::new(self.bind_groups.insert(bind_group))
     }
 
-    pub fn insert_bind_group(&mut self, bind_group: BindGroup) -> ResourceId {
-        ResourceId(self.bind_groups.insert(bind_group))
+    /// Same as `insert_bind_group`, but also records resources
+    /// the bind group references. See [`RenderStorage::bind_group_dependencies`].
+    pub fn insert_bind_group_with_dependencies(
+        &mut self,
+        bind_group: BindGroup,
+        dependencies: BindGroupDependencies,
+    ) -> BindGroupId {
+        let id = self.insert_bind_group(bind_group);
+        self.bind_group_dependencies.insert(id, dependencies);
+        id
     }
 
-    pub fn replace_buffer(&mut self, buffer_id: ResourceId, buffer: Buffer) {
+    /// Replaces the pipeline, e.g. after its shader has been changed.
+    /// Bind groups are created for the layouts of the old pipeline, so the new
+    /// one is rejected if any of its bind group layouts differ. The old
+    /// pipeline stays in use in this case.
+    pub fn replace_pipeline(
+        &mut self,
+        pipeline_id: PipelineId,
+        pipeline: GpuPipeline,
+    ) -> Result<(), PipelineReplaceError> {
+        let old = self
+            .pipelines
+            .get_mut(pipeline_id.0)
+            .ok_or(PipelineReplaceError::NotFound(pipeline_id))?;
+        if let Some(index) = old.layout_mismatch(&pipeline) {
+            return Err(PipelineReplaceError::LayoutChanged {
+                label: pipeline.label.unwrap_or_else(|| "unlabeled".to_string()),
+                index,
+            });
+        }
+        if old.bind_group_layouts.is_none() || pipeline.bind_group_layouts.is_none() {
+            warn!(
+                "Pipeline {:?} layout is derived from the shader and can not be checked on replace",
+                pipeline.label.as_deref().unwrap_or("unlabeled")
+            );
+        }
+        *old = pipeline;
+        Ok(())
+    }
+
+    pub fn replace_buffer(&mut self, buffer_id: BufferId, buffer: Buffer) {
         if let Some(b) = self.buffers.get_mut(buffer_id.0) {
             *b = buffer;
         };
     }
 
-    pub fn replace_texture(&mut self, texture_id: ResourceId, texture: GpuTexture) {
+    pub fn replace_texture(&mut self, texture_id: TextureId, texture: GpuTexture) {
         if let Some(t) = self.textures.get_mut(texture_id.0) {
             *t = texture;
         };
     }
 
-    pub fn replace_mesh(&mut self, mesh_id: ResourceId, mesh: GpuMesh) {
+    pub fn replace_mesh(&mut self, mesh_id: MeshId, mesh: GpuMesh) {
         if let Some(m) = self.meshes.get_mut(mesh_id.0) {
             *m = mesh;
         };
     }
 
-    pub fn replace_bind_group(&mut self, bind_group_id: ResourceId, bind_group: BindGroup) {
+    pub fn replace_bind_group(&mut self, bind_group_id: BindGroupId, bind_group: BindGroup) {
         if let Some(b) = self.bind_groups.get_mut(bind_group_id.0) {
             *b = bind_group;
         };
     }
 
-    pub fn register_bind_group_layout<A: AssetBindGroup>(&mut self, renderer: &Renderer) {
-        let t_name = std::any::type_name::<A>();
-        if !self.layouts.contains_key(t_name) {
-            self.layouts.insert(t_name, A::bind_group_layout(renderer));
-        }
+    pub fn replace_bind_group_with_dependencies(
+        &mut self,
+        bind_group_id: BindGroupId,
+        bind_group: BindGroup,
+        dependencies: BindGroupDependencies,
+    ) {
+        self.replace_bind_group(bind_group_id, bind_group);
+        self.bind_group_dependencies
+            .insert(bind_group_id, dependencies);
     }
 
-    pub fn get_bind_group_layout<A: AssetBindGroup>(&self) -> &BindGroupLayout {
+    /// Resources the bind group was created from. `None` for bind groups
+    /// inserted without dependencies.
+    pub fn bind_group_dependencies(&self, id: BindGroupId) -> Option<&BindGroupDependencies> {
+        self.bind_group_dependencies.get(&id)
+    }
+
+    /// Creates the layout of the bind group ahead of time.
+    /// Layouts are also registered on the first `get_bind_group_layout` call.
+    pub fn register_bind_group_layout<A: AssetBindGroup>(&self, renderer: &Renderer) {
+        _ = self.get_bind_group_layout::<A>(renderer);
+    }
+
+    /// Returns the layout of the bind group, creating it on first use.
+    pub fn get_bind_group_layout<A: AssetBindGroup>(
+        &self,
+        renderer: &Renderer,
+    ) -> Arc<BindGroupLayout> {
         let t_name = std::any::type_name::<A>();
-        if !self.layouts.contains_key(t_name) {
-            panic!("Trying to get a layout of an asset that was never built");
+        self.layouts
+            .lock()
+            .unwrap()
+            .entry(t_name)
+            .or_insert_with(|| Arc::new(A::bind_group_layout(renderer)))
+            .clone()
+    }
+
+    /// Returns compiled shader module for the file at `shader_path`.
+    /// Module is compiled only once for the same path and source.
+    /// The cache lives only as long as the storage. wgpu 0.19 has no
+    /// `PipelineCache`, so there is no backend compilation to persist.
+    pub fn get_shader_module(&self, renderer: &Renderer, shader_path: &str) -> Arc<ShaderModule> {
+        let source = read_shader_source(shader_path);
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let key = (shader_path.to_string(), hasher.finish());
+
+        let mut cache = self.shaders.lock().unwrap();
+        if let Some(module) = cache.modules.get(&key) {
+            let module = module.clone();
+            cache.stats.hits += 1;
+            trace!("Reusing cached shader: {}", shader_path);
+            return module;
         }
-        self.layouts.get(t_name).unwrap()
+        debug!("Compiling shader: {}", shader_path);
+        let module = Arc::new(create_shader_module(renderer, shader_path, source));
+        cache.modules.insert(key, module.clone());
+        cache.stats.misses += 1;
+        cache.stats.modules = cache.modules.len();
+        module
+    }
+
+    pub fn shader_cache_stats(&self) -> ShaderCacheStats {
+        self.shaders.lock().unwrap().stats
+    }
+
+    /// Adds `stats` to the stats of the current frame. Render phases
+    /// accumulate stats of their commands and record them once.
+    pub fn record_stats(&self, stats: RenderStats) {
+        self.frame_stats.add(stats);
+    }
+
+    /// Stats recorded since the last `end_frame`
+    pub fn frame_stats(&self) -> RenderStats {
+        self.frame_stats.get()
+    }
+
+    /// Stats of the frame finished by the last `end_frame`
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats
+    }
+
+    /// Finishes the frame. Stats of the current frame become
+    /// `last_frame_stats` and recording starts from zero.
+    pub fn end_frame(&mut self) -> RenderStats {
+        self.last_frame_stats = std::mem::take(&mut self.frame_stats).get();
+        self.last_frame_stats
     }
 
-    pub fn get_buffer(&self, id: ResourceId) -> &Buffer {
+    pub fn get_buffer(&self, id: BufferId) -> &Buffer {
         self.buffers.get(id.0).unwrap()
     }
 
-    pub fn get_texture(&self, id: ResourceId) -> &GpuTexture {
+    pub fn get_texture(&self, id: TextureId) -> &GpuTexture {
         self.textures.get(id.0).unwrap()
     }
 
-    pub fn get_mesh(&self, id: ResourceId) -> &GpuMesh {
+    pub fn get_mesh(&self, id: MeshId) -> &GpuMesh {
         self.meshes.get(id.0).unwrap()
     }
 
-    pub fn get_mesh_mut(&mut self, id: ResourceId) -> &mut GpuMesh {
+    pub fn get_mesh_mut(&mut self, id: MeshId) -> &mut GpuMesh {
         self.meshes.get_mut(id.0).unwrap()
     }
 
-    pub fn get_bind_group(&self, id: ResourceId) -> &BindGroup {
+    pub fn get_bind_group(&self, id: BindGroupId) -> &BindGroup {
         self.bind_groups.get(id.0).unwrap()
     }
 
-    pub fn get_pipeline(&self, id: ResourceId) -> &RenderPipeline {
+    pub fn get_pipeline(&self, id: PipelineId) -> &RenderPipeline {
+        &self.pipelines.get(id.0).unwrap().pipeline
+    }
+
+    pub fn get_gpu_pipeline(&self, id: PipelineId) -> &GpuPipeline {
         self.pipelines.get(id.0).unwrap()
     }
+
+    pub fn get_compute_pipeline(&self, id: ComputePipelineId) -> &ComputePipeline {
+        self.compute_pipelines.get(id.0).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_stats_frames() {
+        let mut storage = RenderStorage::new();
+        let mut stats = RenderStats::default();
+        stats.record_draw(PrimitiveTopology::TriangleList, 36);
+        stats.record_draw(PrimitiveTopology::TriangleStrip, 4);
+        stats.record_draw(PrimitiveTopology::LineList, 8);
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.triangles, 14);
+
+        let mut instanced = RenderStats::default();
+        instanced.record_instanced_draw(PrimitiveTopology::TriangleList, 36, 10);
+        assert_eq!(instanced.draw_calls, 1);
+        assert_eq!(instanced.triangles, 120);
+
+        storage.record_stats(stats);
+        storage.record_stats(stats);
+        assert_eq!(storage.frame_stats().draw_calls, 6);
+        assert_eq!(storage.last_frame_stats(), RenderStats::default());
+
+        assert_eq!(storage.end_frame().triangles, 28);
+        assert_eq!(storage.last_frame_stats().triangles, 28);
+        assert_eq!(storage.frame_stats(), RenderStats::default());
+    }
 }
@@ -86,7 +86,7 @@ macro_rules! impl_simple_sized_gpu_buffer {
 macro_rules! impl_simple_texture_bind_group {
     ($handle:ty, $bind_group:ident, $view_dimension:block, $sample_type:block, $sampler_binding_type:block) => {
         #[derive(Debug, Clone, Copy)]
-        pub struct $bind_group(pub ResourceId);
+        pub struct $bind_group(pub BindGroupId);
 
         impl AssetBindGroup for $bind_group {
             type ResourceHandle = $handle;
@@ -122,7 +122,7 @@ macro_rules! impl_simple_texture_bind_group {
                 storage: &mut RenderStorage,
                 resource: &Self::ResourceHandle,
             ) -> Self {
-                let layout = storage.get_bind_group_layout::<Self>();
+                let layout = &storage.get_bind_group_layout::<Self>(renderer);
                 let texture = storage.get_texture(resource.texture_id);
 
                 let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
@@ -140,7 +140,13 @@ macro_rules! impl_simple_texture_bind_group {
                     label: Some(std::any::type_name::<Self>()),
                 });
 
-                Self(storage.insert_bind_group(bind_group))
+                Self(storage.insert_bind_group_with_dependencies(
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![resource.texture_id],
+                        buffers: vec![],
+                    },
+                ))
             }
 
             fn replace(
@@ -149,7 +155,7 @@ macro_rules! impl_simple_texture_bind_group {
                 storage: &mut RenderStorage,
                 resource: &Self::ResourceHandle,
             ) {
-                let layout = storage.get_bind_group_layout::<Self>();
+                let layout = &storage.get_bind_group_layout::<Self>(renderer);
                 let texture = storage.get_texture(resource.texture_id);
 
                 let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
@@ -167,12 +173,49 @@ macro_rules! impl_simple_texture_bind_group {
                     label: Some(std::any::type_name::<Self>()),
                 });
 
-                storage.replace_bind_group(self.0, bind_group);
+                storage.replace_bind_group_with_dependencies(
+                    self.0,
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![resource.texture_id],
+                        buffers: vec![],
+                    },
+                );
             }
         }
     };
 }
 
+/// Same as `impl_simple_texture_bind_group` for depth textures with comparison samplers.
+/// Texture needs to be created with a `compare` function in the sampler
+/// (e.g. `EmptyTexture::new_depth_comparison`).
+#[macro_export]
+macro_rules! impl_simple_depth_texture_bind_group {
+    ($handle:ty, $bind_group:ident, $view_dimension:block) => {
+        $crate::impl_simple_texture_bind_group!(
+            $handle,
+            $bind_group,
+            $view_dimension,
+            { TextureSampleType::Depth },
+            { SamplerBindingType::Comparison }
+        );
+    };
+}
+
+/// Same as `impl_simple_texture_bind_group` for 3D textures (e.g. `VolumeTexture`)
+#[macro_export]
+macro_rules! impl_simple_volume_texture_bind_group {
+    ($handle:ty, $bind_group:ident, $sample_type:block, $sampler_binding_type:block) => {
+        $crate::impl_simple_texture_bind_group!(
+            $handle,
+            $bind_group,
+            { TextureViewDimension::D3 },
+            $sample_type,
+            $sampler_binding_type
+        );
+    };
+}
+
 /// Shorthadn for creating simple `GpuResource` buffer with `ResourceHandle` and `AssetBindGroup`
 /// types and traits
 /// uniform type need to implement `From<&BufferType>` and `bytemuck` traits
@@ -200,7 +243,7 @@ macro_rules! impl_simple_buffer {
 
         #[derive(Debug, Clone, Copy)]
         pub struct $handle {
-            buffer_id: ResourceId,
+            buffer_id: BufferId,
         }
 
         impl ResourceHandle for $handle {
@@ -233,7 +276,7 @@ macro_rules! impl_simple_buffer {
         }
 
         #[derive(Debug, Clone, Copy)]
-        pub struct $bind_group(pub ResourceId);
+        pub struct $bind_group(pub BindGroupId);
 
         impl AssetBindGroup for $bind_group {
             type ResourceHandle = $handle;
@@ -261,7 +304,7 @@ macro_rules! impl_simple_buffer {
                 storage: &mut RenderStorage,
                 resource: &Self::ResourceHandle,
             ) -> Self {
-                let layout = storage.get_bind_group_layout::<Self>();
+                let layout = &storage.get_bind_group_layout::<Self>(renderer);
                 let buffer = storage.get_buffer(resource.buffer_id);
 
                 let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
@@ -273,7 +316,13 @@ macro_rules! impl_simple_buffer {
                     label: Some(std::any::type_name::<Self>()),
                 });
 
-                Self(storage.insert_bind_group(bind_group))
+                Self(storage.insert_bind_group_with_dependencies(
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![],
+                        buffers: vec![resource.buffer_id],
+                    },
+                ))
             }
 
             fn replace(
@@ -282,7 +331,7 @@ macro_rules! impl_simple_buffer {
                 storage: &mut RenderStorage,
                 resource: &Self::ResourceHandle,
             ) {
-                let layout = storage.get_bind_group_layout::<Self>();
+                let layout = &storage.get_bind_group_layout::<Self>(renderer);
                 let buffer = storage.get_buffer(resource.buffer_id);
 
                 let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
@@ -294,8 +343,236 @@ macro_rules! impl_simple_buffer {
                     label: Some(std::any::type_name::<Self>()),
                 });
 
-                storage.replace_bind_group(self.0, bind_group);
+                storage.replace_bind_group_with_dependencies(
+                    self.0,
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![],
+                        buffers: vec![resource.buffer_id],
+                    },
+                );
+            }
+        }
+    };
+}
+
+/// Distance in bytes between uniforms of the `uniform_size` in a buffer
+/// bound with dynamic offsets. Offsets need to be aligned to the
+/// `min_uniform_buffer_offset_alignment` limit of the device.
+pub fn dynamic_uniform_stride(renderer: &Renderer, uniform_size: u64) -> u32 {
+    let alignment = renderer
+        .device()
+        .limits()
+        .min_uniform_buffer_offset_alignment as u64;
+    (uniform_size.div_ceil(alignment) * alignment) as u32
+}
+
+/// Packs uniforms into bytes placing every one `stride` bytes apart.
+/// There is always space for at least one uniform, because
+/// an empty buffer can not be bound.
+pub fn pack_dynamic_uniforms<T: bytemuck::Pod>(uniforms: &[T], stride: u32) -> Vec<u8> {
+    let mut bytes = vec![0; uniforms.len().max(1) * stride as usize];
+    for (uniform, chunk) in uniforms.iter().zip(bytes.chunks_exact_mut(stride as usize)) {
+        let uniform = bytemuck::bytes_of(uniform);
+        chunk[..uniform.len()].copy_from_slice(uniform);
+    }
+    bytes
+}
+
+/// Same as `impl_simple_buffer`, but creates one buffer for the whole `Vec` of
+/// `$buffer` types. Bind group is created with a dynamic offset, so every uniform
+/// is bound by passing `$handle::offset` with [`BindGroupMeta::with_dynamic_offset`]
+/// instead of having its own buffer and bind group.
+#[macro_export]
+macro_rules! impl_simple_dynamic_buffer {
+    ($buffer:ty, $uniform:ty, $buffers:ident, $resource:ident, $handle:ident, $bind_group:ident, $visibility:block) => {
+        #[derive(Debug, Default)]
+        pub struct $buffers(pub Vec<$buffer>);
+
+        #[derive(Debug)]
+        pub struct $resource {
+            buffer: Buffer,
+            stride: u32,
+        }
+
+        impl $buffers {
+            fn uniforms(&self) -> Vec<$uniform> {
+                self.0.iter().map(|buffer| buffer.into()).collect()
+            }
+        }
+
+        impl GpuResource for $buffers {
+            type ResourceType = $resource;
+
+            fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+                let stride = dynamic_uniform_stride(
+                    renderer,
+                    std::mem::size_of::<$uniform>() as BufferAddress,
+                );
+                let buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+                    label: Some(std::any::type_name::<Self>()),
+                    contents: &pack_dynamic_uniforms(&self.uniforms(), stride),
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                });
+                Self::ResourceType { buffer, stride }
+            }
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        pub struct $handle {
+            buffer_id: BufferId,
+            stride: u32,
+        }
+
+        impl $handle {
+            /// Dynamic offset of the uniform with the `index`
+            pub fn offset(&self, index: usize) -> u32 {
+                index as u32 * self.stride
+            }
+
+            /// Writes only the uniform with the `index`
+            pub fn update_at(
+                &self,
+                renderer: &Renderer,
+                storage: &RenderStorage,
+                index: usize,
+                original: &$buffer,
+            ) {
+                let uniform: $uniform = original.into();
+                renderer.queue().write_buffer(
+                    storage.get_buffer(self.buffer_id),
+                    self.offset(index) as BufferAddress,
+                    bytemuck::cast_slice(&[uniform]),
+                );
+            }
+        }
+
+        impl ResourceHandle for $handle {
+            type OriginalResource<'a> = $buffers;
+            type ResourceType = $resource;
+
+            fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+                Self {
+                    buffer_id: storage.insert_buffer(resource.buffer),
+                    stride: resource.stride,
+                }
+            }
+
+            fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+                storage.replace_buffer(self.buffer_id, resource.buffer);
+            }
+
+            /// Number of uniforms can not exceed the one the buffer was built with
+            fn update(
+                &self,
+                renderer: &Renderer,
+                storage: &RenderStorage,
+                original: &Self::OriginalResource<'_>,
+            ) {
+                renderer.queue().write_buffer(
+                    storage.get_buffer(self.buffer_id),
+                    0,
+                    &pack_dynamic_uniforms(&original.uniforms(), self.stride),
+                );
+            }
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        pub struct $bind_group(pub BindGroupId);
+
+        impl $bind_group {
+            fn create_bind_group(
+                renderer: &Renderer,
+                storage: &RenderStorage,
+                resource: &$handle,
+            ) -> BindGroup {
+                let layout = &storage.get_bind_group_layout::<Self>(renderer);
+                let buffer = storage.get_buffer(resource.buffer_id);
+
+                renderer.device().create_bind_group(&BindGroupDescriptor {
+                    layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer,
+                            offset: 0,
+                            size: std::num::NonZeroU64::new(std::mem::size_of::<$uniform>() as u64),
+                        }),
+                    }],
+                    label: Some(std::any::type_name::<Self>()),
+                })
+            }
+        }
+
+        impl AssetBindGroup for $bind_group {
+            type ResourceHandle = $handle;
+
+            fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+                renderer
+                    .device()
+                    .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                        entries: &[BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: $visibility,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: true,
+                                min_binding_size: std::num::NonZeroU64::new(std::mem::size_of::<
+                                    $uniform,
+                                >(
+                                )
+                                    as u64),
+                            },
+                            count: None,
+                        }],
+                        label: Some(std::any::type_name::<Self>()),
+                    })
+            }
+
+            fn new(
+                renderer: &Renderer,
+                storage: &mut RenderStorage,
+                resource: &Self::ResourceHandle,
+            ) -> Self {
+                let bind_group = Self::create_bind_group(renderer, storage, resource);
+                Self(storage.insert_bind_group_with_dependencies(
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![],
+                        buffers: vec![resource.buffer_id],
+                    },
+                ))
+            }
+
+            fn replace(
+                &self,
+                renderer: &Renderer,
+                storage: &mut RenderStorage,
+                resource: &Self::ResourceHandle,
+            ) {
+                let bind_group = Self::create_bind_group(renderer, storage, resource);
+                storage.replace_bind_group_with_dependencies(
+                    self.0,
+                    bind_group,
+                    BindGroupDependencies {
+                        textures: vec![],
+                        buffers: vec![resource.buffer_id],
+                    },
+                );
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traits_pack_dynamic_uniforms() {
+        let bytes = pack_dynamic_uniforms(&[1u32, 2, 3], 8);
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(bytemuck::cast_slice::<u8, u32>(&bytes), &[1, 0, 2, 0, 3, 0]);
+        assert_eq!(pack_dynamic_uniforms::<u32>(&[], 8), vec![0; 8]);
+    }
+}
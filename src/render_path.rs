@@ -0,0 +1,365 @@
+use crate::camera::CameraBindGroup;
+use crate::const_vec;
+use crate::fullscreen::FullscreenTriangle;
+use crate::gbuffer::{GBuffer, GBufferBindGroup, GBufferHandle};
+use crate::light::PointLightsBindGroup;
+use crate::material::ColorMaterialBindGroup;
+use crate::mesh::{MeshRenderCommand, MeshVertex};
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::texture::EmptyTexture;
+use crate::transform::TransformBindGroup;
+use crate::utils::ConstVec;
+
+const FORWARD_SHADER: &str = concat!(
+    include_str!("shaders/render_path_types.wgsl"),
+    include_str!("shaders/render_path_shading.wgsl"),
+    include_str!("shaders/render_path_forward.wgsl"),
+);
+const GEOMETRY_SHADER: &str = concat!(
+    include_str!("shaders/render_path_types.wgsl"),
+    include_str!("shaders/render_path_geometry.wgsl"),
+);
+const LIGHTING_SHADER: &str = concat!(
+    include_str!("shaders/render_path_types.wgsl"),
+    include_str!("shaders/render_path_shading.wgsl"),
+    include_str!("shaders/render_path_lighting.wgsl"),
+);
+
+/// How the [`RenderPathRenderer`] draws the scene
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Objects are lit while they are drawn into the target
+    #[default]
+    Forward,
+    /// Objects are drawn into the [`GBuffer`] and lit by one fullscreen pass
+    Deferred,
+}
+
+/// Object drawn by both render paths
+#[derive(Debug, Clone, Copy)]
+pub struct RenderPathObject {
+    pub mesh_id: MeshId,
+    pub material: ColorMaterialBindGroup,
+    pub transform: TransformBindGroup,
+}
+
+/// Draws the same scene with either [`RenderPath`]. Pipelines, phases and
+/// targets of both paths are created upfront, so switching the path is a
+/// single [`RenderPathRenderer::set_path`] call.
+/// Both paths shade objects with the material diffuse color and shininess
+/// lit by all point lights, so they produce the same image.
+#[derive(Debug)]
+pub struct RenderPathRenderer {
+    path: RenderPath,
+    pub forward_pipeline_id: PipelineId,
+    pub geometry_pipeline_id: PipelineId,
+    pub lighting_pipeline_id: PipelineId,
+    pub depth_texture_id: TextureId,
+    pub gbuffer: GBuffer,
+    pub gbuffer_handle: GBufferHandle,
+    pub gbuffer_bind_group: GBufferBindGroup,
+    pub forward_phase: RenderPhase,
+    pub geometry_phase: RenderPhase,
+    pub lighting_phase: RenderPhase,
+}
+
+impl RenderPathRenderer {
+    /// Renderer drawing into the `target` texture with the `format`
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        target: TextureId,
+        format: TextureFormat,
+        path: RenderPath,
+    ) -> Self {
+        let depth_texture_id = storage.insert_texture(EmptyTexture::new_depth().build(renderer));
+        let gbuffer = GBuffer::new(TextureFormat::Rgba32Float);
+        let gbuffer_handle = GBufferHandle::new(storage, gbuffer.build(renderer));
+        let gbuffer_bind_group = GBufferBindGroup::new(renderer, storage, &gbuffer_handle);
+
+        let primitive = PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        };
+        let depth_stencil = DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        };
+        let color_targets = [Some(ColorTargetState {
+            format,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })];
+
+        let shader =
+            create_shader_module(renderer, "render_path_forward.wgsl", FORWARD_SHADER.into());
+        let forward_pipeline = PipelineBuilder {
+            shader_path: "render_path_forward.wgsl",
+            label: Some("render_path_forward_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<ColorMaterialBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<TransformBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<PointLightsBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[MeshVertex::layout()],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&color_targets),
+            fragment_entry_point: "fs_main",
+            primitive,
+            depth_stencil: Some(depth_stencil.clone()),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        let shader = create_shader_module(
+            renderer,
+            "render_path_geometry.wgsl",
+            GEOMETRY_SHADER.into(),
+        );
+        let geometry_pipeline = PipelineBuilder {
+            shader_path: "render_path_geometry.wgsl",
+            label: Some("render_path_geometry_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<ColorMaterialBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<TransformBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[MeshVertex::layout()],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&gbuffer.color_targets()),
+            fragment_entry_point: "fs_main",
+            primitive,
+            depth_stencil: Some(depth_stencil),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        let shader = create_shader_module(
+            renderer,
+            "render_path_lighting.wgsl",
+            LIGHTING_SHADER.into(),
+        );
+        let lighting_pipeline = PipelineBuilder {
+            shader_path: "render_path_lighting.wgsl",
+            label: Some("render_path_lighting_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<GBufferBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<PointLightsBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&color_targets),
+            fragment_entry_point: "fs_main",
+            primitive,
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        let forward_phase = RenderPhase::builder()
+            .color(target)
+            .clear(Color::TRANSPARENT)
+            .depth(depth_texture_id)
+            .clear(1.0)
+            .build();
+        let geometry_phase = RenderPhase::builder()
+            .color(gbuffer_handle.position_texture_id)
+            .clear(Color::TRANSPARENT)
+            .color(gbuffer_handle.normal_texture_id)
+            .clear(Color::TRANSPARENT)
+            .color(gbuffer_handle.albedo_texture_id)
+            .clear(Color::TRANSPARENT)
+            .depth(depth_texture_id)
+            .clear(1.0)
+            .build();
+        let lighting_phase = RenderPhase::builder()
+            .color(target)
+            .clear(Color::TRANSPARENT)
+            .build();
+
+        Self {
+            path,
+            forward_pipeline_id: storage.insert_pipeline(forward_pipeline),
+            geometry_pipeline_id: storage.insert_pipeline(geometry_pipeline),
+            lighting_pipeline_id: storage.insert_pipeline(lighting_pipeline),
+            depth_texture_id,
+            gbuffer,
+            gbuffer_handle,
+            gbuffer_bind_group,
+            forward_phase,
+            geometry_phase,
+            lighting_phase,
+        }
+    }
+
+    pub fn path(&self) -> RenderPath {
+        self.path
+    }
+
+    /// Takes effect from the next [`RenderPathRenderer::render`]
+    pub fn set_path(&mut self, path: RenderPath) {
+        self.path = path;
+    }
+
+    /// Color of the target pixels not covered by any object
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.forward_phase.set_clear_color(0, color);
+        self.lighting_phase.set_clear_color(0, color);
+    }
+
+    /// Recreates depth and g-buffer targets with the new renderer size
+    pub fn resize(&self, renderer: &Renderer, storage: &mut RenderStorage) {
+        storage.replace_texture(
+            self.depth_texture_id,
+            EmptyTexture::new_depth().build(renderer),
+        );
+        self.gbuffer_handle
+            .replace(storage, self.gbuffer.build(renderer));
+        self.gbuffer_bind_group
+            .replace(renderer, storage, &self.gbuffer_handle);
+    }
+
+    /// Commands drawing `objects` with the pipeline of the current path
+    pub fn commands(
+        &self,
+        objects: &[RenderPathObject],
+        camera: CameraBindGroup,
+        lights: PointLightsBindGroup,
+    ) -> Vec<MeshRenderCommand> {
+        match self.path {
+            RenderPath::Forward => {
+                object_commands(self.forward_pipeline_id, objects, camera, Some(lights))
+            }
+            RenderPath::Deferred => {
+                object_commands(self.geometry_pipeline_id, objects, camera, None)
+            }
+        }
+    }
+
+    /// Draws `objects` into the target with the current path
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        current_frame_storage: &CurrentFrameStorage,
+        objects: &[RenderPathObject],
+        camera: CameraBindGroup,
+        lights: PointLightsBindGroup,
+    ) {
+        let commands = self.commands(objects, camera, lights);
+        match self.path {
+            RenderPath::Forward => {
+                self.forward_phase
+                    .execute(encoder, current_frame_storage, &commands);
+            }
+            RenderPath::Deferred => {
+                self.geometry_phase
+                    .execute(encoder, current_frame_storage, &commands);
+                let lighting = FullscreenTriangle::command(
+                    self.lighting_pipeline_id,
+                    const_vec![
+                        BindGroupMeta::new(0, self.gbuffer_bind_group.0),
+                        BindGroupMeta::new(1, camera.0),
+                        BindGroupMeta::new(2, lights.0),
+                    ],
+                );
+                let mut render_pass = self
+                    .lighting_phase
+                    .render_pass(encoder, current_frame_storage);
+                lighting.execute(&mut render_pass, current_frame_storage);
+            }
+        }
+    }
+}
+
+/// Material, transform, camera and optional lights bind groups in this order
+fn object_commands(
+    pipeline_id: PipelineId,
+    objects: &[RenderPathObject],
+    camera: CameraBindGroup,
+    lights: Option<PointLightsBindGroup>,
+) -> Vec<MeshRenderCommand> {
+    objects
+        .iter()
+        .map(|object| {
+            let mut bind_groups = const_vec![
+                BindGroupMeta::new(0, object.material.0),
+                BindGroupMeta::new(1, object.transform.0),
+                BindGroupMeta::new(2, camera.0),
+            ];
+            if let Some(lights) = lights {
+                bind_groups.push(BindGroupMeta::new(3, lights.0));
+            }
+            MeshRenderCommand {
+                pipeline_id,
+                mesh_id: object.mesh_id,
+                index_slice: None,
+                vertex_slice: None,
+                scissor_rect: None,
+                viewport: None,
+                bind_groups,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id<T>(index: usize) -> ResourceId<T> {
+        ResourceId::from_index(index)
+    }
+
+    #[test]
+    fn render_path_object_commands() {
+        let objects = [RenderPathObject {
+            mesh_id: id(0),
+            material: ColorMaterialBindGroup(id(1)),
+            transform: TransformBindGroup(id(2)),
+        }];
+        let camera = CameraBindGroup(id(3));
+        let lights = PointLightsBindGroup(id(4));
+
+        let forward = object_commands(id(5), &objects, camera, Some(lights));
+        let bind_groups: Vec<_> = forward[0]
+            .bind_groups
+            .iter()
+            .map(|bg| (bg.index, bg.bind_group_id))
+            .collect();
+        assert_eq!(
+            bind_groups,
+            vec![(0, id(1)), (1, id(2)), (2, id(3)), (3, id(4))]
+        );
+
+        let deferred = object_commands(id(6), &objects, camera, None);
+        assert_eq!(deferred[0].pipeline_id, id(6));
+        assert_eq!(deferred[0].mesh_id, id(0));
+        assert_eq!(deferred[0].bind_groups.iter().count(), 3);
+    }
+}
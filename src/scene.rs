@@ -0,0 +1,440 @@
+use crate::cgmath_imports::*;
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SceneError {
+    #[error("Error reading scene: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error parsing RON scene: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Error writing RON scene: {0}")]
+    RonWrite(#[from] ron::Error),
+    #[error("Error parsing JSON scene: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported scene format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Error loading scene model: {0}")]
+    Model(#[from] crate::model::Error),
+    #[error("Object {0} uses unknown material {1}")]
+    UnknownMaterial(String, String),
+    #[error("Object {0} needs a material")]
+    MissingMaterial(String),
+    #[error("Object {0} has zero length rotation axis")]
+    ZeroRotationAxis(String),
+    #[error("Scene has multiple objects named {0}")]
+    DuplicateObject(String),
+    #[error("Scene has multiple lights named {0}")]
+    DuplicateLight(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneTransform {
+    pub translation: [f32; 3],
+    /// Rotation axis and angle in degrees
+    pub axis: [f32; 3],
+    pub angle: f32,
+    pub scale: [f32; 3],
+}
+
+impl Default for SceneTransform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            axis: [0.0, 1.0, 0.0],
+            angle: 0.0,
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl SceneTransform {
+    /// `None` if the rotation axis has zero length, as there is
+    /// no rotation around it
+    pub fn to_transform(&self) -> Option<Transform> {
+        let axis = Vector3::from(self.axis);
+        if axis.magnitude2() < f32::EPSILON {
+            return None;
+        }
+        Some(Transform {
+            translation: self.translation.into(),
+            rotation: Quaternion::from_axis_angle(axis.normalize(), Deg(self.angle)),
+            scale: self.scale.into(),
+        })
+    }
+}
+
+/// Colors are in linear space, same as in [`ColorMaterial`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneColorMaterial {
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+}
+
+impl From<&SceneColorMaterial> for ColorMaterial {
+    fn from(value: &SceneColorMaterial) -> Self {
+        Self {
+            ambient: value.ambient,
+            diffuse: value.diffuse,
+            specular: value.specular,
+            shininess: value.shininess,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneMesh {
+    Cube {
+        length: f32,
+        width: f32,
+        height: f32,
+    },
+    Sphere {
+        radius: f32,
+        subdivisions: usize,
+    },
+    /// OBJ model with its own materials. Path is relative to the scene file.
+    Obj(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneObject {
+    pub name: String,
+    pub mesh: SceneMesh,
+    /// Name of the material from `Scene::materials`. Ignored for OBJ models.
+    #[serde(default)]
+    pub material: Option<String>,
+    #[serde(default)]
+    pub transform: SceneTransform,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenePointLight {
+    pub name: String,
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+/// Perspective camera. Angles are in degrees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+/// Data driven scene description. Can be stored as RON or JSON.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub materials: HashMap<String, SceneColorMaterial>,
+    pub objects: Vec<SceneObject>,
+    pub lights: Vec<ScenePointLight>,
+    pub camera: Option<SceneCamera>,
+}
+
+#[derive(Debug)]
+pub enum SceneObjectMeshes {
+    /// Shape drawn with a `ColorMaterial`
+    Shape {
+        mesh_id: MeshId,
+        material_bind_group: ColorMaterialBindGroup,
+    },
+    /// OBJ model drawn with its own textured materials
+    Model(Vec<ModelHadle>),
+}
+
+#[derive(Debug)]
+pub struct SceneObjectHandle {
+    pub transform: Transform,
+    pub transform_handle: TransformHandle,
+    pub transform_bind_group: TransformBindGroup,
+    pub meshes: SceneObjectMeshes,
+}
+
+#[derive(Debug)]
+pub struct ScenePointLightHandle {
+    pub light: PointLight,
+    pub light_handle: PointLightHandle,
+    pub light_bind_group: PointLightBindGroup,
+}
+
+#[derive(Debug)]
+pub struct SceneCameraHandle {
+    pub camera: Camera,
+    pub camera_handle: CameraHandle,
+    pub camera_bind_group: CameraBindGroup,
+}
+
+/// Handles of the instantiated scene by name
+#[derive(Debug, Default)]
+pub struct SceneHandles {
+    pub objects: HashMap<String, SceneObjectHandle>,
+    pub lights: HashMap<String, ScenePointLightHandle>,
+    pub camera: Option<SceneCameraHandle>,
+}
+
+impl Scene {
+    /// Reads scene description. Format is picked by the file extension.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => Ok(ron::from_str(&data)?),
+            Some("json") => Ok(serde_json::from_str(&data)?),
+            _ => Err(SceneError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    /// Writes scene description. Format is picked by the file extension.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let data = match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+            Some("json") => serde_json::to_string_pretty(self)?,
+            _ => return Err(SceneError::UnsupportedFormat(path.display().to_string())),
+        };
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Reads scene description and instantiates everything in it.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+    ) -> Result<SceneHandles, SceneError> {
+        let path = path.as_ref();
+        let scene = Self::read(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        scene.build(base_dir, renderer, storage)
+    }
+
+    /// Instantiates the scene. OBJ paths are resolved relative to `base_dir`.
+    /// Scene is validated before any resources are created.
+    pub fn build(
+        &self,
+        base_dir: &Path,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+    ) -> Result<SceneHandles, SceneError> {
+        let transforms = self.validate()?;
+
+        let mut materials = HashMap::new();
+        for (name, material) in self.materials.iter() {
+            let material = ColorMaterial::from(material);
+            let handle = ColorMaterialHandle::new(storage, material.build(renderer));
+            let bind_group = ColorMaterialBindGroup::new(renderer, storage, &handle);
+            materials.insert(name.as_str(), bind_group);
+        }
+
+        let mut handles = SceneHandles::default();
+
+        for (object, transform) in self.objects.iter().zip(transforms) {
+            let meshes = match &object.mesh {
+                SceneMesh::Obj(path) => {
+                    let model = Model::load(base_dir.join(path))?;
+                    let (model_handles, _) = model.build(renderer, storage);
+                    SceneObjectMeshes::Model(model_handles)
+                }
+                shape => {
+                    let material_name = object
+                        .material
+                        .as_ref()
+                        .ok_or_else(|| SceneError::MissingMaterial(object.name.clone()))?;
+                    let material_bind_group =
+                        *materials.get(material_name.as_str()).ok_or_else(|| {
+                            SceneError::UnknownMaterial(object.name.clone(), material_name.clone())
+                        })?;
+                    let mesh: Mesh = match shape {
+                        SceneMesh::Cube {
+                            length,
+                            width,
+                            height,
+                        } => Cube::new(*length, *width, *height).into(),
+                        SceneMesh::Sphere {
+                            radius,
+                            subdivisions,
+                        } => Icoshphere::new(*radius, *subdivisions).into(),
+                        SceneMesh::Obj(_) => unreachable!(),
+                    };
+                    SceneObjectMeshes::Shape {
+                        mesh_id: storage.insert_mesh(mesh.build(renderer)),
+                        material_bind_group,
+                    }
+                }
+            };
+
+            let transform_handle = TransformHandle::new(storage, transform.build(renderer));
+            let transform_bind_group =
+                TransformBindGroup::new(renderer, storage, &transform_handle);
+
+            handles.objects.insert(
+                object.name.clone(),
+                SceneObjectHandle {
+                    transform,
+                    transform_handle,
+                    transform_bind_group,
+                    meshes,
+                },
+            );
+        }
+
+        for light in self.lights.iter() {
+            let point_light = PointLight::new(
+                light.position,
+                light.color,
+                light.constant,
+                light.linear,
+                light.quadratic,
+            );
+            let light_handle = PointLightHandle::new(storage, point_light.build(renderer));
+            let light_bind_group = PointLightBindGroup::new(renderer, storage, &light_handle);
+            handles.lights.insert(
+                light.name.clone(),
+                ScenePointLightHandle {
+                    light: point_light,
+                    light_handle,
+                    light_bind_group,
+                },
+            );
+        }
+
+        if let Some(c) = &self.camera {
+            let camera = Camera::Perspective(PerspectiveCamera {
+                position: c.position.into(),
+                yaw: Deg(c.yaw).into(),
+                pitch: Deg(c.pitch).into(),
+                aspect: renderer.size().width as f32 / renderer.size().height as f32,
+                fovy: Deg(c.fovy).into(),
+                znear: c.znear,
+                zfar: c.zfar,
+            });
+            let camera_handle = CameraHandle::new(storage, camera.build(renderer));
+            let camera_bind_group = CameraBindGroup::new(renderer, storage, &camera_handle);
+            handles.camera = Some(SceneCameraHandle {
+                camera,
+                camera_handle,
+                camera_bind_group,
+            });
+        }
+
+        Ok(handles)
+    }
+
+    /// Checks that names are unique and returns transforms of the objects
+    fn validate(&self) -> Result<Vec<Transform>, SceneError> {
+        let mut names = HashSet::new();
+        if let Some(object) = self.objects.iter().find(|o| !names.insert(o.name.as_str())) {
+            return Err(SceneError::DuplicateObject(object.name.clone()));
+        }
+        names.clear();
+        if let Some(light) = self.lights.iter().find(|l| !names.insert(l.name.as_str())) {
+            return Err(SceneError::DuplicateLight(light.name.clone()));
+        }
+        self.objects
+            .iter()
+            .map(|object| {
+                object
+                    .transform
+                    .to_transform()
+                    .ok_or_else(|| SceneError::ZeroRotationAxis(object.name.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"(
+        materials: {
+            "grey": (
+                ambient: (0.4, 0.4, 0.4),
+                diffuse: (0.6, 0.6, 0.6),
+                specular: (1.0, 1.0, 1.0),
+                shininess: 32.0,
+            ),
+        },
+        objects: [
+            (
+                name: "floor",
+                mesh: Cube(length: 9.0, width: 1.0, height: 5.0),
+                material: Some("grey"),
+                transform: (scale: (3.0, 1.0, 3.0)),
+            ),
+            (
+                name: "cube",
+                mesh: Obj("cube/cube.obj"),
+            ),
+        ],
+        lights: [
+            (
+                name: "sun",
+                position: (-1.0, 9.0, 5.0),
+                color: (1.0, 1.0, 1.0),
+                constant: 1.0,
+                linear: 0.109,
+                quadratic: 0.032,
+            ),
+        ],
+    )"#;
+
+    #[test]
+    fn scene_from_ron() {
+        let scene: Scene = ron::from_str(SCENE).unwrap();
+        assert_eq!(scene.materials.len(), 1);
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.objects[0].transform.scale, [3.0, 1.0, 3.0]);
+        assert_eq!(scene.objects[0].transform.translation, [0.0; 3]);
+        assert!(scene.objects[1].material.is_none());
+        assert_eq!(scene.lights[0].name, "sun");
+        assert!(scene.camera.is_none());
+    }
+
+    #[test]
+    fn scene_json_round_trip() {
+        let scene: Scene = ron::from_str(SCENE).unwrap();
+        let json = serde_json::to_string(&scene).unwrap();
+        let scene: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!(scene.objects[0].name, "floor");
+        assert!(matches!(scene.objects[1].mesh, SceneMesh::Obj(ref p) if p == "cube/cube.obj"));
+    }
+    #[test]
+    fn scene_validate() {
+        let mut scene: Scene = ron::from_str(SCENE).unwrap();
+        assert_eq!(scene.validate().unwrap().len(), 2);
+
+        scene.objects[1].transform.axis = [0.0; 3];
+        assert!(matches!(
+            scene.validate(),
+            Err(SceneError::ZeroRotationAxis(name)) if name == "cube"
+        ));
+
+        scene.objects[1].transform.axis = [1.0, 0.0, 0.0];
+        scene.objects[1].name = "floor".into();
+        assert!(matches!(
+            scene.validate(),
+            Err(SceneError::DuplicateObject(name)) if name == "floor"
+        ));
+
+        scene.objects[1].name = "cube".into();
+        scene.lights.push(scene.lights[0].clone());
+        assert!(matches!(
+            scene.validate(),
+            Err(SceneError::DuplicateLight(name)) if name == "sun"
+        ));
+    }
+}
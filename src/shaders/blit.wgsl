@@ -0,0 +1,37 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  return textureSample(t_source, s_source, vertex.tex_coords);
+}
+
+// Used with sources which have texture coordinates starting at the bottom left corner
+@fragment
+fn fs_main_flip_v(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let tex_coords = vec2<f32>(vertex.tex_coords.x, 1.0 - vertex.tex_coords.y);
+  return textureSample(t_source, s_source, tex_coords);
+}
@@ -0,0 +1,40 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var t_lut: texture_3d<f32>;
+@group(1) @binding(1)
+var s_lut: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let color = textureSample(t_source, s_source, vertex.tex_coords);
+  // Maps [0, 1] range to the centers of the first and the last texels,
+  // so the LUT is interpolated only between its entries
+  let size = f32(textureDimensions(t_lut).x);
+  let lut_coords = saturate(color.rgb) * (size - 1.0) / size + 0.5 / size;
+  return vec4<f32>(textureSample(t_lut, s_lut, lut_coords).rgb, color.a);
+}
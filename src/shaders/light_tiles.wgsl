@@ -0,0 +1,123 @@
+struct LightTilesUniform {
+  tile_size: u32,
+  max_lights_per_tile: u32,
+  tiles_x: u32,
+  tiles_y: u32,
+  width: u32,
+  height: u32,
+  attenuation_cutoff: f32,
+};
+
+struct LightUniform {
+  position: vec3<f32>,
+  color: vec3<f32>,
+  // Matches padding of the `PointLightUniform`
+  _pad: f32,
+  a_constant: f32,
+  a_linear: f32,
+  a_quadratic: f32,
+  intensity: f32,
+};
+
+struct Lights {
+  lights_num: i32,
+  lights: array<LightUniform>,
+};
+
+@group(0) @binding(0)
+var<uniform> settings: LightTilesUniform;
+@group(0) @binding(1)
+var<storage, read_write> tiles: array<u32>;
+@group(0) @binding(2)
+var<storage, read> lights: Lights;
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// Distance at which attenuation drops to the cutoff. Negative if the light
+// is never dimmer than the cutoff. Same as `PointLight::radius`.
+fn light_radius(light: LightUniform) -> f32 {
+  let c = light.a_constant - light.intensity / settings.attenuation_cutoff;
+  if (0.0 < light.a_quadratic) {
+    let discriminant = light.a_linear * light.a_linear - 4.0 * light.a_quadratic * c;
+    return max((-light.a_linear + sqrt(discriminant)) / (2.0 * light.a_quadratic), 0.0);
+  }
+  if (0.0 < light.a_linear) {
+    return max(-c / light.a_linear, 0.0);
+  }
+  return -1.0;
+}
+
+// NDC rectangle (min.xy, max.xy) covering the light sphere
+fn light_bounds(light: LightUniform) -> vec4<f32> {
+  let full_screen = vec4<f32>(-1.0, -1.0, 1.0, 1.0);
+  let radius = light_radius(light);
+  if (radius < 0.0) {
+    return full_screen;
+  }
+
+  var bounds = vec4<f32>(1.0, 1.0, -1.0, -1.0);
+  var behind: u32 = 0u;
+  for (var i: u32 = 0u; i < 8u; i = i + 1u) {
+    let corner = vec3<f32>(
+      select(-radius, radius, (i & 1u) != 0u),
+      select(-radius, radius, (i & 2u) != 0u),
+      select(-radius, radius, (i & 4u) != 0u),
+    );
+    let clip = camera.view_projection * vec4<f32>(light.position + corner, 1.0);
+    if (clip.w <= 0.0) {
+      behind = behind + 1u;
+      continue;
+    }
+    let ndc = clip.xy / clip.w;
+    bounds = vec4<f32>(min(bounds.xy, ndc), max(bounds.zw, ndc));
+  }
+  // Whole light is behind the camera, empty rectangle touches no tiles
+  if (behind == 8u) {
+    return vec4<f32>(2.0, 2.0, -2.0, -2.0);
+  }
+  // Some corners are behind the camera, projection is not bounded anymore
+  if (behind != 0u) {
+    return full_screen;
+  }
+  return bounds;
+}
+
+// Replaced with `LIGHT_TILES_WORKGROUP_SIZE`
+const WORKGROUP_SIZE: u32 = 8u;
+
+@compute @workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  if (settings.tiles_x <= id.x || settings.tiles_y <= id.y) {
+    return;
+  }
+
+  let size = vec2<f32>(f32(settings.width), f32(settings.height));
+  let min_pixel = vec2<f32>(id.xy * settings.tile_size);
+  let max_pixel = min(min_pixel + f32(settings.tile_size), size);
+  // Pixels are Y-down, NDC is Y-up
+  let tile_min = vec2<f32>(min_pixel.x / size.x * 2.0 - 1.0, 1.0 - max_pixel.y / size.y * 2.0);
+  let tile_max = vec2<f32>(max_pixel.x / size.x * 2.0 - 1.0, 1.0 - min_pixel.y / size.y * 2.0);
+
+  let tile = (id.y * settings.tiles_x + id.x) * (settings.max_lights_per_tile + 1u);
+  var count: u32 = 0u;
+  for (var i: i32 = 0; i < lights.lights_num; i = i + 1) {
+    if (count == settings.max_lights_per_tile) {
+      break;
+    }
+    let bounds = light_bounds(lights.lights[i]);
+    if (all(bounds.xy <= tile_max) && all(tile_min <= bounds.zw)) {
+      tiles[tile + 1u + count] = u32(i);
+      count = count + 1u;
+    }
+  }
+  tiles[tile] = count;
+}
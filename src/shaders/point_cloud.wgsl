@@ -0,0 +1,89 @@
+// Vertex shader
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct Globals {
+  time: f32,
+  delta_time: f32,
+  frame: u32,
+  resolution: vec2<f32>,
+};
+@group(1) @binding(0)
+var<uniform> globals: Globals;
+
+struct PointStyle {
+  size: f32,
+  shape: u32,
+};
+@group(2) @binding(0)
+var<uniform> style: PointStyle;
+
+const SHAPE_SQUARE: u32 = 0u;
+const SHAPE_CIRCLE: u32 = 1u;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) color: vec4<f32>,
+  // Position inside of the point in [-1, 1] range
+  @location(1) local: vec2<f32>,
+};
+
+// One pixel sized point per instance
+@vertex
+fn vs_points(point: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  out.clip_position = camera.view_projection * vec4<f32>(point.position, 1.0);
+  out.color = point.color;
+  out.local = vec2<f32>(0.0);
+  return out;
+}
+
+// Screen facing quad of `style.size` pixels per instance
+@vertex
+fn vs_quads(
+  @builtin(vertex_index) vertex_index: u32,
+  point: VertexInput,
+) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+  );
+  let corner = corners[vertex_index];
+  let clip = camera.view_projection * vec4<f32>(point.position, 1.0);
+  // Half of the size in pixels is `size / resolution` in NDC
+  let offset = corner * style.size / globals.resolution * clip.w;
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(clip.xy + offset, clip.zw);
+  out.color = point.color;
+  out.local = corner;
+  return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  if style.shape == SHAPE_CIRCLE && 1.0 < dot(vertex.local, vertex.local) {
+    discard;
+  }
+  return vertex.color;
+}
@@ -0,0 +1,54 @@
+// Vertex shader
+
+@group(1) @binding(0)
+var<uniform> transform: TransformUniform;
+@group(2) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+  @location(3) tangent: vec3<f32>,
+  @location(4) bitangent: vec3<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) world_position: vec3<f32>,
+  @location(1) world_normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+) -> VertexOutput {
+  let world_position = transform.transform * vec4<f32>(vertex.position, 1.0);
+
+  var out: VertexOutput;
+  out.clip_position = camera.view_projection * world_position;
+  out.world_position = world_position.xyz;
+  out.world_normal = (transform.rotate * vec4<f32>(vertex.normal, 0.0)).xyz;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var<uniform> properties: MaterialProperties;
+
+struct FragmentOut {
+  // W is 1 for covered pixels, the lighting pass skips the rest
+  @location(0) position: vec4<f32>,
+  @location(1) normal: vec4<f32>,
+  @location(2) albedo: vec4<f32>,
+};
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> FragmentOut {
+  var out: FragmentOut;
+  out.position = vec4<f32>(vertex.world_position, 1.0);
+  out.normal = vec4<f32>(normalize(vertex.world_normal), 0.0);
+  out.albedo = vec4<f32>(properties.diffuse, clamp(properties.shininess / MAX_SHININESS, 0.0, 1.0));
+  return out;
+}
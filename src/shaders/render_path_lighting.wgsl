@@ -0,0 +1,45 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_position: texture_2d<f32>;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(4)
+var t_albedo: texture_2d<f32>;
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(2) @binding(0)
+var<storage, read> lights: Lights;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let pixel = vec2<i32>(vertex.clip_position.xy);
+  let position = textureLoad(t_position, pixel, 0);
+  // Nothing was drawn here, keep the clear color
+  if (position.w == 0.0) {
+    discard;
+  }
+  let normal = textureLoad(t_normal, pixel, 0).xyz;
+  let albedo = textureLoad(t_albedo, pixel, 0);
+
+  let color = shade(position.xyz, normalize(normal), albedo.rgb, albedo.a * MAX_SHININESS);
+  return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,21 @@
+// Blinn-Phong shading by all point lights.
+// Uses `camera` and `lights` globals of the including shader.
+fn shade(position: vec3<f32>, normal: vec3<f32>, albedo: vec3<f32>, shininess: f32) -> vec3<f32> {
+  let view_dir = normalize(camera.position - position);
+  var result = vec3<f32>(0.0, 0.0, 0.0);
+  for (var i: i32 = 0; i < lights.lights_num; i = i + 1) {
+    let light = lights.lights[i];
+    let distance = distance(light.position, position);
+    let attenuation = light.intensity / (light.a_constant + light.a_linear * distance +
+                      light.a_quadratic * (distance * distance));
+
+    let light_dir = normalize(light.position - position);
+    let half_dir = normalize(view_dir + light_dir);
+
+    let diffuse_color = albedo * light.color * max(dot(normal, light_dir), 0.0);
+    let specular_color = light.color * pow(max(dot(normal, half_dir), 0.0), shininess);
+
+    result = result + (diffuse_color + specular_color) * attenuation;
+  }
+  return result;
+}
@@ -0,0 +1,43 @@
+// Types shared by the render path shaders
+
+struct MaterialProperties {
+  ambient: vec3<f32>,
+  diffuse: vec3<f32>,
+  specular: vec3<f32>,
+  // Matches padding of the `MaterialPropertiesUniform`
+  _pad: f32,
+  shininess: f32,
+};
+
+struct TransformUniform {
+  transform: mat4x4<f32>,
+  rotate: mat4x4<f32>,
+};
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+
+struct LightUniform {
+  position: vec3<f32>,
+  color: vec3<f32>,
+  // Matches padding of the `PointLightUniform`
+  _pad: f32,
+  a_constant: f32,
+  a_linear: f32,
+  a_quadratic: f32,
+  intensity: f32,
+};
+
+struct Lights {
+  lights_num: i32,
+  lights: array<LightUniform>,
+};
+
+// Shininess is stored in the albedo alpha normalized by this value
+const MAX_SHININESS: f32 = 512.0;
@@ -0,0 +1,76 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_current: texture_2d<f32>;
+@group(0) @binding(1)
+var s_current: sampler;
+
+@group(1) @binding(0)
+var t_history: texture_2d<f32>;
+@group(1) @binding(1)
+var s_history: sampler;
+
+@group(2) @binding(0)
+var t_velocity: texture_2d<f32>;
+@group(2) @binding(1)
+var s_velocity: sampler;
+
+struct TaaUniform {
+  blend_factor: f32,
+};
+@group(3) @binding(0)
+var<uniform> taa: TaaUniform;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let size = vec2<i32>(textureDimensions(t_current));
+  let pixel = vec2<i32>(vertex.clip_position.xy);
+
+  let current = textureLoad(t_current, pixel, 0).rgb;
+
+  // History is clamped to the 3x3 neighborhood of the current pixel
+  // to reject samples which are not visible anymore
+  var color_min = current;
+  var color_max = current;
+  for (var y = -1; y <= 1; y += 1) {
+    for (var x = -1; x <= 1; x += 1) {
+      let neighbor_pixel = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+      let neighbor = textureLoad(t_current, neighbor_pixel, 0).rgb;
+      color_min = min(color_min, neighbor);
+      color_max = max(color_max, neighbor);
+    }
+  }
+
+  let velocity = textureLoad(t_velocity, pixel, 0).xy;
+  let prev_tex_coords = vertex.tex_coords - velocity;
+  let history = clamp(
+    textureSample(t_history, s_history, prev_tex_coords).rgb,
+    color_min,
+    color_max,
+  );
+
+  // Pixels coming from outside of the screen have no history
+  let off_screen = any(prev_tex_coords < vec2<f32>(0.0)) || any(vec2<f32>(1.0) < prev_tex_coords);
+  let blend_factor = select(taa.blend_factor, 1.0, off_screen);
+
+  return vec4<f32>(mix(history, current, blend_factor), 1.0);
+}
@@ -0,0 +1,81 @@
+// Vertex shader
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Fullscreen triangle without vertex buffer
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.tex_coords = uv;
+  return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_depth: texture_2d<f32>;
+
+struct CameraUniform {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  view_projection: mat4x4<f32>,
+  view_projection_inverse: mat4x4<f32>,
+  view_projection_without_translation: mat4x4<f32>,
+  position: vec3<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VolumetricLight {
+  light_direction: vec3<f32>,
+  density: f32,
+  color: vec3<f32>,
+  decay: f32,
+  weight: f32,
+  exposure: f32,
+  samples: u32,
+};
+@group(2) @binding(0)
+var<uniform> light: VolumetricLight;
+
+// 1 where the sky is visible, 0 where geometry occludes the light
+fn light_mask(tex_coords: vec2<f32>) -> f32 {
+  if any(tex_coords < vec2<f32>(0.0)) || any(vec2<f32>(1.0) < tex_coords) {
+    return 0.0;
+  }
+  let size = vec2<f32>(textureDimensions(t_depth));
+  let pixel = vec2<i32>(min(tex_coords * size, size - 1.0));
+  let depth = textureLoad(t_depth, pixel, 0).r;
+  return select(0.0, 1.0, 1.0 <= depth);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  // Directional light is infinitely far, so only camera rotation matters
+  let light_clip = camera.view_projection_without_translation * vec4<f32>(-light.light_direction, 1.0);
+  if light_clip.w <= 0.0 {
+    return vec4<f32>(0.0);
+  }
+  let light_tex_coords = light_clip.xy / light_clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+
+  // Marches from the pixel towards the light accumulating unoccluded samples
+  let samples = max(light.samples, 1u);
+  let delta = (vertex.tex_coords - light_tex_coords) * light.density / f32(samples);
+  var tex_coords = vertex.tex_coords;
+  var illumination_decay = 1.0;
+  var result = 0.0;
+  for (var i = 0u; i < samples; i += 1u) {
+    tex_coords -= delta;
+    result += light_mask(tex_coords) * illumination_decay * light.weight;
+    illumination_decay *= light.decay;
+  }
+
+  return vec4<f32>(light.color * result * light.exposure / f32(samples), 0.0);
+}
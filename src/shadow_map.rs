@@ -1,8 +1,12 @@
-use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::camera::{Camera, OPENGL_TO_WGPU_MATRIX};
 use crate::prelude::GpuTexture;
 use crate::render::prelude::*;
 use crate::texture::EmptyTexture;
-use crate::{cgmath_imports::*, impl_simple_buffer, impl_simple_texture_bind_group};
+use crate::{
+    cgmath_imports::*, impl_simple_buffer, impl_simple_depth_texture_bind_group,
+    impl_simple_texture_bind_group,
+};
+use cgmath::EuclideanSpace;
 
 #[derive(Debug)]
 pub struct ShadowMap {
@@ -16,11 +20,21 @@ impl Default for ShadowMap {
                 dimensions: None,
                 format: TextureFormat::Depth32Float,
                 filtered: true,
+                compare: None,
             },
         }
     }
 }
 
+impl ShadowMap {
+    /// Shadow map for [`ShadowMapComparisonBindGroup`]
+    pub fn comparison() -> Self {
+        Self {
+            shadow_map: EmptyTexture::new_depth_comparison(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ShadowMapResource {
     texture: GpuTexture,
@@ -37,7 +51,7 @@ impl GpuResource for ShadowMap {
 
 #[derive(Debug, Clone, Copy)]
 pub struct ShadowMapHandle {
-    pub texture_id: ResourceId,
+    pub texture_id: TextureId,
 }
 
 impl ResourceHandle for ShadowMapHandle {
@@ -63,6 +77,10 @@ impl_simple_texture_bind_group!(
     { SamplerBindingType::Filtering }
 );
 
+impl_simple_depth_texture_bind_group!(ShadowMapHandle, ShadowMapComparisonBindGroup, {
+    TextureViewDimension::D2
+});
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ShadowMapDLightUniform {
@@ -113,6 +131,80 @@ impl ShadowMapDLight {
         }
     }
 
+    /// Light with orthographic bounds tightly covering the `camera` frustum,
+    /// so none of the shadow map resolution is wasted. Needs to be recreated
+    /// every time the camera moves. If the `scene_aabb` as `(min, max)` is provided,
+    /// bounds are also clipped to it and the depth range covers the whole scene,
+    /// so objects outside of the frustum still cast shadows into it.
+    pub fn fit_to_camera<D: Into<Vector3<f32>>>(
+        direction: D,
+        camera: &Camera,
+        scene_aabb: Option<([f32; 3], [f32; 3])>,
+    ) -> Self {
+        let corners = camera.frustum_corners();
+        let mut light = Self {
+            position: Point3::centroid(&corners),
+            direction: direction.into(),
+            left: 0.0,
+            right: 0.0,
+            bottom: 0.0,
+            top: 0.0,
+            near: 0.0,
+            far: 0.0,
+        };
+
+        // Bounds in the light view space
+        let view = light.view();
+        let bounds = |points: &mut dyn Iterator<Item = Point3<f32>>| {
+            points.fold(
+                (Vector3::from([f32::MAX; 3]), Vector3::from([f32::MIN; 3])),
+                |(min, max), p| {
+                    let p = (view * p.to_homogeneous()).truncate();
+                    (
+                        Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                        Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                    )
+                },
+            )
+        };
+        let (mut min, mut max) = bounds(&mut corners.into_iter());
+        if let Some((scene_min, scene_max)) = scene_aabb {
+            let (scene_min, scene_max) = bounds(&mut (0..8).map(|i| {
+                Point3::new(
+                    if i & 1 == 0 {
+                        scene_min[0]
+                    } else {
+                        scene_max[0]
+                    },
+                    if i & 2 == 0 {
+                        scene_min[1]
+                    } else {
+                        scene_max[1]
+                    },
+                    if i & 4 == 0 {
+                        scene_min[2]
+                    } else {
+                        scene_max[2]
+                    },
+                )
+            }));
+            // Frustum outside of the scene has nothing to shadow
+            let clipped_min = Vector3::new(min.x.max(scene_min.x), min.y.max(scene_min.y), 0.0);
+            let clipped_max = Vector3::new(max.x.min(scene_max.x), max.y.min(scene_max.y), 0.0);
+            if clipped_min.x < clipped_max.x && clipped_min.y < clipped_max.y {
+                (min.x, min.y, max.x, max.y) =
+                    (clipped_min.x, clipped_min.y, clipped_max.x, clipped_max.y);
+            }
+            (min.z, max.z) = (scene_min.z, scene_max.z);
+        }
+
+        // Light looks along -Z
+        (light.left, light.right) = (min.x, max.x);
+        (light.bottom, light.top) = (min.y, max.y);
+        (light.near, light.far) = (-max.z, -min.z);
+        light
+    }
+
     fn view(&self) -> Matrix4<f32> {
         OPENGL_TO_WGPU_MATRIX
             * Matrix4::look_to_rh(self.position, self.direction, Vector3::unit_y())
@@ -143,7 +235,7 @@ impl_simple_buffer!(
 );
 
 #[derive(Debug, Clone, Copy)]
-pub struct ShadowBindGroup(pub ResourceId);
+pub struct ShadowBindGroup(pub BindGroupId);
 
 impl AssetBindGroup for ShadowBindGroup {
     type ResourceHandle = (ShadowMapHandle, ShadowMapDLightHandle);
@@ -189,7 +281,7 @@ impl AssetBindGroup for ShadowBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) -> Self {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
 
         let (shadow_map, shadow_d_light) = resource;
         let texture = storage.get_texture(shadow_map.texture_id);
@@ -214,7 +306,13 @@ impl AssetBindGroup for ShadowBindGroup {
             label: Some("shadow_bind_group"),
         });
 
-        Self(storage.insert_bind_group(bind_group))
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![shadow_map.texture_id],
+                buffers: vec![shadow_d_light.buffer_id],
+            },
+        ))
     }
 
     fn replace(
@@ -223,7 +321,7 @@ impl AssetBindGroup for ShadowBindGroup {
         storage: &mut RenderStorage,
         resource: &Self::ResourceHandle,
     ) {
-        let layout = storage.get_bind_group_layout::<Self>();
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
 
         let (shadow_map, shadow_d_light) = resource;
         let texture = storage.get_texture(shadow_map.texture_id);
@@ -248,6 +346,65 @@ impl AssetBindGroup for ShadowBindGroup {
             label: Some("shadow_bind_group"),
         });
 
-        storage.replace_bind_group(self.0, bind_group);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![shadow_map.texture_id],
+                buffers: vec![shadow_d_light.buffer_id],
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::PerspectiveCamera;
+
+    #[test]
+    fn shadow_map_fit_to_camera() {
+        let camera = Camera::Perspective(PerspectiveCamera {
+            position: (0.0, 2.0, 0.0).into(),
+            yaw: Rad(0.0),
+            pitch: Deg(-20.0).into(),
+            aspect: 1.5,
+            fovy: Deg(60.0).into(),
+            znear: 0.1,
+            zfar: 20.0,
+        });
+        let direction = Vector3::new(1.0, -3.0, -3.0);
+        let to_light_ndc = |light: &ShadowMapDLight, p: Point3<f32>| {
+            let clip = light.projection() * light.view() * p.to_homogeneous();
+            clip.truncate() / clip.w
+        };
+
+        let light = ShadowMapDLight::fit_to_camera(direction, &camera, None);
+        let corners = camera.frustum_corners().map(|c| to_light_ndc(&light, c));
+        for ndc in corners.iter() {
+            assert!(
+                ndc.x.abs() <= 1.0 + 1e-4 && ndc.y.abs() <= 1.0 + 1e-4,
+                "{ndc:?}"
+            );
+            assert!(-1e-4 <= ndc.z && ndc.z <= 1.0 + 1e-4, "{ndc:?}");
+        }
+        // Bounds are tight
+        let max_x = corners.iter().map(|c| c.x.abs()).fold(0.0, f32::max);
+        assert!((max_x - 1.0).abs() < 1e-4);
+
+        // Scene smaller than the frustum shrinks the bounds, but keeps
+        // whole scene in the depth range
+        let scene = ([-2.0, 0.0, -2.0], [2.0, 1.0, 2.0]);
+        let fitted = ShadowMapDLight::fit_to_camera(direction, &camera, Some(scene));
+        assert!(fitted.right - fitted.left < light.right - light.left);
+        for i in 0..8 {
+            let corner = Point3::new(
+                if i & 1 == 0 { scene.0[0] } else { scene.1[0] },
+                if i & 2 == 0 { scene.0[1] } else { scene.1[1] },
+                if i & 4 == 0 { scene.0[2] } else { scene.1[2] },
+            );
+            let ndc = to_light_ndc(&fitted, corner);
+            assert!(-1e-4 <= ndc.z && ndc.z <= 1.0 + 1e-4, "{ndc:?}");
+        }
     }
 }
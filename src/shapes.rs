@@ -99,6 +99,10 @@ impl Quad {
         }
     }
 
+    /// Quad with reversed winding, so it faces the other way.
+    /// Texture coordinates stay the same. For showing render targets
+    /// upside down use [`crate::fullscreen::FullscreenQuad::new`] with
+    /// [`UvOrigin`](crate::render::prelude::UvOrigin).
     pub fn flipped(width: f32, height: f32) -> Self {
         Self {
             width,
@@ -149,29 +153,66 @@ impl From<Quad> for Mesh {
 #[derive(Debug, Copy, Clone)]
 pub struct Plane {
     pub size: f32,
+    /// Number of quads along the Z axis
+    pub rows: u32,
+    /// Number of quads along the X axis
+    pub cols: u32,
 }
 
 impl Plane {
     pub fn new(size: f32) -> Self {
-        Self { size }
+        Self::subdivided(size, 1, 1)
+    }
+
+    /// Grid of `rows` x `cols` quads with (rows + 1) x (cols + 1) vertices.
+    /// Useful for displacing vertices with a heightmap.
+    pub fn subdivided(size: f32, rows: u32, cols: u32) -> Self {
+        Self {
+            size,
+            rows: rows.max(1),
+            cols: cols.max(1),
+        }
     }
 }
 
 impl From<Plane> for Mesh {
     fn from(plane: Plane) -> Self {
         let extent = plane.size / 2.0;
+        let (rows, cols) = (plane.rows.max(1), plane.cols.max(1));
+
+        let mut vertices: Vec<MeshVertex> = (0..=rows)
+            .flat_map(|row| {
+                (0..=cols).map(move |col| {
+                    let u = col as f32 / cols as f32;
+                    let v = row as f32 / rows as f32;
+                    (
+                        [-extent + u * plane.size, 0.0, -extent + v * plane.size],
+                        [u, 1.0 - v],
+                        [0.0, 1.0, 0.0],
+                    )
+                        .into()
+                })
+            })
+            .collect();
 
-        let mut vertices: Vec<MeshVertex> = [
-            ([extent, 0.0, -extent], [1.0, 1.0], [0.0, 1.0, 0.0]),
-            ([extent, 0.0, extent], [1.0, 0.0], [0.0, 1.0, 0.0]),
-            ([-extent, 0.0, extent], [0.0, 0.0], [0.0, 1.0, 0.0]),
-            ([-extent, 0.0, -extent], [0.0, 1.0], [0.0, 1.0, 0.0]),
-        ]
-        .into_iter()
-        .map(Into::into)
-        .collect();
-
-        let indices = vec![0, 2, 1, 0, 3, 2];
+        let indices = (0..rows)
+            .flat_map(|row| {
+                (0..cols).flat_map(move |col| {
+                    let top_left = row * (cols + 1) + col;
+                    let top_right = top_left + 1;
+                    let bottom_left = top_left + cols + 1;
+                    let bottom_right = bottom_left + 1;
+                    [
+                        top_right,
+                        bottom_left,
+                        bottom_right,
+                        top_right,
+                        top_left,
+                        bottom_left,
+                    ]
+                })
+            })
+            .collect::<Vec<_>>();
 
         MeshVertex::calc_tangents_and_bitangents(&mut vertices, &indices);
 
@@ -299,3 +340,23 @@ impl From<Circle> for Mesh {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::prelude::FrontFace;
+
+    #[test]
+    fn plane_subdivided() {
+        let mesh: Mesh = Plane::subdivided(2.0, 2, 3).into();
+        assert_eq!(mesh.vertices.len(), 3 * 4);
+        assert_eq!(mesh.indices.len(), 2 * 3 * 6);
+        assert!(mesh.validate().is_ok());
+        assert_eq!(mesh.detect_front_face(), Some(FrontFace::Ccw));
+
+        let last = mesh.vertices.last().unwrap();
+        assert_eq!(last.position, [1.0, 0.0, 1.0]);
+        assert_eq!(last.tex_coords, [1.0, 0.0]);
+        assert_eq!(mesh.vertices[0].tex_coords, [0.0, 1.0]);
+    }
+}
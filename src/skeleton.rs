@@ -0,0 +1,124 @@
+use crate::render::prelude::*;
+use crate::transform::Transform;
+use crate::{cgmath_imports::*, impl_simple_buffer};
+use cgmath::SquareMatrix;
+
+/// Joints past this count are not uploaded by the [`SkeletonUniform`]
+pub const MAX_JOINTS: usize = 128;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkeletonUniform {
+    joints: [[[f32; 4]; 4]; MAX_JOINTS],
+}
+
+impl From<&Skeleton> for SkeletonUniform {
+    fn from(value: &Skeleton) -> Self {
+        // unused joints are left as identity so stray indices do not collapse vertices
+        let mut joints = [Matrix4::<f32>::identity().into(); MAX_JOINTS];
+        for (i, matrix) in value
+            .joint_matrices()
+            .into_iter()
+            .take(MAX_JOINTS)
+            .enumerate()
+        {
+            joints[i] = matrix.into();
+        }
+        Self { joints }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Joint {
+    /// Index of the parent joint in the [`Skeleton::joints`]
+    pub parent: Option<usize>,
+    /// Transform relative to the parent joint
+    pub transform: Transform,
+    pub inverse_bind_matrix: Matrix4<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    /// Transform of the joint in the model space
+    pub fn global_transform(&self, joint: usize) -> Matrix4<f32> {
+        let j = &self.joints[joint];
+        let local = Matrix4::from(&j.transform);
+        match j.parent {
+            Some(parent) => self.global_transform(parent) * local,
+            None => local,
+        }
+    }
+
+    /// Matrices that move vertices from the bind pose into the current pose
+    pub fn joint_matrices(&self) -> Vec<Matrix4<f32>> {
+        self.joints
+            .iter()
+            .enumerate()
+            .map(|(i, joint)| self.global_transform(i) * joint.inverse_bind_matrix)
+            .collect()
+    }
+}
+
+impl_simple_buffer!(
+    Skeleton,
+    SkeletonUniform,
+    SkeletonResources,
+    SkeletonHandle,
+    SkeletonBindGroup,
+    { BufferUsages::STORAGE | BufferUsages::COPY_DST },
+    { ShaderStages::VERTEX },
+    { BufferBindingType::Storage { read_only: true } }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joint(parent: Option<usize>, translation: Vector3<f32>) -> Joint {
+        let transform = Transform {
+            translation,
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        };
+        Joint {
+            parent,
+            transform,
+            inverse_bind_matrix: Matrix4::from(&transform).invert().unwrap(),
+        }
+    }
+
+    #[test]
+    fn skeleton_bind_pose_is_identity() {
+        let mut skeleton = Skeleton {
+            joints: vec![
+                joint(None, Vector3::new(0.0, 1.0, 0.0)),
+                joint(Some(0), Vector3::new(0.0, 2.0, 0.0)),
+            ],
+        };
+        // inverse bind of the child is relative to the model space
+        skeleton.joints[1].inverse_bind_matrix =
+            Matrix4::from_translation(Vector3::new(0.0, -3.0, 0.0));
+
+        for matrix in skeleton.joint_matrices() {
+            assert_eq!(matrix, Matrix4::identity());
+        }
+    }
+
+    #[test]
+    fn skeleton_child_follows_parent() {
+        let mut skeleton = Skeleton {
+            joints: vec![
+                joint(None, Vector3::new(0.0, 0.0, 0.0)),
+                joint(Some(0), Vector3::new(0.0, 1.0, 0.0)),
+            ],
+        };
+        skeleton.joints[0].transform.translation = Vector3::new(1.0, 0.0, 0.0);
+
+        let global = skeleton.global_transform(1);
+        assert_eq!(global.w.truncate(), Vector3::new(1.0, 1.0, 0.0));
+    }
+}
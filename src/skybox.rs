@@ -1,6 +1,6 @@
 use crate::mesh::GpuMesh;
 use crate::render::prelude::*;
-use crate::{impl_simple_texture_bind_group, texture};
+use crate::{impl_simple_buffer, impl_simple_texture_bind_group, texture};
 use image::ImageError;
 use texture::GpuTexture;
 
@@ -24,17 +24,58 @@ impl VertexLayout for SkyboxVertex {
     }
 }
 
+/// Environment texture of the [`Skybox`]
+#[derive(Debug)]
+pub enum SkyboxTexture {
+    /// Bound with [`SkyboxBindGroup`]
+    Cube(texture::CubeMap),
+    /// Bound with [`SkyboxEquirectBindGroup`]
+    Equirect(texture::EquirectTexture),
+}
+
+impl GpuResource for SkyboxTexture {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        match self {
+            Self::Cube(cube_map) => cube_map.build(renderer),
+            Self::Equirect(equirect) => equirect.build(renderer),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Skybox {
     pub vertices: Vec<f32>,
     pub num_elements: u32,
-    pub cube_map: texture::CubeMap,
+    pub texture: SkyboxTexture,
 }
 
 impl Skybox {
     pub fn load<P: AsRef<std::path::Path>>(paths: [P; 6]) -> Result<Self, ImageError> {
         let cube_map = texture::CubeMap::load(paths)?;
+        Ok(Self::new(SkyboxTexture::Cube(cube_map)))
+    }
+
+    /// Same as `load`, but with custom sampler settings, e.g. to match
+    /// the sampler of the environment map used for lighting
+    pub fn load_with_sampler<P: AsRef<std::path::Path>>(
+        paths: [P; 6],
+        sampler: texture::SamplerConfig,
+    ) -> Result<Self, ImageError> {
+        let cube_map = texture::CubeMap::load_with_sampler(paths, sampler)?;
+        Ok(Self::new(SkyboxTexture::Cube(cube_map)))
+    }
+
+    /// Loads single equirectangular image (e.g. `.hdr`) without
+    /// converting it into a cube map. Shader needs to map view
+    /// direction into the texture coordinates itself.
+    pub fn load_equirect_2d<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ImageError> {
+        let equirect = texture::EquirectTexture::load(path)?;
+        Ok(Self::new(SkyboxTexture::Equirect(equirect)))
+    }
 
+    pub fn new(texture: SkyboxTexture) -> Self {
         let vertices: Vec<f32> = vec![
             -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0,
             -1.0, 1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0,
@@ -45,24 +86,26 @@ impl Skybox {
             -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0,
         ];
 
-        Ok(Self {
+        Self {
             vertices,
             num_elements: 36,
-            cube_map,
-        })
+            texture,
+        }
     }
 }
 
 pub struct SkyboxResources {
     texture: GpuTexture,
     mesh: GpuMesh,
+    environment: EnvironmentMapResources,
 }
 
 impl GpuResource for Skybox {
     type ResourceType = SkyboxResources;
 
     fn build(&self, renderer: &Renderer) -> Self::ResourceType {
-        let texture = self.cube_map.build(renderer);
+        let texture = self.texture.build(renderer);
+        let environment = EnvironmentMap::new(&texture.texture).build(renderer);
 
         let vertex_buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
             label: Some("cube_map_vertex_buffer"),
@@ -75,14 +118,20 @@ impl GpuResource for Skybox {
             num_elements: self.num_elements,
         };
 
-        Self::ResourceType { texture, mesh }
+        Self::ResourceType {
+            texture,
+            mesh,
+            environment,
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct SkyboxHandle {
-    pub texture_id: ResourceId,
-    pub mesh_id: ResourceId,
+    pub texture_id: TextureId,
+    pub mesh_id: MeshId,
+    /// [`EnvironmentMap`] of the texture
+    pub environment: EnvironmentMapHandle,
 }
 
 impl ResourceHandle for SkyboxHandle {
@@ -93,12 +142,14 @@ impl ResourceHandle for SkyboxHandle {
         Self {
             texture_id: storage.insert_texture(resource.texture),
             mesh_id: storage.insert_mesh(resource.mesh),
+            environment: EnvironmentMapHandle::new(storage, resource.environment),
         }
     }
 
     fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
         storage.replace_texture(self.texture_id, resource.texture);
         storage.replace_mesh(self.mesh_id, resource.mesh);
+        self.environment.replace(storage, resource.environment);
     }
 }
 
@@ -109,3 +160,212 @@ impl_simple_texture_bind_group!(
     { TextureSampleType::Float { filterable: true } },
     { SamplerBindingType::Filtering }
 );
+
+impl_simple_texture_bind_group!(
+    SkyboxHandle,
+    SkyboxEquirectBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: true } },
+    { SamplerBindingType::Filtering }
+);
+
+/// Mip information of the environment cube map for roughness based sampling.
+/// Shaders select the mip with `textureSampleLevel(.., roughness * max_mip)`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    /// Index of the last mip level
+    pub max_mip: u32,
+    /// Width of the cube map face at mip level 0 in texels
+    pub face_size: u32,
+}
+
+impl EnvironmentMap {
+    pub fn new(texture: &Texture) -> Self {
+        Self {
+            max_mip: texture.mip_level_count() - 1,
+            face_size: texture.width(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentMapUniform {
+    pub max_mip: f32,
+    pub face_size: f32,
+    _pad: [f32; 2],
+}
+
+impl From<&EnvironmentMap> for EnvironmentMapUniform {
+    fn from(value: &EnvironmentMap) -> Self {
+        Self {
+            max_mip: value.max_mip as f32,
+            face_size: value.face_size as f32,
+            ..Default::default()
+        }
+    }
+}
+
+impl_simple_buffer!(
+    EnvironmentMap,
+    EnvironmentMapUniform,
+    EnvironmentMapResources,
+    EnvironmentMapHandle,
+    EnvironmentMapBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+/// Same as [`SkyboxBindGroup`] with the [`EnvironmentMap`] of the
+/// cube map at binding 2. [`SkyboxHandle`] rebuilds the environment
+/// map with the texture, so it always matches the bound texture.
+#[derive(Debug, Clone, Copy)]
+pub struct SkyboxEnvironmentBindGroup(pub BindGroupId);
+
+impl SkyboxEnvironmentBindGroup {
+    fn create_bind_group(
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        texture_id: TextureId,
+        buffer_id: BufferId,
+    ) -> BindGroup {
+        let layout = &storage.get_bind_group_layout::<Self>(renderer);
+        let texture = storage.get_texture(texture_id);
+        renderer.device().create_bind_group(&BindGroupDescriptor {
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&texture.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&texture.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: storage.get_buffer(buffer_id).as_entire_binding(),
+                },
+            ],
+            label: Some("skybox_environment_bind_group"),
+        })
+    }
+}
+
+impl AssetBindGroup for SkyboxEnvironmentBindGroup {
+    type ResourceHandle = SkyboxHandle;
+
+    fn bind_group_layout(renderer: &Renderer) -> BindGroupLayout {
+        renderer
+            .device()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: TextureViewDimension::Cube,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("skybox_environment_bind_group_layout"),
+            })
+    }
+
+    fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) -> Self {
+        let buffer_id = resource.environment.buffer_id;
+        let bind_group = Self::create_bind_group(renderer, storage, resource.texture_id, buffer_id);
+        Self(storage.insert_bind_group_with_dependencies(
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![resource.texture_id],
+                buffers: vec![buffer_id],
+            },
+        ))
+    }
+
+    fn replace(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        resource: &Self::ResourceHandle,
+    ) {
+        let buffer_id = resource.environment.buffer_id;
+        let bind_group = Self::create_bind_group(renderer, storage, resource.texture_id, buffer_id);
+        storage.replace_bind_group_with_dependencies(
+            self.0,
+            bind_group,
+            BindGroupDependencies {
+                textures: vec![resource.texture_id],
+                buffers: vec![buffer_id],
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skybox(face_size: u32, mip_level_count: u32) -> Skybox {
+        let faces = (0..mip_level_count)
+            .map(|mip| (face_size >> mip).pow(2) as usize * 4 * 6)
+            .sum();
+        Skybox::new(SkyboxTexture::Cube(texture::CubeMap {
+            format: TextureFormat::Rgba8Unorm,
+            texture: Some(vec![0; faces]),
+            dimensions: Some((face_size, face_size)),
+            mip_level_count,
+            sampler: texture::SamplerConfig::trilinear(),
+        }))
+    }
+
+    #[test]
+    fn skybox_environment_bind_group_replace() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+
+        let handle = SkyboxHandle::new(&mut storage, skybox(4, 1).build(&renderer));
+        let bind_group = SkyboxEnvironmentBindGroup::new(&renderer, &mut storage, &handle);
+
+        handle.replace(&mut storage, skybox(8, 2).build(&renderer));
+        bind_group.replace(&renderer, &mut storage, &handle);
+
+        let dependencies = storage.bind_group_dependencies(bind_group.0).unwrap();
+        assert_eq!(dependencies.textures, vec![handle.texture_id]);
+        assert_eq!(dependencies.buffers, vec![handle.environment.buffer_id]);
+        assert_eq!(
+            EnvironmentMap::new(&storage.get_texture(handle.texture_id).texture),
+            EnvironmentMap {
+                max_mip: 1,
+                face_size: 8,
+            }
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::prelude::GpuTexture;
+use crate::render::prelude::*;
+use crate::texture::EmptyTexture;
+use crate::{impl_simple_buffer, impl_simple_texture_bind_group};
+
+/// Format of the lit scene texture reflections are sampled from
+pub const SSR_COLOR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsrUniform {
+    max_steps: u32,
+    thickness: f32,
+    max_distance: f32,
+    roughness_fade: f32,
+}
+
+impl From<&Ssr> for SsrUniform {
+    fn from(value: &Ssr) -> Self {
+        Self {
+            max_steps: value.max_steps,
+            thickness: value.thickness,
+            max_distance: value.max_distance,
+            roughness_fade: value.roughness_fade,
+        }
+    }
+}
+
+/// Screen space reflections settings.
+/// Reflection rays are marched against g-buffer positions.
+#[derive(Debug)]
+pub struct Ssr {
+    /// Number of steps along the reflection ray
+    pub max_steps: u32,
+    /// Max depth difference at which ray is considered to hit the surface
+    pub thickness: f32,
+    /// Max length of the reflection ray in world units
+    pub max_distance: f32,
+    /// Surfaces with roughness above this value do not reflect
+    pub roughness_fade: f32,
+}
+
+impl Default for Ssr {
+    fn default() -> Self {
+        Self {
+            max_steps: 64,
+            thickness: 0.2,
+            max_distance: 10.0,
+            roughness_fade: 0.5,
+        }
+    }
+}
+
+impl_simple_buffer!(
+    Ssr,
+    SsrUniform,
+    SsrResources,
+    SsrHandle,
+    SsrBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+/// HDR texture lighting pass renders into. SSR pass samples
+/// reflections from it and composites them into the final image.
+#[derive(Debug, Default)]
+pub struct SsrColor;
+
+impl GpuResource for SsrColor {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        EmptyTexture {
+            dimensions: None,
+            format: SSR_COLOR_FORMAT,
+            filtered: true,
+            compare: None,
+        }
+        .build(renderer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SsrColorHandle {
+    pub texture_id: TextureId,
+}
+
+impl ResourceHandle for SsrColorHandle {
+    type OriginalResource<'a> = SsrColor;
+    type ResourceType = GpuTexture;
+
+    fn new(storage: &mut RenderStorage, resource: Self::ResourceType) -> Self {
+        Self {
+            texture_id: storage.insert_texture(resource),
+        }
+    }
+
+    fn replace(&self, storage: &mut RenderStorage, resource: Self::ResourceType) {
+        storage.replace_texture(self.texture_id, resource);
+    }
+}
+
+impl_simple_texture_bind_group!(
+    SsrColorHandle,
+    SsrColorBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: true } },
+    { SamplerBindingType::Filtering }
+);
@@ -0,0 +1,287 @@
+use crate::cgmath_imports::*;
+use crate::const_vec;
+use crate::fullscreen::{BlitSourceBindGroup, BlitSourceHandle, FullscreenTriangle};
+use crate::gbuffer::GBufferVelocityBindGroup;
+use crate::impl_simple_buffer;
+use crate::mesh::ProceduralCommand;
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::texture::{EmptyTexture, GpuTexture};
+use crate::utils::ConstVec;
+
+const TAA_SHADER: &str = include_str!("shaders/taa.wgsl");
+
+/// Format of the TAA history targets
+pub const TAA_HISTORY_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Element of the Halton low discrepancy sequence in [0, 1) range.
+/// `index` starts from 1.
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while 0 < index {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TaaUniform {
+    blend_factor: f32,
+    _pad: [f32; 3],
+}
+
+impl From<&Taa> for TaaUniform {
+    fn from(value: &Taa) -> Self {
+        Self {
+            blend_factor: value.blend_factor,
+            ..Default::default()
+        }
+    }
+}
+
+/// Temporal anti aliasing settings.
+/// Every frame the camera is jittered by a sub pixel offset with
+/// [`crate::camera::CameraHandle::update_jittered`] and the resolve pass
+/// blends the result with the reprojected history.
+#[derive(Debug)]
+pub struct Taa {
+    /// Weight of the current frame in the result.
+    /// Lower values give smoother edges, but more ghosting.
+    pub blend_factor: f32,
+    /// Number of jitter offsets before the sequence repeats
+    pub jitter_sequence: u32,
+}
+
+impl Default for Taa {
+    fn default() -> Self {
+        Self {
+            blend_factor: 0.1,
+            jitter_sequence: 8,
+        }
+    }
+}
+
+impl Taa {
+    /// Jitter of the `frame` in pixels in [-0.5, 0.5] range.
+    /// Uses Halton(2, 3) sequence.
+    pub fn jitter_offset(&self, frame: u32) -> Vector2<f32> {
+        let index = frame % self.jitter_sequence.max(1) + 1;
+        Vector2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+    }
+
+    /// Jitter of the `frame` in clip space units for
+    /// a render target of `width` x `height` pixels
+    pub fn jitter(&self, frame: u32, width: u32, height: u32) -> Vector2<f32> {
+        let offset = self.jitter_offset(frame);
+        Vector2::new(
+            offset.x * 2.0 / width as f32,
+            offset.y * 2.0 / height as f32,
+        )
+    }
+}
+
+impl_simple_buffer!(
+    Taa,
+    TaaUniform,
+    TaaResources,
+    TaaHandle,
+    TaaBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+/// Screen sized texture TAA resolves into
+#[derive(Debug, Default)]
+pub struct TaaHistory;
+
+impl GpuResource for TaaHistory {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        EmptyTexture {
+            dimensions: None,
+            format: TAA_HISTORY_FORMAT,
+            filtered: true,
+            compare: None,
+        }
+        .build(renderer)
+    }
+}
+
+/// Ping pong pair of [`TaaHistory`] targets. Each frame the resolve pass
+/// reads the previous target and writes into the current one.
+/// Targets are bound as [`BlitSourceBindGroup`], so the current one
+/// can be presented with [`crate::fullscreen::BlitPipeline`].
+#[derive(Debug)]
+pub struct TaaHistoryTargets {
+    pub texture_ids: [TextureId; 2],
+    pub bind_groups: [BlitSourceBindGroup; 2],
+    pub phases: [RenderPhase; 2],
+    current: usize,
+}
+
+impl TaaHistoryTargets {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage) -> Self {
+        let texture_ids = [(); 2].map(|_| storage.insert_texture(TaaHistory.build(renderer)));
+        let bind_groups = texture_ids.map(|texture_id| {
+            BlitSourceBindGroup::new(renderer, storage, &BlitSourceHandle { texture_id })
+        });
+        let phases = texture_ids.map(|view_id| {
+            RenderPhase::new(
+                const_vec![ColorAttachment {
+                    view_id,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                }],
+                None,
+            )
+        });
+
+        Self {
+            texture_ids,
+            bind_groups,
+            phases,
+            current: 0,
+        }
+    }
+
+    /// Makes the current target previous. Should be called
+    /// once per frame before the resolve pass.
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    /// Phase rendering into the current target
+    pub fn current_phase(&self) -> &RenderPhase {
+        &self.phases[self.current]
+    }
+
+    pub fn current_bind_group(&self) -> BlitSourceBindGroup {
+        self.bind_groups[self.current]
+    }
+
+    pub fn previous_bind_group(&self) -> BlitSourceBindGroup {
+        self.bind_groups[1 - self.current]
+    }
+
+    /// Recreates both targets with the new renderer size
+    pub fn resize(&self, renderer: &Renderer, storage: &mut RenderStorage) {
+        for (texture_id, bind_group) in self.texture_ids.iter().zip(self.bind_groups.iter()) {
+            storage.replace_texture(*texture_id, TaaHistory.build(renderer));
+            bind_group.replace(
+                renderer,
+                storage,
+                &BlitSourceHandle {
+                    texture_id: *texture_id,
+                },
+            );
+        }
+    }
+}
+
+/// Resolves jittered frame into the [`TaaHistoryTargets`].
+/// History is reprojected with the g-buffer velocity and clamped
+/// to the neighborhood of the current pixel to reduce ghosting.
+#[derive(Debug, Clone, Copy)]
+pub struct TaaPipeline {
+    pub pipeline_id: PipelineId,
+}
+
+impl TaaPipeline {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage) -> Self {
+        let shader = create_shader_module(renderer, "taa.wgsl", TAA_SHADER.into());
+        let pipeline = PipelineBuilder {
+            shader_path: "taa.wgsl",
+            label: Some("taa_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<BlitSourceBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<BlitSourceBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<GBufferVelocityBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<TaaBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format: TAA_HISTORY_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_pipeline(pipeline),
+        }
+    }
+
+    /// Command resolving `current` frame with the previous target of `history`.
+    /// Has to be executed in the [`TaaHistoryTargets::current_phase`].
+    pub fn command(
+        &self,
+        current: BlitSourceBindGroup,
+        history: &TaaHistoryTargets,
+        velocity: GBufferVelocityBindGroup,
+        taa: TaaBindGroup,
+    ) -> ProceduralCommand {
+        FullscreenTriangle::command(
+            self.pipeline_id,
+            const_vec![
+                BindGroupMeta::new(0, current.0),
+                BindGroupMeta::new(1, history.previous_bind_group().0),
+                BindGroupMeta::new(2, velocity.0),
+                BindGroupMeta::new(3, taa.0),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taa_halton() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(2, 2), 0.25);
+        assert_eq!(halton(3, 2), 0.75);
+        assert_eq!(halton(1, 3), 1.0 / 3.0);
+        assert_eq!(halton(2, 3), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn taa_jitter_sequence_repeats() {
+        let taa = Taa {
+            jitter_sequence: 4,
+            ..Default::default()
+        };
+        assert_eq!(taa.jitter_offset(1), taa.jitter_offset(5));
+        assert_ne!(taa.jitter_offset(1), taa.jitter_offset(2));
+        let jitter = taa.jitter(0, 100, 100);
+        assert_eq!(jitter.x, 0.0);
+        assert!((jitter.y + 1.0 / 300.0).abs() < f32::EPSILON);
+    }
+}
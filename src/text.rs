@@ -0,0 +1,137 @@
+use crate::{
+    const_vec,
+    egui::{
+        write_image_delta, EguiBuffer, EguiBufferBindGroup, EguiBufferHandle, EguiTextureBindGroup,
+        EguiTextureHandle,
+    },
+    mesh::{Mesh, MeshHandle, MeshRenderCommand},
+    render::prelude::*,
+    utils::ConstVec,
+};
+use egui::epaint::{
+    text::{FontDefinitions, Fonts},
+    Color32, FontId, Pos2, Shape, TessellationOptions, Tessellator,
+};
+
+const MAX_FONT_TEXTURE_SIDE: usize = 2048;
+
+/// Renders text labels in screen space using egui font atlas
+/// without running full egui context.
+/// Uses the same vertex layout and bind groups as [`crate::egui::EguiRenderContext`],
+/// so egui shader can be used for the pipeline.
+pub struct TextRenderContext {
+    fonts: Fonts,
+    shapes: Vec<Shape>,
+    mesh: MeshHandle,
+    texture: Option<(EguiTextureHandle, EguiTextureBindGroup)>,
+
+    screen_size: [f32; 2],
+    uniform_buffer_handle: EguiBufferHandle,
+    uniform_buffer_bind_group: EguiBufferBindGroup,
+}
+
+impl TextRenderContext {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage) -> Self {
+        let screen_size = [renderer.size().width as f32, renderer.size().height as f32];
+        let egui_buffer = EguiBuffer { screen_size };
+        let buffer_handle = EguiBufferHandle::new(storage, egui_buffer.build(renderer));
+        let buffer_bind_group = EguiBufferBindGroup::new(renderer, storage, &buffer_handle);
+
+        Self {
+            fonts: Fonts::new(1.0, MAX_FONT_TEXTURE_SIDE, FontDefinitions::default()),
+            shapes: Vec::new(),
+            mesh: MeshHandle::new(
+                storage,
+                Mesh {
+                    name: "text".to_owned(),
+                    vertices: vec![],
+                    indices: vec![],
+                }
+                .build(renderer),
+            ),
+            texture: None,
+            screen_size,
+            uniform_buffer_handle: buffer_handle,
+            uniform_buffer_bind_group: buffer_bind_group,
+        }
+    }
+
+    /// Queues text for the next [`TextRenderContext::update`].
+    /// Position is the top left corner of the text in pixels.
+    pub fn draw_text(&mut self, position: [f32; 2], text: &str, size: f32, color: [u8; 4]) {
+        let color = Color32::from_rgba_unmultiplied(color[0], color[1], color[2], color[3]);
+        let galley = self
+            .fonts
+            .layout_no_wrap(text.to_owned(), FontId::proportional(size), color);
+        self.shapes.push(Shape::galley(
+            Pos2::new(position[0], position[1]),
+            galley,
+            color,
+        ));
+    }
+
+    /// Tessellates queued text and uploads it with any font atlas changes to the GPU
+    pub fn update(&mut self, renderer: &Renderer, storage: &mut RenderStorage) {
+        let current_screen_size = [renderer.size().width as f32, renderer.size().height as f32];
+        if self.screen_size != current_screen_size {
+            let egui_buffer = EguiBuffer {
+                screen_size: current_screen_size,
+            };
+            self.uniform_buffer_handle
+                .update(renderer, storage, &egui_buffer);
+            self.screen_size = current_screen_size;
+        }
+
+        let mut mesh = egui::epaint::Mesh::default();
+        let mut tessellator = Tessellator::new(
+            1.0,
+            TessellationOptions::default(),
+            self.fonts.font_image_size(),
+            vec![],
+        );
+        for shape in self.shapes.drain(..) {
+            tessellator.tessellate_shape(shape, &mut mesh);
+        }
+
+        if let Some(image_delta) = self.fonts.font_image_delta() {
+            let texture_handle = self.texture.as_ref().map(|(handle, _)| handle);
+            if let Some(texture) =
+                write_image_delta(renderer, storage, texture_handle, &image_delta)
+            {
+                self.texture = Some(texture);
+            }
+        }
+
+        self.mesh.update_vertices(renderer, storage, &mesh.vertices);
+        self.mesh.update_indices(renderer, storage, &mesh.indices);
+
+        // prepare fonts for the next batch of text
+        self.fonts.begin_frame(1.0, MAX_FONT_TEXTURE_SIDE);
+    }
+
+    /// Command drawing all text uploaded by the last [`TextRenderContext::update`].
+    /// Pipeline should use [`crate::egui::EguiVertex`] layout with
+    /// [`EguiBufferBindGroup`] and [`EguiTextureBindGroup`] layouts.
+    pub fn create_command(
+        &self,
+        pipeline_id: PipelineId,
+        storage: &RenderStorage,
+    ) -> Option<MeshRenderCommand> {
+        let (_, texture_bind_group) = self.texture.as_ref()?;
+        if storage.get_mesh(self.mesh.mesh_id).num_elements == 0 {
+            return None;
+        }
+        Some(MeshRenderCommand {
+            pipeline_id,
+            mesh_id: self.mesh.mesh_id,
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect: None,
+            viewport: None,
+            bind_groups: const_vec![
+                BindGroupMeta::new(0, self.uniform_buffer_bind_group.0),
+                BindGroupMeta::new(1, texture_bind_group.0),
+            ],
+        })
+    }
+}
@@ -1,7 +1,9 @@
+use crate::impl_simple_texture_bind_group;
 use crate::render::prelude::*;
+use crate::utils::Task;
 use image::{GenericImageView, ImageError};
 use log::info;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
@@ -48,7 +50,7 @@ pub struct GpuTexture {
 }
 
 impl GpuTexture {
-    pub fn color_attachment(&self) -> RenderPassColorAttachment {
+    pub fn color_attachment(&self) -> RenderPassColorAttachment<'_> {
         RenderPassColorAttachment {
             view: &self.view,
             resolve_target: None,
@@ -60,10 +62,99 @@ impl GpuTexture {
     }
 }
 
+/// Format of the object id render target
+pub const OBJECT_ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
 #[derive(Debug, Clone, Copy)]
 pub enum TextureType {
     Diffuse,
     Normal,
+    /// Grayscale height with white being the highest point
+    Height,
+}
+
+/// Sampler settings of the [`ImageTexture`] and the [`CubeMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerConfig {
+    /// `Repeat` lets tiling textures use texture coordinates outside of [0, 1].
+    /// Cube maps are always sampled across face edges in wgpu,
+    /// so clamping is enough for them to avoid visible seams.
+    pub address_mode: AddressMode,
+    /// `Nearest` keeps sharp texel edges, e.g. for pixel art
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    /// `Linear` gives trilinear filtering, so shaders can pick
+    /// a fractional mip by roughness with `textureSampleLevel`.
+    pub mipmap_filter: FilterMode,
+    /// Maximum anisotropy. 1 disables anisotropic filtering.
+    /// Only used when all filters are `Linear`.
+    pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            address_mode: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Default filtering with `Repeat` address mode
+    pub fn repeat() -> Self {
+        Self {
+            address_mode: AddressMode::Repeat,
+            ..Default::default()
+        }
+    }
+
+    /// `Nearest` filtering with the default address mode
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    /// Trilinear filtering, default of the [`CubeMap`]
+    pub fn trilinear() -> Self {
+        Self {
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        }
+    }
+
+    /// Anisotropic filtering requires all filters to be linear.
+    /// wgpu accepts clamps in `1..=16` range.
+    fn effective_anisotropy_clamp(&self) -> u16 {
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == FilterMode::Linear);
+        if linear {
+            self.anisotropy_clamp.clamp(1, 16)
+        } else {
+            1
+        }
+    }
+
+    fn descriptor(&self) -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: self.effective_anisotropy_clamp(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -71,6 +162,7 @@ pub struct ImageTexture {
     texture_type: TextureType,
     texture: Option<image::RgbaImage>,
     dimensions: Option<(u32, u32)>,
+    sampler: SamplerConfig,
 }
 
 impl ImageTexture {
@@ -82,8 +174,75 @@ impl ImageTexture {
             texture_type,
             texture: Some(img.to_rgba8()),
             dimensions: Some(img.dimensions()),
+            sampler: SamplerConfig::default(),
         })
     }
+
+    /// Loads texture with custom sampler settings
+    pub fn load_with_sampler<P: AsRef<Path>>(
+        path: P,
+        texture_type: TextureType,
+        sampler: SamplerConfig,
+    ) -> Result<Self, ImageError> {
+        Ok(Self::load(path, texture_type)?.with_sampler(sampler))
+    }
+
+    /// Replaces sampler settings, e.g. of the texture
+    /// loaded with [`ImageTexture::load_async`]
+    pub fn with_sampler(self, sampler: SamplerConfig) -> Self {
+        Self { sampler, ..self }
+    }
+
+    /// Loads and decodes the image on a separate thread.
+    /// Resulting texture still needs to be built on the main thread.
+    pub fn load_async<P: Into<PathBuf>>(
+        path: P,
+        texture_type: TextureType,
+    ) -> Task<Result<Self, ImageError>> {
+        let path = path.into();
+        Task::spawn(move || Self::load(path, texture_type))
+    }
+
+    /// 1x1 white diffuse texture
+    pub fn placeholder_diffuse() -> Self {
+        Self::from_pixel(TextureType::Diffuse, [255, 255, 255, 255])
+    }
+
+    /// 1x1 flat normal texture pointing along the surface normal
+    pub fn placeholder_normal() -> Self {
+        Self::from_pixel(TextureType::Normal, [128, 128, 255, 255])
+    }
+
+    /// 1x1 height texture with the whole surface at the highest point
+    pub fn placeholder_height() -> Self {
+        Self::from_pixel(TextureType::Height, [255, 255, 255, 255])
+    }
+
+    /// Placeholder texture for the provided type
+    pub fn placeholder(texture_type: TextureType) -> Self {
+        match texture_type {
+            TextureType::Diffuse => Self::placeholder_diffuse(),
+            TextureType::Normal => Self::placeholder_normal(),
+            TextureType::Height => Self::placeholder_height(),
+        }
+    }
+
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.dimensions
+    }
+
+    pub fn sampler(&self) -> SamplerConfig {
+        self.sampler
+    }
+
+    fn from_pixel(texture_type: TextureType, pixel: [u8; 4]) -> Self {
+        Self {
+            texture_type,
+            texture: Some(image::RgbaImage::from_pixel(1, 1, image::Rgba(pixel))),
+            dimensions: Some((1, 1)),
+            sampler: SamplerConfig::default(),
+        }
+    }
 }
 
 impl GpuResource for ImageTexture {
@@ -111,26 +270,18 @@ impl GpuResource for ImageTexture {
             dimension: TextureDimension::D2,
             format: match self.texture_type {
                 TextureType::Diffuse => TextureFormat::Rgba8UnormSrgb,
-                TextureType::Normal => TextureFormat::Rgba8Unorm,
+                TextureType::Normal | TextureType::Height => TextureFormat::Rgba8Unorm,
             },
             view_formats: match self.texture_type {
                 TextureType::Diffuse => &[TextureFormat::Rgba8UnormSrgb],
-                TextureType::Normal => &[TextureFormat::Rgba8Unorm],
+                TextureType::Normal | TextureType::Height => &[TextureFormat::Rgba8Unorm],
             },
             usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
             label: Some("texture"),
         });
 
         let view = texture.create_view(&TextureViewDescriptor::default());
-        let sampler = renderer.device().create_sampler(&SamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = renderer.device().create_sampler(&self.sampler.descriptor());
 
         if let Some(data) = &self.texture {
             renderer.queue().write_texture(
@@ -163,6 +314,9 @@ pub struct EmptyTexture {
     pub dimensions: Option<(u32, u32)>,
     pub format: TextureFormat,
     pub filtered: bool,
+    /// Creates comparison sampler with this function.
+    /// Needed for depth textures bound with `SamplerBindingType::Comparison`.
+    pub compare: Option<CompareFunction>,
 }
 
 impl EmptyTexture {
@@ -171,6 +325,50 @@ impl EmptyTexture {
             dimensions: None,
             format: TextureFormat::Depth32Float,
             filtered: true,
+            compare: None,
+        }
+    }
+
+    /// Depth texture with comparison sampler for
+    /// [`impl_simple_depth_texture_bind_group`](crate::impl_simple_depth_texture_bind_group).
+    /// Linear filtering makes the hardware average 4 comparisons.
+    pub fn new_depth_comparison() -> Self {
+        Self {
+            dimensions: None,
+            format: TextureFormat::Depth32Float,
+            filtered: true,
+            compare: Some(CompareFunction::LessEqual),
+        }
+    }
+
+    /// Depth texture with nearest sampler, so it can be sampled in later
+    /// passes with [`DepthTextureBindGroup`]
+    pub fn new_depth_sampled() -> Self {
+        Self {
+            dimensions: None,
+            format: TextureFormat::Depth32Float,
+            filtered: false,
+            compare: None,
+        }
+    }
+
+    /// Depth texture with 8 bit stencil aspect
+    pub fn new_depth_stencil() -> Self {
+        Self {
+            dimensions: None,
+            format: TextureFormat::Depth24PlusStencil8,
+            filtered: true,
+            compare: None,
+        }
+    }
+
+    /// Texture for per pixel object ids used for picking
+    pub fn new_object_id() -> Self {
+        Self {
+            dimensions: None,
+            format: OBJECT_ID_FORMAT,
+            filtered: false,
+            compare: None,
         }
     }
 }
@@ -199,7 +397,9 @@ impl GpuResource for EmptyTexture {
             dimension: TextureDimension::D2,
             format: self.format,
             view_formats: &[self.format],
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
             label: None,
         };
         let texture = renderer.device().create_texture(&desc);
@@ -218,7 +418,7 @@ impl GpuResource for EmptyTexture {
             mag_filter: filter_mode,
             min_filter: filter_mode,
             mipmap_filter: FilterMode::Nearest,
-            compare: None,
+            compare: self.compare,
             lod_min_clamp: 0.0,
             lod_max_clamp: 100.0,
             ..Default::default()
@@ -232,31 +432,208 @@ impl GpuResource for EmptyTexture {
     }
 }
 
+/// Depth texture in the storage sampled by post processing passes,
+/// e.g. to reconstruct positions from depth
+#[derive(Debug, Clone, Copy)]
+pub struct DepthTextureHandle {
+    pub texture_id: TextureId,
+}
+
+// Depth values can not be filtered, so the texture needs a nearest sampler
+// (e.g. `EmptyTexture::new_depth_sampled`)
+impl_simple_texture_bind_group!(
+    DepthTextureHandle,
+    DepthTextureBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Depth },
+    { SamplerBindingType::NonFiltering }
+);
+
+// Same for depth written into a float color target by a prepass (e.g. `R32Float`).
+// Also binds depth textures read with `textureLoad`, which GL backend
+// does not support for `texture_depth_2d`.
+impl_simple_texture_bind_group!(
+    DepthTextureHandle,
+    FloatDepthTextureBindGroup,
+    { TextureViewDimension::D2 },
+    { TextureSampleType::Float { filterable: false } },
+    { SamplerBindingType::NonFiltering }
+);
+
+/// 3D texture with a [`TextureViewDimension::D3`] view.
+/// Can be bound with [`impl_simple_volume_texture_bind_group`](crate::impl_simple_volume_texture_bind_group).
+#[derive(Debug)]
+pub struct VolumeTexture {
+    /// Width, height and depth in texels
+    pub dimensions: (u32, u32, u32),
+    pub format: TextureFormat,
+    /// Texels ordered by x, then y, then z. Texture is left empty if `None`.
+    pub data: Option<Vec<u8>>,
+    pub filtered: bool,
+}
+
+impl GpuResource for VolumeTexture {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let texture_size = Extent3d {
+            width: self.dimensions.0,
+            height: self.dimensions.1,
+            depth_or_array_layers: self.dimensions.2,
+        };
+
+        let texture = renderer.device().create_texture(&TextureDescriptor {
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: self.format,
+            view_formats: &[self.format],
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            label: Some("volume_texture"),
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D3),
+            ..Default::default()
+        });
+        let filter_mode = if self.filtered {
+            FilterMode::Linear
+        } else {
+            FilterMode::Nearest
+        };
+        let sampler = renderer.device().create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: filter_mode,
+            min_filter: filter_mode,
+            ..Default::default()
+        });
+
+        if let Some(data) = &self.data {
+            let block_size = self.format.block_copy_size(None).unwrap();
+            debug_assert_eq!(
+                data.len(),
+                (block_size * self.dimensions.0 * self.dimensions.1 * self.dimensions.2) as usize,
+                "volume texture data does not match dimensions"
+            );
+            renderer.queue().write_texture(
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                data,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(block_size * self.dimensions.0),
+                    rows_per_image: Some(self.dimensions.1),
+                },
+                texture_size,
+            );
+        }
+
+        Self::ResourceType {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+/// Number of mip levels in a full mip chain for the given dimensions
+pub fn max_mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Concatenates faces of every mip level. Each level is
+/// downsampled from the previous one separately for every face.
+fn generate_cube_mips(faces: Vec<image::RgbaImage>, mip_level_count: u32) -> Vec<u8> {
+    let mut levels = vec![faces];
+    for _ in 1..mip_level_count {
+        let next = levels
+            .last()
+            .unwrap()
+            .iter()
+            .map(|face| {
+                image::imageops::resize(
+                    face,
+                    (face.width() / 2).max(1),
+                    (face.height() / 2).max(1),
+                    image::imageops::FilterType::Triangle,
+                )
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+        .into_iter()
+        .flatten()
+        .flat_map(|face| face.into_raw())
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct CubeMap {
     pub format: TextureFormat,
+    /// Faces of all mip levels. Level 0 faces come first.
     pub texture: Option<Vec<u8>>,
     pub dimensions: Option<(u32, u32)>,
+    pub mip_level_count: u32,
+    pub sampler: SamplerConfig,
 }
 
 impl CubeMap {
+    /// Loads cube map with a full mip chain
     pub fn load<P: AsRef<Path>>(paths: [P; 6]) -> Result<Self, ImageError> {
-        let mut texture_data = Vec::new();
+        Self::load_with_mips(paths, u32::MAX)
+    }
+
+    /// Loads cube map with a full mip chain and custom sampler settings
+    pub fn load_with_sampler<P: AsRef<Path>>(
+        paths: [P; 6],
+        sampler: SamplerConfig,
+    ) -> Result<Self, ImageError> {
+        Ok(Self {
+            sampler,
+            ..Self::load(paths)?
+        })
+    }
+
+    /// Loads cube map and generates `mip_level_count` mip levels.
+    /// Mip level count is clamped to the full mip chain length.
+    pub fn load_with_mips<P: AsRef<Path>>(
+        paths: [P; 6],
+        mip_level_count: u32,
+    ) -> Result<Self, ImageError> {
+        let mut faces = Vec::new();
         let mut dimensions = (0, 0);
         for path in paths {
             let path_copy = path.as_ref().to_path_buf();
             info!("Loading texture from {:#?}", path_copy);
             let img = image::open(path)?;
             dimensions = img.dimensions();
-            texture_data.extend(img.to_rgba8().into_raw());
+            faces.push(img.to_rgba8());
         }
 
+        let mip_level_count = mip_level_count.clamp(1, max_mip_levels(dimensions.0, dimensions.1));
         Ok(Self {
             format: TextureFormat::Rgba8UnormSrgb,
-            texture: Some(texture_data),
+            texture: Some(generate_cube_mips(faces, mip_level_count)),
             dimensions: Some(dimensions),
+            mip_level_count,
+            sampler: SamplerConfig::trilinear(),
         })
     }
+
+    /// Loads and decodes all cube map faces on a separate thread.
+    /// Resulting cube map still needs to be built on the main thread.
+    pub fn load_async<P: Into<PathBuf>>(paths: [P; 6]) -> Task<Result<Self, ImageError>> {
+        let paths = paths.map(Into::into);
+        Task::spawn(move || Self::load(paths))
+    }
 }
 
 impl GpuResource for CubeMap {
@@ -279,7 +656,7 @@ impl GpuResource for CubeMap {
 
         let texture = renderer.device().create_texture(&TextureDescriptor {
             size: texture_size,
-            mip_level_count: 1,
+            mip_level_count: self.mip_level_count,
             sample_count: 1,
             dimension: TextureDimension::D2,
             format: self.format,
@@ -292,33 +669,132 @@ impl GpuResource for CubeMap {
             dimension: Some(TextureViewDimension::Cube),
             ..Default::default()
         });
+        let sampler = renderer.device().create_sampler(&self.sampler.descriptor());
+
+        if let Some(data) = &self.texture {
+            let mut offset = 0;
+            for mip_level in 0..self.mip_level_count {
+                let size = texture_size.mip_level_size(mip_level, TextureDimension::D2);
+                let level_bytes = (4 * size.width * size.height * 6) as usize;
+                renderer.queue().write_texture(
+                    ImageCopyTexture {
+                        texture: &texture,
+                        mip_level,
+                        origin: Origin3d::ZERO,
+                        aspect: TextureAspect::All,
+                    },
+                    &data[offset..offset + level_bytes],
+                    ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * size.width),
+                        rows_per_image: Some(size.height),
+                    },
+                    size,
+                );
+                offset += level_bytes;
+            }
+        }
+
+        Self::ResourceType {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+/// Converts float image into `Rgba16Float` texel data
+fn rgba32f_to_rgba16f(image: &image::Rgba32FImage) -> Vec<u8> {
+    let texels: Vec<u16> = image
+        .as_raw()
+        .iter()
+        .map(|c| half::f16::from_f32(*c).to_bits())
+        .collect();
+    bytemuck::cast_slice(&texels).to_vec()
+}
+
+/// Single 2D texture with equirectangular projection of the environment.
+/// HDR images are stored as `Rgba16Float`, others as `Rgba8UnormSrgb`.
+#[derive(Debug)]
+pub struct EquirectTexture {
+    pub format: TextureFormat,
+    pub texture: Vec<u8>,
+    pub dimensions: (u32, u32),
+}
+
+impl EquirectTexture {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        info!(
+            "loading equirect texture from {:#?}",
+            path.as_ref().to_path_buf()
+        );
+        let img = image::open(path)?;
+        let dimensions = img.dimensions();
+
+        let (format, texture) = match img {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => (
+                TextureFormat::Rgba16Float,
+                rgba32f_to_rgba16f(&img.to_rgba32f()),
+            ),
+            _ => (TextureFormat::Rgba8UnormSrgb, img.to_rgba8().into_raw()),
+        };
+
+        Ok(Self {
+            format,
+            texture,
+            dimensions,
+        })
+    }
+}
+
+impl GpuResource for EquirectTexture {
+    type ResourceType = GpuTexture;
+
+    fn build(&self, renderer: &Renderer) -> Self::ResourceType {
+        let texture_size = Extent3d {
+            width: self.dimensions.0,
+            height: self.dimensions.1,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = renderer.device().create_texture(&TextureDescriptor {
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            view_formats: &[self.format],
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            label: Some("equirect_texture"),
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        // Longitude wraps around, latitude stops at the poles
         let sampler = renderer.device().create_sampler(&SamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_u: AddressMode::Repeat,
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
             mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
             ..Default::default()
         });
 
-        if let Some(data) = &self.texture {
-            renderer.queue().write_texture(
-                ImageCopyTexture {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: Origin3d::ZERO,
-                    aspect: TextureAspect::All,
-                },
-                data,
-                ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * texture_size.width),
-                    rows_per_image: Some(texture_size.height),
-                },
-                texture_size,
-            );
-        }
+        let block_size = self.format.block_copy_size(None).unwrap();
+        renderer.queue().write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &self.texture,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(block_size * self.dimensions.0),
+                rows_per_image: Some(self.dimensions.1),
+            },
+            texture_size,
+        );
 
         Self::ResourceType {
             texture,
@@ -327,3 +803,70 @@ impl GpuResource for CubeMap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_sampler_anisotropy() {
+        let sampler = SamplerConfig {
+            anisotropy_clamp: 16,
+            ..SamplerConfig::trilinear()
+        };
+        assert_eq!(sampler.effective_anisotropy_clamp(), 16);
+        let too_high = SamplerConfig {
+            anisotropy_clamp: 64,
+            ..sampler
+        };
+        assert_eq!(too_high.effective_anisotropy_clamp(), 16);
+        let nearest = SamplerConfig {
+            mipmap_filter: FilterMode::Nearest,
+            ..sampler
+        };
+        assert_eq!(nearest.effective_anisotropy_clamp(), 1);
+        assert_eq!(SamplerConfig::trilinear().effective_anisotropy_clamp(), 1);
+        let default = SamplerConfig {
+            anisotropy_clamp: 16,
+            ..Default::default()
+        };
+        assert_eq!(default.effective_anisotropy_clamp(), 1);
+    }
+
+    #[test]
+    fn texture_sampler_config() {
+        let descriptor = SamplerConfig::repeat().descriptor();
+        assert_eq!(descriptor.address_mode_u, AddressMode::Repeat);
+        assert_eq!(descriptor.address_mode_v, AddressMode::Repeat);
+        assert_eq!(descriptor.mag_filter, FilterMode::Linear);
+
+        let texture = ImageTexture::placeholder_diffuse().with_sampler(SamplerConfig::nearest());
+        assert_eq!(texture.sampler().mag_filter, FilterMode::Nearest);
+        assert_eq!(texture.sampler().address_mode, AddressMode::ClampToEdge);
+    }
+
+    #[test]
+    fn texture_max_mip_levels() {
+        assert_eq!(max_mip_levels(1, 1), 1);
+        assert_eq!(max_mip_levels(512, 512), 10);
+        assert_eq!(max_mip_levels(640, 480), 10);
+    }
+
+    #[test]
+    fn texture_cube_mips_size() {
+        let faces = vec![image::RgbaImage::new(4, 2); 6];
+        let data = generate_cube_mips(faces, 3);
+        assert_eq!(data.len(), 4 * 6 * (4 * 2 + 2 + 1));
+    }
+
+    #[test]
+    fn texture_rgba32f_to_rgba16f() {
+        let image = image::Rgba32FImage::from_raw(1, 1, vec![0.0, 1.0, 2.0, 65504.0]).unwrap();
+        let data = rgba32f_to_rgba16f(&image);
+        let texels: Vec<u16> = data
+            .chunks(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(texels, [0x0000, 0x3c00, 0x4000, 0x7bff]);
+    }
+}
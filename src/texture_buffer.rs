@@ -1,19 +1,18 @@
 use crate::render::prelude::*;
 use image::{ImageBuffer, Rgba};
 
-#[cfg(feature = "headless")]
-use std::num::NonZeroU32;
-
 pub struct TextureBuffer {
     buffer: Buffer,
     width: u32,
     height: u32,
+    bytes_per_row: u32,
 }
 
 impl TextureBuffer {
     pub fn new(renderer: &Renderer, width: u32, height: u32) -> Self {
         let u32_size = std::mem::size_of::<u32>() as u32;
-        let output_buffer_size = (u32_size * width * height) as wgpu::BufferAddress;
+        let bytes_per_row = padded_bytes_per_row(width, u32_size);
+        let output_buffer_size = (bytes_per_row * height) as wgpu::BufferAddress;
         let output_buffer_desc = wgpu::BufferDescriptor {
             size: output_buffer_size,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
@@ -25,18 +24,20 @@ impl TextureBuffer {
             buffer,
             width,
             height,
+            bytes_per_row,
         }
     }
 
-    #[cfg(feature = "headless")]
+    /// Copies offscreen texture of the headless renderer into the buffer
     pub fn copy_render_surface_to_texture(&self, renderer: &Renderer) {
         let mut encoder = renderer.create_encoder();
 
-        let u32_size = std::mem::size_of::<u32>() as u32;
         encoder.copy_texture_to_buffer(
             wgpu::ImageCopyTexture {
                 aspect: wgpu::TextureAspect::All,
-                texture: renderer.surface_texture(),
+                texture: renderer
+                    .surface_texture()
+                    .expect("Only headless renderer has surface texture"),
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
@@ -44,8 +45,8 @@ impl TextureBuffer {
                 buffer: &self.buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: NonZeroU32::new(u32_size * self.width),
-                    rows_per_image: NonZeroU32::new(self.height),
+                    bytes_per_row: Some(self.bytes_per_row),
+                    rows_per_image: Some(self.height),
                 },
             },
             wgpu::Extent3d {
@@ -61,14 +62,47 @@ impl TextureBuffer {
     pub async fn get_image_buffer(
         &self,
         renderer: &Renderer<'_>,
+    ) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        self.read_image_buffer(renderer)
+    }
+
+    /// Same as [`TextureBuffer::get_image_buffer`], but blocks
+    /// until the buffer is mapped
+    pub fn read_image_buffer(
+        &self,
+        renderer: &Renderer<'_>,
     ) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
         let buffer_slice = self.buffer.slice(..);
 
         buffer_slice.map_async(MapMode::Read, |_| {});
         renderer.device().poll(Maintain::Wait);
 
-        let data = buffer_slice.get_mapped_range().to_owned();
+        let data = remove_row_padding(
+            &buffer_slice.get_mapped_range(),
+            self.bytes_per_row,
+            self.width * std::mem::size_of::<u32>() as u32,
+        );
         self.buffer.unmap();
         ImageBuffer::<Rgba<u8>, _>::from_raw(self.width, self.height, data)
     }
 }
+
+/// Strips padding added by [`padded_bytes_per_row`] from the end of every row
+fn remove_row_padding(data: &[u8], padded_bytes_per_row: u32, bytes_per_row: u32) -> Vec<u8> {
+    data.chunks_exact(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..bytes_per_row as usize])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_buffer_remove_row_padding() {
+        let data = [1, 2, 3, 0, 4, 5, 6, 0];
+        assert_eq!(remove_row_padding(&data, 4, 3), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(remove_row_padding(&data, 8, 8), data.to_vec());
+    }
+}
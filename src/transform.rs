@@ -1,24 +1,25 @@
 use cgmath::Zero;
 
 use crate::render::prelude::*;
-use crate::{cgmath_imports::*, impl_simple_buffer};
+use crate::{cgmath_imports::*, impl_simple_buffer, impl_simple_dynamic_buffer};
 
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct TransformUniform {
     transform: [[f32; 4]; 4],
     rotate: [[f32; 4]; 4],
+    /// Same as `transform` unless updated with
+    /// [`TransformHandle::update_with_previous`]
+    prev_transform: [[f32; 4]; 4],
 }
 
 impl From<&Transform> for TransformUniform {
     fn from(value: &Transform) -> Self {
-        let rotate = Matrix4::from(value.rotation);
+        let transform: [[f32; 4]; 4] = Matrix4::from(value).into();
         Self {
-            transform: (Matrix4::from_translation(value.translation)
-                * rotate
-                * Matrix4::from_nonuniform_scale(value.scale.x, value.scale.y, value.scale.z))
-            .into(),
-            rotate: rotate.into(),
+            transform,
+            rotate: Matrix4::from(value.rotation).into(),
+            prev_transform: transform,
         }
     }
 }
@@ -58,3 +59,52 @@ impl_simple_buffer!(
     { ShaderStages::VERTEX },
     { BufferBindingType::Uniform }
 );
+
+impl_simple_dynamic_buffer!(
+    Transform,
+    TransformUniform,
+    Transforms,
+    TransformsResources,
+    TransformsHandle,
+    DynamicTransformBindGroup,
+    { ShaderStages::VERTEX }
+);
+
+impl TransformHandle {
+    /// Same as `update`, but also writes transform of the previous
+    /// frame used for motion vectors
+    pub fn update_with_previous(
+        &self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        transform: &Transform,
+        previous: &Transform,
+    ) {
+        let uniform = TransformUniform {
+            prev_transform: Matrix4::from(previous).into(),
+            ..transform.into()
+        };
+        renderer.queue().write_buffer(
+            storage.get_buffer(self.buffer_id),
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_empty_bind_group() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(4, 4)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+        let handle = TransformsHandle::new(&mut storage, Transforms::default().build(&renderer));
+        // Binding zero sized buffer would be a validation error
+        DynamicTransformBindGroup::new(&renderer, &mut storage, &handle);
+        renderer.device().poll(Maintain::Wait);
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+
 #[derive(Debug, Clone, Copy)]
 pub struct ConstVec<const S: usize, T> {
     data: [T; S],
@@ -17,6 +19,10 @@ impl<const S: usize, T> ConstVec<S, T> {
         &self.data[0..self.len]
     }
 
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data[0..self.len]
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.data[0..self.len].iter()
     }
@@ -53,6 +59,79 @@ impl<const S: usize, T> FromIterator<T> for ConstVec<S, T> {
     }
 }
 
+/// Storage past `len` is uninitialized, so moving out of it is only
+/// allowed for `Copy` types, which have nothing to drop.
+impl<const S: usize, T: Copy> IntoIterator for ConstVec<S, T> {
+    type Item = T;
+    type IntoIter = std::iter::Take<std::array::IntoIter<T, S>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter().take(self.len)
+    }
+}
+
+impl<'a, const S: usize, T> IntoIterator for &'a ConstVec<S, T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<const S: usize, T> Deref for ConstVec<S, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<const S: usize, T> DerefMut for ConstVec<S, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<const S: usize, T> Index<usize> for ConstVec<S, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<const S: usize, T> IndexMut<usize> for ConstVec<S, T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const S: usize, T: serde::Serialize> serde::Serialize for ConstVec<S, T> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+/// Limited to `Copy` types, as pushing into the uninitialized storage
+/// drops its previous content.
+#[cfg(feature = "serde")]
+impl<'de, const S: usize, T: Copy + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for ConstVec<S, T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = Vec::<T>::deserialize(deserializer)?;
+        if S < items.len() {
+            return Err(serde::de::Error::invalid_length(
+                items.len(),
+                &format!("at most {S} elements").as_str(),
+            ));
+        }
+        Ok(items.into_iter().collect())
+    }
+}
+
 #[macro_export]
 macro_rules! const_vec {
     () => (ConstVec::default());
@@ -117,6 +196,14 @@ mod tests {
         assert_eq!(&[1], cv.as_slice());
     }
 
+    #[test]
+    fn const_vec_as_mut_slice() {
+        let mut cv = ConstVec::<2, u32>::default();
+        cv.push(1);
+        cv.as_mut_slice()[0] = 2;
+        assert_eq!(&[2], cv.as_slice());
+    }
+
     #[test]
     fn const_vec_iter() {
         let mut cv = ConstVec::<1, u32>::default();
@@ -125,4 +212,27 @@ mod tests {
         let from_iter = cv.iter().copied().collect::<Vec<u32>>();
         assert_eq!(&from_iter, &[1]);
     }
+
+    #[test]
+    fn const_vec_into_iter() {
+        let cv: ConstVec<4, u32> = [1, 2, 3].into_iter().collect();
+        assert_eq!(cv.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!((&cv).into_iter().sum::<u32>(), 6);
+    }
+
+    #[test]
+    fn const_vec_index_and_deref() {
+        let mut cv: ConstVec<4, u32> = [1, 2].into_iter().collect();
+        cv[1] = 5;
+        assert_eq!(cv[1], 5);
+        assert_eq!(cv.len(), 2);
+        assert_eq!(cv.first(), Some(&1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn const_vec_index_out_of_len() {
+        let cv: ConstVec<4, u32> = [1].into_iter().collect();
+        _ = cv[1];
+    }
 }
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+/// Runs updates with a fixed time step independent of the frame rate.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    pub accumulator: Duration,
+    pub step: Duration,
+    /// Maximum number of updates per frame. Prevents the spiral
+    /// of death when updates are slower than the step.
+    pub max_steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        Self {
+            accumulator: Duration::ZERO,
+            step,
+            max_steps: 8,
+        }
+    }
+
+    /// Adds frame time and calls `update` with the step duration for every
+    /// full step accumulated. Returns interpolation alpha between the
+    /// previous and the current update states in [0, 1) range.
+    pub fn advance(&mut self, frame_time: Duration, mut update: impl FnMut(Duration)) -> f32 {
+        self.accumulator += frame_time;
+
+        let mut steps = 0;
+        while self.step <= self.accumulator {
+            if steps == self.max_steps {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            update(self.step);
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        self.alpha()
+    }
+
+    /// Fraction of the step accumulated since the last update
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_timestep_steps() {
+        let mut ft = FixedTimestep::new(Duration::from_millis(10));
+        let mut n = 0;
+        let alpha = ft.advance(Duration::from_millis(25), |dt| {
+            assert_eq!(dt, Duration::from_millis(10));
+            n += 1;
+        });
+        assert_eq!(n, 2);
+        assert!((alpha - 0.5).abs() < 1e-5);
+
+        let alpha = ft.advance(Duration::from_millis(5), |_| n += 1);
+        assert_eq!(n, 3);
+        assert_eq!(alpha, 0.0);
+    }
+
+    #[test]
+    fn fixed_timestep_max_steps() {
+        let mut ft = FixedTimestep::new(Duration::from_millis(10));
+        ft.max_steps = 2;
+        let mut n = 0;
+        let alpha = ft.advance(Duration::from_millis(100), |_| n += 1);
+        assert_eq!(n, 2);
+        assert_eq!(alpha, 0.0);
+    }
+}
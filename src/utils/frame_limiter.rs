@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+/// Caps the frame rate by waiting out the rest of the frame budget.
+/// Useful with present modes which do not wait for the vertical sync.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimiter {
+    pub target_fps: f32,
+    /// Last part of the wait is spent spinning instead of sleeping,
+    /// as sleeping can overshoot by the OS scheduler granularity.
+    pub spin_threshold: Duration,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(target_fps: f32) -> Self {
+        Self {
+            target_fps,
+            spin_threshold: Duration::from_millis(2),
+            next_frame: None,
+        }
+    }
+
+    /// Time budget of one frame
+    pub fn frame_budget(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.target_fps.max(f32::EPSILON))
+    }
+
+    /// Time left until the start of the next frame
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.next_frame
+            .map(|next_frame| next_frame.saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+
+    /// Schedules the next frame one budget after the previous one,
+    /// so waits do not accumulate errors. Frames which took longer than
+    /// the budget restart the schedule from `now`.
+    fn schedule(&mut self, now: Instant) {
+        let budget = self.frame_budget();
+        self.next_frame = Some(match self.next_frame {
+            Some(next_frame) if now < next_frame + budget => next_frame + budget,
+            _ => now + budget,
+        });
+    }
+
+    /// Waits until the start of the next frame. Needs to be called
+    /// once every frame after the frame is submitted.
+    pub fn wait(&mut self) {
+        let remaining = self.remaining(Instant::now());
+        if self.spin_threshold < remaining {
+            std::thread::sleep(remaining - self.spin_threshold);
+        }
+        while !self.remaining(Instant::now()).is_zero() {
+            std::hint::spin_loop();
+        }
+        self.schedule(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_limiter_schedule() {
+        let mut limiter = FrameLimiter::new(100.0);
+        let budget = Duration::from_millis(10);
+        assert!(limiter.frame_budget().abs_diff(budget) < Duration::from_micros(1));
+
+        let start = Instant::now();
+        assert_eq!(limiter.remaining(start), Duration::ZERO);
+
+        limiter.schedule(start);
+        let next = limiter.next_frame.unwrap();
+        assert_eq!(
+            limiter.remaining(start + Duration::from_millis(4)),
+            next - start - Duration::from_millis(4)
+        );
+
+        // On time frame keeps the schedule
+        limiter.schedule(next);
+        assert_eq!(limiter.next_frame.unwrap(), next + limiter.frame_budget());
+
+        // Late frame restarts it
+        let late = next + Duration::from_millis(50);
+        limiter.schedule(late);
+        assert_eq!(limiter.next_frame.unwrap(), late + limiter.frame_budget());
+    }
+
+    #[test]
+    fn frame_limiter_wait() {
+        let mut limiter = FrameLimiter::new(200.0);
+        let start = Instant::now();
+        limiter.wait();
+        limiter.wait();
+        limiter.wait();
+        assert!(Duration::from_millis(10) <= start.elapsed());
+    }
+}
@@ -1,5 +1,11 @@
 pub mod const_vec;
+pub mod fixed_timestep;
+pub mod frame_limiter;
 pub mod sparse_set;
+pub mod task;
 
 pub use const_vec::*;
+pub use fixed_timestep::*;
+pub use frame_limiter::*;
 pub use sparse_set::*;
+pub use task::*;
@@ -0,0 +1,172 @@
+use std::{
+    future::Future,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs `job` on the rayon thread pool
+#[cfg(feature = "rayon")]
+fn execute(job: Job) {
+    rayon::spawn(job);
+}
+
+/// Runs `job` on one of the worker threads shared by all tasks.
+/// There is a worker for every available CPU core. Jobs must not
+/// panic, as a panic would stop the worker.
+#[cfg(not(feature = "rayon"))]
+fn execute(job: Job) {
+    use std::sync::{mpsc, OnceLock};
+
+    static WORKERS: OnceLock<mpsc::Sender<Job>> = OnceLock::new();
+    let sender = WORKERS.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+        for i in 0..workers {
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("zero_task_{i}"))
+                .spawn(move || loop {
+                    let job = receiver.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    job();
+                })
+                .unwrap();
+        }
+        sender
+    });
+    sender.send(job).unwrap();
+}
+
+#[derive(Debug)]
+struct TaskState<T> {
+    /// Result of the closure or the payload of its panic
+    result: Option<std::thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future resolving to the result of the closure running on a worker thread.
+/// Does not depend on any async runtime. If the closure panics, the panic
+/// is resumed on the thread awaiting the task or calling [`Task::try_get`].
+#[derive(Debug)]
+pub struct Task<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Runs `f` on a worker thread, or on the rayon thread
+    /// pool if the `rayon` feature is enabled.
+    pub fn spawn(f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(TaskState {
+            result: None,
+            waker: None,
+        }));
+        let thread_state = state.clone();
+        execute(Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            let mut state = thread_state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }));
+        Self { state }
+    }
+}
+
+impl<T> Task<T> {
+    /// Takes the result without blocking. Returns `None` if the task is
+    /// not finished yet or the result was already taken.
+    pub fn try_get(&mut self) -> Option<T> {
+        let result = self.state.lock().unwrap().result.take();
+        result.map(|result| result.unwrap_or_else(|panic| resume_unwind(panic)))
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(panic)) => {
+                // Unlock first, so the mutex is not poisoned
+                drop(state);
+                resume_unwind(panic)
+            }
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_result() {
+        let task = Task::spawn(|| 2 + 2);
+        assert_eq!(pollster::block_on(task), 4);
+    }
+
+    #[test]
+    fn test_task_multiple() {
+        let tasks: Vec<_> = (0..4).map(|i| Task::spawn(move || i * 2)).collect();
+        let results: Vec<_> = tasks.into_iter().map(pollster::block_on).collect();
+        assert_eq!(results, vec![0, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_task_more_than_workers() {
+        let tasks: Vec<_> = (0..64).map(|i| Task::spawn(move || i)).collect();
+        let results: Vec<_> = tasks.into_iter().map(pollster::block_on).collect();
+        assert_eq!(results, (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_task_try_get() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut task = Task::spawn(move || receiver.recv().unwrap());
+        assert_eq!(task.try_get(), None);
+
+        sender.send(4).unwrap();
+        let result = loop {
+            if let Some(result) = task.try_get() {
+                break result;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(result, 4);
+        assert_eq!(task.try_get(), None);
+    }
+    #[test]
+    #[should_panic(expected = "task failed")]
+    fn test_task_panic() {
+        pollster::block_on(Task::spawn(|| -> u32 { panic!("task failed") }));
+    }
+
+    #[test]
+    fn test_task_panic_try_get() {
+        let mut task = Task::spawn(|| -> u32 { panic!("task failed") });
+        let panic = loop {
+            match catch_unwind(AssertUnwindSafe(|| task.try_get())) {
+                Ok(Some(_)) => unreachable!(),
+                Ok(None) => std::thread::yield_now(),
+                Err(panic) => break panic,
+            }
+        };
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"task failed"));
+        // Workers keep running after the panic
+        assert_eq!(pollster::block_on(Task::spawn(|| 4)), 4);
+    }
+}
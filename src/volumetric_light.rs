@@ -0,0 +1,220 @@
+use crate::camera::CameraBindGroup;
+use crate::cgmath_imports::*;
+use crate::const_vec;
+use crate::fullscreen::FullscreenTriangle;
+use crate::impl_simple_buffer;
+use crate::mesh::ProceduralCommand;
+use crate::render::pipeline_builder::create_shader_module;
+use crate::render::prelude::*;
+use crate::texture::FloatDepthTextureBindGroup;
+use crate::utils::ConstVec;
+
+const VOLUMETRIC_LIGHT_SHADER: &str = include_str!("shaders/volumetric_light.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct VolumetricLightUniform {
+    light_direction: [f32; 3],
+    density: f32,
+    color: [f32; 3],
+    decay: f32,
+    weight: f32,
+    exposure: f32,
+    samples: u32,
+    _pad: f32,
+}
+
+impl From<&VolumetricLight> for VolumetricLightUniform {
+    fn from(value: &VolumetricLight) -> Self {
+        Self {
+            light_direction: value.light_direction.into(),
+            density: value.density,
+            color: value.color,
+            decay: value.decay,
+            weight: value.weight,
+            exposure: value.exposure,
+            samples: value.samples,
+            ..Default::default()
+        }
+    }
+}
+
+/// Screen space light scattering (god rays) from a directional light.
+/// Every pixel is blurred towards the light position on the screen,
+/// accumulating samples where the sky is not occluded by the scene depth.
+#[derive(Debug)]
+pub struct VolumetricLight {
+    /// Direction the light shines in. Usually the
+    /// [`crate::shadow_map::ShadowMapDLight::direction`].
+    pub light_direction: Vector3<f32>,
+    pub color: [f32; 3],
+    /// Fraction of the distance to the light covered by the samples
+    pub density: f32,
+    /// Falloff of each following sample
+    pub decay: f32,
+    /// Contribution of each sample
+    pub weight: f32,
+    /// Scale of the result. Result is normalized by the number of samples.
+    pub exposure: f32,
+    /// Number of samples along the ray to the light
+    pub samples: u32,
+}
+
+impl Default for VolumetricLight {
+    fn default() -> Self {
+        Self {
+            light_direction: Vector3::new(0.0, -1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            density: 0.9,
+            decay: 0.97,
+            weight: 1.0,
+            exposure: 1.0,
+            samples: 64,
+        }
+    }
+}
+
+impl_simple_buffer!(
+    VolumetricLight,
+    VolumetricLightUniform,
+    VolumetricLightResources,
+    VolumetricLightHandle,
+    VolumetricLightBindGroup,
+    { BufferUsages::UNIFORM | BufferUsages::COPY_DST },
+    { ShaderStages::FRAGMENT },
+    { BufferBindingType::Uniform }
+);
+
+/// Adds [`VolumetricLight`] scattering on top of the render target.
+/// Meant to be drawn into the lit HDR target right after the lighting pass.
+#[derive(Debug, Clone, Copy)]
+pub struct VolumetricLightPipeline {
+    pub pipeline_id: PipelineId,
+}
+
+impl VolumetricLightPipeline {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage, format: TextureFormat) -> Self {
+        let shader = create_shader_module(
+            renderer,
+            "volumetric_light.wgsl",
+            VOLUMETRIC_LIGHT_SHADER.into(),
+        );
+        let pipeline = PipelineBuilder {
+            shader_path: "volumetric_light.wgsl",
+            label: Some("volumetric_light_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<FloatDepthTextureBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<VolumetricLightBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::OVER,
+                }),
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build_with_module(renderer, &shader);
+
+        Self {
+            pipeline_id: storage.insert_pipeline(pipeline),
+        }
+    }
+
+    /// Command adding light scattering over the whole render target
+    pub fn command(
+        &self,
+        depth: FloatDepthTextureBindGroup,
+        camera: CameraBindGroup,
+        light: VolumetricLightBindGroup,
+    ) -> ProceduralCommand {
+        FullscreenTriangle::command(
+            self.pipeline_id,
+            const_vec![
+                BindGroupMeta::new(0, depth.0),
+                BindGroupMeta::new(1, camera.0),
+                BindGroupMeta::new(2, light.0),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, CameraHandle, PerspectiveCamera};
+    use crate::texture::{DepthTextureHandle, EmptyTexture};
+
+    #[test]
+    fn volumetric_light_samples_depth_texture() {
+        let Some(renderer) = pollster::block_on(Renderer::try_new_headless(8, 8)) else {
+            return;
+        };
+        let mut storage = RenderStorage::default();
+        let depth = DepthTextureHandle {
+            texture_id: storage.insert_texture(EmptyTexture::new_depth_sampled().build(&renderer)),
+        };
+        let depth_bind_group = FloatDepthTextureBindGroup::new(&renderer, &mut storage, &depth);
+        let camera = Camera::Perspective(PerspectiveCamera {
+            position: (0.0, 0.0, 0.0).into(),
+            yaw: Deg(0.0).into(),
+            pitch: Deg(0.0).into(),
+            aspect: 1.0,
+            fovy: Deg(90.0).into(),
+            znear: 0.1,
+            zfar: 100.0,
+        });
+        let camera_handle = CameraHandle::new(&mut storage, camera.build(&renderer));
+        let camera_bind_group = CameraBindGroup::new(&renderer, &mut storage, &camera_handle);
+        let light_handle =
+            VolumetricLightHandle::new(&mut storage, VolumetricLight::default().build(&renderer));
+        let light_bind_group =
+            VolumetricLightBindGroup::new(&renderer, &mut storage, &light_handle);
+        let pipeline =
+            VolumetricLightPipeline::new(&renderer, &mut storage, renderer.surface_format());
+
+        let phase = RenderPhase::builder()
+            .color(TextureId::WINDOW_VIEW_ID)
+            .clear(Color::BLACK)
+            .build();
+        let command = pipeline.command(depth_bind_group, camera_bind_group, light_bind_group);
+        let current_frame = renderer.current_frame().unwrap();
+        let current_frame_storage = CurrentFrameStorage {
+            storage: &storage,
+            current_frame_view: current_frame.view(),
+        };
+        let mut encoder = renderer.create_encoder();
+        {
+            let mut render_pass = phase.render_pass(&mut encoder, &current_frame_storage);
+            command.execute(&mut render_pass, &current_frame_storage);
+        }
+        // Layout mismatch with the depth texture would be a validation error
+        renderer.submit(std::iter::once(encoder.finish()));
+        renderer.device().poll(Maintain::Wait);
+    }
+}
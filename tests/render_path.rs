@@ -0,0 +1,112 @@
+//! Renders the same scene with both render paths and compares them with
+//! one reference image, so the paths can not drift apart silently.
+//! Skipped if there is no GPU adapter available.
+
+use zero::{image_compare::assert_render_matches, prelude::*};
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 64;
+
+fn render_scene(path: RenderPath) -> impl FnOnce(&Renderer, &mut RenderStorage) {
+    move |renderer, storage| {
+        let render_path = RenderPathRenderer::new(
+            renderer,
+            storage,
+            TextureId::WINDOW_VIEW_ID,
+            renderer.surface_format(),
+            path,
+        );
+
+        let camera = Camera::Perspective(PerspectiveCamera {
+            position: (-4.0, 3.0, 0.0).into(),
+            yaw: Deg(0.0).into(),
+            pitch: Deg(-30.0).into(),
+            aspect: WIDTH as f32 / HEIGHT as f32,
+            fovy: Deg(90.0).into(),
+            znear: 0.1,
+            zfar: 100.0,
+        });
+        let camera_handle = CameraHandle::new(storage, camera.build(renderer));
+        let camera_bind_group = CameraBindGroup::new(renderer, storage, &camera_handle);
+
+        let lights = PointLights {
+            lights: vec![
+                PointLight::new((0.0, 3.0, 2.0), (1.0, 1.0, 1.0), 1.0, 0.09, 0.032),
+                PointLight::new((1.0, 2.0, -2.0), (1.0, 0.5, 0.2), 1.0, 0.09, 0.032),
+            ],
+        };
+        let lights_handle = PointLightsHandle::new(storage, lights.build(renderer));
+        let lights_bind_group = PointLightsBindGroup::new(renderer, storage, &lights_handle);
+
+        let mut object = |mesh: Mesh, translation: (f32, f32, f32), material: ColorMaterial| {
+            let mesh_id = storage.insert_mesh(mesh.build(renderer));
+            let transform = Transform {
+                translation: translation.into(),
+                rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(30.0)),
+                scale: (1.0, 1.0, 1.0).into(),
+            };
+            let transform_handle = TransformHandle::new(storage, transform.build(renderer));
+            let material_handle = ColorMaterialHandle::new(storage, material.build(renderer));
+            RenderPathObject {
+                mesh_id,
+                material: ColorMaterialBindGroup::new(renderer, storage, &material_handle),
+                transform: TransformBindGroup::new(renderer, storage, &transform_handle),
+            }
+        };
+        let objects = [
+            object(
+                Cube::new(6.0, 0.5, 6.0).into(),
+                (0.0, -0.5, 0.0),
+                ColorMaterial {
+                    ambient: [0.2, 0.2, 0.2],
+                    diffuse: [0.6, 0.6, 0.6],
+                    specular: [1.0, 1.0, 1.0],
+                    shininess: 32.0,
+                },
+            ),
+            object(
+                Cube::new(1.0, 1.0, 1.0).into(),
+                (0.0, 0.5, 0.0),
+                ColorMaterial {
+                    ambient: [0.2, 0.6, 0.2],
+                    diffuse: [0.3, 0.9, 0.3],
+                    specular: [0.1, 0.1, 0.1],
+                    shininess: 4.0,
+                },
+            ),
+        ];
+
+        let current_frame = renderer.current_frame().unwrap();
+        let current_frame_storage = CurrentFrameStorage {
+            storage,
+            current_frame_view: current_frame.view(),
+        };
+        let mut encoder = renderer.create_encoder();
+        render_path.render(
+            &mut encoder,
+            &current_frame_storage,
+            &objects,
+            camera_bind_group,
+            lights_bind_group,
+        );
+        renderer.submit(std::iter::once(encoder.finish()));
+        current_frame.present();
+    }
+}
+
+#[test]
+fn render_paths_match() {
+    let Some(renderer) = pollster::block_on(Renderer::try_new_headless(WIDTH, HEIGHT)) else {
+        eprintln!("No GPU adapter found, skipping");
+        return;
+    };
+
+    for path in [RenderPath::Forward, RenderPath::Deferred] {
+        assert_render_matches(
+            &renderer,
+            render_scene(path),
+            "tests/reference/render_path.png",
+            0.02,
+        );
+    }
+}
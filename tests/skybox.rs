@@ -0,0 +1,137 @@
+//! Renders skybox with a distinct color on every cube map face and compares
+//! it with the reference image, so faces can not be swapped silently.
+//! Skipped if there is no GPU adapter available.
+
+use zero::{const_vec, image_compare::assert_render_matches, prelude::*};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 64;
+const FACE_SIZE: u32 = 4;
+
+/// +X, -X, +Y, -Y, +Z, -Z
+const FACE_COLORS: [[u8; 4]; 6] = [
+    [255, 0, 0, 255],
+    [0, 255, 255, 255],
+    [0, 255, 0, 255],
+    [255, 0, 255, 255],
+    [0, 0, 255, 255],
+    [255, 255, 0, 255],
+];
+
+fn render_skybox(yaw: Deg<f32>, pitch: Deg<f32>) -> impl FnOnce(&Renderer, &mut RenderStorage) {
+    move |renderer, storage| {
+        let skybox_pipeline = PipelineBuilder {
+            shader_path: "./examples/skybox/skybox.wgsl",
+            label: Some("skybox_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &storage.get_bind_group_layout::<SkyboxBindGroup>(renderer),
+                    &storage.get_bind_group_layout::<CameraBindGroup>(renderer),
+                ],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[SkyboxVertex::layout()],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format: renderer.surface_format(),
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build(renderer);
+        let skybox_pipeline_id = storage.insert_pipeline(skybox_pipeline);
+
+        let faces: Vec<u8> = FACE_COLORS
+            .iter()
+            .flat_map(|color| color.repeat((FACE_SIZE * FACE_SIZE) as usize))
+            .collect();
+        let skybox = Skybox::new(SkyboxTexture::Cube(CubeMap {
+            format: TextureFormat::Rgba8UnormSrgb,
+            texture: Some(faces),
+            dimensions: Some((FACE_SIZE, FACE_SIZE)),
+            mip_level_count: 1,
+            sampler: SamplerConfig::trilinear(),
+        }));
+        let skybox_handle = SkyboxHandle::new(storage, skybox.build(renderer));
+        let skybox_bind_group = SkyboxBindGroup::new(renderer, storage, &skybox_handle);
+
+        let camera = Camera::Perspective(PerspectiveCamera {
+            position: (0.0, 0.0, 0.0).into(),
+            yaw: yaw.into(),
+            pitch: pitch.into(),
+            aspect: WIDTH as f32 / HEIGHT as f32,
+            fovy: Deg(90.0).into(),
+            znear: 0.1,
+            zfar: 100.0,
+        });
+        let camera_handle = CameraHandle::new(storage, camera.build(renderer));
+        let camera_bind_group = CameraBindGroup::new(renderer, storage, &camera_handle);
+
+        let phase = RenderPhase::builder()
+            .color(TextureId::WINDOW_VIEW_ID)
+            .clear(Color::BLACK)
+            .build();
+        let command = MeshRenderCommand {
+            pipeline_id: skybox_pipeline_id,
+            mesh_id: skybox_handle.mesh_id,
+            index_slice: None,
+            vertex_slice: None,
+            scissor_rect: None,
+            viewport: None,
+            bind_groups: const_vec![
+                BindGroupMeta::new(0, skybox_bind_group.0),
+                BindGroupMeta::new(1, camera_bind_group.0),
+            ],
+        };
+
+        let current_frame = renderer.current_frame().unwrap();
+        let current_frame_storage = CurrentFrameStorage {
+            storage,
+            current_frame_view: current_frame.view(),
+        };
+        let mut encoder = renderer.create_encoder();
+        {
+            let mut render_pass = phase.render_pass(&mut encoder, &current_frame_storage);
+            command.execute(&mut render_pass, &current_frame_storage);
+        }
+        renderer.submit(std::iter::once(encoder.finish()));
+        current_frame.present();
+    }
+}
+
+#[test]
+fn skybox_face_order() {
+    let Some(renderer) = pollster::block_on(Renderer::try_new_headless(WIDTH, HEIGHT)) else {
+        eprintln!("No GPU adapter found, skipping");
+        return;
+    };
+
+    // Looks between +X and +Z faces from below, so +Y face is also visible
+    assert_render_matches(
+        &renderer,
+        render_skybox(Deg(45.0), Deg(30.0)),
+        "tests/reference/skybox_up.png",
+        0.05,
+    );
+    // Same between -X and -Z faces from above, so -Y face is visible
+    assert_render_matches(
+        &renderer,
+        render_skybox(Deg(225.0), Deg(-30.0)),
+        "tests/reference/skybox_down.png",
+        0.05,
+    );
+}
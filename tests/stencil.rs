@@ -0,0 +1,148 @@
+//! Checks that stencil written by one draw is respected by the next one
+//! when both are executed by a [`RenderPhase`] with stencil operations.
+//! Uses the same depth stencil states as [`zero::outline::Outline`].
+//! Skipped if there is no GPU adapter available.
+
+use wgpu::util::DeviceExt;
+use zero::{const_vec, prelude::*};
+
+const SIZE: u32 = 64;
+
+#[test]
+fn stencil_write_then_read() {
+    let Some(renderer) = pollster::block_on(Renderer::try_new_headless(SIZE, SIZE)) else {
+        eprintln!("No GPU adapter found, skipping");
+        return;
+    };
+    let mut storage = RenderStorage::default();
+
+    let depth_stencil_id =
+        storage.insert_texture(EmptyTexture::new_depth_stencil().build(&renderer));
+
+    let layout = renderer
+        .device()
+        .create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+    let mut bind_group = |scale: [f32; 4]| {
+        let buffer = renderer.device().create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&scale),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        storage.insert_bind_group(bind_group)
+    };
+    // Mask covers left half of the screen and draws red.
+    // Second draw covers whole screen and draws green only where mask was not drawn.
+    let mask_bind_group = bind_group([0.5, 8.0, 1.0, 0.0]);
+    let outline_bind_group = bind_group([2.0, 2.0, 0.0, 1.0]);
+
+    let pipeline = |depth_stencil: DepthStencilState| {
+        PipelineBuilder {
+            shader_path: "./tests/shaders/stencil.wgsl",
+            label: None,
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            }),
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(renderer.surface_format().into())]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(depth_stencil),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        }
+        .build(&renderer)
+    };
+    let mask_pipeline_id = storage.insert_pipeline(pipeline(Outline::mask_depth_stencil()));
+    let outline_pipeline_id = storage.insert_pipeline(pipeline(Outline::outline_depth_stencil()));
+
+    // Vertices are generated in the shader
+    let mesh_id = storage.insert_mesh(GpuMesh {
+        vertex_buffer: renderer.device().create_buffer(&BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        }),
+        index_buffer: None,
+        num_elements: 3,
+    });
+    let command = |pipeline_id, bind_group_id| MeshRenderCommand {
+        pipeline_id,
+        mesh_id,
+        index_slice: None,
+        vertex_slice: None,
+        scissor_rect: None,
+        viewport: None,
+        bind_groups: const_vec![BindGroupMeta::new(0, bind_group_id)],
+    };
+
+    let phase = RenderPhase::new(
+        const_vec![ColorAttachment {
+            view_id: TextureId::WINDOW_VIEW_ID,
+            ops: Operations {
+                load: LoadOp::Clear(Color::BLACK),
+                store: StoreOp::Store,
+            },
+        }],
+        Some(DepthStencil {
+            view_id: depth_stencil_id,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: StoreOp::Store,
+            }),
+            stencil_ops: Some(Operations {
+                load: LoadOp::Clear(0),
+                store: StoreOp::Store,
+            }),
+        }),
+    );
+
+    let texture_buffer = TextureBuffer::new(&renderer, SIZE, SIZE);
+    let current_frame = renderer.current_frame().unwrap();
+    let current_frame_storage = CurrentFrameStorage {
+        storage: &storage,
+        current_frame_view: current_frame.view(),
+    };
+    let mut encoder = renderer.create_encoder();
+    phase.execute(
+        &mut encoder,
+        &current_frame_storage,
+        &[
+            command(mask_pipeline_id, mask_bind_group),
+            command(outline_pipeline_id, outline_bind_group),
+        ],
+    );
+    renderer.submit(std::iter::once(encoder.finish()));
+    current_frame.present();
+
+    texture_buffer.copy_render_surface_to_texture(&renderer);
+    let image = texture_buffer.read_image_buffer(&renderer).unwrap();
+
+    // Left half was marked by the mask and kept its color
+    assert_eq!(image.get_pixel(SIZE / 4, SIZE / 2).0, [255, 0, 255, 0]);
+    // Right half was not marked and was covered by the second draw
+    assert_eq!(image.get_pixel(SIZE * 3 / 4, SIZE / 2).0, [0, 255, 0, 255]);
+}